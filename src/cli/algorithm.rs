@@ -0,0 +1,110 @@
+//! Hash functions selectable with `-a`: the entries of the algorithm registry that hash a
+//! message alone, under their registry names or a few aliases of the command line.
+
+use std::io;
+use std::io::Read;
+use std::io::Write;
+
+use crypto_playground::sha3::registry::Algorithm;
+use crypto_playground::sha3::registry::DynHasher;
+use crypto_playground::sha3::registry::HasherOptions;
+
+use crate::cli::args::UsageError;
+
+
+/// Aliases accepted by `-a` besides the registry names: the bare digest length of a SHA3
+/// variant, and the spelling of Keccak-256 without a hyphen.
+const ALIASES: [(&str, &str); 5] = [
+    ("224", "SHA3-224"),
+    ("256", "SHA3-256"),
+    ("384", "SHA3-384"),
+    ("512", "SHA3-512"),
+    ("keccak256", "Keccak-256"),
+];
+
+/// Whether `algorithm` hashes a message alone, without a key, a tuple of items or other
+/// parameters.
+pub fn is_hash(algorithm: &Algorithm) -> bool {
+    matches!(algorithm, Algorithm::Sha3(_) | Algorithm::Shake(_) | Algorithm::Keccak(_))
+}
+
+/// The registry names of the hash functions, in lower case, e.g. "sha3-224|sha3-256|...".
+pub fn algorithm_names() -> String {
+    Algorithm::ALL.iter()
+        .filter(|algorithm| is_hash(algorithm))
+        .map(|algorithm| algorithm.name().to_ascii_lowercase())
+        .collect::<Vec<_>>()
+        .join("|")
+}
+
+/// Parses the argument of `-a`: the registry name of a hash function, in any case, or one
+/// of the `ALIASES`.
+pub fn parse_algorithm(arg: &str) -> Result<Algorithm, UsageError> {
+    let name = ALIASES.iter()
+        .find(|(alias, _)| alias.eq_ignore_ascii_case(arg))
+        .map_or(arg, |(_, name)| name);
+    Algorithm::from_name(name)
+        .filter(is_hash)
+        .ok_or_else(|| UsageError(format!("unknown algorithm '{arg}', expected {}", algorithm_names())))
+}
+
+
+/// A registry hasher fed through `io::Write`.
+struct HashWriter(Box<dyn DynHasher>);
+
+impl Write for HashWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Hashes everything `reader` yields, in blocks, without holding the whole input.
+/// `output_bits` is the output length of the XOFs, and the digest length of the others.
+pub fn hash_reader(algorithm: Algorithm, output_bits: usize, reader: &mut impl Read) -> io::Result<Vec<u8>> {
+    assert!(output_bits.is_multiple_of(8));
+    let mut writer = HashWriter(algorithm.new_hasher(&HasherOptions::default()));
+    io::copy(reader, &mut writer)?;
+    let mut out = vec![0u8; output_bits / 8];
+    writer.0.finalize_into(&mut out);
+    Ok(out)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crypto_playground::sha3::types::Sha3Variant;
+    use crypto_playground::sha3::types::ShakeVariant;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_algorithm() {
+        assert_eq!(Ok(Algorithm::Sha3(Sha3Variant::SHA3_384)), parse_algorithm("SHA3-384"));
+        assert_eq!(Ok(Algorithm::Sha3(Sha3Variant::SHA3_512)), parse_algorithm("512"));
+        assert_eq!(Ok(Algorithm::Shake(ShakeVariant::SHAKE128)), parse_algorithm("shake128"));
+        assert_eq!(Ok(Algorithm::Keccak(Sha3Variant::SHA3_256)), parse_algorithm("keccak256"));
+        assert_eq!(Ok(Algorithm::Keccak(Sha3Variant::SHA3_384)), parse_algorithm("Keccak-384"));
+        assert!(parse_algorithm("sha256").is_err());
+        // in the registry, but keyed
+        assert!(parse_algorithm("kmac128").is_err());
+        assert_eq!("sha3-224|sha3-256|sha3-384|sha3-512|shake128|shake256|keccak-224|keccak-256|keccak-384|keccak-512", algorithm_names());
+    }
+
+    #[test]
+    fn test_streaming_matches_one_shot() {
+        use crypto_playground::sha3::sha3::keccak_256;
+        use crypto_playground::sha3::sha3::sha3_224;
+        use crypto_playground::sha3::sha3::shake256;
+
+        let data: Vec<u8> = (0..20000u32).map(|i| (i % 251) as u8).collect();
+        let hash = |algorithm, bits| hash_reader(algorithm, bits, &mut &data[..]).unwrap();
+        assert_eq!(sha3_224(&data).to_vec(), hash(Algorithm::Sha3(Sha3Variant::SHA3_224), 224));
+        assert_eq!(shake256(&data, 1000).to_vec(), hash(Algorithm::Shake(ShakeVariant::SHAKE256), 1000));
+        assert_eq!(keccak_256(&data).to_vec(), hash(Algorithm::Keccak(Sha3Variant::SHA3_256), 256));
+    }
+}
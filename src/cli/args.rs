@@ -0,0 +1,189 @@
+//! Minimal getopt-style parser: clustered short options (`-ta256`), long options with
+//! `--name value` or `--name=value`, operands anywhere, and `--` to end the options.
+
+use std::collections::VecDeque;
+use std::ffi::OsString;
+use std::fmt;
+
+
+/// A usage error, reported with a hint to run `--help`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct UsageError(pub String);
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Arg {
+    Short(char),
+    Long(String),
+    Operand(OsString),
+}
+
+impl fmt::Display for Arg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Arg::Short(c) => write!(f, "-{c}"),
+            Arg::Long(name) => write!(f, "--{name}"),
+            Arg::Operand(operand) => write!(f, "{}", operand.to_string_lossy()),
+        }
+    }
+}
+
+
+pub struct ArgParser {
+    args: VecDeque<OsString>,
+    /// Rest of a cluster of short options, or the value after `=` of a long option.
+    pending: Option<String>,
+    pending_is_long_value: bool,
+    only_operands: bool,
+}
+
+impl ArgParser {
+    pub fn new(args: impl IntoIterator<Item = OsString>) -> Self {
+        ArgParser {
+            args: args.into_iter().collect(),
+            pending: None,
+            pending_is_long_value: false,
+            only_operands: false,
+        }
+    }
+
+    pub fn next_arg(&mut self) -> Result<Option<Arg>, UsageError> {
+        if let Some(pending) = self.pending.take() {
+            if self.pending_is_long_value {
+                return Err(UsageError(format!("option does not take a value: '{pending}'")));
+            }
+            let mut chars = pending.chars();
+            let c = chars.next().expect("clusters are never empty");
+            let rest = chars.as_str();
+            if !rest.is_empty() {
+                self.pending = Some(rest.to_string());
+            }
+            return Ok(Some(Arg::Short(c)));
+        }
+        let Some(arg) = self.args.pop_front() else {
+            return Ok(None);
+        };
+        if self.only_operands || arg == "-" {
+            return Ok(Some(Arg::Operand(arg)));
+        }
+        let Some(text) = arg.to_str() else {
+            return Ok(Some(Arg::Operand(arg)));
+        };
+        if text == "--" {
+            self.only_operands = true;
+            return self.next_arg();
+        }
+        if let Some(long) = text.strip_prefix("--") {
+            return Ok(Some(match long.split_once('=') {
+                Some((name, value)) => {
+                    self.pending = Some(value.to_string());
+                    self.pending_is_long_value = true;
+                    Arg::Long(name.to_string())
+                }
+                None => Arg::Long(long.to_string()),
+            }));
+        }
+        if let Some(cluster) = text.strip_prefix('-') {
+            self.pending = Some(cluster.to_string());
+            self.pending_is_long_value = false;
+            return self.next_arg();
+        }
+        Ok(Some(Arg::Operand(arg)))
+    }
+
+    /// Value of the option just returned: the rest of its cluster, the text after `=`,
+    /// or the next argument.
+    pub fn value(&mut self, option: &Arg) -> Result<String, UsageError> {
+        if let Some(pending) = self.pending.take() {
+            return Ok(pending);
+        }
+        let value = self.args.pop_front()
+            .ok_or_else(|| UsageError(format!("option requires an argument: '{option}'")))?;
+        value.into_string()
+            .map_err(|value| UsageError(format!("invalid argument for '{option}': '{}'", value.to_string_lossy())))
+    }
+
+    /// Value of the option just returned, parsed as an integer.
+    pub fn value_usize(&mut self, option: &Arg) -> Result<usize, UsageError> {
+        let value = self.value(option)?;
+        value.parse()
+            .map_err(|_| UsageError(format!("invalid number for '{option}': '{value}'")))
+    }
+}
+
+/// Parses a comma-separated list of numbers and ranges, as in "0,1,22-23", all at most `max`.
+pub fn parse_ranges(list: &str, max: usize) -> Option<Vec<usize>> {
+    let mut numbers = Vec::new();
+    for item in list.split(',') {
+        let (first, last) = item.split_once('-').unwrap_or((item, item));
+        let first: usize = first.parse().ok()?;
+        let last: usize = last.parse().ok()?;
+        if first > last || last > max {
+            return None;
+        }
+        numbers.extend(first..=last);
+    }
+    Some(numbers)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str], takes_value: &[&str]) -> Result<Vec<(String, Option<String>)>, UsageError> {
+        let mut parser = ArgParser::new(args.iter().map(OsString::from));
+        let mut parsed = Vec::new();
+        while let Some(arg) = parser.next_arg()? {
+            let value = if takes_value.contains(&arg.to_string().as_str()) {
+                Some(parser.value(&arg)?)
+            } else {
+                None
+            };
+            parsed.push((arg.to_string(), value));
+        }
+        Ok(parsed)
+    }
+
+    #[test]
+    fn test_options_and_operands() {
+        let parsed = parse(&["-ta", "256", "file", "--length=512", "-l8", "--algorithm", "shake128", "-", "--", "-x"], &["-a", "-l", "--algorithm", "--length"]).unwrap();
+        let expected = [
+            ("-t", None),
+            ("-a", Some("256")),
+            ("file", None),
+            ("--length", Some("512")),
+            ("-l", Some("8")),
+            ("--algorithm", Some("shake128")),
+            ("-", None),
+            ("-x", None),
+        ];
+        assert_eq!(expected.len(), parsed.len());
+        for ((name, value), (parsed_name, parsed_value)) in expected.iter().zip(&parsed) {
+            assert_eq!(name, parsed_name);
+            assert_eq!(value.map(str::to_string), *parsed_value);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(Err(UsageError("option requires an argument: '-a'".to_string())), parse(&["-a"], &["-a"]));
+        assert_eq!(Err(UsageError("option does not take a value: 'x'".to_string())), parse(&["--tag=x"], &[]));
+    }
+
+    #[test]
+    fn test_parse_ranges() {
+        assert_eq!(Some(vec![0, 1, 22, 23]), parse_ranges("0,1,22-23", 23));
+        assert_eq!(Some(vec![5]), parse_ranges("5-5", 23));
+        assert_eq!(None, parse_ranges("24", 23));
+        assert_eq!(None, parse_ranges("3-1", 23));
+        assert_eq!(None, parse_ranges("1,", 23));
+        assert_eq!(None, parse_ranges("-1", 23));
+    }
+}
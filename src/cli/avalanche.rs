@@ -0,0 +1,306 @@
+//! `avalanche` command: output difference statistics and SAC matrices of reduced-round Keccak.
+
+use std::fs::File;
+use std::io;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crypto_playground::sha3::avalanche::AvalancheReport;
+use crypto_playground::sha3::avalanche::Target;
+use crypto_playground::sha3::avalanche::analyze;
+use crypto_playground::sha3::avalanche::random_sac_deviation;
+use crypto_playground::sha3::constants::KECCAK_B;
+use crypto_playground::sha3::constants::KECCAK_CONSTANTS;
+use crypto_playground::sha3::constants::KECCAK_NR;
+use crypto_playground::sha3::registry::Algorithm;
+use crypto_playground::sha3::types::Sha3Variant;
+
+use crate::cli::EXIT_FAILURE;
+use crate::cli::EXIT_SUCCESS;
+use crate::cli::PROGRAM;
+use crate::cli::algorithm::parse_algorithm;
+use crate::cli::args::Arg;
+use crate::cli::args::ArgParser;
+use crate::cli::args::UsageError;
+use crate::cli::args::parse_ranges;
+
+
+const DEFAULT_SAMPLES: usize = 16;
+const DEFAULT_MESSAGE_LEN: usize = 8;
+
+
+pub fn usage() -> String {
+    format!("\
+Usage: {PROGRAM} avalanche [OPTION]...
+Flip each bit of random inputs to KECCAK-p or a SHA3 variant, for each number of rounds,
+and print the mean and standard deviation of the output Hamming distance and how far
+the strict avalanche criterion (SAC) matrix, the probability that output bit j flips
+when input bit i is flipped, deviates from 0.5.
+
+  -t, --target T      keccak-p (default), sha3-224, sha3-256, sha3-384 or sha3-512
+  -b, --width B       width of KECCAK-p: 25, 50, 100, 200, 400, 800 or 1600 (default {KECCAK_B})
+      --rounds LIST   numbers of rounds, e.g. 1-4,24 (default: all the rounds of the width)
+  -m, --msg-len N     message length in bytes for the SHA3 targets (default {DEFAULT_MESSAGE_LEN})
+  -n, --samples N     random inputs (default {DEFAULT_SAMPLES})
+      --seed N        seed of the random inputs (default 0)
+      --histogram     also print the distribution of the output Hamming distances
+      --csv FILE      write the SAC matrix as CSV, a row per input bit
+      --pgm FILE      write the SAC matrix as a PGM heatmap, black for 0, white for 1
+  -h, --help          print this help
+
+--csv and --pgm take a single number of rounds. Each sample evaluates the function once
+per input bit: 1600 times for the full KECCAK-p.
+")
+}
+
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct AvalancheCommand {
+    /// The target for each number of rounds.
+    pub targets: Vec<Target>,
+    pub samples: usize,
+    pub seed: u64,
+    pub histogram: bool,
+    pub csv: Option<PathBuf>,
+    pub pgm: Option<PathBuf>,
+}
+
+impl AvalancheCommand {
+    /// Parses the arguments after `avalanche`; None if help was requested.
+    pub fn parse(parser: &mut ArgParser) -> Result<Option<AvalancheCommand>, UsageError> {
+        let mut sha3_variant = None;
+        let mut width = KECCAK_B;
+        let mut rounds = None;
+        let mut message_len = None;
+        let mut samples = DEFAULT_SAMPLES;
+        let mut seed = 0;
+        let mut histogram = false;
+        let mut csv = None;
+        let mut pgm = None;
+        while let Some(arg) = parser.next_arg()? {
+            match &arg {
+                Arg::Short('h') => return Ok(None),
+                Arg::Long(name) if name == "help" => return Ok(None),
+                Arg::Short('t') => sha3_variant = parse_target(&parser.value(&arg)?)?,
+                Arg::Long(name) if name == "target" => sha3_variant = parse_target(&parser.value(&arg)?)?,
+                Arg::Short('b') => width = parser.value_usize(&arg)?,
+                Arg::Long(name) if name == "width" => width = parser.value_usize(&arg)?,
+                Arg::Long(name) if name == "rounds" => {
+                    let list = parser.value(&arg)?;
+                    rounds = Some(parse_ranges(&list, KECCAK_NR)
+                        .ok_or_else(|| UsageError(format!("invalid rounds '{list}', expected e.g. 1-4,24")))?);
+                }
+                Arg::Short('m') => message_len = Some(parser.value_usize(&arg)?),
+                Arg::Long(name) if name == "msg-len" => message_len = Some(parser.value_usize(&arg)?),
+                Arg::Short('n') => samples = parser.value_usize(&arg)?,
+                Arg::Long(name) if name == "samples" => samples = parser.value_usize(&arg)?,
+                Arg::Long(name) if name == "seed" => seed = parser.value_usize(&arg)? as u64,
+                Arg::Long(name) if name == "histogram" => histogram = true,
+                Arg::Long(name) if name == "csv" => csv = Some(PathBuf::from(parser.value(&arg)?)),
+                Arg::Long(name) if name == "pgm" => pgm = Some(PathBuf::from(parser.value(&arg)?)),
+                _ => return Err(UsageError(format!("unknown option '{arg}'"))),
+            }
+        }
+        let make_target = |rounds| match sha3_variant {
+            None => Target::Permutation { width, rounds },
+            Some(sha3_variant) => Target::Sha3 { sha3_variant, rounds, message_len: message_len.unwrap_or(DEFAULT_MESSAGE_LEN) },
+        };
+        if sha3_variant.is_none() && message_len.is_some() {
+            return Err(UsageError("--msg-len only applies to the SHA3 targets".to_string()));
+        }
+        if sha3_variant.is_some() && width != KECCAK_B {
+            return Err(UsageError("--width only applies to keccak-p".to_string()));
+        }
+        // all the rounds of the width by default, as in the standard functions
+        let full_rounds = KECCAK_CONSTANTS.iter()
+            .find(|constants| constants[2] == width)
+            .map_or(KECCAK_NR, |constants| 12 + 2 * constants[0]);
+        let targets: Vec<Target> = rounds.unwrap_or(vec![full_rounds]).into_iter().map(make_target).collect();
+        for target in &targets {
+            target.check().map_err(|error| UsageError(error.to_string()))?;
+        }
+        if samples == 0 {
+            return Err(UsageError("--samples must be at least 1".to_string()));
+        }
+        if (csv.is_some() || pgm.is_some()) && targets.len() != 1 {
+            return Err(UsageError("--csv and --pgm take a single number of rounds".to_string()));
+        }
+        Ok(Some(AvalancheCommand { targets, samples, seed, histogram, csv, pgm }))
+    }
+}
+
+/// Parses `-t`: None for keccak-p, otherwise the SHA3 variant.
+fn parse_target(arg: &str) -> Result<Option<Sha3Variant>, UsageError> {
+    if arg.eq_ignore_ascii_case("keccak-p") {
+        return Ok(None);
+    }
+    match parse_algorithm(arg) {
+        Ok(Algorithm::Sha3(sha3_variant)) => Ok(Some(sha3_variant)),
+        _ => Err(UsageError(format!("unknown target '{arg}', expected keccak-p, sha3-224, sha3-256, sha3-384 or sha3-512"))),
+    }
+}
+
+
+fn target_name(target: &Target) -> String {
+    match target {
+        Target::Permutation { width, .. } => format!("KECCAK-p[{width}]"),
+        Target::Sha3 { sha3_variant, message_len, .. } => {
+            format!("{} on {message_len}-byte messages", Algorithm::Sha3(*sha3_variant).name())
+        }
+    }
+}
+
+fn rounds(target: &Target) -> usize {
+    match target {
+        Target::Permutation { rounds, .. } | Target::Sha3 { rounds, .. } => *rounds,
+    }
+}
+
+const TABLE_HEADER: &str = "ROUNDS  MEAN DISTANCE  STD DEV  MAX |P-0.5|  MEAN |P-0.5|";
+
+fn write_row(report: &AvalancheReport, out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "{:>6}  {:>13.2}  {:>7.2}  {:>11.4}  {:>12.4}", rounds(&report.target),
+        report.mean_distance(), report.distance_std_dev(), report.max_sac_deviation(), report.mean_sac_deviation())
+}
+
+fn write_histogram(report: &AvalancheReport, out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "output Hamming distances after {} rounds (DISTANCE COUNT):", rounds(&report.target))?;
+    for (distance, count) in report.distances.iter().enumerate().filter(|(_, count)| **count > 0) {
+        writeln!(out, "  {distance:>4} {count}")?;
+    }
+    Ok(())
+}
+
+/// Writes an export of `report` to `path`, reporting the failure on `err`.
+fn export(path: &Path, write: impl FnOnce(&mut BufWriter<File>) -> io::Result<()>, err: &mut impl Write) -> io::Result<bool> {
+    let result = File::create(path).and_then(|file| {
+        let mut writer = BufWriter::new(file);
+        write(&mut writer)?;
+        writer.flush()
+    });
+    if let Err(error) = result {
+        writeln!(err, "{PROGRAM}: {}: {error}", path.display())?;
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+pub fn run(command: &AvalancheCommand, out: &mut impl Write, err: &mut impl Write) -> io::Result<i32> {
+    let first = &command.targets[0];
+    let output_bits = first.output_bits();
+    writeln!(out, "{}: {} input bits, {output_bits} output bits, {} samples, seed {}",
+        target_name(first), first.input_bits(), command.samples, command.seed)?;
+    writeln!(out, "ideal: mean distance {:.2}, standard deviation {:.2}, mean SAC deviation {:.4} from sampling alone",
+        output_bits as f64 / 2.0, (output_bits as f64).sqrt() / 2.0, random_sac_deviation(command.samples))?;
+    writeln!(out, "{TABLE_HEADER}")?;
+    let mut reports = Vec::new();
+    for target in &command.targets {
+        let report = analyze(*target, command.samples, command.seed).expect("parameters are checked when parsing");
+        write_row(&report, out)?;
+        // a row per number of rounds, as soon as it is computed
+        out.flush()?;
+        reports.push(report);
+    }
+    if command.histogram {
+        for report in &reports {
+            write_histogram(report, out)?;
+        }
+    }
+
+    let report = &reports[0];
+    let mut exit_code = EXIT_SUCCESS;
+    if let Some(path) = &command.csv && !export(path, |writer| report.write_csv(writer), err)? {
+        exit_code = EXIT_FAILURE;
+    }
+    if let Some(path) = &command.pgm && !export(path, |writer| report.write_pgm(writer), err)? {
+        exit_code = EXIT_FAILURE;
+    }
+    Ok(exit_code)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+    use std::fs;
+
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<AvalancheCommand>, UsageError> {
+        AvalancheCommand::parse(&mut ArgParser::new(args.iter().map(OsString::from)))
+    }
+
+    fn run_args(args: &[&str]) -> (i32, String, String) {
+        let command = parse(args).unwrap().unwrap();
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let exit_code = run(&command, &mut out, &mut err).unwrap();
+        (exit_code, String::from_utf8(out).unwrap(), String::from_utf8(err).unwrap())
+    }
+
+    #[test]
+    fn test_parse() {
+        let command = parse(&["-b", "200", "--rounds", "1-3,18", "-n", "4", "--seed", "9", "--histogram"]).unwrap().unwrap();
+        assert_eq!(AvalancheCommand {
+            targets: [1, 2, 3, 18].into_iter().map(|rounds| Target::Permutation { width: 200, rounds }).collect(),
+            samples: 4,
+            seed: 9,
+            histogram: true,
+            csv: None,
+            pgm: None,
+        }, command);
+
+        let command = parse(&["-t", "sha3-256", "-m", "4", "--rounds", "2", "--csv", "sac.csv"]).unwrap().unwrap();
+        assert_eq!(vec![Target::Sha3 { sha3_variant: Sha3Variant::SHA3_256, rounds: 2, message_len: 4 }], command.targets);
+        assert_eq!(Some(PathBuf::from("sac.csv")), command.csv);
+        assert_eq!(vec![Target::Permutation { width: 1600, rounds: 24 }], parse(&[]).unwrap().unwrap().targets);
+
+        assert!(parse(&["-h"]).unwrap().is_none());
+        assert!(parse(&["-t", "shake128"]).is_err());
+        assert!(parse(&["-b", "64"]).is_err());
+        assert!(parse(&["-b", "3200"]).is_err());
+        assert!(parse(&["-b", "200", "--rounds", "19"]).is_err());
+        assert!(parse(&["--rounds", "25"]).is_err());
+        assert!(parse(&["-t", "sha3-256", "-b", "200"]).is_err());
+        assert!(parse(&["-m", "4"]).is_err());
+        assert!(parse(&["-t", "sha3-256", "-m", "0"]).is_err());
+        assert!(parse(&["-n", "0"]).is_err());
+        assert!(parse(&["--rounds", "1-2", "--pgm", "sac.pgm"]).is_err());
+    }
+
+    #[test]
+    fn test_table() {
+        let (exit_code, out, err) = run_args(&["-b", "25", "--rounds", "0,12", "-n", "2", "--histogram"]);
+        assert_eq!(EXIT_SUCCESS, exit_code);
+        assert!(err.is_empty());
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!("KECCAK-p[25]: 25 input bits, 25 output bits, 2 samples, seed 0", lines[0]);
+        assert_eq!("ideal: mean distance 12.50, standard deviation 2.50, mean SAC deviation 0.2500 from sampling alone", lines[1]);
+        assert_eq!(TABLE_HEADER, lines[2]);
+        // without rounds, every flip changes exactly the flipped bit
+        assert_eq!("     0           1.00     0.00       0.5000        0.5000", lines[3]);
+        assert!(lines[4].starts_with("    12 "), "{out}");
+        assert_eq!("output Hamming distances after 0 rounds (DISTANCE COUNT):", lines[5]);
+        assert_eq!("     1 50", lines[6]);
+    }
+
+    #[test]
+    fn test_exports() {
+        let dir = std::env::temp_dir().join(format!("crypto-playground-avalanche-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let csv = dir.join("sac.csv");
+        let pgm = dir.join("sac.pgm");
+        let (exit_code, _, _) = run_args(&["-t", "sha3-224", "-m", "1", "-n", "1", "--rounds", "1",
+            "--csv", csv.to_str().unwrap(), "--pgm", pgm.to_str().unwrap()]);
+        assert_eq!(EXIT_SUCCESS, exit_code);
+        assert_eq!(1 + 8, fs::read_to_string(&csv).unwrap().lines().count());
+        assert!(fs::read(&pgm).unwrap().starts_with(b"P5\n224 8\n255\n"));
+
+        let (exit_code, _, err) = run_args(&["-b", "25", "-n", "1", "--csv", dir.join("missing/sac.csv").to_str().unwrap()]);
+        assert_eq!(EXIT_FAILURE, exit_code);
+        assert!(err.contains("sac.csv"), "{err}");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
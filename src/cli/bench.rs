@@ -0,0 +1,323 @@
+//! `bench` command: throughput of every front-end on every Keccak backend.
+
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crypto_playground::sha3::bench::BATCH_LEN;
+use crypto_playground::sha3::bench::Backend;
+use crypto_playground::sha3::bench::BenchOptions;
+use crypto_playground::sha3::bench::BenchResult;
+use crypto_playground::sha3::bench::Frontend;
+use crypto_playground::sha3::bench::MAX_MESSAGE_LEN;
+use crypto_playground::sha3::bench::bench;
+use crypto_playground::sha3::json::JsonValue;
+
+use crate::cli::EXIT_FAILURE;
+use crate::cli::EXIT_SUCCESS;
+use crate::cli::PROGRAM;
+use crate::cli::args::Arg;
+use crate::cli::args::ArgParser;
+use crate::cli::args::UsageError;
+
+
+/// Message sizes measured when `--sizes` is not given.
+const DEFAULT_SIZES: [u64; 4] = [0, 64, 1024, 16 * 1024];
+
+const SIZE_UNITS: [(char, u64); 3] = [('K', 1 << 10), ('M', 1 << 20), ('G', 1 << 30)];
+
+
+pub fn usage() -> String {
+    let backends: Vec<&str> = Backend::ALL.iter().map(Backend::name).collect();
+    let frontends: Vec<&str> = Frontend::ALL.iter().map(Frontend::name).collect();
+    format!("\
+Usage: {PROGRAM} bench [OPTION]...
+Measure every front-end on every Keccak backend for each message size, and print a table
+of the median and 90th percentile times of one hash, the throughput in MB/s (10^6 bytes),
+the permutations per second and an estimate of the cycles per byte, derived from the time
+and the clock rate. A run first warms up, then repeats the measurement; each repetition
+hashes the message until it has lasted --min-time. The batch front-ends hash {BATCH_LEN}
+messages of the given size, up to 64K, in one operation.
+
+  -b, --backend LIST     some of {backends} (default all)
+  -f, --frontend LIST    some of {frontends}
+                         (default all)
+  -s, --sizes LIST       message sizes in bytes, with a K, M or G suffix for KiB, MiB
+                         and GiB, up to 1G (default 0,64,1K,16K)
+  -r, --repetitions N    measured repetitions (default {repetitions})
+  -w, --warmup N         unmeasured runs before them (default {warmup})
+      --min-time MS      shortest repetition in milliseconds (default {min_time})
+      --cpu-ghz F        clock rate for the estimated cycles per byte (default: the
+                         current 'cpu MHz' of /proc/cpuinfo, if any)
+      --json FILE        also write the results as JSON to FILE; with -, print them
+                         instead of the table
+  -h, --help             print this help
+",
+        backends = backends.join(", "),
+        frontends = frontends.join(", "),
+        repetitions = BenchOptions::default().repetitions,
+        warmup = BenchOptions::default().warmup,
+        min_time = BenchOptions::default().min_repetition_time.as_millis())
+}
+
+
+#[derive(Debug, PartialEq)]
+pub struct BenchCommand {
+    pub backends: Vec<Backend>,
+    pub frontends: Vec<Frontend>,
+    pub sizes: Vec<u64>,
+    pub options: BenchOptions,
+    pub cpu_ghz: Option<f64>,
+    pub json: Option<PathBuf>,
+}
+
+impl BenchCommand {
+    /// Parses the arguments after `bench`; None if help was requested.
+    pub fn parse(parser: &mut ArgParser) -> Result<Option<BenchCommand>, UsageError> {
+        let mut backends = Backend::ALL.to_vec();
+        let mut frontends = Frontend::ALL.to_vec();
+        let mut sizes = DEFAULT_SIZES.to_vec();
+        let mut options = BenchOptions::default();
+        let mut cpu_ghz = None;
+        let mut json = None;
+        while let Some(arg) = parser.next_arg()? {
+            match &arg {
+                Arg::Short('h') => return Ok(None),
+                Arg::Long(name) if name == "help" => return Ok(None),
+                Arg::Short('b') => backends = parse_list(&parser.value(&arg)?, "backend", Backend::from_name)?,
+                Arg::Long(name) if name == "backend" => backends = parse_list(&parser.value(&arg)?, "backend", Backend::from_name)?,
+                Arg::Short('f') => frontends = parse_list(&parser.value(&arg)?, "front-end", Frontend::from_name)?,
+                Arg::Long(name) if name == "frontend" => frontends = parse_list(&parser.value(&arg)?, "front-end", Frontend::from_name)?,
+                Arg::Short('s') => sizes = parse_list(&parser.value(&arg)?, "size", parse_size)?,
+                Arg::Long(name) if name == "sizes" => sizes = parse_list(&parser.value(&arg)?, "size", parse_size)?,
+                Arg::Short('r') => options.repetitions = parser.value_usize(&arg)?,
+                Arg::Long(name) if name == "repetitions" => options.repetitions = parser.value_usize(&arg)?,
+                Arg::Short('w') => options.warmup = parser.value_usize(&arg)?,
+                Arg::Long(name) if name == "warmup" => options.warmup = parser.value_usize(&arg)?,
+                Arg::Long(name) if name == "min-time" => options.min_repetition_time = Duration::from_millis(parser.value_usize(&arg)? as u64),
+                Arg::Long(name) if name == "cpu-ghz" => {
+                    let value = parser.value(&arg)?;
+                    let ghz = value.parse::<f64>().ok().filter(|ghz| ghz.is_finite() && *ghz > 0.0)
+                        .ok_or_else(|| UsageError(format!("invalid clock rate '{value}', expected a positive number of GHz")))?;
+                    cpu_ghz = Some(ghz);
+                }
+                Arg::Long(name) if name == "json" => json = Some(PathBuf::from(parser.value(&arg)?)),
+                _ => return Err(UsageError(format!("unknown option '{arg}'"))),
+            }
+        }
+        if options.repetitions == 0 {
+            return Err(UsageError("--repetitions must be at least 1".to_string()));
+        }
+        Ok(Some(BenchCommand { backends, frontends, sizes, options, cpu_ghz, json }))
+    }
+
+    /// The cases to measure; the permutation does not depend on the message size, and the
+    /// batches skip the sizes above their limit.
+    fn cases(&self) -> Vec<(Backend, Frontend, u64)> {
+        let mut cases = Vec::new();
+        for &backend in &self.backends {
+            for &frontend in &self.frontends {
+                match frontend {
+                    Frontend::Permutation => cases.push((backend, frontend, 0)),
+                    _ => cases.extend(self.sizes.iter()
+                        .filter(|&&size| size <= frontend.max_message_len())
+                        .map(|&size| (backend, frontend, size))),
+                }
+            }
+        }
+        cases
+    }
+}
+
+/// Parses a comma-separated list of `what`s.
+fn parse_list<T>(list: &str, what: &str, parse: impl Fn(&str) -> Option<T>) -> Result<Vec<T>, UsageError> {
+    list.split(',')
+        .map(|item| parse(item).ok_or_else(|| UsageError(format!("invalid {what} '{item}'"))))
+        .collect()
+}
+
+/// Parses a size such as "64", "1K" or "1G", up to `MAX_MESSAGE_LEN`.
+fn parse_size(size: &str) -> Option<u64> {
+    let (digits, unit) = match SIZE_UNITS.iter().find(|(suffix, _)| size.ends_with(*suffix)) {
+        Some(&(_, unit)) => (&size[..size.len() - 1], unit),
+        None => (size, 1),
+    };
+    let len = digits.parse::<u64>().ok()?.checked_mul(unit)?;
+    (len <= MAX_MESSAGE_LEN).then_some(len)
+}
+
+/// Formats a size with the largest unit that divides it, as accepted by `--sizes`.
+fn format_size(len: u64) -> String {
+    match SIZE_UNITS.iter().rev().find(|(_, unit)| len > 0 && len.is_multiple_of(*unit)) {
+        Some((suffix, unit)) => format!("{}{suffix}", len / unit),
+        None => len.to_string(),
+    }
+}
+
+/// The current clock rate of the first CPU in /proc/cpuinfo, where it exists.
+fn cpu_ghz_from_cpuinfo() -> Option<f64> {
+    let cpuinfo = fs::read_to_string("/proc/cpuinfo").ok()?;
+    cpuinfo.lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(key, _)| key.trim() == "cpu MHz")
+        .and_then(|(_, mhz)| mhz.trim().parse::<f64>().ok())
+        .map(|mhz| mhz / 1000.0)
+}
+
+
+const TABLE_HEADER: &str = "BACKEND    FRONTEND          SIZE       MEDIAN          P90     MB/S    PERM/S  ~CYCLES/B";
+
+fn write_row(result: &BenchResult, cpu_ghz: Option<f64>, out: &mut impl Write) -> io::Result<()> {
+    let size = match result.frontend {
+        Frontend::Permutation => "-".to_string(),
+        Frontend::Batch(_) => format!("{BATCH_LEN}x{}", format_size(result.message_len)),
+        _ => format_size(result.message_len),
+    };
+    let cycles_per_byte = cpu_ghz
+        .and_then(|ghz| result.estimated_cycles_per_byte(ghz * 1e9))
+        .map_or("-".to_string(), |cycles| format!("{cycles:.1}"));
+    writeln!(out, "{:<10} {:<14} {size:>7} {:>12} {:>12} {:>8.3} {:>9.0} {cycles_per_byte:>10}",
+        result.backend.name(), result.frontend.name(),
+        format!("{:.3?}", result.stats.median), format!("{:.3?}", result.stats.p90),
+        result.bytes_per_second() / 1e6, result.permutations_per_second())
+}
+
+fn to_json(command: &BenchCommand, results: &[BenchResult], cpu_ghz: Option<f64>) -> JsonValue {
+    JsonValue::Object(vec![
+        ("warmup".to_string(), command.options.warmup.into()),
+        ("repetitions".to_string(), command.options.repetitions.into()),
+        ("min_repetition_ns".to_string(), JsonValue::Number(command.options.min_repetition_time.as_nanos() as f64)),
+        ("cpu_ghz".to_string(), cpu_ghz.map_or(JsonValue::Null, JsonValue::Number)),
+        ("results".to_string(), JsonValue::Array(results.iter().map(|result| {
+            let mut json = result.to_json();
+            if let JsonValue::Object(members) = &mut json {
+                // from the time and the clock rate, not from a cycle counter
+                let cycles_per_byte = cpu_ghz.and_then(|ghz| result.estimated_cycles_per_byte(ghz * 1e9));
+                members.push(("estimated_cycles_per_byte".to_string(), cycles_per_byte.map_or(JsonValue::Null, JsonValue::Number)));
+            }
+            json
+        }).collect())),
+    ])
+}
+
+pub fn run(command: &BenchCommand, out: &mut impl Write, err: &mut impl Write) -> io::Result<i32> {
+    let cpu_ghz = command.cpu_ghz.or_else(cpu_ghz_from_cpuinfo);
+    let json_to_stdout = command.json.as_ref().is_some_and(|json| json.as_os_str() == "-");
+    if !json_to_stdout {
+        writeln!(out, "{TABLE_HEADER}")?;
+    }
+    let mut results = Vec::new();
+    for (backend, frontend, size) in command.cases() {
+        let result = bench(backend, frontend, size, &command.options);
+        if !json_to_stdout {
+            write_row(&result, cpu_ghz, out)?;
+            // a row per case, as soon as it is measured
+            out.flush()?;
+        }
+        results.push(result);
+    }
+
+    let Some(json_path) = &command.json else { return Ok(EXIT_SUCCESS) };
+    let json = to_json(command, &results, cpu_ghz).to_pretty_string();
+    if json_to_stdout {
+        writeln!(out, "{json}")?;
+    } else if let Err(error) = fs::write(json_path, json + "\n") {
+        writeln!(err, "{PROGRAM}: {}: {error}", json_path.display())?;
+        return Ok(EXIT_FAILURE);
+    }
+    Ok(EXIT_SUCCESS)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+
+    use crypto_playground::sha3::json::parse_json;
+    use crypto_playground::sha3::types::Sha3Variant;
+    use crypto_playground::sha3::types::ShakeVariant;
+
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<BenchCommand>, UsageError> {
+        BenchCommand::parse(&mut ArgParser::new(args.iter().map(OsString::from)))
+    }
+
+    fn run_args(args: &[&str]) -> (i32, String, String) {
+        let command = parse(args).unwrap().unwrap();
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let exit_code = run(&command, &mut out, &mut err).unwrap();
+        (exit_code, String::from_utf8(out).unwrap(), String::from_utf8(err).unwrap())
+    }
+
+    #[test]
+    fn test_parse() {
+        let command = parse(&["-b", "reference", "-f", "sha3-256,kmac128", "-s", "0,1K,2M,1G", "-r", "7", "-w", "0",
+            "--min-time", "5", "--cpu-ghz", "2.5", "--json", "out.json"]).unwrap().unwrap();
+        assert_eq!(BenchCommand {
+            backends: vec![Backend::Reference],
+            frontends: vec![Frontend::Sha3(Sha3Variant::SHA3_256), Frontend::Kmac(ShakeVariant::SHAKE128)],
+            sizes: vec![0, 1024, 2 << 20, 1 << 30],
+            options: BenchOptions { warmup: 0, repetitions: 7, min_repetition_time: Duration::from_millis(5) },
+            cpu_ghz: Some(2.5),
+            json: Some(PathBuf::from("out.json")),
+        }, command);
+        let command = parse(&[]).unwrap().unwrap();
+        assert_eq!(Frontend::ALL.to_vec(), command.frontends);
+        assert_eq!(DEFAULT_SIZES.to_vec(), command.sizes);
+        assert_eq!(1 + 12 * DEFAULT_SIZES.len(), command.cases().len());
+        let command = parse(&["-f", "sha3-256,batch-sha3-256", "-s", "1K,64K,1M"]).unwrap().unwrap();
+        assert_eq!(3 + 2, command.cases().len());
+
+        assert!(parse(&["-h"]).unwrap().is_none());
+        assert!(parse(&["-f", "md5"]).is_err());
+        assert!(parse(&["-b", "avx2"]).is_err());
+        assert!(parse(&["-s", "2G"]).is_err());
+        assert!(parse(&["-s", "1K,"]).is_err());
+        assert!(parse(&["-r", "0"]).is_err());
+        assert!(parse(&["--cpu-ghz", "-1"]).is_err());
+        assert!(parse(&["sha3-256"]).is_err());
+    }
+
+    #[test]
+    fn test_sizes() {
+        assert_eq!(Some(0), parse_size("0"));
+        assert_eq!(Some(64), parse_size("64"));
+        assert_eq!(Some(16 << 10), parse_size("16K"));
+        assert_eq!(Some(MAX_MESSAGE_LEN), parse_size("1G"));
+        assert_eq!(None, parse_size("K"));
+        assert_eq!(None, parse_size("1T"));
+        for size in ["0", "100", "1K", "1536", "64M", "1G"] {
+            assert_eq!(size, format_size(parse_size(size).unwrap()));
+        }
+    }
+
+    #[test]
+    fn test_table_and_json() {
+        let (exit_code, out, err) = run_args(&["-f", "permutation,sha3-256", "-s", "0,200", "-r", "3", "--min-time", "0", "--cpu-ghz", "1"]);
+        assert_eq!(EXIT_SUCCESS, exit_code);
+        assert!(err.is_empty());
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(TABLE_HEADER, lines[0]);
+        assert_eq!(4, lines.len(), "{out}");
+        assert!(lines[1].starts_with("reference  permutation          - "), "{out}");
+        assert!(lines[2].starts_with("reference  sha3-256             0 "), "{out}");
+        assert!(lines[2].ends_with(" -"), "{out}");
+        assert!(lines[3].starts_with("reference  sha3-256           200 "), "{out}");
+
+        let (_, out, _) = run_args(&["-f", "batch-sha3-256", "-s", "64", "-r", "1", "--min-time", "0"]);
+        assert!(out.lines().nth(1).unwrap().starts_with("reference  batch-sha3-256   16x64 "), "{out}");
+
+        let (_, out, _) = run_args(&["-f", "shake128", "-s", "64", "-r", "1", "--min-time", "0", "--cpu-ghz", "2", "--json", "-"]);
+        let json = parse_json(&out).unwrap();
+        assert_eq!(Some(1), json.get("repetitions").and_then(JsonValue::as_u64));
+        let results = json.get("results").and_then(JsonValue::as_array).unwrap();
+        assert_eq!(1, results.len());
+        assert_eq!(Some("shake128"), results[0].get("frontend").and_then(JsonValue::as_str));
+        assert_eq!(Some(64), results[0].get("message_bytes").and_then(JsonValue::as_u64));
+        assert!(matches!(results[0].get("estimated_cycles_per_byte"), Some(JsonValue::Number(_))));
+    }
+}
@@ -0,0 +1,442 @@
+//! `--check`: verifies the files listed in manifests of GNU or BSD-tag lines, like
+//! `sha3sum -c`. Lines with a tag name their algorithm; untagged lines use the algorithm
+//! given with `-a`, or the SHA3 variant matching their digest length. Digests may be hex
+//! or base64, and SHAKE digests can have any length.
+
+use std::ffi::OsString;
+use std::fs::File;
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Read;
+use std::io::Write;
+
+use crypto_playground::sha3::registry::Algorithm;
+use crypto_playground::sha3::secret::ct_eq;
+use crypto_playground::sha3::types::Sha3Variant;
+
+use crate::cli::EXIT_MALFORMED;
+use crate::cli::EXIT_MISMATCH;
+use crate::cli::EXIT_MISSING;
+use crate::cli::EXIT_SUCCESS;
+use crate::cli::PROGRAM;
+use crate::cli::algorithm::is_hash;
+use crate::cli::format::base64_decode;
+use crate::cli::format::escape_name;
+use crate::cli::format::unescape_name;
+use crate::cli::sum::SumOptions;
+use crate::cli::sum::hash_file;
+
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CheckOptions {
+    /// Don't print OK for verified files.
+    pub quiet: bool,
+    /// Print nothing; only the exit code tells the result.
+    pub status: bool,
+    /// Exit with `EXIT_MALFORMED` if any line is improperly formatted.
+    pub strict: bool,
+    /// Warn about every improperly formatted line.
+    pub warn: bool,
+    /// Skip listed files that do not exist.
+    pub ignore_missing: bool,
+}
+
+
+/// One well-formed manifest line.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub algorithm: Algorithm,
+    pub digest: Vec<u8>,
+    pub name: String,
+}
+
+/// Parses a manifest line without its line ending; None if it is improperly formatted.
+/// `algorithm` and `output_bits` are the values of `-a` and `-l`, if given.
+pub fn parse_line(line: &str, algorithm: Option<Algorithm>, output_bits: Option<usize>) -> Option<Entry> {
+    let (is_escaped, line) = match line.strip_prefix('\\') {
+        Some(line) => (true, line),
+        None => (false, line),
+    };
+    let (tag, name, digest) = split_bsd_line(line).or_else(|| split_gnu_line(line))?;
+    let name = if is_escaped { unescape_name(name)? } else { name.to_string() };
+    let digest = decode_digest(digest)?;
+    let algorithm = match (tag, algorithm) {
+        (Some(tag), Some(algorithm)) if algorithm.name() != tag => return None,
+        (Some(tag), _) => Algorithm::ALL.into_iter().find(|algorithm| is_hash(algorithm) && algorithm.name() == tag)?,
+        (None, Some(algorithm)) => algorithm,
+        (None, None) => sha3_by_digest_len(digest.len())?,
+    };
+    let is_valid_len = match (algorithm.is_xof(), output_bits) {
+        (true, Some(bits)) => 8 * digest.len() == bits,
+        (true, None) => !digest.is_empty(),
+        (false, _) => 8 * digest.len() == algorithm.output_len(),
+    };
+    is_valid_len.then_some(Entry { algorithm, digest, name })
+}
+
+/// Splits `TAG (name) = digest`; the name ends at the last ") = ".
+fn split_bsd_line(line: &str) -> Option<(Option<&str>, &str, &str)> {
+    let (tag, rest) = line.split_once(" (")?;
+    let (name, digest) = rest.rsplit_once(") = ")?;
+    Some((Some(tag), name, digest))
+}
+
+/// Splits `digest  name`, or `digest *name` with the binary-mode marker.
+fn split_gnu_line(line: &str) -> Option<(Option<&str>, &str, &str)> {
+    let (digest, rest) = line.split_once(' ')?;
+    let name = rest.strip_prefix([' ', '*'])?;
+    if name.is_empty() {
+        return None;
+    }
+    Some((None, name, digest))
+}
+
+fn decode_digest(digest: &str) -> Option<Vec<u8>> {
+    if digest.len().is_multiple_of(2) && digest.bytes().all(|c| c.is_ascii_hexdigit()) {
+        return hex::decode(digest).ok();
+    }
+    base64_decode(digest)
+}
+
+fn sha3_by_digest_len(len: usize) -> Option<Algorithm> {
+    [Sha3Variant::SHA3_224, Sha3Variant::SHA3_256, Sha3Variant::SHA3_384, Sha3Variant::SHA3_512]
+        .into_iter()
+        .find(|variant| variant.output_len() == 8 * len)
+        .map(Algorithm::Sha3)
+}
+
+
+/// Outcome of the lines of one or more manifests.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Tally {
+    verified: usize,
+    mismatched: usize,
+    unreadable: usize,
+    /// Missing files skipped with `--ignore-missing`.
+    ignored: usize,
+    malformed: usize,
+    /// Manifests that could not be read or, with `--ignore-missing`, where no file was found.
+    missing_manifests: usize,
+    /// Manifests without a well-formed line.
+    improper_manifests: usize,
+}
+
+impl Tally {
+    fn add(&mut self, other: &Tally) {
+        self.verified += other.verified;
+        self.mismatched += other.mismatched;
+        self.unreadable += other.unreadable;
+        self.ignored += other.ignored;
+        self.malformed += other.malformed;
+        self.missing_manifests += other.missing_manifests;
+        self.improper_manifests += other.improper_manifests;
+    }
+
+    fn exit_code(&self, strict: bool) -> i32 {
+        if self.mismatched > 0 {
+            EXIT_MISMATCH
+        } else if self.unreadable > 0 || self.missing_manifests > 0 {
+            EXIT_MISSING
+        } else if self.improper_manifests > 0 || (strict && self.malformed > 0) {
+            EXIT_MALFORMED
+        } else {
+            EXIT_SUCCESS
+        }
+    }
+}
+
+/// Checks every manifest of `options.files`, printing a line per listed file to `out`
+/// and problems to `err`, and returns the exit code:
+///
+/// - `EXIT_MISMATCH` if a digest did not match,
+/// - otherwise `EXIT_MISSING` if a listed file or a manifest could not be read,
+/// - otherwise `EXIT_MALFORMED` if a manifest had no well-formed line, or with `--strict`,
+///   if any line was improperly formatted.
+pub fn run(options: &SumOptions, out: &mut impl Write, err: &mut impl Write) -> io::Result<i32> {
+    let check = options.check.expect("run in check mode");
+    let mut total = Tally::default();
+    for manifest in &options.files {
+        total.add(&check_manifest(options, &check, manifest, out, err)?);
+    }
+    Ok(total.exit_code(check.strict))
+}
+
+fn check_manifest(
+    options: &SumOptions,
+    check: &CheckOptions,
+    manifest: &OsString,
+    out: &mut impl Write,
+    err: &mut impl Write,
+) -> io::Result<Tally> {
+    let manifest_name = manifest.to_string_lossy();
+    let mut tally = Tally::default();
+    let reader: io::Result<Box<dyn Read>> = if manifest == "-" {
+        Ok(Box::new(io::stdin().lock()))
+    } else {
+        File::open(manifest).map(|file| Box::new(file) as Box<dyn Read>)
+    };
+    let mut reader = match reader {
+        Ok(reader) => BufReader::new(reader),
+        Err(error) => {
+            if !check.status {
+                writeln!(err, "{PROGRAM}: {manifest_name}: {error}")?;
+            }
+            tally.missing_manifests += 1;
+            return Ok(tally);
+        }
+    };
+
+    let mut line = Vec::new();
+    let mut line_number = 0;
+    loop {
+        line.clear();
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(error) => {
+                if !check.status {
+                    writeln!(err, "{PROGRAM}: {manifest_name}: {error}")?;
+                }
+                tally.missing_manifests += 1;
+                return Ok(tally);
+            }
+        }
+        line_number += 1;
+        let text = line.strip_suffix(b"\n").unwrap_or(&line);
+        let text = text.strip_suffix(b"\r").unwrap_or(text);
+        if text.is_empty() || text.starts_with(b"#") {
+            continue;
+        }
+        let entry = str::from_utf8(text).ok().and_then(|text| parse_line(text, options.algorithm, options.output_bits));
+        let Some(entry) = entry else {
+            tally.malformed += 1;
+            if check.warn && !check.status {
+                writeln!(err, "{PROGRAM}: {manifest_name}: {line_number}: improperly formatted checksum line")?;
+            }
+            continue;
+        };
+        check_entry(&entry, check, &mut tally, out, err)?;
+    }
+
+    let found = tally.verified + tally.mismatched + tally.unreadable;
+    if found + tally.ignored == 0 {
+        tally.improper_manifests += 1;
+        if !check.status {
+            writeln!(err, "{PROGRAM}: {manifest_name}: no properly formatted checksum lines found")?;
+        }
+    } else if found == 0 {
+        tally.missing_manifests += 1;
+        if !check.status {
+            writeln!(err, "{PROGRAM}: {manifest_name}: no file was verified")?;
+        }
+    }
+    if !check.status {
+        warn_count(err, tally.malformed, "line is improperly formatted", "lines are improperly formatted")?;
+        warn_count(err, tally.unreadable, "listed file could not be read", "listed files could not be read")?;
+        warn_count(err, tally.mismatched, "computed checksum did NOT match", "computed checksums did NOT match")?;
+    }
+    Ok(tally)
+}
+
+fn check_entry(entry: &Entry, check: &CheckOptions, tally: &mut Tally, out: &mut impl Write, err: &mut impl Write) -> io::Result<()> {
+    let (is_escaped, escaped_name) = escape_name(&entry.name);
+    let prefix = if is_escaped { "\\" } else { "" };
+    let output_bits = 8 * entry.digest.len();
+    match hash_file(entry.algorithm, output_bits, &OsString::from(&entry.name)) {
+        Ok(digest) if ct_eq(&digest, &entry.digest) => {
+            tally.verified += 1;
+            if !check.quiet && !check.status {
+                writeln!(out, "{prefix}{escaped_name}: OK")?;
+            }
+        }
+        Ok(_) => {
+            tally.mismatched += 1;
+            if !check.status {
+                writeln!(out, "{prefix}{escaped_name}: FAILED")?;
+            }
+        }
+        Err(error) if check.ignore_missing && error.kind() == io::ErrorKind::NotFound => tally.ignored += 1,
+        Err(error) => {
+            tally.unreadable += 1;
+            if !check.status {
+                writeln!(err, "{PROGRAM}: {escaped_name}: {error}")?;
+                writeln!(out, "{prefix}{escaped_name}: FAILED open or read")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn warn_count(err: &mut impl Write, count: usize, singular: &str, plural: &str) -> io::Result<()> {
+    match count {
+        0 => Ok(()),
+        1 => writeln!(err, "{PROGRAM}: WARNING: 1 {singular}"),
+        _ => writeln!(err, "{PROGRAM}: WARNING: {count} {plural}"),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    use crypto_playground::sha3::types::ShakeVariant;
+
+    use crate::cli::args::ArgParser;
+    use crate::cli::format::base64_encode;
+
+    use super::*;
+
+    const TEST_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/test_file.txt");
+    const MISSING_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/missing.txt");
+
+    // digests of test/test_file.txt, whatever the working directory
+    fn digest(algorithm: Algorithm, bits: usize) -> Vec<u8> {
+        hash_file(algorithm, bits, &OsString::from(TEST_FILE)).unwrap()
+    }
+
+    fn sha3_256() -> Algorithm {
+        Algorithm::Sha3(Sha3Variant::SHA3_256)
+    }
+
+    /// Writes `manifest` to a file of its own and runs the check with `args`.
+    fn run_check(test_name: &str, manifest: &str, args: &[&str]) -> (i32, String, String) {
+        let path: PathBuf = env::temp_dir().join(format!("crypto-playground-{}-{test_name}.sums", std::process::id()));
+        fs::write(&path, manifest).unwrap();
+        let mut all_args: Vec<OsString> = args.iter().map(OsString::from).collect();
+        all_args.insert(0, OsString::from("-c"));
+        all_args.push(path.clone().into_os_string());
+        let options = SumOptions::parse(&mut ArgParser::new(all_args)).unwrap().unwrap();
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let exit_code = run(&options, &mut out, &mut err).unwrap();
+        fs::remove_file(&path).unwrap();
+        (exit_code, String::from_utf8(out).unwrap(), String::from_utf8(err).unwrap())
+    }
+
+    #[test]
+    fn test_parse_line() {
+        let digest = hex::encode([0xabu8; 32]);
+        let entry = |algorithm, name: &str| Some(Entry { algorithm, digest: vec![0xab; 32], name: name.to_string() });
+
+        assert_eq!(entry(sha3_256(), "file"), parse_line(&format!("{digest}  file"), None, None));
+        assert_eq!(entry(sha3_256(), "a b"), parse_line(&format!("{digest} *a b"), None, None));
+        assert_eq!(entry(sha3_256(), "file"), parse_line(&format!("SHA3-256 (file) = {digest}"), None, None));
+        assert_eq!(entry(sha3_256(), "x) = y"), parse_line(&format!("SHA3-256 (x) = y) = {digest}"), None, None));
+        assert_eq!(entry(sha3_256(), "a\nb"), parse_line(&format!("\\{digest}  a\\nb"), None, None));
+        let keccak = Algorithm::Keccak(Sha3Variant::SHA3_256);
+        assert_eq!(entry(keccak, "f"), parse_line(&format!("Keccak-256 (f) = {digest}"), None, None));
+        assert_eq!(entry(keccak, "f"), parse_line(&format!("{digest}  f"), Some(keccak), None));
+        let shake = Algorithm::Shake(ShakeVariant::SHAKE128);
+        assert_eq!(entry(shake, "f"), parse_line(&format!("SHAKE128 (f) = {digest}"), None, None));
+        assert_eq!(entry(shake, "f"), parse_line(&format!("{digest}  f"), Some(shake), Some(256)));
+        let base64 = base64_encode(&[0xab; 32]);
+        assert_eq!(entry(sha3_256(), "f"), parse_line(&format!("{base64}  f"), None, None));
+
+        let malformed = [
+            format!("{digest} file"),
+            format!("{digest}  "),
+            format!("{}  file", &digest[1..]),
+            format!("{}  file", &digest[2..]),
+            format!("SHA3-512 (file) = {digest}"),
+            format!("SHA3-257 (file) = {digest}"),
+            format!("KMAC256 (file) = {digest}"),
+            format!("SHA3-256 (file)= {digest}"),
+            format!("\\{digest}  a\\tb"),
+            "abc".to_string(),
+        ];
+        for line in malformed {
+            assert_eq!(None, parse_line(&line, None, None), "{line}");
+        }
+        assert_eq!(None, parse_line(&format!("SHA3-256 (f) = {digest}"), Some(keccak), None));
+        assert_eq!(None, parse_line(&format!("{digest}  f"), Some(shake), Some(512)));
+    }
+
+    #[test]
+    fn test_all_ok() {
+        let shake = Algorithm::Shake(ShakeVariant::SHAKE256);
+        let manifest = format!(
+            "# release checksums\n{}  {TEST_FILE}\nSHAKE256 ({TEST_FILE}) = {}\r\n\nKeccak-256 ({TEST_FILE}) = {}\n",
+            hex::encode(digest(sha3_256(), 256)),
+            base64_encode(&digest(shake, 1000)),
+            hex::encode(digest(Algorithm::Keccak(Sha3Variant::SHA3_256), 256)),
+        );
+        let (exit_code, out, err) = run_check("ok", &manifest, &[]);
+        assert_eq!(EXIT_SUCCESS, exit_code);
+        assert_eq!(format!("{TEST_FILE}: OK\n").repeat(3), out);
+        assert_eq!("", err);
+
+        let (exit_code, out, _) = run_check("quiet", &manifest, &["--quiet"]);
+        assert_eq!((EXIT_SUCCESS, ""), (exit_code, out.as_str()));
+    }
+
+    #[test]
+    fn test_mismatch() {
+        let mut wrong = digest(sha3_256(), 256);
+        wrong[31] ^= 1;
+        let manifest = format!(
+            "{}  {TEST_FILE}\n{}  {MISSING_FILE}\nnot a checksum line\n",
+            hex::encode(&wrong),
+            hex::encode(digest(sha3_256(), 256)),
+        );
+        let (exit_code, out, err) = run_check("mismatch", &manifest, &["-w"]);
+        assert_eq!(EXIT_MISMATCH, exit_code);
+        assert_eq!(format!("{TEST_FILE}: FAILED\n{MISSING_FILE}: FAILED open or read\n"), out);
+        let err: Vec<&str> = err.lines().collect();
+        assert_eq!(5, err.len(), "{err:?}");
+        assert!(err[0].starts_with(&format!("crypto-playground: {MISSING_FILE}: ")));
+        assert!(err[1].ends_with(": 3: improperly formatted checksum line"));
+        assert_eq!("crypto-playground: WARNING: 1 line is improperly formatted", err[2]);
+        assert_eq!("crypto-playground: WARNING: 1 listed file could not be read", err[3]);
+        assert_eq!("crypto-playground: WARNING: 1 computed checksum did NOT match", err[4]);
+
+        let (exit_code, out, err) = run_check("status", &manifest, &["--status"]);
+        assert_eq!((EXIT_MISMATCH, "", ""), (exit_code, out.as_str(), err.as_str()));
+    }
+
+    #[test]
+    fn test_missing_files() {
+        let ok = format!("{}  {TEST_FILE}\n", hex::encode(digest(sha3_256(), 256)));
+        let missing = format!("{}  {MISSING_FILE}\n", hex::encode(digest(sha3_256(), 256)));
+
+        let (exit_code, _, _) = run_check("missing", &format!("{ok}{missing}"), &[]);
+        assert_eq!(EXIT_MISSING, exit_code);
+
+        let (exit_code, out, err) = run_check("ignore-missing", &format!("{ok}{missing}"), &["--ignore-missing"]);
+        assert_eq!((EXIT_SUCCESS, format!("{TEST_FILE}: OK\n").as_str(), ""), (exit_code, out.as_str(), err.as_str()));
+
+        let (exit_code, _, err) = run_check("nothing-verified", &missing, &["--ignore-missing"]);
+        assert_eq!(EXIT_MISSING, exit_code);
+        assert!(err.ends_with(": no file was verified\n"), "{err}");
+    }
+
+    #[test]
+    fn test_malformed_lines() {
+        let ok = format!("{}  {TEST_FILE}\n", hex::encode(digest(sha3_256(), 256)));
+
+        let (exit_code, _, err) = run_check("lenient", &format!("{ok}garbage\n"), &[]);
+        assert_eq!(EXIT_SUCCESS, exit_code);
+        assert_eq!("crypto-playground: WARNING: 1 line is improperly formatted\n", err);
+
+        let (exit_code, _, _) = run_check("strict", &format!("{ok}garbage\n"), &["--strict"]);
+        assert_eq!(EXIT_MALFORMED, exit_code);
+
+        let (exit_code, _, err) = run_check("only-garbage", "garbage\nmore garbage\n", &[]);
+        assert_eq!(EXIT_MALFORMED, exit_code);
+        assert!(err.contains(": no properly formatted checksum lines found\n"), "{err}");
+    }
+
+    #[test]
+    fn test_exit_code_precedence() {
+        let tally = Tally { mismatched: 1, unreadable: 1, malformed: 1, improper_manifests: 1, ..Tally::default() };
+        assert_eq!(EXIT_MISMATCH, tally.exit_code(true));
+        let tally = Tally { missing_manifests: 1, improper_manifests: 1, ..Tally::default() };
+        assert_eq!(EXIT_MISSING, tally.exit_code(false));
+        let tally = Tally { verified: 1, malformed: 1, ..Tally::default() };
+        assert_eq!(EXIT_SUCCESS, tally.exit_code(false));
+        assert_eq!(EXIT_MALFORMED, tally.exit_code(true));
+    }
+}
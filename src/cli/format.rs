@@ -0,0 +1,154 @@
+//! Checksum lines as written by GNU coreutils: `digest  name`, or `TAG (name) = digest`
+//! with `--tag`. Names containing a backslash, CR or LF are escaped and the line then
+//! starts with a backslash.
+
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DigestEncoding {
+    Hex,
+    Base64,
+    /// The digest bytes alone, without a name or a newline.
+    Raw,
+}
+
+impl DigestEncoding {
+    pub fn encode(&self, digest: &[u8]) -> Vec<u8> {
+        match self {
+            DigestEncoding::Hex => hex::encode(digest).into_bytes(),
+            DigestEncoding::Base64 => base64_encode(digest).into_bytes(),
+            DigestEncoding::Raw => digest.to_vec(),
+        }
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineFormat {
+    Gnu,
+    Bsd,
+}
+
+/// Formats the line for `name`, including the newline, with a digest already encoded.
+pub fn format_line(format: LineFormat, tag: &str, name: &str, digest: &str) -> String {
+    let (escaped, name) = escape_name(name);
+    let prefix = if escaped { "\\" } else { "" };
+    match format {
+        LineFormat::Gnu => format!("{prefix}{digest}  {name}\n"),
+        LineFormat::Bsd => format!("{prefix}{tag} ({name}) = {digest}\n"),
+    }
+}
+
+/// Returns whether the name needed escaping, and the escaped name.
+pub fn escape_name(name: &str) -> (bool, String) {
+    if !name.contains(['\\', '\n', '\r']) {
+        return (false, name.to_string());
+    }
+    let escaped = name.replace('\\', "\\\\").replace('\n', "\\n").replace('\r', "\\r");
+    (true, escaped)
+}
+
+/// Reverses `escape_name`; None if the name has an unknown escape sequence.
+pub fn unescape_name(name: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(name.len());
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next()? {
+            '\\' => unescaped.push('\\'),
+            'n' => unescaped.push('\n'),
+            'r' => unescaped.push('\r'),
+            _ => return None,
+        }
+    }
+    Some(unescaped)
+}
+
+
+/// Standard base64 (RFC 4648, Sec. 4) with padding, as written by `cksum --base64`.
+pub fn base64_encode(data: &[u8]) -> String {
+    let mut text = String::with_capacity(4 * data.len().div_ceil(3));
+    for chunk in data.chunks(3) {
+        let mut block = [0u8; 3];
+        block[..chunk.len()].copy_from_slice(chunk);
+        let bits = u32::from(block[0]) << 16 | u32::from(block[1]) << 8 | u32::from(block[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                text.push(char::from(BASE64_ALPHABET[(bits >> (18 - 6 * i)) as usize & 0x3f]));
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+/// Decodes padded standard base64, rejecting non-zero padding bits.
+pub fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let text = text.as_bytes();
+    if !text.len().is_multiple_of(4) {
+        return None;
+    }
+    let mut data = Vec::with_capacity(3 * text.len() / 4);
+    for (i, block) in text.chunks(4).enumerate() {
+        let is_last = i == text.len() / 4 - 1;
+        let padding = block.iter().rev().take_while(|&&c| c == b'=').count();
+        if padding > 2 || (padding > 0 && !is_last) {
+            return None;
+        }
+        let mut bits = 0u32;
+        for &c in &block[..4 - padding] {
+            let value = BASE64_ALPHABET.iter().position(|&a| a == c)?;
+            bits = bits << 6 | value as u32;
+        }
+        bits <<= 6 * padding;
+        let bytes = [(bits >> 16) as u8, (bits >> 8) as u8, bits as u8];
+        let len = 3 - padding;
+        if bytes[len..].iter().any(|&byte| byte != 0) {
+            return None;
+        }
+        data.extend_from_slice(&bytes[..len]);
+    }
+    Some(data)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64() {
+        // RFC 4648, Sec. 10
+        let vectors = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+        for (data, text) in vectors {
+            assert_eq!(text, base64_encode(data.as_bytes()));
+            assert_eq!(Some(data.as_bytes().to_vec()), base64_decode(text));
+        }
+        for text in ["Zg", "Zh==", "Zg==Zg==", "Z===", "Zm9v!A=="] {
+            assert_eq!(None, base64_decode(text), "{text}");
+        }
+    }
+
+    #[test]
+    fn test_lines() {
+        assert_eq!("abcd  file\n", format_line(LineFormat::Gnu, "SHA3-256", "file", "abcd"));
+        assert_eq!("SHA3-256 (file) = abcd\n", format_line(LineFormat::Bsd, "SHA3-256", "file", "abcd"));
+        assert_eq!("\\abcd  a\\nb\\\\c\n", format_line(LineFormat::Gnu, "SHA3-256", "a\nb\\c", "abcd"));
+        assert_eq!(Some("a\nb\\c".to_string()), unescape_name("a\\nb\\\\c"));
+        assert_eq!(None, unescape_name("a\\tb"));
+        assert_eq!(None, unescape_name("a\\"));
+    }
+}
@@ -0,0 +1,117 @@
+//! JUnit XML reports, the format most test dashboards import.
+
+use std::io;
+use std::io::Write;
+use std::time::Duration;
+
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Passed,
+    Failed(String),
+    Skipped(String),
+    /// The test could not be run at all.
+    Error(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TestCase {
+    pub classname: String,
+    pub name: String,
+    pub outcome: Outcome,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TestSuite {
+    pub name: String,
+    pub time: Duration,
+    pub cases: Vec<TestCase>,
+}
+
+/// Number of (tests, failures, errors, skipped) cases.
+fn counts<'a>(cases: impl Iterator<Item = &'a TestCase>) -> (usize, usize, usize, usize) {
+    cases.fold((0, 0, 0, 0), |(tests, failures, errors, skipped), case| match case.outcome {
+        Outcome::Passed => (tests + 1, failures, errors, skipped),
+        Outcome::Failed(_) => (tests + 1, failures + 1, errors, skipped),
+        Outcome::Error(_) => (tests + 1, failures, errors + 1, skipped),
+        Outcome::Skipped(_) => (tests + 1, failures, errors, skipped + 1),
+    })
+}
+
+pub fn write_junit(name: &str, suites: &[TestSuite], out: &mut impl Write) -> io::Result<()> {
+    let (tests, failures, errors, skipped) = counts(suites.iter().flat_map(|suite| suite.cases.iter()));
+    let time: Duration = suites.iter().map(|suite| suite.time).sum();
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(out, r#"<testsuites name="{}" tests="{tests}" failures="{failures}" errors="{errors}" skipped="{skipped}" time="{:.3}">"#,
+        escape_xml(name), time.as_secs_f64())?;
+    for suite in suites {
+        let (tests, failures, errors, skipped) = counts(suite.cases.iter());
+        writeln!(out, r#"  <testsuite name="{}" tests="{tests}" failures="{failures}" errors="{errors}" skipped="{skipped}" time="{:.3}">"#,
+            escape_xml(&suite.name), suite.time.as_secs_f64())?;
+        for case in &suite.cases {
+            let open = format!(r#"    <testcase classname="{}" name="{}""#, escape_xml(&case.classname), escape_xml(&case.name));
+            match &case.outcome {
+                Outcome::Passed => writeln!(out, "{open}/>")?,
+                Outcome::Failed(message) => writeln!(out, "{open}>\n      <failure message=\"{}\"/>\n    </testcase>", escape_xml(message))?,
+                Outcome::Skipped(message) => writeln!(out, "{open}>\n      <skipped message=\"{}\"/>\n    </testcase>", escape_xml(message))?,
+                Outcome::Error(message) => writeln!(out, "{open}>\n      <error message=\"{}\"/>\n    </testcase>", escape_xml(message))?,
+            }
+        }
+        writeln!(out, "  </testsuite>")?;
+    }
+    writeln!(out, "</testsuites>")
+}
+
+/// Escapes text for use in an attribute value.
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' => escaped.push_str("&#10;"),
+            // other control characters are not allowed in XML 1.0
+            c if c.is_control() && c != '\t' => escaped.push('\u{fffd}'),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_junit() {
+        let suites = [TestSuite {
+            name: "a&b.rsp".to_string(),
+            time: Duration::from_millis(1500),
+            cases: vec![
+                TestCase { classname: "SHA3-256".to_string(), name: "ShortMsg".to_string(), outcome: Outcome::Passed },
+                TestCase { classname: "SHA3-256".to_string(), name: "Monte".to_string(), outcome: Outcome::Failed("COUNT = 1 \"diverged\"".to_string()) },
+                TestCase { classname: "SHA3-256".to_string(), name: "<bits>".to_string(), outcome: Outcome::Skipped("not supported".to_string()) },
+            ],
+        }];
+        let mut out = Vec::new();
+        write_junit("kat", &suites, &mut out).unwrap();
+        assert_eq!("\
+<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<testsuites name=\"kat\" tests=\"3\" failures=\"1\" errors=\"0\" skipped=\"1\" time=\"1.500\">
+  <testsuite name=\"a&amp;b.rsp\" tests=\"3\" failures=\"1\" errors=\"0\" skipped=\"1\" time=\"1.500\">
+    <testcase classname=\"SHA3-256\" name=\"ShortMsg\"/>
+    <testcase classname=\"SHA3-256\" name=\"Monte\">
+      <failure message=\"COUNT = 1 &quot;diverged&quot;\"/>
+    </testcase>
+    <testcase classname=\"SHA3-256\" name=\"&lt;bits&gt;\">
+      <skipped message=\"not supported\"/>
+    </testcase>
+  </testsuite>
+</testsuites>
+", String::from_utf8(out).unwrap());
+    }
+}
@@ -0,0 +1,402 @@
+//! `kat` command: runs NIST .rsp files and ACVP vector sets against the library.
+
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::Instant;
+
+use crypto_playground::sha3::acvp::AcvpOptions;
+use crypto_playground::sha3::acvp::AcvpReport;
+use crypto_playground::sha3::acvp::find_acvp_files;
+use crypto_playground::sha3::acvp::run_acvp_file;
+use crypto_playground::sha3::kat::FileReport;
+use crypto_playground::sha3::kat::KatOptions;
+use crypto_playground::sha3::kat::find_rsp_files;
+use crypto_playground::sha3::kat::run_rsp_file;
+use crypto_playground::sha3::monte::MONTE_CHECKPOINTS;
+
+use crate::cli::EXIT_FAILURE;
+use crate::cli::EXIT_SUCCESS;
+use crate::cli::PROGRAM;
+use crate::cli::args::Arg;
+use crate::cli::args::ArgParser;
+use crate::cli::args::UsageError;
+use crate::cli::junit::Outcome;
+use crate::cli::junit::TestCase;
+use crate::cli::junit::TestSuite;
+use crate::cli::junit::write_junit;
+
+
+pub fn usage() -> String {
+    format!("\
+Usage: {PROGRAM} kat [OPTION]... PATH...
+Run known-answer test files against the library and print a pass/fail table. A PATH is
+a NIST CAVP .rsp file, an ACVP prompt.json (with expectedResults.json next to it) or
+internalProjection.json, or a directory searched for them. The algorithm comes from the
+.rsp header or the ACVP algorithm field.
+
+      --monte-checkpoints N  check the first N Monte Carlo checkpoints, 1000 hashes
+                             each (default {MONTE_CHECKPOINTS})
+      --ldt-max-bits N       skip ACVP large data tests longer than N bits
+      --junit FILE           also write the results as JUnit XML to FILE
+  -h, --help                 print this help
+
+Exit status: 0 if every test passed, {EXIT_FAILURE} if a test failed or a file could not be run.
+")
+}
+
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct KatCommand {
+    pub paths: Vec<PathBuf>,
+    pub monte_checkpoints: usize,
+    pub ldt_max_bits: u64,
+    pub junit: Option<PathBuf>,
+}
+
+impl KatCommand {
+    /// Parses the arguments after `kat`; None if help was requested.
+    pub fn parse(parser: &mut ArgParser) -> Result<Option<KatCommand>, UsageError> {
+        let mut paths = Vec::new();
+        let mut monte_checkpoints = MONTE_CHECKPOINTS;
+        let mut ldt_max_bits = u64::MAX;
+        let mut junit = None;
+        while let Some(arg) = parser.next_arg()? {
+            match &arg {
+                Arg::Short('h') => return Ok(None),
+                Arg::Long(name) if name == "help" => return Ok(None),
+                Arg::Long(name) if name == "monte-checkpoints" => monte_checkpoints = parser.value_usize(&arg)?,
+                Arg::Long(name) if name == "ldt-max-bits" => ldt_max_bits = parser.value_usize(&arg)? as u64,
+                Arg::Long(name) if name == "junit" => junit = Some(PathBuf::from(parser.value(&arg)?)),
+                Arg::Operand(path) => paths.push(PathBuf::from(path)),
+                _ => return Err(UsageError(format!("unknown option '{arg}'"))),
+            }
+        }
+        if paths.is_empty() {
+            return Err(UsageError("kat needs at least one file or directory".to_string()));
+        }
+        Ok(Some(KatCommand { paths, monte_checkpoints, ldt_max_bits, junit }))
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum VectorFormat {
+    Rsp,
+    Acvp,
+}
+
+/// A vector file to run, or the error that kept a PATH from being searched.
+type VectorFile = (PathBuf, Result<VectorFormat, String>);
+
+/// Expands the operands into vector files, in order and without duplicates.
+fn vector_files(paths: &[PathBuf]) -> Vec<VectorFile> {
+    let mut files: Vec<VectorFile> = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            match find_rsp_files(path) {
+                Ok(found) => files.extend(found.into_iter().map(|path| (path, Ok(VectorFormat::Rsp)))),
+                Err(error) => files.push((path.clone(), Err(error.to_string()))),
+            }
+            match find_acvp_files(path) {
+                Ok(found) => files.extend(found.into_iter().map(|path| (path, Ok(VectorFormat::Acvp)))),
+                Err(error) => files.push((path.clone(), Err(error.to_string()))),
+            }
+        } else if path.file_name().is_some_and(|name| name == "expectedResults.json") {
+            files.push((path.with_file_name("prompt.json"), Ok(VectorFormat::Acvp)));
+        } else {
+            files.push((path.clone(), detect_format(path)));
+        }
+    }
+    let mut seen = Vec::new();
+    files.retain(|(path, _)| {
+        let is_new = !seen.contains(path);
+        seen.push(path.clone());
+        is_new
+    });
+    files
+}
+
+/// ACVP files are JSON, an object or an array of objects; anything else is taken as .rsp.
+fn detect_format(path: &Path) -> Result<VectorFormat, String> {
+    let content = fs::read(path).map_err(|error| format!("{}: {error}", path.display()))?;
+    let text = content.trim_ascii_start();
+    let is_json = text.starts_with(b"{") || text.strip_prefix(b"[").is_some_and(|rest| rest.trim_ascii_start().starts_with(b"{"));
+    Ok(if is_json { VectorFormat::Acvp } else { VectorFormat::Rsp })
+}
+
+
+/// Result of one vector file, as a row of the table and a JUnit test suite.
+struct FileOutcome {
+    path: PathBuf,
+    algorithm: String,
+    passed: usize,
+    failed: usize,
+    skipped: usize,
+    /// Why the file failed, or could not be run.
+    details: Vec<String>,
+    is_error: bool,
+    cases: Vec<TestCase>,
+    time: Duration,
+}
+
+impl FileOutcome {
+    fn error(path: PathBuf, message: String, time: Duration) -> FileOutcome {
+        // the errors of the kat and acvp modules start with the path, which the details show
+        // already
+        let message = match message.strip_prefix(&format!("{}: ", path.display())) {
+            Some(message) => message.to_string(),
+            None => message,
+        };
+        FileOutcome {
+            cases: vec![TestCase { classname: "kat".to_string(), name: path.display().to_string(), outcome: Outcome::Error(message.clone()) }],
+            path,
+            algorithm: "-".to_string(),
+            passed: 0,
+            failed: 0,
+            skipped: 0,
+            details: vec![message],
+            is_error: true,
+            time,
+        }
+    }
+
+    fn from_rsp(report: FileReport, time: Duration) -> FileOutcome {
+        let algorithm = report.algorithm.name();
+        let test_name = report.test_name.clone().unwrap_or_default();
+        let mut details = Vec::new();
+        if !report.failures.is_empty() {
+            details.push(format!("failing records at lines {:?}", report.failures));
+        }
+        if let Some(count) = report.monte_divergence {
+            details.push(format!("Monte Carlo test diverged at COUNT = {count}"));
+        }
+        let outcome = if !details.is_empty() {
+            Outcome::Failed(details.join(", "))
+        } else if report.passed == 0 {
+            Outcome::Skipped(format!("all {} records skipped", report.skipped))
+        } else {
+            Outcome::Passed
+        };
+        FileOutcome {
+            algorithm: format!("{algorithm} {test_name}").trim_end().to_string(),
+            cases: vec![TestCase { classname: algorithm.to_string(), name: test_name, outcome }],
+            path: report.path,
+            passed: report.passed,
+            failed: report.failed,
+            skipped: report.skipped,
+            details,
+            is_error: false,
+            time,
+        }
+    }
+
+    fn from_acvp(report: AcvpReport, time: Duration) -> FileOutcome {
+        let algorithm = report.algorithm.name();
+        let mut details = Vec::new();
+        let mut cases = Vec::new();
+        for group in &report.groups {
+            let name = format!("tgId {} ({})", group.tg_id, group.test_type);
+            let outcome = if !group.is_supported {
+                Outcome::Skipped(format!("{} tests not supported", group.test_type))
+            } else if !group.failures.is_empty() {
+                let message = format!("failing tcIds {:?}", group.failures);
+                details.push(format!("{name}: {message}"));
+                Outcome::Failed(message)
+            } else {
+                Outcome::Passed
+            };
+            cases.push(TestCase { classname: algorithm.to_string(), name, outcome });
+        }
+        FileOutcome {
+            algorithm: match &report.revision {
+                Some(revision) => format!("{algorithm} {revision}"),
+                None => algorithm.to_string(),
+            },
+            passed: report.passed(),
+            failed: report.failed(),
+            skipped: report.skipped(),
+            path: report.path,
+            details,
+            is_error: false,
+            cases,
+            time,
+        }
+    }
+
+    fn result(&self) -> &'static str {
+        if self.is_error {
+            "ERROR"
+        } else if self.failed > 0 {
+            "FAIL"
+        } else if self.passed == 0 {
+            // nothing was checked, e.g. only bit-oriented messages or unsupported test types
+            "skip"
+        } else {
+            "pass"
+        }
+    }
+}
+
+fn run_file(command: &KatCommand, path: PathBuf, format: Result<VectorFormat, String>) -> FileOutcome {
+    let start = Instant::now();
+    match format {
+        Ok(VectorFormat::Rsp) => match run_rsp_file(&path, &KatOptions { monte_checkpoints: command.monte_checkpoints }) {
+            Ok(report) => FileOutcome::from_rsp(report, start.elapsed()),
+            Err(error) => FileOutcome::error(path, error.to_string(), start.elapsed()),
+        },
+        Ok(VectorFormat::Acvp) => {
+            let options = AcvpOptions { monte_checkpoints: command.monte_checkpoints, ldt_max_len: command.ldt_max_bits };
+            match run_acvp_file(&path, &options) {
+                Ok(report) => FileOutcome::from_acvp(report, start.elapsed()),
+                Err(error) => FileOutcome::error(path, error.to_string(), start.elapsed()),
+            }
+        }
+        Err(message) => FileOutcome::error(path, message, start.elapsed()),
+    }
+}
+
+fn write_table(outcomes: &[FileOutcome], out: &mut impl Write) -> io::Result<()> {
+    let algorithm_width = outcomes.iter().map(|outcome| outcome.algorithm.len()).chain([9]).max().unwrap_or_default();
+    writeln!(out, "RESULT  PASSED  FAILED  SKIPPED  {:algorithm_width$}  FILE", "ALGORITHM")?;
+    for outcome in outcomes {
+        writeln!(out, "{:6}  {:>6}  {:>6}  {:>7}  {:algorithm_width$}  {}",
+            outcome.result(), outcome.passed, outcome.failed, outcome.skipped, outcome.algorithm, outcome.path.display())?;
+    }
+    for outcome in outcomes {
+        for detail in &outcome.details {
+            writeln!(out, "{}: {detail}", outcome.path.display())?;
+        }
+    }
+    let count = |result: &str| outcomes.iter().filter(|outcome| outcome.result() == result).count();
+    writeln!(out, "{} files: {} passed, {} failed, {} skipped, {} errors",
+        outcomes.len(), count("pass"), count("FAIL"), count("skip"), count("ERROR"))
+}
+
+pub fn run(command: &KatCommand, out: &mut impl Write, err: &mut impl Write) -> io::Result<i32> {
+    let files = vector_files(&command.paths);
+    if files.is_empty() {
+        writeln!(err, "{PROGRAM}: no .rsp files or ACVP vector sets found")?;
+        return Ok(EXIT_FAILURE);
+    }
+    let outcomes: Vec<FileOutcome> = files.into_iter().map(|(path, format)| run_file(command, path, format)).collect();
+    write_table(&outcomes, out)?;
+
+    if let Some(junit_path) = &command.junit {
+        let suites: Vec<TestSuite> = outcomes.iter().map(|outcome| TestSuite {
+            name: outcome.path.display().to_string(),
+            time: outcome.time,
+            cases: outcome.cases.clone(),
+        }).collect();
+        let written = File::create(junit_path).and_then(|file| {
+            let mut file = BufWriter::new(file);
+            write_junit(&format!("{PROGRAM} kat"), &suites, &mut file)?;
+            file.flush()
+        });
+        if let Err(error) = written {
+            writeln!(err, "{PROGRAM}: {}: {error}", junit_path.display())?;
+            return Ok(EXIT_FAILURE);
+        }
+    }
+    let is_success = outcomes.iter().all(|outcome| matches!(outcome.result(), "pass" | "skip"));
+    Ok(if is_success { EXIT_SUCCESS } else { EXIT_FAILURE })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::ffi::OsString;
+
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<KatCommand>, UsageError> {
+        KatCommand::parse(&mut ArgParser::new(args.iter().map(OsString::from)))
+    }
+
+    fn run_args(args: &[&str]) -> (i32, String, String) {
+        let command = parse(args).unwrap().unwrap();
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let exit_code = run(&command, &mut out, &mut err).unwrap();
+        (exit_code, String::from_utf8(out).unwrap(), String::from_utf8(err).unwrap())
+    }
+
+    fn vectors(path: &str) -> String {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("test_vectors").join(path).display().to_string()
+    }
+
+    #[test]
+    fn test_parse() {
+        let command = parse(&["--monte-checkpoints", "3", "--ldt-max-bits=1000", "--junit", "out.xml", "a", "b"]).unwrap().unwrap();
+        assert_eq!(KatCommand {
+            paths: vec![PathBuf::from("a"), PathBuf::from("b")],
+            monte_checkpoints: 3,
+            ldt_max_bits: 1000,
+            junit: Some(PathBuf::from("out.xml")),
+        }, command);
+        assert!(parse(&["-h"]).unwrap().is_none());
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["--monte-checkpoints", "x", "a"]).is_err());
+    }
+
+    #[test]
+    fn test_passing_files() {
+        let acvp = vectors("ACVP");
+        let monte = vectors("SHA3/SHA3_256Monte.rsp");
+        let expected_results = vectors("ACVP/SHA3-256/expectedResults.json");
+        let (exit_code, out, err) = run_args(&["--monte-checkpoints", "2", &monte, &acvp, &expected_results]);
+        assert_eq!((EXIT_SUCCESS, ""), (exit_code, err.as_str()), "{out}");
+        assert!(out.starts_with("RESULT  PASSED  FAILED  SKIPPED  ALGORITHM "), "{out}");
+        assert!(out.contains("pass         2       0       98  SHA3-256 Monte "), "{out}");
+        assert!(out.contains("  KMAC256 "), "{out}");
+        assert!(out.ends_with("7 files: 7 passed, 0 failed, 0 skipped, 0 errors\n"), "{out}");
+    }
+
+    #[test]
+    fn test_failures_and_junit() {
+        let dir = env::temp_dir().join(format!("crypto-playground-cli-kat-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let failing = dir.join("failing.rsp");
+        fs::write(&failing, "#  \"SHA3-256 ShortMsg\" information for \"test\"\n\n[L = 256]\n\n\
+            Len = 0\nMsg = 00\nMD = a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a\n\n\
+            Len = 8\nMsg = e9\nMD = 00d04dd1e6cfc29a4460d521796852f25d9ef8d28b44ee91ff5b759d72c1e6d6\n").unwrap();
+        let unsupported = dir.join("sha1.rsp");
+        fs::write(&unsupported, "#  \"SHA-1 ShortMsg\" information for \"test\"\n").unwrap();
+        let missing = dir.join("missing.rsp");
+        fs::write(&missing, "[L = 256]\n").unwrap();
+        let bits = dir.join("bits.rsp");
+        fs::write(&bits, "#  \"SHA3-256 ShortMsg\" information for \"test\"\n\n[L = 256]\n\n\
+            Len = 1\nMsg = 00\nMD = a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a\n").unwrap();
+        let junit = dir.join("junit.xml");
+        let (failing, unsupported, missing, bits, junit) = (failing.to_str().unwrap(), unsupported.to_str().unwrap(),
+            missing.to_str().unwrap(), bits.to_str().unwrap(), junit.to_str().unwrap());
+
+        let (exit_code, out, _) = run_args(&["--junit", junit, failing, unsupported, missing, bits, &vectors("ACVP/SHAKE-128")]);
+        assert_eq!(EXIT_FAILURE, exit_code);
+        assert!(out.contains(&format!("FAIL         1       1        0  SHA3-256 ShortMsg  {failing}\n")), "{out}");
+        assert!(out.contains(&format!("ERROR        0       0        0  -                  {unsupported}\n")), "{out}");
+        assert!(out.contains(&format!("skip         0       0        1  SHA3-256 ShortMsg  {bits}\n")), "{out}");
+        assert!(out.contains(&format!("{failing}: failing records at lines [9]\n")), "{out}");
+        assert!(out.contains(&format!("{unsupported}: unsupported algorithm 'SHA-1'\n")), "{out}");
+        assert!(out.contains(&format!("{missing}: missing algorithm\n")), "{out}");
+        assert!(out.ends_with("5 files: 1 passed, 1 failed, 1 skipped, 2 errors\n"), "{out}");
+
+        // skipped files alone do not fail the run
+        assert_eq!(EXIT_SUCCESS, run_args(&[bits]).0);
+
+        let xml = fs::read_to_string(junit).unwrap();
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites name=\"crypto-playground kat\" tests=\""), "{xml}");
+        assert!(xml.contains("<failure message=\"failing records at lines [9]\"/>"), "{xml}");
+        assert!(xml.contains("<error message=\""), "{xml}");
+        assert!(xml.contains("classname=\"SHAKE128\" name=\"tgId 1 (AFT)\"/>"), "{xml}");
+
+        assert_eq!(EXIT_FAILURE, run_args(&[dir.join("empty-dir-does-not-exist").to_str().unwrap()]).0);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,518 @@
+//! `mac` command: KMAC and HMAC-SHA3 tags of files, and TupleHash over a list of files.
+//!
+//! Keys are read from a file, an environment variable or an inherited file descriptor,
+//! never from the command line, where other users could see them in the process list.
+
+use std::env;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::path::PathBuf;
+
+use crypto_playground::sha3::registry::Algorithm;
+use crypto_playground::sha3::registry::DynHasher;
+use crypto_playground::sha3::registry::HasherOptions;
+use crypto_playground::sha3::secret::Secret;
+use crypto_playground::sha3::secret::Zeroize;
+use crypto_playground::sha3::secret::ct_eq;
+
+use crate::cli::EXIT_FAILURE;
+use crate::cli::EXIT_MISMATCH;
+use crate::cli::EXIT_SUCCESS;
+use crate::cli::PROGRAM;
+use crate::cli::args::Arg;
+use crate::cli::args::ArgParser;
+use crate::cli::args::UsageError;
+use crate::cli::format::LineFormat;
+use crate::cli::format::format_line;
+
+
+/// Shortest tag accepted, in bits, as SP 800-185 and SP 800-107 recommend.
+pub const MIN_TAG_BITS: usize = 32;
+
+/// Longest key accepted, in bytes. The buffer is allocated once at this size, so that
+/// no reallocation leaves a copy of the key behind.
+pub const MAX_KEY_BYTES: usize = 4096;
+
+
+pub fn usage() -> String {
+    format!("\
+Usage: {PROGRAM} mac -a ALG KEY-OPTION [OPTION]... [FILE]...
+  or:  {PROGRAM} mac -a ALG KEY-OPTION --verify TAG [OPTION]... [FILE]
+  or:  {PROGRAM} mac -a tuplehash128|tuplehash256 [OPTION]... FILE...
+Print or verify the KMAC or HMAC-SHA3 tag of every FILE; with no FILE, or when FILE
+is -, read standard input. TupleHash takes no key and prints one tag over the
+sequence of FILEs, which does not change when bytes move from one file to the next.
+
+  -a, --alg ALG          kmac128, kmac256, hmac-sha3-224 to hmac-sha3-512, tuplehash128
+                         or tuplehash256, in any case
+  -k, --key-file FILE    read the key from FILE
+      --key-env VAR      read the key from the environment variable VAR
+      --key-fd N         read the key from the inherited file descriptor N
+      --hex-key          the key is written in hexadecimal
+  -S, --custom S         customization string of KMAC and TupleHash
+  -l, --out-bits N       tag length, a multiple of 8 and at least {MIN_TAG_BITS} (default: 256 for
+                         kmac128 and tuplehash128, 512 for kmac256 and tuplehash256, the
+                         digest length for HMAC, which can only be truncated)
+      --verify TAG       check the hexadecimal TAG in constant time; its length is
+                         the tag length
+  -h, --help             print this help
+
+Keys are never taken from the command line. Raw keys are used byte for byte,
+including any trailing newline; whitespace around a hexadecimal key is ignored.
+
+Exit status: 0 on success, {EXIT_MISMATCH} if a file could not be read or a tag did not
+verify, 2 for usage errors.
+")
+}
+
+
+/// Whether `-a` accepts `algorithm`: the keyed modes with a fixed-length tag, KMAC and
+/// HMAC-SHA3, and TupleHash.
+pub fn is_mac(algorithm: &Algorithm) -> bool {
+    matches!(algorithm, Algorithm::Kmac(_) | Algorithm::HmacSha3(_) | Algorithm::TupleHash(_))
+}
+
+/// The registry names of the algorithms of `mac`, in lower case, e.g. "kmac128|kmac256|...".
+pub fn mac_algorithm_names() -> String {
+    Algorithm::ALL.iter()
+        .filter(|algorithm| is_mac(algorithm))
+        .map(|algorithm| algorithm.name().to_ascii_lowercase())
+        .collect::<Vec<_>>()
+        .join("|")
+}
+
+/// Parses the argument of `-a`: the registry name of a MAC algorithm, in any case.
+pub fn parse_mac_algorithm(arg: &str) -> Result<Algorithm, UsageError> {
+    Algorithm::from_name(arg)
+        .filter(is_mac)
+        .ok_or_else(|| UsageError(format!("unknown algorithm '{arg}', expected {}", mac_algorithm_names())))
+}
+
+
+/// Where the key is read from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KeySource {
+    File(PathBuf),
+    Env(OsString),
+    Fd(u32),
+}
+
+impl fmt::Display for KeySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeySource::File(path) => write!(f, "key file '{}'", path.display()),
+            KeySource::Env(name) => write!(f, "environment variable {}", name.to_string_lossy()),
+            KeySource::Fd(fd) => write!(f, "file descriptor {fd}"),
+        }
+    }
+}
+
+impl KeySource {
+    /// Reads the key, decoding it from hex if `is_hex`; every buffer that held it is wiped.
+    pub fn read_key(&self, is_hex: bool) -> io::Result<Secret<Vec<u8>>> {
+        let key = match self {
+            KeySource::File(path) => read_limited(File::open(path)?)?,
+            KeySource::Env(name) => {
+                let value = env::var_os(name)
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "not set"))?;
+                Secret::new(value.into_encoded_bytes())
+            }
+            // Opening /dev/fd/N duplicates the descriptor without any unsafe code.
+            KeySource::Fd(fd) => read_limited(File::open(format!("/dev/fd/{fd}"))?)?,
+        };
+        let key = if is_hex { decode_hex_key(&key)? } else { key };
+        if key.expose_secret().is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "the key is empty"));
+        }
+        Ok(key)
+    }
+}
+
+fn read_limited(reader: impl Read) -> io::Result<Secret<Vec<u8>>> {
+    let mut key = Secret::new(Vec::with_capacity(MAX_KEY_BYTES + 1));
+    reader.take(MAX_KEY_BYTES as u64 + 1).read_to_end(key.expose_secret_mut())?;
+    if key.expose_secret().len() > MAX_KEY_BYTES {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("the key is longer than {MAX_KEY_BYTES} bytes")));
+    }
+    Ok(key)
+}
+
+fn decode_hex_key(text: &Secret<Vec<u8>>) -> io::Result<Secret<Vec<u8>>> {
+    let text = text.expose_secret().trim_ascii();
+    let mut key = Secret::new(vec![0u8; text.len() / 2]);
+    hex::decode_to_slice(text, key.expose_secret_mut())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "the key is not valid hexadecimal"))?;
+    Ok(key)
+}
+
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct MacCommand {
+    pub algorithm: Algorithm,
+    pub key_source: Option<KeySource>,
+    pub is_hex_key: bool,
+    pub customization: String,
+    pub output_bits: usize,
+    pub verify: Option<Vec<u8>>,
+    /// Files to authenticate, "-" being stdin; stdin alone when none are given.
+    pub files: Vec<OsString>,
+}
+
+impl MacCommand {
+    /// Parses the arguments after `mac`; None if help was requested.
+    pub fn parse(parser: &mut ArgParser) -> Result<Option<MacCommand>, UsageError> {
+        let mut algorithm = None;
+        let mut key_sources = Vec::new();
+        let mut is_hex_key = false;
+        let mut customization = None;
+        let mut output_bits = None;
+        let mut verify = None;
+        let mut files = Vec::new();
+        while let Some(arg) = parser.next_arg()? {
+            match &arg {
+                Arg::Short('h') => return Ok(None),
+                Arg::Long(name) if name == "help" => return Ok(None),
+                Arg::Short('a') => algorithm = Some(parse_mac_algorithm(&parser.value(&arg)?)?),
+                Arg::Long(name) if name == "alg" || name == "algorithm" => algorithm = Some(parse_mac_algorithm(&parser.value(&arg)?)?),
+                Arg::Short('k') => key_sources.push(KeySource::File(PathBuf::from(parser.value(&arg)?))),
+                Arg::Long(name) if name == "key-file" => key_sources.push(KeySource::File(PathBuf::from(parser.value(&arg)?))),
+                Arg::Long(name) if name == "key-env" => key_sources.push(KeySource::Env(OsString::from(parser.value(&arg)?))),
+                Arg::Long(name) if name == "key-fd" => {
+                    let fd = parser.value_usize(&arg)?;
+                    let fd = u32::try_from(fd).map_err(|_| UsageError(format!("invalid file descriptor {fd}")))?;
+                    key_sources.push(KeySource::Fd(fd));
+                }
+                Arg::Long(name) if name == "key" => {
+                    return Err(UsageError("keys are not accepted on the command line, use --key-file, --key-env or --key-fd".to_string()));
+                }
+                Arg::Long(name) if name == "hex-key" => is_hex_key = true,
+                Arg::Short('S') => customization = Some(parser.value(&arg)?),
+                Arg::Long(name) if name == "custom" => customization = Some(parser.value(&arg)?),
+                Arg::Short('l') => output_bits = Some(parser.value_usize(&arg)?),
+                Arg::Long(name) if name == "out-bits" => output_bits = Some(parser.value_usize(&arg)?),
+                Arg::Long(name) if name == "verify" => {
+                    let tag = parser.value(&arg)?;
+                    verify = Some(hex::decode(&tag).map_err(|_| UsageError(format!("invalid tag '{tag}', expected hexadecimal")))?);
+                }
+                Arg::Operand(file) => files.push(file.clone()),
+                _ => return Err(UsageError(format!("unknown option '{arg}'"))),
+            }
+        }
+
+        let algorithm = algorithm.ok_or_else(|| UsageError(format!("mac needs -a {}", mac_algorithm_names())))?;
+        if key_sources.len() > 1 {
+            return Err(UsageError("give only one of --key-file, --key-env and --key-fd".to_string()));
+        }
+        let key_source = key_sources.pop();
+        match (algorithm.is_keyed(), &key_source) {
+            (true, None) => return Err(UsageError(format!("{} needs a key: --key-file, --key-env or --key-fd", algorithm.name()))),
+            (false, Some(_)) => return Err(UsageError(format!("{} does not take a key", algorithm.name()))),
+            _ => {}
+        }
+        if is_hex_key && key_source.is_none() {
+            return Err(UsageError("--hex-key needs a key".to_string()));
+        }
+        if customization.is_some() && matches!(algorithm, Algorithm::HmacSha3(_)) {
+            return Err(UsageError("HMAC has no customization string".to_string()));
+        }
+
+        if let Some(tag) = &verify {
+            let tag_bits = 8 * tag.len();
+            if output_bits.is_some_and(|bits| bits != tag_bits) {
+                return Err(UsageError(format!("the tag to verify has {tag_bits} bits, not {}", output_bits.unwrap_or_default())));
+            }
+            output_bits = Some(tag_bits);
+        }
+        let output_bits = output_bits.unwrap_or(algorithm.output_len());
+        if output_bits < MIN_TAG_BITS || !output_bits.is_multiple_of(8) {
+            return Err(UsageError(format!("invalid tag length {output_bits}, expected a multiple of 8 of at least {MIN_TAG_BITS}")));
+        }
+        if let Algorithm::HmacSha3(_) = algorithm && output_bits > algorithm.output_len() {
+            return Err(UsageError(format!("{} tags have at most {} bits", algorithm.name(), algorithm.output_len())));
+        }
+
+        if files.is_empty() {
+            files.push(OsString::from("-"));
+        }
+        if files.iter().filter(|file| *file == "-").count() > 1 {
+            return Err(UsageError("standard input can only be read once".to_string()));
+        }
+        if verify.is_some() && algorithm.is_keyed() && files.len() > 1 {
+            return Err(UsageError("--verify checks a single FILE".to_string()));
+        }
+        Ok(Some(MacCommand {
+            algorithm,
+            key_source,
+            is_hex_key,
+            customization: customization.unwrap_or_default(),
+            output_bits,
+            verify,
+            files,
+        }))
+    }
+}
+
+
+/// A registry hasher of a keyed mode fed through `io::Write`.
+struct Mac(Box<dyn DynHasher>);
+
+impl Mac {
+    fn new(algorithm: Algorithm, key: &[u8], customization: &[u8]) -> Mac {
+        assert!(algorithm.is_keyed());
+        Mac(algorithm.new_hasher(&HasherOptions { key, customization, ..HasherOptions::default() }))
+    }
+
+    /// Tag of `out.len()` bytes; HMAC tags are truncated to their leftmost bytes.
+    fn finalize_into(self, out: &mut [u8]) {
+        let algorithm = self.0.algorithm();
+        if let Algorithm::HmacSha3(_) = algorithm {
+            let mut full = vec![0u8; algorithm.output_len() / 8];
+            self.0.finalize_into(&mut full);
+            out.copy_from_slice(&full[..out.len()]);
+            full.zeroize();
+        } else {
+            self.0.finalize_into(out);
+        }
+    }
+}
+
+impl Write for Mac {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+
+fn open_input(file: &OsString) -> io::Result<Box<dyn Read>> {
+    if file == "-" {
+        Ok(Box::new(io::stdin().lock()))
+    } else {
+        Ok(Box::new(File::open(file)?))
+    }
+}
+
+fn read_input(file: &OsString) -> io::Result<Vec<u8>> {
+    if file == "-" {
+        let mut data = Vec::new();
+        io::stdin().lock().read_to_end(&mut data)?;
+        Ok(data)
+    } else {
+        fs::read(file)
+    }
+}
+
+/// Streams `file` through a MAC keyed with `key`.
+fn mac_file(command: &MacCommand, key: &[u8], file: &OsString) -> io::Result<Vec<u8>> {
+    let mut mac = Mac::new(command.algorithm, key, command.customization.as_bytes());
+    io::copy(&mut open_input(file)?, &mut mac)?;
+    let mut tag = vec![0u8; command.output_bits / 8];
+    mac.finalize_into(&mut tag);
+    Ok(tag)
+}
+
+/// TupleHash of the contents of all the files, each file being one element.
+fn tuple_hash_files(command: &MacCommand) -> Result<Vec<u8>, (String, io::Error)> {
+    let options = HasherOptions { customization: command.customization.as_bytes(), ..HasherOptions::default() };
+    let mut tuple_hash = command.algorithm.new_hasher(&options);
+    for file in &command.files {
+        let data = read_input(file).map_err(|error| (file.to_string_lossy().into_owned(), error))?;
+        // each update is one item of the tuple
+        tuple_hash.update(&data);
+    }
+    let mut tag = vec![0u8; command.output_bits / 8];
+    tuple_hash.finalize_into(&mut tag);
+    Ok(tag)
+}
+
+/// Compares the tag in constant time and prints `name: OK` or `name: FAILED`.
+fn report_verification(expected: &[u8], mut tag: Vec<u8>, name: &str, out: &mut impl Write) -> io::Result<i32> {
+    let is_valid = ct_eq(&tag, expected);
+    tag.zeroize();
+    if is_valid {
+        writeln!(out, "{name}: OK")?;
+        Ok(EXIT_SUCCESS)
+    } else {
+        writeln!(out, "{name}: FAILED")?;
+        Ok(EXIT_MISMATCH)
+    }
+}
+
+pub fn run(command: &MacCommand, out: &mut impl Write, err: &mut impl Write) -> io::Result<i32> {
+    if let Algorithm::TupleHash(_) = command.algorithm {
+        let tag = match tuple_hash_files(command) {
+            Ok(tag) => tag,
+            Err((name, error)) => {
+                writeln!(err, "{PROGRAM}: {name}: {error}")?;
+                return Ok(EXIT_FAILURE);
+            }
+        };
+        return match &command.verify {
+            Some(expected) => report_verification(expected, tag, "tuple", out),
+            None => writeln!(out, "{}", hex::encode(&tag)).map(|_| EXIT_SUCCESS),
+        };
+    }
+
+    let key_source = command.key_source.as_ref().expect("keyed algorithms have a key");
+    let key = match key_source.read_key(command.is_hex_key) {
+        Ok(key) => key,
+        Err(error) => {
+            writeln!(err, "{PROGRAM}: {key_source}: {error}")?;
+            return Ok(EXIT_FAILURE);
+        }
+    };
+    let mut exit_code = EXIT_SUCCESS;
+    for file in &command.files {
+        let name = file.to_string_lossy();
+        let tag = match mac_file(command, key.expose_secret(), file) {
+            Ok(tag) => tag,
+            Err(error) => {
+                writeln!(err, "{PROGRAM}: {name}: {error}")?;
+                exit_code = EXIT_FAILURE;
+                continue;
+            }
+        };
+        match &command.verify {
+            Some(expected) => exit_code = report_verification(expected, tag, &name, out)?,
+            None => out.write_all(format_line(LineFormat::Gnu, "", &name, &hex::encode(&tag)).as_bytes())?,
+        }
+    }
+    Ok(exit_code)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crypto_playground::sha3::hmac::hmac_sha3;
+    use crypto_playground::sha3::sp800_185::kmac256;
+    use crypto_playground::sha3::sp800_185::tuple_hash128;
+    use crypto_playground::sha3::types::Sha3Variant;
+    use crypto_playground::sha3::types::ShakeVariant;
+
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<MacCommand>, UsageError> {
+        MacCommand::parse(&mut ArgParser::new(args.iter().map(OsString::from)))
+    }
+
+    fn run_args(args: &[&str]) -> (i32, String, String) {
+        let command = parse(args).unwrap().unwrap();
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let exit_code = run(&command, &mut out, &mut err).unwrap();
+        (exit_code, String::from_utf8(out).unwrap(), String::from_utf8(err).unwrap())
+    }
+
+    #[test]
+    fn test_parse() {
+        let command = parse(&["--alg", "KMAC128", "-k", "key", "-S", "app", "-l", "128", "a"]).unwrap().unwrap();
+        assert_eq!(Algorithm::Kmac(ShakeVariant::SHAKE128), command.algorithm);
+        assert_eq!(Some(KeySource::File(PathBuf::from("key"))), command.key_source);
+        assert_eq!("app", command.customization);
+        assert_eq!(128, command.output_bits);
+
+        let command = parse(&["-a", "hmac-sha3-256", "--key-env", "MAC_KEY", "--verify", "00112233"]).unwrap().unwrap();
+        assert_eq!(32, command.output_bits);
+        assert_eq!(vec![OsString::from("-")], command.files);
+        assert_eq!(512, parse(&["-a", "kmac256", "--key-fd", "3"]).unwrap().unwrap().output_bits);
+
+        assert!(parse(&["-h"]).unwrap().is_none());
+        assert!(parse(&["-k", "key"]).is_err());
+        assert!(parse(&["-a", "kmac256"]).is_err());
+        // in the registry, but not a MAC
+        assert!(parse(&["-a", "sha3-256", "-k", "key"]).is_err());
+        assert!(parse(&["-a", "kmac256", "--key", "secret"]).is_err());
+        assert!(parse(&["-a", "kmac256", "-k", "a", "--key-env", "B"]).is_err());
+        assert!(parse(&["-a", "tuplehash128", "-k", "key", "a"]).is_err());
+        assert!(parse(&["-a", "hmac-sha3-256", "-k", "key", "-S", "app"]).is_err());
+        assert!(parse(&["-a", "hmac-sha3-256", "-k", "key", "-l", "512"]).is_err());
+        assert!(parse(&["-a", "kmac128", "-k", "key", "-l", "24"]).is_err());
+        assert!(parse(&["-a", "kmac128", "-k", "key", "-l", "100"]).is_err());
+        assert!(parse(&["-a", "kmac128", "-k", "key", "--verify", "0011"]).is_err());
+        assert!(parse(&["-a", "kmac128", "-k", "key", "--verify", "xyz"]).is_err());
+        assert!(parse(&["-a", "kmac128", "-k", "key", "-l", "64", "--verify", "00112233"]).is_err());
+        assert!(parse(&["-a", "kmac128", "-k", "key", "--verify", "00112233", "a", "b"]).is_err());
+        assert!(parse(&["-a", "kmac128", "-k", "key", "-", "-"]).is_err());
+    }
+
+    #[test]
+    fn test_read_key() {
+        let dir = env::temp_dir().join(format!("crypto-playground-cli-mac-key-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("key");
+
+        fs::write(&path, b"raw key\n").unwrap();
+        assert_eq!(b"raw key\n", &KeySource::File(path.clone()).read_key(false).unwrap().expose_secret()[..]);
+        fs::write(&path, b" 00ff10\n").unwrap();
+        assert_eq!(vec![0x00, 0xff, 0x10], *KeySource::File(path.clone()).read_key(true).unwrap().expose_secret());
+        fs::write(&path, b"0g").unwrap();
+        assert!(KeySource::File(path.clone()).read_key(true).is_err());
+        fs::write(&path, b"").unwrap();
+        assert!(KeySource::File(path.clone()).read_key(false).is_err());
+        fs::write(&path, vec![1u8; MAX_KEY_BYTES + 1]).unwrap();
+        assert!(KeySource::File(path.clone()).read_key(false).is_err());
+        assert!(KeySource::File(dir.join("missing")).read_key(false).is_err());
+
+        // Setting a variable is unsafe in a multi-threaded test, so read one that is already set.
+        let (name, value) = env::vars_os().find(|(_, value)| !value.is_empty()).unwrap();
+        assert_eq!(value.into_encoded_bytes(), *KeySource::Env(name).read_key(false).unwrap().expose_secret());
+        assert!(KeySource::Env(OsString::from("CRYPTO_PLAYGROUND_UNSET_KEY")).read_key(false).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_mac_and_verify() {
+        let dir = env::temp_dir().join(format!("crypto-playground-cli-mac-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let key_path = dir.join("key");
+        let a_path = dir.join("a");
+        let b_path = dir.join("b");
+        fs::write(&key_path, "0123456789abcdef").unwrap();
+        fs::write(&a_path, "message a").unwrap();
+        fs::write(&b_path, "message b").unwrap();
+        let (key, a, b) = (key_path.to_str().unwrap(), a_path.to_str().unwrap(), b_path.to_str().unwrap());
+
+        let kmac_a = hex::encode(&kmac256(b"\x01\x23\x45\x67\x89\xab\xcd\xef", b"message a", 256, b"app")[..]);
+        let (exit_code, out, _) = run_args(&["-a", "kmac256", "-k", key, "--hex-key", "-S", "app", "-l", "256", a]);
+        assert_eq!(EXIT_SUCCESS, exit_code);
+        assert_eq!(format!("{kmac_a}  {a}\n"), out);
+
+        let hmac_a = hmac_sha3(Sha3Variant::SHA3_256, b"0123456789abcdef", b"message a");
+        let hmac_b = hmac_sha3(Sha3Variant::SHA3_256, b"0123456789abcdef", b"message b");
+        let (_, out, _) = run_args(&["-a", "hmac-sha3-256", "-k", key, a, b]);
+        assert_eq!(format!("{}  {a}\n{}  {b}\n", hex::encode(&hmac_a[..]), hex::encode(&hmac_b[..])), out);
+
+        let truncated = hex::encode(&hmac_a[..16]);
+        assert_eq!((EXIT_SUCCESS, format!("{a}: OK\n")), {
+            let (exit_code, out, _) = run_args(&["-a", "hmac-sha3-256", "-k", key, "--verify", &truncated, a]);
+            (exit_code, out)
+        });
+        assert_eq!((EXIT_MISMATCH, format!("{b}: FAILED\n")), {
+            let (exit_code, out, _) = run_args(&["-a", "hmac-sha3-256", "-k", key, "--verify", &truncated, b]);
+            (exit_code, out)
+        });
+
+        let tuple = hex::encode(&tuple_hash128(&[b"message a", b"message b"], 256, b"")[..]);
+        assert_eq!(format!("{tuple}\n"), run_args(&["-a", "tuplehash128", a, b]).1);
+        assert_eq!(EXIT_SUCCESS, run_args(&["-a", "tuplehash128", "--verify", &tuple, a, b]).0);
+        assert_eq!(EXIT_MISMATCH, run_args(&["-a", "tuplehash128", "--verify", &tuple, b, a]).0);
+
+        let missing = dir.join("missing");
+        let missing = missing.to_str().unwrap();
+        assert_eq!(EXIT_FAILURE, run_args(&["-a", "kmac128", "-k", missing, a]).0);
+        let (exit_code, out, err) = run_args(&["-a", "kmac128", "-k", key, missing, a]);
+        assert_eq!(EXIT_FAILURE, exit_code);
+        assert!(out.ends_with(&format!("  {a}\n")), "{out}");
+        assert!(err.contains(missing), "{err}");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
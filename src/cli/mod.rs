@@ -0,0 +1,176 @@
+//! Command-line front end of the crate: a `sha3sum`-compatible hasher.
+
+mod algorithm;
+mod args;
+mod avalanche;
+mod bench;
+mod check;
+mod format;
+mod junit;
+mod kat;
+mod mac;
+mod sum;
+mod trace;
+mod tree;
+mod visualize;
+mod xof;
+
+use std::ffi::OsString;
+use std::io;
+use std::io::BufWriter;
+use std::io::Write;
+
+use crate::cli::args::ArgParser;
+use crate::cli::args::UsageError;
+use crate::cli::avalanche::AvalancheCommand;
+use crate::cli::bench::BenchCommand;
+use crate::cli::kat::KatCommand;
+use crate::cli::mac::MacCommand;
+use crate::cli::sum::SumOptions;
+use crate::cli::trace::TraceCommand;
+use crate::cli::tree::TreeCommand;
+use crate::cli::visualize::VisualizeCommand;
+use crate::cli::xof::XofCommand;
+
+
+pub const PROGRAM: &str = "crypto-playground";
+/// Subcommands, recognized as the first argument.
+const COMMANDS: [&str; 8] = ["tree", "mac", "xof", "kat", "trace", "bench", "avalanche", "visualize"];
+
+pub const EXIT_SUCCESS: i32 = 0;
+/// A file could not be read, or output could not be written.
+pub const EXIT_FAILURE: i32 = 1;
+pub const EXIT_USAGE: i32 = 2;
+/// `--check`: a digest did not match.
+pub const EXIT_MISMATCH: i32 = 1;
+/// `--check`: a listed file or a manifest could not be read.
+pub const EXIT_MISSING: i32 = 3;
+/// `--check`: a manifest had no well-formed line, or with `--strict`, any malformed line.
+pub const EXIT_MALFORMED: i32 = 4;
+
+
+fn usage() -> String {
+    format!("\
+Usage: {PROGRAM} [OPTION]... [FILE]...
+  or:  {PROGRAM} tree [OPTION]... DIR
+  or:  {PROGRAM} mac -a ALG KEY-OPTION [OPTION]... [FILE]...
+  or:  {PROGRAM} xof [OPTION]... [FILE]
+  or:  {PROGRAM} kat [OPTION]... PATH...
+  or:  {PROGRAM} trace [OPTION]...
+  or:  {PROGRAM} bench [OPTION]...
+  or:  {PROGRAM} avalanche [OPTION]...
+  or:  {PROGRAM} visualize [OPTION]...
+Print or check SHA3 checksums; with no FILE, or when FILE is -, read standard input.
+'tree' hashes a whole directory, 'mac' computes KMAC, HMAC-SHA3 and TupleHash tags,
+'xof' streams SHAKE output, 'kat' runs NIST and ACVP test vectors, 'trace' prints
+the state step by step, 'bench' measures the throughput, 'avalanche' the diffusion
+of reduced-round Keccak and 'visualize' draws the state and the step mappings;
+see '{PROGRAM} COMMAND --help'. Hash a file named like a command as ./tree,
+./mac, etc.
+
+  -a, --algorithm ALG  sha3-224 to sha3-512, shake128, shake256 or keccak-224 to
+                       keccak-512, in any case (default sha3-256); 224, 256, 384
+                       and 512 select SHA3, keccak256 Keccak-256
+  -l, --length BITS    output length of shake128 and shake256, a multiple of 8
+                       (default 256 and 512)
+      --tag            BSD-style lines: SHA3-256 (FILE) = DIGEST
+      --untagged       GNU-style lines: DIGEST  FILE (default)
+      --hex            hexadecimal digests (default)
+      --base64         base64 digests
+      --raw            the digest bytes alone, for a single input
+  -c, --check          read checksums from the FILEs and verify them; the
+                       algorithm comes from the tag, -a or the digest length
+  -h, --help           print this help
+
+Options when verifying checksums:
+      --ignore-missing don't fail or report status for missing files
+      --quiet          don't print OK for each verified file
+      --status         don't print anything, the exit code shows success
+      --strict         exit with status {EXIT_MALFORMED} for improperly formatted lines
+  -w, --warn           warn about improperly formatted lines
+
+Exit status: 0 on success, {EXIT_FAILURE} if a file could not be read or, with --check,
+a checksum did not match, {EXIT_USAGE} for usage errors, and with --check, {EXIT_MISSING} if
+a listed file could not be read or {EXIT_MALFORMED} for improperly formatted manifests.
+")
+}
+
+/// Runs the command line `args` (without the program name) and returns the exit code.
+pub fn run(args: impl IntoIterator<Item = OsString>) -> i32 {
+    let mut args = args.into_iter().peekable();
+    let command = args.next_if(|arg| COMMANDS.iter().any(|command| arg == command));
+    let mut parser = ArgParser::new(args);
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    let mut err = io::stderr();
+    let result = match command.as_ref().and_then(|command| command.to_str()) {
+        Some("tree") => match TreeCommand::parse(&mut parser) {
+            Ok(Some(command)) => tree::run(&command, &mut out, &mut err),
+            Ok(None) => out.write_all(tree::usage().as_bytes()).map(|_| EXIT_SUCCESS),
+            Err(error) => return usage_error(&error),
+        },
+        Some("mac") => match MacCommand::parse(&mut parser) {
+            Ok(Some(command)) => mac::run(&command, &mut out, &mut err),
+            Ok(None) => out.write_all(mac::usage().as_bytes()).map(|_| EXIT_SUCCESS),
+            Err(error) => return usage_error(&error),
+        },
+        Some("xof") => match XofCommand::parse(&mut parser) {
+            Ok(Some(command)) => xof::run(&command, &mut out, &mut err),
+            Ok(None) => out.write_all(xof::usage().as_bytes()).map(|_| EXIT_SUCCESS),
+            Err(error) => return usage_error(&error),
+        },
+        Some("kat") => match KatCommand::parse(&mut parser) {
+            Ok(Some(command)) => kat::run(&command, &mut out, &mut err),
+            Ok(None) => out.write_all(kat::usage().as_bytes()).map(|_| EXIT_SUCCESS),
+            Err(error) => return usage_error(&error),
+        },
+        Some("trace") => match TraceCommand::parse(&mut parser) {
+            Ok(Some(command)) => trace::run(&command, &mut out, &mut err),
+            Ok(None) => out.write_all(trace::usage().as_bytes()).map(|_| EXIT_SUCCESS),
+            Err(error) => return usage_error(&error),
+        },
+        Some("bench") => match BenchCommand::parse(&mut parser) {
+            Ok(Some(command)) => bench::run(&command, &mut out, &mut err),
+            Ok(None) => out.write_all(bench::usage().as_bytes()).map(|_| EXIT_SUCCESS),
+            Err(error) => return usage_error(&error),
+        },
+        Some("avalanche") => match AvalancheCommand::parse(&mut parser) {
+            Ok(Some(command)) => avalanche::run(&command, &mut out, &mut err),
+            Ok(None) => out.write_all(avalanche::usage().as_bytes()).map(|_| EXIT_SUCCESS),
+            Err(error) => return usage_error(&error),
+        },
+        Some("visualize") => match VisualizeCommand::parse(&mut parser) {
+            Ok(Some(command)) => visualize::run(&command, &mut out, &mut err),
+            Ok(None) => out.write_all(visualize::usage().as_bytes()).map(|_| EXIT_SUCCESS),
+            Err(error) => return usage_error(&error),
+        },
+        _ => match SumOptions::parse(&mut parser) {
+            Ok(Some(options)) if options.check.is_some() => check::run(&options, &mut out, &mut err),
+            Ok(Some(options)) => sum::run(&options, &mut out, &mut err),
+            Ok(None) => out.write_all(usage().as_bytes()).map(|_| EXIT_SUCCESS),
+            Err(error) => return usage_error(&error),
+        },
+    };
+    let exit_code = match result {
+        Ok(exit_code) => exit_code,
+        Err(error) => {
+            eprintln!("{PROGRAM}: write error: {error}");
+            return EXIT_FAILURE;
+        }
+    };
+    match out.flush() {
+        Ok(()) => exit_code,
+        // The command is done and the reader of the pipe has left, as in `xof | head -c N`.
+        Err(error) if error.kind() == io::ErrorKind::BrokenPipe => exit_code,
+        Err(error) => {
+            eprintln!("{PROGRAM}: write error: {error}");
+            EXIT_FAILURE
+        }
+    }
+}
+
+fn usage_error(error: &UsageError) -> i32 {
+    eprintln!("{PROGRAM}: {error}");
+    eprintln!("Try '{PROGRAM} --help' for more information.");
+    EXIT_USAGE
+}
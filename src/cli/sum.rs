@@ -0,0 +1,245 @@
+//! Default command: prints a checksum line for every file, like `sha3sum`.
+
+use std::ffi::OsString;
+use std::fs::File;
+use std::io;
+use std::io::Write;
+
+use crypto_playground::sha3::registry::Algorithm;
+use crypto_playground::sha3::types::Sha3Variant;
+
+use crate::cli::PROGRAM;
+use crate::cli::EXIT_FAILURE;
+use crate::cli::EXIT_SUCCESS;
+use crate::cli::algorithm::hash_reader;
+use crate::cli::algorithm::parse_algorithm;
+use crate::cli::args::Arg;
+use crate::cli::args::ArgParser;
+use crate::cli::args::UsageError;
+use crate::cli::check::CheckOptions;
+use crate::cli::format::DigestEncoding;
+use crate::cli::format::LineFormat;
+use crate::cli::format::format_line;
+
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct SumOptions {
+    /// Algorithm given with `-a`; lines of a manifest without a tag are otherwise
+    /// matched by digest length.
+    pub algorithm: Option<Algorithm>,
+    pub output_bits: Option<usize>,
+    pub format: LineFormat,
+    pub encoding: DigestEncoding,
+    /// Verify the manifests given as files instead of printing checksums.
+    pub check: Option<CheckOptions>,
+    /// Files to hash or manifests to check, "-" being stdin; stdin alone when none are given.
+    pub files: Vec<OsString>,
+}
+
+impl SumOptions {
+    /// Parses the arguments; None if help was requested.
+    pub fn parse(parser: &mut ArgParser) -> Result<Option<SumOptions>, UsageError> {
+        let mut algorithm = None;
+        let mut output_bits = None;
+        let mut format = None;
+        let mut encoding = None;
+        let mut is_check = false;
+        let mut check = CheckOptions::default();
+        let mut files = Vec::new();
+        while let Some(arg) = parser.next_arg()? {
+            match &arg {
+                Arg::Short('h') => return Ok(None),
+                Arg::Long(name) if name == "help" => return Ok(None),
+                Arg::Short('a') => algorithm = Some(parse_algorithm(&parser.value(&arg)?)?),
+                Arg::Long(name) if name == "algorithm" => algorithm = Some(parse_algorithm(&parser.value(&arg)?)?),
+                Arg::Short('l') => output_bits = Some(parser.value_usize(&arg)?),
+                Arg::Long(name) if name == "length" => output_bits = Some(parser.value_usize(&arg)?),
+                Arg::Long(name) if name == "tag" => format = Some(LineFormat::Bsd),
+                Arg::Long(name) if name == "untagged" => format = Some(LineFormat::Gnu),
+                Arg::Long(name) if name == "base64" => encoding = Some(DigestEncoding::Base64),
+                Arg::Long(name) if name == "raw" => encoding = Some(DigestEncoding::Raw),
+                Arg::Long(name) if name == "hex" => encoding = Some(DigestEncoding::Hex),
+                Arg::Short('c') => is_check = true,
+                Arg::Long(name) if name == "check" => is_check = true,
+                Arg::Long(name) if name == "quiet" => check.quiet = true,
+                Arg::Long(name) if name == "status" => check.status = true,
+                Arg::Long(name) if name == "strict" => check.strict = true,
+                Arg::Long(name) if name == "ignore-missing" => check.ignore_missing = true,
+                Arg::Short('w') => check.warn = true,
+                Arg::Long(name) if name == "warn" => check.warn = true,
+                Arg::Operand(file) => files.push(file.clone()),
+                _ => return Err(UsageError(format!("unknown option '{arg}'"))),
+            }
+        }
+        if let Some(bits) = output_bits {
+            if algorithm.is_some_and(|algorithm| !algorithm.is_xof()) {
+                return Err(UsageError("-l only applies to shake128 and shake256".to_string()));
+            }
+            if bits == 0 || !bits.is_multiple_of(8) {
+                return Err(UsageError(format!("invalid output length {bits}, expected a positive multiple of 8")));
+            }
+        }
+        if files.is_empty() {
+            files.push(OsString::from("-"));
+        }
+        if is_check {
+            if format.is_some() || encoding.is_some() {
+                return Err(UsageError("--tag, --untagged, --hex, --base64 and --raw are meaningless when verifying checksums".to_string()));
+            }
+            return Ok(Some(SumOptions {
+                algorithm, output_bits, format: LineFormat::Gnu, encoding: DigestEncoding::Hex, check: Some(check), files,
+            }));
+        }
+        if check != CheckOptions::default() {
+            return Err(UsageError("--quiet, --status, --strict, --ignore-missing and --warn are meaningful only when verifying checksums".to_string()));
+        }
+        if output_bits.is_some() && algorithm.is_none() {
+            return Err(UsageError("-l only applies to shake128 and shake256".to_string()));
+        }
+        let format = format.unwrap_or(LineFormat::Gnu);
+        let encoding = encoding.unwrap_or(DigestEncoding::Hex);
+        if encoding == DigestEncoding::Raw && (files.len() > 1 || format == LineFormat::Bsd) {
+            return Err(UsageError("--raw needs a single input and no --tag".to_string()));
+        }
+        Ok(Some(SumOptions { algorithm, output_bits, format, encoding, check: None, files }))
+    }
+
+    /// Algorithm used to print checksums, SHA3-256 by default.
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm.unwrap_or(Algorithm::Sha3(Sha3Variant::SHA3_256))
+    }
+
+    pub fn output_bits(&self) -> usize {
+        self.output_bits.unwrap_or(self.algorithm().output_len())
+    }
+}
+
+
+/// Hashes `file`, or stdin for "-", streaming it through the hasher.
+pub fn hash_file(algorithm: Algorithm, output_bits: usize, file: &OsString) -> io::Result<Vec<u8>> {
+    if file == "-" {
+        hash_reader(algorithm, output_bits, &mut io::stdin().lock())
+    } else {
+        hash_reader(algorithm, output_bits, &mut File::open(file)?)
+    }
+}
+
+/// Prints a line per file to `out` and errors to `err`; returns the exit code.
+pub fn run(options: &SumOptions, out: &mut impl Write, err: &mut impl Write) -> io::Result<i32> {
+    let mut exit_code = EXIT_SUCCESS;
+    for file in &options.files {
+        let name = file.to_string_lossy();
+        let digest = match hash_file(options.algorithm(), options.output_bits(), file) {
+            Ok(digest) => digest,
+            Err(error) => {
+                writeln!(err, "{PROGRAM}: {name}: {error}")?;
+                exit_code = EXIT_FAILURE;
+                continue;
+            }
+        };
+        let encoded = options.encoding.encode(&digest);
+        if options.encoding == DigestEncoding::Raw {
+            out.write_all(&encoded)?;
+            continue;
+        }
+        let encoded = String::from_utf8(encoded).expect("hex and base64 are ASCII");
+        let line = format_line(options.format, options.algorithm().name(), &name, &encoded);
+        out.write_all(line.as_bytes())?;
+    }
+    Ok(exit_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use crypto_playground::sha3::types::ShakeVariant;
+
+    use super::*;
+
+    const TEST_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/test_file.txt");
+    const MISSING_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/test/missing.txt");
+
+    fn hash_bytes(algorithm: Algorithm, output_bits: usize, mut bytes: &[u8]) -> Vec<u8> {
+        hash_reader(algorithm, output_bits, &mut bytes).unwrap()
+    }
+
+    fn parse(args: &[&str]) -> Result<Option<SumOptions>, UsageError> {
+        SumOptions::parse(&mut ArgParser::new(args.iter().map(OsString::from)))
+    }
+
+    fn run_args(args: &[&str]) -> (i32, String, String) {
+        let options = parse(args).unwrap().unwrap();
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let exit_code = run(&options, &mut out, &mut err).unwrap();
+        (exit_code, String::from_utf8_lossy(&out).into_owned(), String::from_utf8(err).unwrap())
+    }
+
+    #[test]
+    fn test_parse() {
+        let options = parse(&["-a", "shake128", "-l", "512", "--tag", "a", "b"]).unwrap().unwrap();
+        assert_eq!(Algorithm::Shake(ShakeVariant::SHAKE128), options.algorithm());
+        assert_eq!(512, options.output_bits());
+        assert_eq!(LineFormat::Bsd, options.format);
+        assert_eq!(vec![OsString::from("a"), OsString::from("b")], options.files);
+
+        let options = parse(&[]).unwrap().unwrap();
+        assert_eq!(Algorithm::Sha3(Sha3Variant::SHA3_256), options.algorithm());
+        assert_eq!(vec![OsString::from("-")], options.files);
+        assert_eq!(None, options.check);
+
+        let options = parse(&["-c", "--quiet", "--strict", "sums"]).unwrap().unwrap();
+        assert_eq!(Some(CheckOptions { quiet: true, strict: true, ..CheckOptions::default() }), options.check);
+        assert_eq!(None, options.algorithm);
+
+        assert_eq!(None, parse(&["-a384", "--help"]).unwrap());
+        assert!(parse(&["-a", "sha3-256", "-l", "128"]).is_err());
+        assert!(parse(&["-a", "shake256", "-l", "12"]).is_err());
+        assert!(parse(&["--raw", "a", "b"]).is_err());
+        assert!(parse(&["--bogus"]).is_err());
+        assert!(parse(&["--status", "a"]).is_err());
+        assert!(parse(&["-c", "--tag", "a"]).is_err());
+    }
+
+    #[test]
+    fn test_output_formats() {
+        let digest = hex::encode(hash_bytes(Algorithm::Sha3(Sha3Variant::SHA3_256), 256, &std::fs::read(TEST_FILE).unwrap()[..]));
+
+        let (exit_code, out, _) = run_args(&[TEST_FILE]);
+        assert_eq!(0, exit_code);
+        assert_eq!(format!("{digest}  {TEST_FILE}\n"), out);
+
+        let (_, out, _) = run_args(&["--tag", TEST_FILE]);
+        assert_eq!(format!("SHA3-256 ({TEST_FILE}) = {digest}\n"), out);
+
+        let (_, out, _) = run_args(&["--base64", TEST_FILE]);
+        let encoded = crate::cli::format::base64_encode(&hex::decode(&digest).unwrap());
+        assert_eq!(format!("{encoded}  {TEST_FILE}\n"), out);
+
+        let options = parse(&["--raw", TEST_FILE]).unwrap().unwrap();
+        let mut out = Vec::new();
+        run(&options, &mut out, &mut Vec::new()).unwrap();
+        assert_eq!(hex::decode(&digest).unwrap(), out);
+    }
+
+    #[test]
+    fn test_algorithms() {
+        // digests of "abc" from Python's hashlib, Keccak-256 from Ethereum
+        let vectors = [
+            ("shake128", 256, "5881092dd818bf5cf8a3ddb793fbcba74097d5c526a6d35f97b83351940f2cc8"),
+            ("keccak256", 256, "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45"),
+            ("sha3-224", 224, "e642824c3f8cf24ad09234ee7d3c766fc9a3a5168d0c94ad73b46fdf"),
+        ];
+        for (name, bits, expected) in vectors {
+            let algorithm = parse_algorithm(name).unwrap();
+            assert_eq!(expected, hex::encode(hash_bytes(algorithm, bits, &b"abc"[..])));
+        }
+    }
+
+    #[test]
+    fn test_missing_file() {
+        let (exit_code, out, err) = run_args(&[MISSING_FILE, TEST_FILE]);
+        assert_eq!(EXIT_FAILURE, exit_code);
+        assert!(err.starts_with(&format!("crypto-playground: {MISSING_FILE}: ")), "{err}");
+        assert!(out.ends_with(&format!("  {TEST_FILE}\n")));
+    }
+}
@@ -0,0 +1,294 @@
+//! `trace` command: prints the Keccak state after each absorbed block and each step mapping.
+
+use std::io;
+use std::io::Write;
+
+use crypto_playground::sha3::constants::KECCAK_B;
+use crypto_playground::sha3::constants::KECCAK_NR;
+use crypto_playground::sha3::registry::Algorithm;
+use crypto_playground::sha3::trace::StateLayout;
+use crypto_playground::sha3::trace::StepMapping;
+use crypto_playground::sha3::trace::TracePoint;
+use crypto_playground::sha3::trace::changed_bits;
+use crypto_playground::sha3::trace::format_state;
+use crypto_playground::sha3::trace::trace_sponge;
+use crypto_playground::sha3::types::Sha3Variant;
+use crypto_playground::sha3::types::new_state;
+
+use crate::cli::EXIT_SUCCESS;
+use crate::cli::PROGRAM;
+use crate::cli::algorithm::parse_algorithm;
+use crate::cli::args::Arg;
+use crate::cli::args::ArgParser;
+use crate::cli::args::UsageError;
+use crate::cli::args::parse_ranges;
+
+
+pub fn usage() -> String {
+    format!("\
+Usage: {PROGRAM} trace [OPTION]...
+Hash a message and print the state after each absorbed block and after each step mapping
+(theta, rho, pi, chi and iota) of each round of KECCAK-p, then the digest. Rounds are
+numbered 0 to {last_round} as in FIPS 202; squeezing long SHAKE outputs adds permutations.
+
+  -a, --alg ALG        sha3-224 to sha3-512, shake128, shake256 or keccak-224 to
+                       keccak-512, in any case (default sha3-256); 224, 256, 384
+                       and 512 select SHA3, keccak256 Keccak-256
+  -l, --length BITS    output length of shake128 and shake256, a multiple of 8
+      --msg-hex HEX    the message bytes in hexadecimal (default: the empty message)
+      --msg TEXT       the message as text
+      --layout LAYOUT  lanes: the 25 lanes A[x][y] as 64-bit integers, bit z of
+                       the lane being bit z of the integer (default);
+                       bytes: the 200 bytes of the state string S
+      --rounds LIST    only print these rounds, e.g. 0,1,22-23
+      --steps LIST     only print these of absorb,theta,rho,pi,chi,iota, e.g. absorb,iota
+                       for the state after each block and each round
+      --diff           mark the bits changed by each step: a '^' line follows each
+                       line of the state that changed, with the XOR of the old and
+                       new values
+  -h, --help           print this help
+", last_round = KECCAK_NR - 1)
+}
+
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct TraceCommand {
+    pub algorithm: Algorithm,
+    pub output_bits: usize,
+    pub message: Vec<u8>,
+    pub layout: StateLayout,
+    pub rounds: Vec<usize>,
+    /// Whether the state is printed after each absorbed block.
+    pub absorb: bool,
+    pub steps: Vec<StepMapping>,
+    pub diff: bool,
+}
+
+impl TraceCommand {
+    /// Parses the arguments after `trace`; None if help was requested.
+    pub fn parse(parser: &mut ArgParser) -> Result<Option<TraceCommand>, UsageError> {
+        let mut algorithm = Algorithm::Sha3(Sha3Variant::SHA3_256);
+        let mut output_bits = None;
+        let mut message = Vec::new();
+        let mut layout = StateLayout::Lanes;
+        let mut rounds: Vec<usize> = (0..KECCAK_NR).collect();
+        let mut absorb = true;
+        let mut steps = StepMapping::ALL.to_vec();
+        let mut diff = false;
+        while let Some(arg) = parser.next_arg()? {
+            match &arg {
+                Arg::Short('h') => return Ok(None),
+                Arg::Long(name) if name == "help" => return Ok(None),
+                Arg::Short('a') => algorithm = parse_algorithm(&parser.value(&arg)?)?,
+                Arg::Long(name) if name == "alg" || name == "algorithm" => algorithm = parse_algorithm(&parser.value(&arg)?)?,
+                Arg::Short('l') => output_bits = Some(parser.value_usize(&arg)?),
+                Arg::Long(name) if name == "length" => output_bits = Some(parser.value_usize(&arg)?),
+                Arg::Long(name) if name == "msg-hex" => {
+                    let hex = parser.value(&arg)?;
+                    message = hex::decode(&hex).map_err(|_| UsageError(format!("invalid message '{hex}', expected hexadecimal")))?;
+                }
+                Arg::Long(name) if name == "msg" => message = parser.value(&arg)?.into_bytes(),
+                Arg::Long(name) if name == "layout" => layout = parse_layout(&parser.value(&arg)?)?,
+                Arg::Long(name) if name == "rounds" => rounds = parse_rounds(&parser.value(&arg)?)?,
+                Arg::Long(name) if name == "steps" => (absorb, steps) = parse_steps(&parser.value(&arg)?)?,
+                Arg::Long(name) if name == "diff" => diff = true,
+                _ => return Err(UsageError(format!("unknown option '{arg}'"))),
+            }
+        }
+        if let Some(bits) = output_bits {
+            if !algorithm.is_xof() {
+                return Err(UsageError("-l only applies to shake128 and shake256".to_string()));
+            }
+            if bits == 0 || !bits.is_multiple_of(8) {
+                return Err(UsageError(format!("invalid output length {bits}, expected a positive multiple of 8")));
+            }
+        }
+        let output_bits = output_bits.unwrap_or(algorithm.output_len());
+        Ok(Some(TraceCommand { algorithm, output_bits, message, layout, rounds, absorb, steps, diff }))
+    }
+
+    fn is_printed(&self, point: TracePoint) -> bool {
+        match point {
+            TracePoint::Absorb { .. } => self.absorb,
+            TracePoint::Step { round, step, .. } => self.rounds.contains(&round) && self.steps.contains(&step),
+        }
+    }
+}
+
+fn parse_layout(arg: &str) -> Result<StateLayout, UsageError> {
+    match arg {
+        "lanes" => Ok(StateLayout::Lanes),
+        "bytes" => Ok(StateLayout::Bytes),
+        _ => Err(UsageError(format!("unknown layout '{arg}', expected lanes or bytes"))),
+    }
+}
+
+fn parse_rounds(list: &str) -> Result<Vec<usize>, UsageError> {
+    parse_ranges(list, KECCAK_NR - 1)
+        .ok_or_else(|| UsageError(format!("invalid rounds '{list}', expected e.g. 0,1,22-23 within 0-{}", KECCAK_NR - 1)))
+}
+
+/// Parses a comma-separated list of step names; "absorb" stands for the absorbed blocks.
+fn parse_steps(list: &str) -> Result<(bool, Vec<StepMapping>), UsageError> {
+    let mut absorb = false;
+    let mut steps = Vec::new();
+    for name in list.split(',') {
+        if name == "absorb" {
+            absorb = true;
+            continue;
+        }
+        let step = StepMapping::ALL.into_iter()
+            .find(|step| step.name() == name)
+            .ok_or_else(|| UsageError(format!("unknown step '{name}', expected absorb, theta, rho, pi, chi or iota")))?;
+        steps.push(step);
+    }
+    Ok((absorb, steps))
+}
+
+
+/// Capacity and domain separation suffix of the sponge behind `algorithm`, one of the hash
+/// functions of `-a`.
+fn sponge_parameters(algorithm: Algorithm) -> (usize, &'static [u8]) {
+    let suffix: &'static [u8] = match algorithm {
+        Algorithm::Sha3(_) => &[0, 1],
+        Algorithm::Shake(_) => &[1, 1, 1, 1],
+        Algorithm::Keccak(_) => &[],
+        _ => unreachable!("-a only selects hash functions"),
+    };
+    (algorithm.capacity(), suffix)
+}
+
+fn write_point(point: TracePoint, out: &mut impl Write) -> io::Result<()> {
+    match point {
+        TracePoint::Absorb { block } => write!(out, "== block {block} absorbed"),
+        TracePoint::Step { permutation, round, step } => write!(out, "== permutation {permutation}, round {round}, {}", step.name()),
+    }
+}
+
+pub fn run(command: &TraceCommand, out: &mut impl Write, _err: &mut impl Write) -> io::Result<i32> {
+    let (keccak_c, suffix) = sponge_parameters(command.algorithm);
+    writeln!(out, "{} of {} bytes, rate {} bits, capacity {keccak_c} bits",
+        command.algorithm.name(), command.message.len(), KECCAK_B - keccak_c)?;
+
+    let mut previous = new_state(KECCAK_B / 25);
+    let mut result = Ok(());
+    let digest = trace_sponge(keccak_c, suffix, &command.message, command.output_bits, |point, a| {
+        if result.is_ok() && command.is_printed(point) {
+            result = (|| {
+                write_point(point, out)?;
+                if command.diff {
+                    let count = changed_bits(a, &previous);
+                    write!(out, " ({count} {} changed)", if count == 1 { "bit" } else { "bits" })?;
+                }
+                writeln!(out)?;
+                out.write_all(format_state(a, command.layout, command.diff.then_some(&previous)).as_bytes())
+            })();
+        }
+        previous = a.clone();
+    });
+    result?;
+    writeln!(out, "== output")?;
+    writeln!(out, "{}", hex::encode(&digest[..]))?;
+    Ok(EXIT_SUCCESS)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+
+    use crypto_playground::sha3::sha3::sha3_256;
+    use crypto_playground::sha3::sha3::shake128;
+    use crypto_playground::sha3::types::ShakeVariant;
+
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<TraceCommand>, UsageError> {
+        TraceCommand::parse(&mut ArgParser::new(args.iter().map(OsString::from)))
+    }
+
+    fn run_args(args: &[&str]) -> (i32, String, String) {
+        let command = parse(args).unwrap().unwrap();
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let exit_code = run(&command, &mut out, &mut err).unwrap();
+        (exit_code, String::from_utf8(out).unwrap(), String::from_utf8(err).unwrap())
+    }
+
+    #[test]
+    fn test_parse() {
+        let command = parse(&["--alg", "shake128", "-l", "16", "--msg-hex", "6162", "--layout", "bytes",
+            "--rounds", "0,22-23", "--steps", "absorb,chi,iota", "--diff"]).unwrap().unwrap();
+        assert_eq!(TraceCommand {
+            algorithm: Algorithm::Shake(ShakeVariant::SHAKE128),
+            output_bits: 16,
+            message: b"ab".to_vec(),
+            layout: StateLayout::Bytes,
+            rounds: vec![0, 22, 23],
+            absorb: true,
+            steps: vec![StepMapping::Chi, StepMapping::Iota],
+            diff: true,
+        }, command);
+
+        let command = parse(&["--msg", "abc", "--steps", "theta"]).unwrap().unwrap();
+        assert_eq!(Algorithm::Sha3(Sha3Variant::SHA3_256), command.algorithm);
+        assert_eq!(256, command.output_bits);
+        assert_eq!(b"abc".to_vec(), command.message);
+        assert_eq!(StateLayout::Lanes, command.layout);
+        assert_eq!(KECCAK_NR, command.rounds.len());
+        assert!(!command.absorb);
+
+        assert!(parse(&["-h"]).unwrap().is_none());
+        assert!(parse(&["--msg-hex", "6"]).is_err());
+        assert!(parse(&["-l", "16"]).is_err());
+        assert!(parse(&["-a", "shake256", "-l", "12"]).is_err());
+        assert!(parse(&["--layout", "grid"]).is_err());
+        assert!(parse(&["--rounds", "24"]).is_err());
+        assert!(parse(&["--rounds", "3-1"]).is_err());
+        assert!(parse(&["--rounds", "1,"]).is_err());
+        assert!(parse(&["--steps", "omega"]).is_err());
+        assert!(parse(&["message"]).is_err());
+    }
+
+    #[test]
+    fn test_trace() {
+        let (exit_code, out, err) = run_args(&["--msg", "abc"]);
+        assert_eq!(EXIT_SUCCESS, exit_code);
+        assert!(err.is_empty());
+        assert!(out.starts_with("SHA3-256 of 3 bytes, rate 1088 bits, capacity 512 bits\n== block 0 absorbed\n"), "{out}");
+        assert_eq!(1 + KECCAK_NR * 5 + 1, out.matches("== ").count());
+        assert_eq!((1 + KECCAK_NR * 5) * 25, out.matches("] = ").count());
+        assert!(out.ends_with(&format!("== output\n{}\n", hex::encode(&sha3_256(b"abc")[..]))), "{out}");
+        assert!(!out.contains('^'));
+
+        // the first lane of the last state holds the first 8 bytes of the digest
+        let (_, out, _) = run_args(&["--msg", "abc", "--rounds", "23", "--steps", "iota"]);
+        let lane0 = u64::from_le_bytes(sha3_256(b"abc")[..8].try_into().unwrap());
+        assert_eq!(format!("\
+SHA3-256 of 3 bytes, rate 1088 bits, capacity 512 bits
+== permutation 0, round 23, iota
+  [0][0] = {lane0:016X}
+"), out.lines().take(3).map(|line| format!("{line}\n")).collect::<String>());
+    }
+
+    #[test]
+    fn test_diff() {
+        // the 01 suffix, the padding and "abc" set 13 bits of the zero state
+        let (_, out, _) = run_args(&["--msg", "abc", "--steps", "absorb", "--diff"]);
+        assert!(out.contains("== block 0 absorbed (13 bits changed)\n  [0][0] = 0000000006636261\n^ [0][0]   0000000006636261 (12 bits)\n"), "{out}");
+        assert!(out.contains("^ [1][3]   8000000000000000 (1 bit)\n"), "{out}");
+
+        // iota only flips bits of lane [0][0]
+        let (_, out, _) = run_args(&["--layout", "bytes", "--rounds", "0", "--steps", "iota", "--diff"]);
+        assert!(out.contains("== permutation 0, round 0, iota (1 bit changed)\n"), "{out}");
+        assert_eq!(1, out.matches("\n^ ").count(), "{out}");
+        assert!(out.contains("\n^ 01 .. .. .. .. .. .. .. .. .. .. .. .. .. .. ..\n"), "{out}");
+    }
+
+    #[test]
+    fn test_squeeze_permutations() {
+        let (_, out, _) = run_args(&["-a", "shake128", "-l", "2048", "--steps", "iota", "--rounds", "23"]);
+        assert!(out.contains("== permutation 1, round 23, iota\n"), "{out}");
+        assert!(out.ends_with(&format!("== output\n{}\n", hex::encode(&shake128(b"", 2048)[..]))), "{out}");
+    }
+}
@@ -0,0 +1,261 @@
+//! `tree` command: manifest and root digest of a directory tree, see `sha3::tree`.
+
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::PathBuf;
+
+use crypto_playground::sha3::registry::Algorithm;
+use crypto_playground::sha3::tree::Glob;
+use crypto_playground::sha3::tree::Manifest;
+use crypto_playground::sha3::tree::SymlinkPolicy;
+use crypto_playground::sha3::tree::TreeOptions;
+use crypto_playground::sha3::tree::hash_tree;
+use crypto_playground::sha3::tree::verify_tree;
+use crypto_playground::sha3::types::Sha3Variant;
+
+use crate::cli::EXIT_FAILURE;
+use crate::cli::EXIT_MALFORMED;
+use crate::cli::EXIT_MISMATCH;
+use crate::cli::EXIT_MISSING;
+use crate::cli::EXIT_SUCCESS;
+use crate::cli::PROGRAM;
+use crate::cli::algorithm::parse_algorithm;
+use crate::cli::args::Arg;
+use crate::cli::args::ArgParser;
+use crate::cli::args::UsageError;
+
+
+pub fn usage() -> String {
+    format!("\
+Usage: {PROGRAM} tree [OPTION]... DIR
+Hash every file under DIR on a pool of threads and print a manifest sorted by path,
+ending with the root digest of the tree.
+
+  -a, --algorithm ALG    sha3-224, sha3-256 (default), sha3-384 or sha3-512
+  -o, --output FILE      write the manifest to FILE instead of standard output
+      --include GLOB     list only files matching GLOB; can be repeated
+      --exclude GLOB     leave out files and directories matching GLOB; can be repeated
+      --symlinks POLICY  skip (default), follow, or record the link target
+  -j, --jobs N           number of worker threads (default: number of CPUs)
+      --root             print only the root digest
+      --verify MANIFEST  compare DIR with MANIFEST and list added, removed and
+                         changed files
+  -h, --help             print this help
+
+A GLOB without '/' matches file names, otherwise paths relative to DIR; '*' stays
+within a directory and '**' crosses directories.
+
+Exit status with --verify: 0 if the tree matches, {EXIT_MISMATCH} if files differ, {EXIT_MISSING} if a file
+could not be read, {EXIT_MALFORMED} for an improperly formatted manifest.
+")
+}
+
+
+#[derive(Debug)]
+pub struct TreeCommand {
+    pub dir: PathBuf,
+    pub options: TreeOptions,
+    pub output: Option<PathBuf>,
+    pub root_only: bool,
+    pub verify: Option<PathBuf>,
+}
+
+impl TreeCommand {
+    /// Parses the arguments after `tree`; None if help was requested.
+    pub fn parse(parser: &mut ArgParser) -> Result<Option<TreeCommand>, UsageError> {
+        let mut options = TreeOptions::default();
+        let mut output = None;
+        let mut root_only = false;
+        let mut verify = None;
+        let mut dirs: Vec<OsString> = Vec::new();
+        while let Some(arg) = parser.next_arg()? {
+            match &arg {
+                Arg::Short('h') => return Ok(None),
+                Arg::Long(name) if name == "help" => return Ok(None),
+                Arg::Short('a') => options.sha3_variant = parse_variant(&parser.value(&arg)?)?,
+                Arg::Long(name) if name == "algorithm" => options.sha3_variant = parse_variant(&parser.value(&arg)?)?,
+                Arg::Short('o') => output = Some(PathBuf::from(parser.value(&arg)?)),
+                Arg::Long(name) if name == "output" => output = Some(PathBuf::from(parser.value(&arg)?)),
+                Arg::Long(name) if name == "include" => options.include.push(parse_glob(&parser.value(&arg)?)?),
+                Arg::Long(name) if name == "exclude" => options.exclude.push(parse_glob(&parser.value(&arg)?)?),
+                Arg::Long(name) if name == "symlinks" => options.symlinks = parse_symlinks(&parser.value(&arg)?)?,
+                Arg::Short('j') => options.threads = parse_jobs(parser.value_usize(&arg)?)?,
+                Arg::Long(name) if name == "jobs" => options.threads = parse_jobs(parser.value_usize(&arg)?)?,
+                Arg::Long(name) if name == "root" => root_only = true,
+                Arg::Long(name) if name == "verify" => verify = Some(PathBuf::from(parser.value(&arg)?)),
+                Arg::Operand(dir) => dirs.push(dir.clone()),
+                _ => return Err(UsageError(format!("unknown option '{arg}'"))),
+            }
+        }
+        let [dir] = &dirs[..] else {
+            return Err(UsageError("tree needs exactly one directory".to_string()));
+        };
+        if verify.is_some() && (output.is_some() || root_only) {
+            return Err(UsageError("--verify cannot be combined with --output or --root".to_string()));
+        }
+        Ok(Some(TreeCommand { dir: PathBuf::from(dir), options, output, root_only, verify }))
+    }
+}
+
+fn parse_variant(arg: &str) -> Result<Sha3Variant, UsageError> {
+    match parse_algorithm(arg)? {
+        Algorithm::Sha3(sha3_variant) => Ok(sha3_variant),
+        _ => Err(UsageError(format!("tree only supports the SHA3 hashes, not '{arg}'"))),
+    }
+}
+
+fn parse_glob(arg: &str) -> Result<Glob, UsageError> {
+    Glob::new(arg).map_err(|error| UsageError(error.to_string()))
+}
+
+fn parse_symlinks(arg: &str) -> Result<SymlinkPolicy, UsageError> {
+    match arg {
+        "skip" => Ok(SymlinkPolicy::Skip),
+        "follow" => Ok(SymlinkPolicy::Follow),
+        "record" => Ok(SymlinkPolicy::Record),
+        _ => Err(UsageError(format!("unknown symlink policy '{arg}', expected skip, follow or record"))),
+    }
+}
+
+fn parse_jobs(jobs: usize) -> Result<usize, UsageError> {
+    if jobs == 0 {
+        return Err(UsageError("--jobs must be at least 1".to_string()));
+    }
+    Ok(jobs)
+}
+
+
+pub fn run(command: &TreeCommand, out: &mut impl Write, err: &mut impl Write) -> io::Result<i32> {
+    let dir_name = command.dir.display();
+    if let Some(manifest_path) = &command.verify {
+        let text = match fs::read_to_string(manifest_path) {
+            Ok(text) => text,
+            Err(error) => {
+                writeln!(err, "{PROGRAM}: {}: {error}", manifest_path.display())?;
+                return Ok(EXIT_MISSING);
+            }
+        };
+        let manifest = match Manifest::parse(&text) {
+            Ok(manifest) => manifest,
+            Err(error) => {
+                writeln!(err, "{PROGRAM}: {}: {error}", manifest_path.display())?;
+                return Ok(EXIT_MALFORMED);
+            }
+        };
+        let diff = match verify_tree(&command.dir, &manifest, &command.options) {
+            Ok(diff) => diff,
+            Err(error) => {
+                writeln!(err, "{PROGRAM}: {error}")?;
+                return Ok(EXIT_MISSING);
+            }
+        };
+        for (label, paths) in [("added", &diff.added), ("removed", &diff.removed), ("changed", &diff.changed)] {
+            for path in paths {
+                writeln!(out, "{label}: {}", path.escape_debug())?;
+            }
+        }
+        return Ok(if diff.is_empty() { EXIT_SUCCESS } else { EXIT_MISMATCH });
+    }
+
+    let manifest = match hash_tree(&command.dir, &command.options) {
+        Ok(manifest) => manifest,
+        Err(error) => {
+            writeln!(err, "{PROGRAM}: {error}")?;
+            return Ok(EXIT_FAILURE);
+        }
+    };
+    if command.root_only {
+        writeln!(out, "{}  {dir_name}", hex::encode(&manifest.root_digest()[..]))?;
+        return Ok(EXIT_SUCCESS);
+    }
+    match &command.output {
+        Some(path) => {
+            if let Err(error) = fs::write(path, manifest.to_text()) {
+                writeln!(err, "{PROGRAM}: {}: {error}", path.display())?;
+                return Ok(EXIT_FAILURE);
+            }
+        }
+        None => out.write_all(manifest.to_text().as_bytes())?,
+    }
+    Ok(EXIT_SUCCESS)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<TreeCommand>, UsageError> {
+        TreeCommand::parse(&mut ArgParser::new(args.iter().map(OsString::from)))
+    }
+
+    fn run_args(args: &[&str]) -> (i32, String, String) {
+        let command = parse(args).unwrap().unwrap();
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let exit_code = run(&command, &mut out, &mut err).unwrap();
+        (exit_code, String::from_utf8(out).unwrap(), String::from_utf8(err).unwrap())
+    }
+
+    #[test]
+    fn test_parse() {
+        let command = parse(&["-a", "512", "--include", "*.rs", "--exclude=target", "--symlinks", "record", "-j2", "src"]).unwrap().unwrap();
+        assert_eq!(PathBuf::from("src"), command.dir);
+        assert_eq!(Sha3Variant::SHA3_512, command.options.sha3_variant);
+        assert_eq!(vec!["*.rs"], command.options.include.iter().map(Glob::as_str).collect::<Vec<_>>());
+        assert_eq!(SymlinkPolicy::Record, command.options.symlinks);
+        assert_eq!(2, command.options.threads);
+
+        assert!(parse(&["--help"]).unwrap().is_none());
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["a", "b"]).is_err());
+        assert!(parse(&["-a", "shake128", "dir"]).is_err());
+        assert!(parse(&["--symlinks", "maybe", "dir"]).is_err());
+        assert!(parse(&["-j", "0", "dir"]).is_err());
+        assert!(parse(&["--include", "[a", "dir"]).is_err());
+        assert!(parse(&["--verify", "m", "--root", "dir"]).is_err());
+    }
+
+    #[test]
+    fn test_manifest_and_verify() {
+        let dir = env::temp_dir().join(format!("crypto-playground-cli-tree-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("tree/sub")).unwrap();
+        fs::write(dir.join("tree/a.txt"), "a").unwrap();
+        fs::write(dir.join("tree/sub/b.txt"), "b").unwrap();
+        let tree = dir.join("tree");
+        let tree = tree.to_str().unwrap();
+        let manifest_path = dir.join("tree.manifest");
+        let manifest_path = manifest_path.to_str().unwrap();
+
+        let (exit_code, out, _) = run_args(&[tree]);
+        assert_eq!(EXIT_SUCCESS, exit_code);
+        assert!(out.starts_with("sha3-tree 1 SHA3-256\nf "), "{out}");
+        let (_, root, _) = run_args(&["--root", tree]);
+        assert!(out.ends_with(&format!("# root {}", root.replace(&format!("  {tree}"), ""))), "{out}");
+
+        assert_eq!(EXIT_SUCCESS, run_args(&["-o", manifest_path, tree]).0);
+        assert_eq!(out, fs::read_to_string(manifest_path).unwrap());
+        assert_eq!((EXIT_SUCCESS, String::new()), {
+            let (exit_code, out, _) = run_args(&["--verify", manifest_path, tree]);
+            (exit_code, out)
+        });
+
+        fs::write(dir.join("tree/a.txt"), "A").unwrap();
+        fs::write(dir.join("tree/c.txt"), "c").unwrap();
+        fs::remove_file(dir.join("tree/sub/b.txt")).unwrap();
+        let (exit_code, out, _) = run_args(&["--verify", manifest_path, tree]);
+        assert_eq!(EXIT_MISMATCH, exit_code);
+        assert_eq!("added: c.txt\nremoved: sub/b.txt\nchanged: a.txt\n", out);
+
+        fs::write(manifest_path, "not a manifest\n").unwrap();
+        assert_eq!(EXIT_MALFORMED, run_args(&["--verify", manifest_path, tree]).0);
+        fs::remove_file(manifest_path).unwrap();
+        assert_eq!(EXIT_MISSING, run_args(&["--verify", manifest_path, tree]).0);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,321 @@
+//! `visualize` command: draws the Keccak state as text or SVG, with the bits a step mapping
+//! changed or combines marked.
+
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::PathBuf;
+
+use crypto_playground::sha3::constants::KECCAK_B;
+use crypto_playground::sha3::constants::KECCAK_CONSTANTS;
+use crypto_playground::sha3::trace::StepMapping;
+use crypto_playground::sha3::types::State;
+use crypto_playground::sha3::visualize::Frame;
+use crypto_playground::sha3::visualize::Marks;
+use crypto_playground::sha3::visualize::View;
+use crypto_playground::sha3::visualize::permutation_frames;
+use crypto_playground::sha3::visualize::render_svg;
+use crypto_playground::sha3::visualize::render_text;
+use crypto_playground::sha3::visualize::state_from_bytes;
+use crypto_playground::sha3::visualize::text_legend;
+
+use crate::cli::EXIT_FAILURE;
+use crate::cli::EXIT_SUCCESS;
+use crate::cli::PROGRAM;
+use crate::cli::args::Arg;
+use crate::cli::args::ArgParser;
+use crate::cli::args::UsageError;
+use crate::cli::args::parse_ranges;
+
+
+pub fn usage() -> String {
+    format!("\
+Usage: {PROGRAM} visualize [OPTION]...
+Draw a Keccak state, the bits of the message followed by zeros, as the grid of its 25 lanes
+or as the slices of the 5 x 5 x w cube. As in the figures of FIPS 202, x runs left to right
+as 3, 4, 0, 1, 2 and y top to bottom as 2, 1, 0, 4, 3.
+
+  -b, --width B        width of the state: 25, 50, 100, 200, 400, 800 or 1600 (default {KECCAK_B})
+      --msg-hex HEX    the first bytes of the state string S in hexadecimal (default: all zero)
+      --msg TEXT       the first bytes of S as text
+      --view VIEW      lanes: a lane per line, z from left to right (default);
+                       slices: the slices side by side, z = 0 first
+      --format FORMAT  ascii, ansi (colors for the terminal) or svg (default ascii)
+      --frames         draw the state after each step mapping of KECCAK-p[b, 12 + 2l],
+                       marking the bits the step changed
+      --rounds LIST    with --frames, only draw these rounds, e.g. 0,1
+      --steps LIST     with --frames, only draw these of theta,rho,pi,chi,iota
+      --explain STEP   mark the bits that STEP (theta, rho, pi, chi or iota) combines into the
+                       bit given by --bit, as in FIPS 202 Figures 3 to 6
+      --bit X,Y,Z      the output bit of --explain (default 0,0,0)
+  -o, --output FILE    write the drawing to FILE instead of standard output
+  -h, --help           print this help
+")
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Ascii,
+    Ansi,
+    Svg,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Drawing {
+    /// The state alone.
+    State,
+    /// The state after the selected steps of the permutation.
+    Frames { rounds: Vec<usize>, steps: Vec<StepMapping> },
+    /// The inputs of a step mapping for one output bit.
+    Explain { step: StepMapping, bit: (usize, usize, usize) },
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct VisualizeCommand {
+    pub width: usize,
+    pub message: Vec<u8>,
+    pub view: View,
+    pub format: Format,
+    pub drawing: Drawing,
+    pub output: Option<PathBuf>,
+}
+
+impl VisualizeCommand {
+    /// Parses the arguments after `visualize`; None if help was requested.
+    pub fn parse(parser: &mut ArgParser) -> Result<Option<VisualizeCommand>, UsageError> {
+        let mut width = KECCAK_B;
+        let mut message = Vec::new();
+        let mut view = View::Lanes;
+        let mut format = Format::Ascii;
+        let mut frames = false;
+        let mut rounds = None;
+        let mut steps = None;
+        let mut explain = None;
+        let mut bit = None;
+        let mut output = None;
+        while let Some(arg) = parser.next_arg()? {
+            match &arg {
+                Arg::Short('h') => return Ok(None),
+                Arg::Long(name) if name == "help" => return Ok(None),
+                Arg::Short('b') => width = parser.value_usize(&arg)?,
+                Arg::Long(name) if name == "width" => width = parser.value_usize(&arg)?,
+                Arg::Long(name) if name == "msg-hex" => {
+                    let hex = parser.value(&arg)?;
+                    message = hex::decode(&hex).map_err(|_| UsageError(format!("invalid message '{hex}', expected hexadecimal")))?;
+                }
+                Arg::Long(name) if name == "msg" => message = parser.value(&arg)?.into_bytes(),
+                Arg::Long(name) if name == "view" => view = parse_view(&parser.value(&arg)?)?,
+                Arg::Long(name) if name == "format" => format = parse_format(&parser.value(&arg)?)?,
+                Arg::Long(name) if name == "frames" => frames = true,
+                Arg::Long(name) if name == "rounds" => rounds = Some(parser.value(&arg)?),
+                Arg::Long(name) if name == "steps" => steps = Some(parse_steps(&parser.value(&arg)?)?),
+                Arg::Long(name) if name == "explain" => explain = Some(parse_step(&parser.value(&arg)?)?),
+                Arg::Long(name) if name == "bit" => bit = Some(parser.value(&arg)?),
+                Arg::Short('o') => output = Some(PathBuf::from(parser.value(&arg)?)),
+                Arg::Long(name) if name == "output" => output = Some(PathBuf::from(parser.value(&arg)?)),
+                _ => return Err(UsageError(format!("unknown option '{arg}'"))),
+            }
+        }
+        let nr = KECCAK_CONSTANTS.iter()
+            .find(|constants| constants[2] == width)
+            .map(|constants| 12 + 2 * constants[0])
+            .ok_or_else(|| UsageError(format!("invalid width {width}, expected 25, 50, 100, 200, 400, 800 or 1600")))?;
+        let w = width / 25;
+        if 8 * message.len() > width {
+            return Err(UsageError(format!("message of {} bytes longer than the state of {width} bits", message.len())));
+        }
+        if !frames && (rounds.is_some() || steps.is_some()) {
+            return Err(UsageError("--rounds and --steps only apply to --frames".to_string()));
+        }
+        if explain.is_none() && bit.is_some() {
+            return Err(UsageError("--bit only applies to --explain".to_string()));
+        }
+        let drawing = match (frames, explain) {
+            (true, Some(_)) => return Err(UsageError("--frames and --explain cannot be combined".to_string())),
+            (true, None) => {
+                let rounds = match rounds {
+                    Some(list) => parse_ranges(&list, nr - 1)
+                        .ok_or_else(|| UsageError(format!("invalid rounds '{list}', expected e.g. 0,1 within 0-{}", nr - 1)))?,
+                    None => (0..nr).collect(),
+                };
+                Drawing::Frames { rounds, steps: steps.unwrap_or(StepMapping::ALL.to_vec()) }
+            }
+            (false, Some(step)) => Drawing::Explain { step, bit: parse_bit(&bit.unwrap_or("0,0,0".to_string()), w)? },
+            (false, None) => Drawing::State,
+        };
+        Ok(Some(VisualizeCommand { width, message, view, format, drawing, output }))
+    }
+}
+
+fn parse_view(arg: &str) -> Result<View, UsageError> {
+    match arg {
+        "lanes" => Ok(View::Lanes),
+        "slices" => Ok(View::Slices),
+        _ => Err(UsageError(format!("unknown view '{arg}', expected lanes or slices"))),
+    }
+}
+
+fn parse_format(arg: &str) -> Result<Format, UsageError> {
+    match arg {
+        "ascii" => Ok(Format::Ascii),
+        "ansi" => Ok(Format::Ansi),
+        "svg" => Ok(Format::Svg),
+        _ => Err(UsageError(format!("unknown format '{arg}', expected ascii, ansi or svg"))),
+    }
+}
+
+fn parse_step(name: &str) -> Result<StepMapping, UsageError> {
+    StepMapping::ALL.into_iter()
+        .find(|step| step.name() == name)
+        .ok_or_else(|| UsageError(format!("unknown step '{name}', expected theta, rho, pi, chi or iota")))
+}
+
+fn parse_steps(list: &str) -> Result<Vec<StepMapping>, UsageError> {
+    list.split(',').map(parse_step).collect()
+}
+
+/// Parses the coordinates x,y,z of a bit of a state of lane size w.
+fn parse_bit(arg: &str, w: usize) -> Result<(usize, usize, usize), UsageError> {
+    let invalid = || UsageError(format!("invalid bit '{arg}', expected X,Y,Z with X and Y in 0-4 and Z in 0-{}", w - 1));
+    let coordinates: Vec<usize> = arg.split(',')
+        .map(|coordinate| coordinate.parse().map_err(|_| invalid()))
+        .collect::<Result<_, _>>()?;
+    match coordinates[..] {
+        [x, y, z] if x < 5 && y < 5 && z < w => Ok((x, y, z)),
+        _ => Err(invalid()),
+    }
+}
+
+
+/// The frames to draw: the state, the permutation step by step, or the inputs of one bit.
+fn frames(command: &VisualizeCommand, a: &State) -> Vec<Frame> {
+    let w = a.len();
+    match &command.drawing {
+        Drawing::State => vec![Frame { title: format!("state, b = {}", command.width), state: a.clone(), marks: Marks::none(w) }],
+        Drawing::Frames { rounds, steps } => {
+            let nr = KECCAK_CONSTANTS.iter()
+                .find(|constants| constants[2] == command.width)
+                .map_or(0, |constants| 12 + 2 * constants[0]);
+            permutation_frames(a, nr, |round, step| rounds.contains(&round) && steps.contains(&step))
+        }
+        &Drawing::Explain { step, bit: (x, y, z) } => vec![Frame {
+            title: format!("{}: A'[{x}][{y}][{z}] from the marked bits of A", step.name()),
+            state: a.clone(),
+            marks: Marks::step_sources(step, w, (x, y, z)),
+        }],
+    }
+}
+
+fn render(command: &VisualizeCommand, frames: &[Frame]) -> String {
+    let ansi = match command.format {
+        Format::Svg => return render_svg(frames, command.view),
+        Format::Ascii => false,
+        Format::Ansi => true,
+    };
+    let mut text = format!("{}\n", text_legend(ansi));
+    for frame in frames {
+        text.push_str(&format!("\n== {}\n", frame.title));
+        text.push_str(&render_text(&frame.state, command.view, &frame.marks, ansi));
+    }
+    text
+}
+
+pub fn run(command: &VisualizeCommand, out: &mut impl Write, err: &mut impl Write) -> io::Result<i32> {
+    let a = state_from_bytes(command.width / 25, &command.message).expect("message checked when parsing");
+    let drawing = render(command, &frames(command, &a));
+    match &command.output {
+        Some(path) => {
+            if let Err(error) = fs::write(path, drawing) {
+                writeln!(err, "{PROGRAM}: {}: {error}", path.display())?;
+                return Ok(EXIT_FAILURE);
+            }
+        }
+        None => out.write_all(drawing.as_bytes())?,
+    }
+    Ok(EXIT_SUCCESS)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::ffi::OsString;
+
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<VisualizeCommand>, UsageError> {
+        VisualizeCommand::parse(&mut ArgParser::new(args.iter().map(OsString::from)))
+    }
+
+    fn run_args(args: &[&str]) -> (i32, String, String) {
+        let command = parse(args).unwrap().unwrap();
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let exit_code = run(&command, &mut out, &mut err).unwrap();
+        (exit_code, String::from_utf8(out).unwrap(), String::from_utf8(err).unwrap())
+    }
+
+    #[test]
+    fn test_parse() {
+        let command = parse(&["-b", "200", "--msg-hex", "0102", "--view", "slices", "--format", "svg",
+            "--frames", "--rounds", "0,17", "--steps", "theta,chi", "-o", "frames.svg"]).unwrap().unwrap();
+        assert_eq!(VisualizeCommand {
+            width: 200,
+            message: vec![1, 2],
+            view: View::Slices,
+            format: Format::Svg,
+            drawing: Drawing::Frames { rounds: vec![0, 17], steps: vec![StepMapping::Theta, StepMapping::Chi] },
+            output: Some(PathBuf::from("frames.svg")),
+        }, command);
+
+        let command = parse(&["--explain", "rho", "--bit", "1,0,63"]).unwrap().unwrap();
+        assert_eq!(Drawing::Explain { step: StepMapping::Rho, bit: (1, 0, 63) }, command.drawing);
+        assert_eq!(Drawing::State, parse(&[]).unwrap().unwrap().drawing);
+        assert!(parse(&["--help"]).unwrap().is_none());
+
+        assert!(parse(&["-b", "300"]).is_err());
+        assert!(parse(&["-b", "25", "--msg", "abcd"]).is_err());
+        assert!(parse(&["--view", "cube"]).is_err());
+        assert!(parse(&["--rounds", "0"]).is_err());
+        assert!(parse(&["--frames", "-b", "200", "--rounds", "18"]).is_err());
+        assert!(parse(&["--bit", "0,0,0"]).is_err());
+        assert!(parse(&["--explain", "chi", "--bit", "0,0,64"]).is_err());
+        assert!(parse(&["--explain", "chi", "--bit", "0,0"]).is_err());
+        assert!(parse(&["--explain", "chi", "--frames"]).is_err());
+    }
+
+    #[test]
+    fn test_run() {
+        let (exit_code, out, err) = run_args(&["-b", "50", "--msg-hex", "01"]);
+        assert_eq!((EXIT_SUCCESS, ""), (exit_code, err.as_str()));
+        assert!(out.contains("\n== state, b = 50\n"), "{out}");
+        assert!(out.contains("\nx=0 y=0  #.\n"), "{out}");
+
+        let (_, out, _) = run_args(&["-b", "50", "--frames", "--rounds", "0"]);
+        assert_eq!(5, out.matches("\n== round 0, ").count());
+        assert!(out.contains("\n== round 0, iota\n"), "{out}");
+        assert!(out.contains("\nx=0 y=0  @.\n"), "{out}");
+
+        let (_, out, _) = run_args(&["-b", "25", "--view", "slices", "--explain", "chi", "--bit", "4,0,0"]);
+        assert!(out.contains("== chi: A'[4][0][0] from the marked bits of A\nz=0\n.....\n.....\n.---.\n"), "{out}");
+
+        let (_, out, _) = run_args(&["-b", "25", "--format", "svg"]);
+        assert!(out.starts_with("<svg "));
+    }
+
+    #[test]
+    fn test_output_file() {
+        let dir = env::temp_dir().join(format!("visualize-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("state.svg");
+        let (exit_code, out, _) = run_args(&["-b", "100", "--format", "svg", "--view", "slices", "-o", path.to_str().unwrap()]);
+        assert_eq!((EXIT_SUCCESS, ""), (exit_code, out.as_str()));
+        assert!(fs::read_to_string(&path).unwrap().ends_with("</svg>\n"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        let (exit_code, _, err) = run_args(&["-o", dir.join("missing/state.txt").to_str().unwrap()]);
+        assert_eq!(EXIT_FAILURE, exit_code);
+        assert!(err.contains("state.txt"), "{err}");
+    }
+}
@@ -0,0 +1,266 @@
+//! `xof` command: SHAKE or cSHAKE output of any length, e.g. as deterministic test data.
+
+use std::ffi::OsString;
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::time::Instant;
+
+use crypto_playground::sha3::registry::Algorithm;
+use crypto_playground::sha3::sha3::ShakeReader;
+use crypto_playground::sha3::sp800_185::CShake;
+use crypto_playground::sha3::types::ShakeVariant;
+
+use crate::cli::EXIT_FAILURE;
+use crate::cli::EXIT_SUCCESS;
+use crate::cli::PROGRAM;
+use crate::cli::algorithm::parse_algorithm;
+use crate::cli::args::Arg;
+use crate::cli::args::ArgParser;
+use crate::cli::args::UsageError;
+
+
+/// Output is squeezed and written in blocks of this size.
+const CHUNK_LEN: usize = 64 * 1024;
+
+
+pub fn usage() -> String {
+    format!("\
+Usage: {PROGRAM} xof [OPTION]... [FILE]
+Absorb a seed from FILE, standard input or --seed-hex, then write the SHAKE output to
+standard output: N bytes with -n, otherwise without end until the output is closed.
+
+  -a, --algorithm ALG    shake128 or shake256 (default)
+      --seed-hex HEX     absorb the bytes HEX instead of reading FILE
+  -S, --custom S         cSHAKE customization string (none gives plain SHAKE)
+  -n, --bytes N          write exactly N bytes
+      --skip N           start at byte offset N of the output stream
+  -v, --verbose          report the bytes written and the throughput on standard error
+  -h, --help             print this help
+")
+}
+
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Seed {
+    /// A file, "-" being stdin.
+    File(OsString),
+    Bytes(Vec<u8>),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct XofCommand {
+    pub shake_variant: ShakeVariant,
+    pub seed: Seed,
+    pub customization: String,
+    /// Output length in bytes; unbounded when None.
+    pub len: Option<u64>,
+    pub skip: u64,
+    pub verbose: bool,
+}
+
+impl XofCommand {
+    /// Parses the arguments after `xof`; None if help was requested.
+    pub fn parse(parser: &mut ArgParser) -> Result<Option<XofCommand>, UsageError> {
+        let mut shake_variant = ShakeVariant::SHAKE256;
+        let mut seed_bytes = None;
+        let mut customization = String::new();
+        let mut len = None;
+        let mut skip = 0;
+        let mut verbose = false;
+        let mut files = Vec::new();
+        while let Some(arg) = parser.next_arg()? {
+            match &arg {
+                Arg::Short('h') => return Ok(None),
+                Arg::Long(name) if name == "help" => return Ok(None),
+                Arg::Short('a') => shake_variant = parse_variant(&parser.value(&arg)?)?,
+                Arg::Long(name) if name == "algorithm" => shake_variant = parse_variant(&parser.value(&arg)?)?,
+                Arg::Long(name) if name == "seed-hex" => {
+                    let seed = parser.value(&arg)?;
+                    seed_bytes = Some(hex::decode(&seed).map_err(|_| UsageError(format!("invalid seed '{seed}', expected hexadecimal")))?);
+                }
+                Arg::Short('S') => customization = parser.value(&arg)?,
+                Arg::Long(name) if name == "custom" => customization = parser.value(&arg)?,
+                Arg::Short('n') => len = Some(parser.value_usize(&arg)? as u64),
+                Arg::Long(name) if name == "bytes" => len = Some(parser.value_usize(&arg)? as u64),
+                Arg::Long(name) if name == "skip" => skip = parser.value_usize(&arg)? as u64,
+                Arg::Short('v') => verbose = true,
+                Arg::Long(name) if name == "verbose" => verbose = true,
+                Arg::Operand(file) => files.push(file.clone()),
+                _ => return Err(UsageError(format!("unknown option '{arg}'"))),
+            }
+        }
+        let seed = match (seed_bytes, &files[..]) {
+            (Some(bytes), []) => Seed::Bytes(bytes),
+            (None, []) => Seed::File(OsString::from("-")),
+            (None, [file]) => Seed::File(file.clone()),
+            (Some(_), _) => return Err(UsageError("--seed-hex replaces the FILE".to_string())),
+            (None, _) => return Err(UsageError("xof absorbs a single FILE".to_string())),
+        };
+        Ok(Some(XofCommand { shake_variant, seed, customization, len, skip, verbose }))
+    }
+
+    /// Absorbs the seed and returns the reader positioned at the `--skip` offset.
+    pub fn reader(&self) -> io::Result<ShakeReader> {
+        let mut cshake = CShake::new(self.shake_variant, b"", self.customization.as_bytes());
+        match &self.seed {
+            Seed::Bytes(bytes) => cshake.update(bytes),
+            Seed::File(file) if file == "-" => {
+                io::copy(&mut io::stdin().lock(), &mut cshake)?;
+            }
+            Seed::File(file) => {
+                io::copy(&mut File::open(file)?, &mut cshake)?;
+            }
+        }
+        let mut reader = cshake.finalize_xof();
+        reader.skip(self.skip);
+        Ok(reader)
+    }
+}
+
+fn parse_variant(arg: &str) -> Result<ShakeVariant, UsageError> {
+    match parse_algorithm(arg)? {
+        Algorithm::Shake(shake_variant) => Ok(shake_variant),
+        _ => Err(UsageError(format!("xof only supports shake128 and shake256, not '{arg}'"))),
+    }
+}
+
+
+/// An unbounded stream ends when its reader goes away; whatever is still buffered is lost.
+fn is_closed_pipe(len: Option<u64>, error: &io::Error) -> bool {
+    len.is_none() && error.kind() == io::ErrorKind::BrokenPipe
+}
+
+/// Squeezes the output into `out` until `len` bytes are written or, when unbounded, until
+/// `out` reports a broken pipe. Returns the number of bytes written.
+fn stream(reader: &mut ShakeReader, len: Option<u64>, out: &mut impl Write) -> io::Result<u64> {
+    let mut chunk = vec![0u8; CHUNK_LEN];
+    let mut written = 0u64;
+    while len.is_none_or(|len| written < len) {
+        let chunk_len = len.map_or(CHUNK_LEN, |len| (len - written).min(CHUNK_LEN as u64) as usize);
+        reader.squeeze(&mut chunk[..chunk_len]);
+        match out.write_all(&chunk[..chunk_len]) {
+            Err(error) if is_closed_pipe(len, &error) => break,
+            result => result?,
+        }
+        written += chunk_len as u64;
+    }
+    Ok(written)
+}
+
+pub fn run(command: &XofCommand, out: &mut impl Write, err: &mut impl Write) -> io::Result<i32> {
+    let mut reader = match command.reader() {
+        Ok(reader) => reader,
+        Err(error) => {
+            let Seed::File(file) = &command.seed else { unreachable!("only files can fail") };
+            writeln!(err, "{PROGRAM}: {}: {error}", file.to_string_lossy())?;
+            return Ok(EXIT_FAILURE);
+        }
+    };
+    let start = Instant::now();
+    let written = stream(&mut reader, command.len, out)?;
+    match out.flush() {
+        Err(error) if is_closed_pipe(command.len, &error) => {}
+        result => result?,
+    }
+    if command.verbose {
+        let seconds = start.elapsed().as_secs_f64();
+        let mib_per_second = written as f64 / (1024.0 * 1024.0) / seconds.max(1e-9);
+        writeln!(err, "{PROGRAM}: {written} bytes in {seconds:.3} s ({mib_per_second:.1} MiB/s)")?;
+    }
+    Ok(EXIT_SUCCESS)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crypto_playground::sha3::sha3::shake128;
+    use crypto_playground::sha3::sp800_185::cshake256;
+
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<XofCommand>, UsageError> {
+        XofCommand::parse(&mut ArgParser::new(args.iter().map(OsString::from)))
+    }
+
+    fn run_args(args: &[&str]) -> (i32, Vec<u8>, String) {
+        let command = parse(args).unwrap().unwrap();
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let exit_code = run(&command, &mut out, &mut err).unwrap();
+        (exit_code, out, String::from_utf8(err).unwrap())
+    }
+
+    /// Accepts `capacity` bytes, then fails like a pipe whose reader has exited.
+    struct ClosingPipe {
+        received: Vec<u8>,
+        capacity: usize,
+    }
+
+    impl Write for ClosingPipe {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let len = buf.len().min(self.capacity - self.received.len());
+            if len == 0 {
+                return Err(io::Error::from(io::ErrorKind::BrokenPipe));
+            }
+            self.received.extend_from_slice(&buf[..len]);
+            Ok(len)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let command = parse(&["-a", "shake128", "--seed-hex", "00ff", "-S", "app", "-n", "10", "--skip", "5", "-v"]).unwrap().unwrap();
+        assert_eq!(XofCommand {
+            shake_variant: ShakeVariant::SHAKE128,
+            seed: Seed::Bytes(vec![0x00, 0xff]),
+            customization: "app".to_string(),
+            len: Some(10),
+            skip: 5,
+            verbose: true,
+        }, command);
+        assert_eq!(Seed::File(OsString::from("-")), parse(&[]).unwrap().unwrap().seed);
+        assert_eq!(None, parse(&["seed"]).unwrap().unwrap().len);
+
+        assert!(parse(&["-h"]).unwrap().is_none());
+        assert!(parse(&["-a", "sha3-256"]).is_err());
+        assert!(parse(&["--seed-hex", "0g"]).is_err());
+        assert!(parse(&["--seed-hex", "00", "seed"]).is_err());
+        assert!(parse(&["a", "b"]).is_err());
+    }
+
+    #[test]
+    fn test_output() {
+        let expected = shake128(b"abc", 8 * 1000);
+        assert_eq!(expected.to_vec(), run_args(&["-a", "shake128", "--seed-hex", "616263", "-n", "1000"]).1);
+        assert_eq!(expected[300..].to_vec(), run_args(&["-a", "shake128", "--seed-hex", "616263", "-n", "700", "--skip", "300"]).1);
+        assert!(run_args(&["--seed-hex", "", "-n", "0"]).1.is_empty());
+
+        let expected = cshake256(b"abc", 8 * 200, b"", b"app");
+        let (exit_code, out, err) = run_args(&["--seed-hex", "616263", "-S", "app", "-n", "200", "-v"]);
+        assert_eq!(EXIT_SUCCESS, exit_code);
+        assert_eq!(expected.to_vec(), out);
+        assert!(err.contains("200 bytes in"), "{err}");
+
+        let missing = std::env::temp_dir().join("crypto-playground-missing-seed");
+        assert_eq!(EXIT_FAILURE, run_args(&[missing.to_str().unwrap()]).0);
+    }
+
+    #[test]
+    fn test_unbounded_stops_at_broken_pipe() {
+        let command = parse(&["-a", "shake128", "--seed-hex", "616263"]).unwrap().unwrap();
+        let mut pipe = ClosingPipe { received: Vec::new(), capacity: 3 * CHUNK_LEN + 100 };
+        let mut err = Vec::new();
+        assert_eq!(EXIT_SUCCESS, run(&command, &mut pipe, &mut err).unwrap());
+        assert_eq!(shake128(b"abc", 8 * pipe.capacity).to_vec(), pipe.received);
+
+        // A bounded output must be complete.
+        let command = parse(&["--seed-hex", "616263", "-n", "1000"]).unwrap().unwrap();
+        let mut pipe = ClosingPipe { received: Vec::new(), capacity: 10 };
+        assert!(run(&command, &mut pipe, &mut err).is_err());
+    }
+}
@@ -1 +1,8 @@
-pub mod sha3;
\ No newline at end of file
+#![cfg_attr(not(feature = "std"), no_std)]
+// The step mappings are written with explicit x, y, z loops to follow the notation of the specs.
+#![allow(clippy::needless_range_loop)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+pub mod sha3;
@@ -1,10 +1,8 @@
-use crypto_playground::sha3::{sha3, types::{BitString, ByteString}};
+mod cli;
 
-fn main() {
-    let m = "Test";
-    let bytes = m.as_bytes();
-    let digest = hex::encode(sha3::sha3_256(bytes).as_slice());
-    println!("Digest for '{m}' : {digest}");
+use std::env;
+use std::process;
 
-    //let bytes = ByteString::from(vec![1, 1, 0, 0, 1]);
+fn main() {
+    process::exit(cli::run(env::args_os().skip(1)));
 }
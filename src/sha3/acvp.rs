@@ -0,0 +1,680 @@
+//! Runner for test vectors in the JSON format of NIST's Automated Cryptographic Validation
+//! Protocol (ACVP).
+//!
+//! A vector set comes as a `prompt.json` file holding the test groups and cases, and an
+//! `expectedResults.json` file holding the expected outputs under the same `tgId`/`tcId`.
+//! Both files are either the vector set object itself or an array whose first element is
+//! `{"acvVersion": ...}`. An `internalProjection.json` file, where the expected outputs are
+//! stored in the prompt, can be run on its own.
+//!
+//! All lengths (`len`, `msgLen`, `outLen`, ...) are in bits. Cases with lengths that are not
+//! multiples of 8 are skipped, as the crate only hashes byte-oriented data.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+use derive_more::{Display, Error};
+
+use crate::sha3::json::JsonError;
+use crate::sha3::json::JsonValue;
+use crate::sha3::json::parse_json;
+use crate::sha3::monte::MONTE_CHECKPOINTS;
+use crate::sha3::monte::check_monte;
+use crate::sha3::monte::check_shake_monte;
+use crate::sha3::registry::Algorithm;
+use crate::sha3::sha3::Sha3;
+use crate::sha3::sha3::Shake;
+use crate::sha3::sha3::sha3_family;
+use crate::sha3::sha3::shake_family;
+use crate::sha3::sp800_185::cshake;
+use crate::sha3::sp800_185::kmac;
+use crate::sha3::sp800_185::kmac_xof;
+use crate::sha3::sp800_185::parallel_hash;
+use crate::sha3::sp800_185::parallel_hash_xof;
+use crate::sha3::sp800_185::tuple_hash;
+use crate::sha3::sp800_185::tuple_hash_xof;
+use crate::sha3::types::ByteString;
+use crate::sha3::types::Sha3Variant;
+use crate::sha3::types::ShakeVariant;
+
+
+/// Whether ACVP vector sets can be run against `algorithm`: SHA3, SHAKE and the SP 800-185
+/// functions, whose XOF forms are selected by the `xof` field of the cases.
+pub fn is_acvp_algorithm(algorithm: &Algorithm) -> bool {
+    matches!(algorithm,
+        Algorithm::Sha3(_)
+        | Algorithm::Shake(_)
+        | Algorithm::CShake(_)
+        | Algorithm::Kmac(_)
+        | Algorithm::TupleHash(_)
+        | Algorithm::ParallelHash(_))
+}
+
+/// Maps the `algorithm` names used by ACVP, e.g. "SHA3-256", "SHAKE-128" or "KMAC-256", to the
+/// registry, whose names have no hyphen before the strength, apart from SHA3.
+pub fn algorithm_from_name(name: &str) -> Option<Algorithm> {
+    Algorithm::from_name(name)
+        .or_else(|| Algorithm::from_name(&name.replace('-', "")))
+        .filter(is_acvp_algorithm)
+}
+
+/// Whether the test groups of type `test_type` can be run; Monte Carlo tests are
+/// implemented for SHAKE and for the SHA3 hashes in their "standard" version.
+fn supports(algorithm: Algorithm, test_type: &str, group: &JsonValue) -> bool {
+    match (algorithm, test_type) {
+        (Algorithm::Sha3(_), "AFT" | "LDT") => true,
+        (Algorithm::Sha3(_), "MCT") => {
+            group.get("mctVersion").and_then(JsonValue::as_str).is_none_or(|version| version == "standard")
+        }
+        (Algorithm::Shake(_), "AFT" | "VOT" | "MCT" | "LDT") => true,
+        (Algorithm::Kmac(_), "AFT" | "MVT") => true,
+        (Algorithm::CShake(_) | Algorithm::TupleHash(_) | Algorithm::ParallelHash(_), "AFT") => true,
+        _ => false,
+    }
+}
+
+
+pub struct AcvpOptions {
+    /// Number of Monte Carlo checkpoints to check per test case; each costs 1000 hashes.
+    pub monte_checkpoints: usize,
+    /// Large Data Tests whose expanded message is longer than this (in bits) are skipped.
+    pub ldt_max_len: u64,
+}
+
+impl Default for AcvpOptions {
+    fn default() -> Self {
+        AcvpOptions {
+            monte_checkpoints: MONTE_CHECKPOINTS,
+            ldt_max_len: u64::MAX,
+        }
+    }
+}
+
+
+#[derive(Debug, Display, Error)]
+pub enum AcvpError {
+    #[display("{}: {_1}", _0.display())]
+    Io(PathBuf, #[error(source)] io::Error),
+    #[display("{}: {_1}", _0.display())]
+    Json(PathBuf, #[error(source)] JsonError),
+    #[display("{}: {_1}", _0.display())]
+    InvalidFormat(PathBuf, #[error(not(source))] String),
+    #[display("{}: missing algorithm", _0.display())]
+    MissingAlgorithm(#[error(not(source))] PathBuf),
+    #[display("{}: unsupported algorithm '{_1}'", _0.display())]
+    UnsupportedAlgorithm(PathBuf, #[error(not(source))] String),
+}
+
+
+/// Outcome of one test group.
+pub struct GroupReport {
+    pub tg_id: u64,
+    pub test_type: String,
+    pub passed: usize,
+    pub failed: usize,
+    /// Cases with bit-oriented lengths or Large Data Tests over `AcvpOptions::ldt_max_len`;
+    /// all cases when the test type is not supported.
+    pub skipped: usize,
+    pub is_supported: bool,
+    /// `tcId` of the cases that failed.
+    pub failures: Vec<u64>,
+}
+
+impl fmt::Display for GroupReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "tgId {} ({}): ", self.tg_id, self.test_type)?;
+        if !self.is_supported {
+            return write!(f, "not supported, skipped {}", self.skipped);
+        }
+        write!(f, "passed {}, failed {}, skipped {}", self.passed, self.failed, self.skipped)?;
+        if !self.failures.is_empty() {
+            write!(f, " (failing tcIds {:?})", self.failures)?;
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of one vector set, with a summary per test group.
+pub struct AcvpReport {
+    pub path: PathBuf,
+    pub algorithm: Algorithm,
+    pub revision: Option<String>,
+    pub groups: Vec<GroupReport>,
+}
+
+impl AcvpReport {
+    pub fn is_success(&self) -> bool {
+        self.failed() == 0
+    }
+
+    pub fn passed(&self) -> usize {
+        self.groups.iter().map(|group| group.passed).sum()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.groups.iter().map(|group| group.failed).sum()
+    }
+
+    pub fn skipped(&self) -> usize {
+        self.groups.iter().map(|group| group.skipped).sum()
+    }
+}
+
+impl fmt::Display for AcvpReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.algorithm.name())?;
+        if let Some(revision) = &self.revision {
+            write!(f, " revision {revision}")?;
+        }
+        write!(f, ": passed {}, failed {}, skipped {}", self.passed(), self.failed(), self.skipped())?;
+        for group in &self.groups {
+            write!(f, "\n  {group}")?;
+        }
+        Ok(())
+    }
+}
+
+
+/// Returns the vector set object of an ACVP file, skipping the `acvVersion` element.
+fn vector_set(file: &JsonValue) -> Option<&JsonValue> {
+    match file {
+        JsonValue::Array(values) => values.iter().find(|value| value.get("acvVersion").is_none()),
+        JsonValue::Object(_) => Some(file),
+        _ => None,
+    }
+}
+
+fn test_groups(vector_set: &JsonValue) -> Result<&[JsonValue], String> {
+    vector_set.get("testGroups").and_then(JsonValue::as_array)
+        .ok_or_else(|| "missing testGroups".to_string())
+}
+
+fn tests(group: &JsonValue) -> Result<&[JsonValue], String> {
+    group.get("tests").and_then(JsonValue::as_array)
+        .ok_or_else(|| "missing tests".to_string())
+}
+
+fn id(value: &JsonValue, name: &str) -> Result<u64, String> {
+    value.get(name).and_then(JsonValue::as_u64)
+        .ok_or_else(|| format!("missing {name}"))
+}
+
+
+/// A test case with its group, looking fields up in the case first, then in the group.
+struct Case<'a> {
+    group: &'a JsonValue,
+    prompt: &'a JsonValue,
+    expected: &'a JsonValue,
+}
+
+impl<'a> Case<'a> {
+    fn get(&self, name: &str) -> Option<&'a JsonValue> {
+        self.prompt.get(name).or_else(|| self.group.get(name))
+    }
+
+    fn field(&self, name: &str) -> Result<&'a JsonValue, String> {
+        self.get(name).ok_or_else(|| format!("missing {name}"))
+    }
+
+    fn number(&self, name: &str) -> Result<u64, String> {
+        self.field(name)?.as_u64().ok_or_else(|| format!("invalid number in {name}"))
+    }
+
+    fn hex(&self, name: &str) -> Result<ByteString, String> {
+        decode_hex(self.field(name)?, name)
+    }
+
+    /// A length in bits, or None if it is not a whole number of bytes.
+    fn byte_len(&self, name: &str) -> Result<Option<usize>, String> {
+        let bits = self.number(name)?;
+        Ok(bits.is_multiple_of(8).then_some(bits as usize / 8))
+    }
+
+    /// The first `len_name` bits of the hex field `hex_name`, or None if they are not whole bytes.
+    fn message(&self, hex_name: &str, len_name: &str) -> Result<Option<ByteString>, String> {
+        let Some(len) = self.byte_len(len_name)? else {
+            return Ok(None);
+        };
+        let mut msg = self.hex(hex_name)?;
+        if msg.len() < len {
+            return Err(format!("{len_name} is longer than {hex_name}"));
+        }
+        msg.truncate(len);
+        Ok(Some(msg))
+    }
+
+    /// The customization string S, given as text, or as hex when the group has `hexCustomization`.
+    fn customization(&self) -> Result<ByteString, String> {
+        if let Some(hex) = self.get("customizationHex") {
+            return decode_hex(hex, "customizationHex");
+        }
+        match self.get("customization") {
+            Some(text) => text.as_str()
+                .map(|text| ByteString::from(text.as_bytes().to_vec()))
+                .ok_or_else(|| "invalid customization".to_string()),
+            None => Ok(ByteString::new()),
+        }
+    }
+
+    fn is_xof(&self) -> bool {
+        self.get("xof").and_then(JsonValue::as_bool).unwrap_or(false)
+    }
+
+    fn expected_hex(&self, name: &str) -> Result<ByteString, String> {
+        decode_hex(self.expected.get(name).ok_or_else(|| format!("missing expected {name}"))?, name)
+    }
+}
+
+fn decode_hex(value: &JsonValue, name: &str) -> Result<ByteString, String> {
+    value.as_str()
+        .and_then(|hex| hex::decode(hex).ok())
+        .map(ByteString::from)
+        .ok_or_else(|| format!("invalid hex string in {name}"))
+}
+
+
+/// Outcome of a single case; `None` means skipped.
+type CaseResult = Result<Option<bool>, String>;
+
+fn run_case(algorithm: Algorithm, test_type: &str, case: &Case, options: &AcvpOptions) -> CaseResult {
+    match (algorithm, test_type) {
+        (Algorithm::Sha3(_) | Algorithm::Shake(_), "LDT") => run_ldt(algorithm, case, options),
+        (Algorithm::Sha3(sha3_variant), "MCT") => run_sha3_mct(sha3_variant, case, options),
+        (Algorithm::Shake(shake_variant), "MCT") => run_shake_mct(shake_variant, case, options),
+        (Algorithm::Sha3(sha3_variant), _) => {
+            let Some(msg) = case.message("msg", "len")? else {
+                return Ok(None);
+            };
+            Ok(Some(sha3_family(&msg, sha3_variant.capacity(), sha3_variant.output_len()) == case.expected_hex("md")?))
+        }
+        (Algorithm::Shake(shake_variant), _) => {
+            let (Some(msg), Some(out_len)) = (case.message("msg", "len")?, case.byte_len("outLen")?) else {
+                return Ok(None);
+            };
+            Ok(Some(shake_family(&msg, shake_variant.capacity(), 8 * out_len) == case.expected_hex("md")?))
+        }
+        (Algorithm::CShake(shake_variant), _) => {
+            let (Some(msg), Some(out_len)) = (case.message("msg", "len")?, case.byte_len("outLen")?) else {
+                return Ok(None);
+            };
+            let function_name = case.get("functionName").and_then(JsonValue::as_str).unwrap_or("");
+            let md = cshake(shake_variant, &msg, 8 * out_len, function_name.as_bytes(), &case.customization()?);
+            Ok(Some(md == case.expected_hex("md")?))
+        }
+        (Algorithm::Kmac(shake_variant), _) => {
+            let (Some(key), Some(msg), Some(mac_len)) =
+                (case.message("key", "keyLen")?, case.message("msg", "msgLen")?, case.byte_len("macLen")?) else {
+                return Ok(None);
+            };
+            let kmac = if case.is_xof() { kmac_xof } else { kmac };
+            let mac = kmac(shake_variant, &key, &msg, 8 * mac_len, &case.customization()?);
+            if test_type == "MVT" {
+                // the prompt holds a candidate tag, and the expected result says whether it verifies
+                let test_passed = case.expected.get("testPassed").and_then(JsonValue::as_bool)
+                    .ok_or_else(|| "missing expected testPassed".to_string())?;
+                Ok(Some((mac == case.hex("mac")?) == test_passed))
+            } else {
+                Ok(Some(mac == case.expected_hex("mac")?))
+            }
+        }
+        (Algorithm::TupleHash(shake_variant), _) => {
+            let Some(out_len) = case.byte_len("outLen")? else {
+                return Ok(None);
+            };
+            let items = case.field("tuple")?.as_array().ok_or_else(|| "invalid tuple".to_string())?
+                .iter()
+                .map(|item| decode_hex(item, "tuple"))
+                .collect::<Result<Vec<_>, _>>()?;
+            let items: Vec<&[u8]> = items.iter().map(|item| item.as_slice()).collect();
+            let tuple_hash = if case.is_xof() { tuple_hash_xof } else { tuple_hash };
+            let md = tuple_hash(shake_variant, &items, 8 * out_len, &case.customization()?);
+            Ok(Some(md == case.expected_hex("md")?))
+        }
+        (Algorithm::ParallelHash(shake_variant), _) => {
+            let (Some(msg), Some(out_len)) = (case.message("msg", "len")?, case.byte_len("outLen")?) else {
+                return Ok(None);
+            };
+            let block_size = case.number("blockSize")? as usize;
+            if block_size == 0 {
+                return Err("blockSize must be positive".to_string());
+            }
+            let parallel_hash = if case.is_xof() { parallel_hash_xof } else { parallel_hash };
+            let md = parallel_hash(shake_variant, &msg, block_size, 8 * out_len, &case.customization()?);
+            Ok(Some(md == case.expected_hex("md")?))
+        }
+        _ => unreachable!("{algorithm} has no ACVP vector sets"),
+    }
+}
+
+/// The SHA3 Monte Carlo test of the `monte` module, with `msg` as the seed and `resultsArray`
+/// as the checkpoints. Only the first `AcvpOptions::monte_checkpoints` are checked.
+fn run_sha3_mct(sha3_variant: Sha3Variant, case: &Case, options: &AcvpOptions) -> CaseResult {
+    let Some(seed) = case.message("msg", "len")? else {
+        return Ok(None);
+    };
+    let expected = mct_results(case, options)?;
+    let hash = |m: &[u8]| sha3_family(m, sha3_variant.capacity(), sha3_variant.output_len());
+    Ok(Some(check_monte(&seed, &expected, hash).is_ok()))
+}
+
+/// The SHAKE Monte Carlo test of the `monte` module, between the group's `minOutLen` and
+/// `maxOutLen`. The `outLen` of each checkpoint is the length of its `md`.
+fn run_shake_mct(shake_variant: ShakeVariant, case: &Case, options: &AcvpOptions) -> CaseResult {
+    let Some(seed) = case.message("msg", "len")? else {
+        return Ok(None);
+    };
+    let min_out_len = case.number("minOutLen")? as usize;
+    let max_out_len = case.number("maxOutLen")? as usize;
+    if min_out_len < 16 || min_out_len > max_out_len {
+        return Err(format!("invalid output lengths {min_out_len} to {max_out_len}"));
+    }
+    let expected = mct_results(case, options)?;
+    let shake = |m: &[u8], out_len: usize| shake_family(m, shake_variant.capacity(), out_len);
+    Ok(Some(check_shake_monte(&seed, min_out_len, max_out_len, &expected, shake).is_ok()))
+}
+
+/// The `md` of the first `AcvpOptions::monte_checkpoints` entries of `resultsArray`.
+fn mct_results(case: &Case, options: &AcvpOptions) -> Result<Vec<ByteString>, String> {
+    let results = case.expected.get("resultsArray").and_then(JsonValue::as_array)
+        .ok_or_else(|| "missing expected resultsArray".to_string())?;
+    results.iter()
+        .take(options.monte_checkpoints)
+        .map(|result| decode_hex(result.get("md").unwrap_or(&JsonValue::Null), "md"))
+        .collect()
+}
+
+/// Large Data Test: `largeMsg.content` is repeated up to `largeMsg.fullLength` bits and streamed
+/// through the incremental hasher, so the expanded message is never held in memory.
+fn run_ldt(algorithm: Algorithm, case: &Case, options: &AcvpOptions) -> CaseResult {
+    let large_msg = Case {
+        group: case.group,
+        prompt: case.field("largeMsg")?,
+        expected: case.expected,
+    };
+    let technique = large_msg.field("expansionTechnique")?.as_str();
+    if technique != Some("repeating") {
+        return Err(format!("unsupported expansionTechnique {technique:?}"));
+    }
+    let full_len = large_msg.number("fullLength")?;
+    let (Some(content), true) = (large_msg.message("content", "contentLength")?, full_len.is_multiple_of(8)) else {
+        return Ok(None);
+    };
+    if full_len > options.ldt_max_len {
+        return Ok(None);
+    }
+    if content.is_empty() && full_len > 0 {
+        return Err("empty largeMsg.content".to_string());
+    }
+
+    let stream = |update: &mut dyn FnMut(&[u8])| {
+        let mut remaining = full_len / 8;
+        while remaining > 0 {
+            let n = remaining.min(content.len() as u64) as usize;
+            update(&content[..n]);
+            remaining -= n as u64;
+        }
+    };
+    let md = match algorithm {
+        Algorithm::Sha3(sha3_variant) => {
+            let mut sha3 = Sha3::new(sha3_variant);
+            stream(&mut |bytes| sha3.update(bytes));
+            sha3.digest()
+        }
+        Algorithm::Shake(shake_variant) => {
+            let Some(out_len) = case.byte_len("outLen")? else {
+                return Ok(None);
+            };
+            let mut shake = Shake::new(shake_variant);
+            stream(&mut |bytes| shake.update(bytes));
+            let mut md = ByteString::from(vec![0u8; out_len]);
+            shake.finalize_xof().squeeze(&mut md);
+            md
+        }
+        _ => unreachable!(),
+    };
+    Ok(Some(md == case.expected_hex("md")?))
+}
+
+
+/// Runs a vector set given its prompt and, unless the prompt is an internal projection,
+/// its expected results.
+pub fn run_acvp(path: &Path, prompt: &JsonValue, expected_results: Option<&JsonValue>, options: &AcvpOptions) -> Result<AcvpReport, AcvpError> {
+    let invalid = |message: String| AcvpError::InvalidFormat(path.to_path_buf(), message);
+    let prompt = vector_set(prompt).ok_or_else(|| invalid("no vector set".to_string()))?;
+    let expected_results = match expected_results {
+        Some(expected_results) => Some(vector_set(expected_results).ok_or_else(|| invalid("no expected results".to_string()))?),
+        None => None,
+    };
+
+    let algorithm_name = prompt.get("algorithm")
+        .and_then(JsonValue::as_str)
+        .ok_or_else(|| AcvpError::MissingAlgorithm(path.to_path_buf()))?;
+    let mode = prompt.get("mode").and_then(JsonValue::as_str);
+    let algorithm = match (algorithm_from_name(algorithm_name), mode) {
+        (Some(algorithm), None) => algorithm,
+        (_, Some(mode)) => return Err(AcvpError::UnsupportedAlgorithm(path.to_path_buf(), format!("{algorithm_name} {mode}"))),
+        (None, None) => return Err(AcvpError::UnsupportedAlgorithm(path.to_path_buf(), algorithm_name.to_string())),
+    };
+
+    let mut report = AcvpReport {
+        path: path.to_path_buf(),
+        algorithm,
+        revision: prompt.get("revision").and_then(JsonValue::as_str).map(str::to_string),
+        groups: Vec::new(),
+    };
+    for group in test_groups(prompt).map_err(invalid)? {
+        let tg_id = id(group, "tgId").map_err(invalid)?;
+        let test_type = group.get("testType").and_then(JsonValue::as_str).unwrap_or("AFT");
+        let expected_group = match expected_results {
+            Some(expected_results) => Some(
+                test_groups(expected_results).map_err(invalid)?
+                    .iter()
+                    .find(|expected_group| id(expected_group, "tgId") == Ok(tg_id))
+                    .ok_or_else(|| invalid(format!("tgId {tg_id}: no expected results")))?,
+            ),
+            None => None,
+        };
+
+        let cases = tests(group).map_err(invalid)?;
+        let mut group_report = GroupReport {
+            tg_id,
+            test_type: test_type.to_string(),
+            passed: 0,
+            failed: 0,
+            skipped: 0,
+            is_supported: supports(algorithm, test_type, group),
+            failures: Vec::new(),
+        };
+        if !group_report.is_supported {
+            group_report.skipped = cases.len();
+            report.groups.push(group_report);
+            continue;
+        }
+
+        for prompt_case in cases {
+            let tc_id = id(prompt_case, "tcId").map_err(invalid)?;
+            let expected = match expected_group {
+                Some(expected_group) => tests(expected_group).map_err(invalid)?
+                    .iter()
+                    .find(|expected_case| id(expected_case, "tcId") == Ok(tc_id))
+                    .ok_or_else(|| invalid(format!("tcId {tc_id}: no expected result")))?,
+                None => prompt_case,
+            };
+            let case = Case {
+                group,
+                prompt: prompt_case,
+                expected,
+            };
+            match run_case(algorithm, test_type, &case, options) {
+                Ok(Some(true)) => group_report.passed += 1,
+                Ok(Some(false)) => {
+                    group_report.failed += 1;
+                    group_report.failures.push(tc_id);
+                }
+                Ok(None) => group_report.skipped += 1,
+                Err(message) => return Err(invalid(format!("tgId {tg_id}, tcId {tc_id}: {message}"))),
+            }
+        }
+        report.groups.push(group_report);
+    }
+    Ok(report)
+}
+
+fn read_json(path: &Path) -> Result<JsonValue, AcvpError> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| AcvpError::Io(path.to_path_buf(), e))?;
+    parse_json(&content)
+        .map_err(|e| AcvpError::Json(path.to_path_buf(), e))
+}
+
+/// Runs a `prompt.json` file against the `expectedResults.json` file next to it,
+/// or an `internalProjection.json` file on its own.
+pub fn run_acvp_file(path: &Path, options: &AcvpOptions) -> Result<AcvpReport, AcvpError> {
+    let prompt = read_json(path)?;
+    if path.file_name().is_some_and(|name| name == "internalProjection.json") {
+        return run_acvp(path, &prompt, None, options);
+    }
+    let expected_results = read_json(&path.with_file_name("expectedResults.json"))?;
+    run_acvp(path, &prompt, Some(&expected_results), options)
+}
+
+/// Runs all vector sets found under `dir`, in sorted order.
+pub fn run_acvp_dir(dir: &Path, options: &AcvpOptions) -> Result<Vec<AcvpReport>, AcvpError> {
+    find_acvp_files(dir)?.iter().map(|path| run_acvp_file(path, options)).collect()
+}
+
+/// Lists the vector sets found under `dir` (see `run_acvp_file`), in sorted order.
+pub fn find_acvp_files(dir: &Path) -> Result<Vec<PathBuf>, AcvpError> {
+    let mut paths = Vec::new();
+    collect_acvp_files(dir, &mut paths)?;
+    paths.sort();
+    Ok(paths)
+}
+
+/// Collects the `prompt.json` files, and the `internalProjection.json` files of directories
+/// without a prompt.
+fn collect_acvp_files(dir: &Path, paths: &mut Vec<PathBuf>) -> Result<(), AcvpError> {
+    let entries = fs::read_dir(dir)
+        .map_err(|e| AcvpError::Io(dir.to_path_buf(), e))?;
+    let mut projection = None;
+    let mut has_prompt = false;
+    for entry in entries {
+        let path = entry
+            .map_err(|e| AcvpError::Io(dir.to_path_buf(), e))?
+            .path();
+        if path.is_dir() {
+            collect_acvp_files(&path, paths)?;
+        } else if path.file_name().is_some_and(|name| name == "prompt.json") {
+            has_prompt = true;
+            paths.push(path);
+        } else if path.file_name().is_some_and(|name| name == "internalProjection.json") {
+            projection = Some(path);
+        }
+    }
+    if let (Some(projection), false) = (projection, has_prompt) {
+        paths.push(projection);
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(prompt: &str, expected_results: &str) -> Result<AcvpReport, AcvpError> {
+        let prompt = parse_json(prompt).unwrap();
+        let expected_results = parse_json(expected_results).unwrap();
+        run_acvp(Path::new("prompt.json"), &prompt, Some(&expected_results), &AcvpOptions::default())
+    }
+
+    #[test]
+    fn test_all_acvp_files() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("test_vectors/ACVP");
+        let reports = run_acvp_dir(&dir, &AcvpOptions::default()).unwrap();
+        assert_eq!(6, reports.len());
+        for report in &reports {
+            assert!(report.is_success(), "{report}");
+            assert!(report.passed() > 0, "{report}");
+        }
+    }
+
+    #[test]
+    fn test_algorithm_names() {
+        let names = [
+            ("SHA3-224", Algorithm::Sha3(Sha3Variant::SHA3_224)),
+            ("SHA3-512", Algorithm::Sha3(Sha3Variant::SHA3_512)),
+            ("SHAKE-128", Algorithm::Shake(ShakeVariant::SHAKE128)),
+            ("CSHAKE-256", Algorithm::CShake(ShakeVariant::SHAKE256)),
+            ("KMAC-128", Algorithm::Kmac(ShakeVariant::SHAKE128)),
+            ("TupleHash-256", Algorithm::TupleHash(ShakeVariant::SHAKE256)),
+            ("ParallelHash-128", Algorithm::ParallelHash(ShakeVariant::SHAKE128)),
+        ];
+        for (name, algorithm) in names {
+            assert_eq!(Some(algorithm), algorithm_from_name(name));
+        }
+        // in the registry, but not in ACVP
+        assert_eq!(None, algorithm_from_name("Keccak-256"));
+        assert_eq!(None, algorithm_from_name("HMAC-SHA3-256"));
+    }
+
+    #[test]
+    fn test_failures_are_reported() {
+        let prompt = r#"[{"acvVersion": "1.0"}, {"vsId": 1, "algorithm": "SHA3-256", "revision": "2.0", "testGroups": [
+            {"tgId": 1, "testType": "AFT", "tests": [
+                {"tcId": 1, "msg": "", "len": 0},
+                {"tcId": 2, "msg": "E9", "len": 8},
+                {"tcId": 3, "msg": "80", "len": 1}]}]}]"#;
+        let expected_results = r#"[{"acvVersion": "1.0"}, {"vsId": 1, "algorithm": "SHA3-256", "revision": "2.0", "testGroups": [
+            {"tgId": 1, "tests": [
+                {"tcId": 1, "md": "A7FFC6F8BF1ED76651C14756A061D662F580FF4DE43B49FA82D80A4B80F8434A"},
+                {"tcId": 2, "md": "00"},
+                {"tcId": 3, "md": "00"}]}]}]"#;
+        let report = run(prompt, expected_results).unwrap();
+        assert_eq!((1, 1, 1), (report.passed(), report.failed(), report.skipped()));
+        assert_eq!("prompt.json: SHA3-256 revision 2.0: passed 1, failed 1, skipped 1\n  \
+            tgId 1 (AFT): passed 1, failed 1, skipped 1 (failing tcIds [2])", report.to_string());
+    }
+
+    #[test]
+    fn test_unsupported_test_type_is_skipped() {
+        let prompt = r#"{"algorithm": "SHA3-256", "testGroups": [
+            {"tgId": 3, "testType": "MCT", "mctVersion": "alternate", "tests": [{"tcId": 1, "msg": "00", "len": 8}]}]}"#;
+        let expected_results = r#"{"testGroups": [{"tgId": 3, "tests": [{"tcId": 1, "resultsArray": []}]}]}"#;
+        let report = run(prompt, expected_results).unwrap();
+        assert!(!report.groups[0].is_supported);
+        assert_eq!("tgId 3 (MCT): not supported, skipped 1", report.groups[0].to_string());
+    }
+
+    #[test]
+    fn test_shake_mct() {
+        // the seed is shorter than the 128 bits of each message, which are padded with zeros
+        let prompt = r#"{"algorithm": "SHAKE-256", "testGroups": [
+            {"tgId": 1, "testType": "MCT", "minOutLen": 16, "maxOutLen": 264, "tests": [{"tcId": 1, "msg": "00", "len": 8}]}]}"#;
+        let expected_results = |md: &str| format!(r#"{{"testGroups": [{{"tgId": 1, "tests": [{{"tcId": 1, "resultsArray": [
+            {{"md": "{md}", "outLen": {}}}, {{"md": "704E761CB25A96", "outLen": 56}}]}}]}}]}}"#, 4 * md.len());
+        let report = run(prompt, &expected_results("85AB75A253C905460A035E")).unwrap();
+        assert_eq!((1, 0), (report.passed(), report.failed()), "{report}");
+
+        // the right bytes, but not all of them
+        let report = run(prompt, &expected_results("85AB75A253C905460A03")).unwrap();
+        assert_eq!((0, 1), (report.passed(), report.failed()), "{report}");
+
+        let err = run(&prompt.replace("264", "8"), &expected_results("")).err().unwrap();
+        assert_eq!("prompt.json: tgId 1, tcId 1: invalid output lengths 16 to 8", err.to_string());
+    }
+
+    #[test]
+    fn test_invalid_files() {
+        let err = run(r#"{"algorithm": "SHA2-256", "testGroups": []}"#, "{}").err().unwrap();
+        assert_eq!("prompt.json: unsupported algorithm 'SHA2-256'", err.to_string());
+
+        let prompt = r#"{"algorithm": "SHA3-224", "testGroups": [{"tgId": 1, "testType": "AFT", "tests": [{"tcId": 7, "msg": "0", "len": 8}]}]}"#;
+        let expected_results = r#"{"testGroups": [{"tgId": 1, "tests": [{"tcId": 7, "md": "00"}]}]}"#;
+        let err = run(prompt, expected_results).err().unwrap();
+        assert_eq!("prompt.json: tgId 1, tcId 7: invalid hex string in msg", err.to_string());
+    }
+}
@@ -0,0 +1,304 @@
+//! Avalanche analysis of Keccak-based functions, for the reduced-round experiments: each
+//! bit of a random input is flipped in turn and the output compared with that of the
+//! original input.
+//!
+//! The report holds the distribution of the output Hamming distances, ideally binomial
+//! around half the output bits, and the dependence matrix of the strict avalanche criterion
+//! (SAC): the probability that output bit j flips when input bit i is flipped, ideally 0.5
+//! for every pair. Entries stuck at 0 or 1 show the bits that a few rounds do not yet mix.
+
+use std::io;
+use std::io::Write;
+
+use derive_more::{Display, Error};
+
+use crate::sha3::constants::KECCAK_CONSTANTS;
+use crate::sha3::rng::SplitMix64;
+use crate::sha3::sha3::keccak_p;
+use crate::sha3::sha3::keccak_sponge;
+use crate::sha3::types::BitString;
+use crate::sha3::types::Sha3Variant;
+
+
+/// The function under analysis; inputs and outputs are strings of bits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Target {
+    /// KECCAK-p[width, rounds] on the raw state: width input and output bits.
+    Permutation { width: usize, rounds: usize },
+    /// A SHA3 variant, i.e. KECCAK[c](M || 01, d), on messages of `message_len` bytes, with
+    /// KECCAK-p[1600, rounds] in the sponge; 24 rounds give the standard function.
+    Sha3 { sha3_variant: Sha3Variant, rounds: usize, message_len: usize },
+}
+
+impl Target {
+    pub fn input_bits(&self) -> usize {
+        match self {
+            Target::Permutation { width, .. } => *width,
+            Target::Sha3 { message_len, .. } => 8 * message_len,
+        }
+    }
+
+    pub fn output_bits(&self) -> usize {
+        match self {
+            Target::Permutation { width, .. } => *width,
+            Target::Sha3 { sha3_variant, .. } => sha3_variant.output_len(),
+        }
+    }
+
+    /// Checks the width, the number of rounds and the message length.
+    pub fn check(&self) -> Result<(), AvalancheError> {
+        let (width, rounds) = match self {
+            Target::Permutation { width, rounds } => (*width, *rounds),
+            Target::Sha3 { message_len: 0, .. } => return Err(AvalancheError::EmptyMessage),
+            Target::Sha3 { rounds, .. } => (1600, *rounds),
+        };
+        let el = KECCAK_CONSTANTS.iter()
+            .find(|constants| constants[2] == width)
+            .map(|constants| constants[0])
+            .ok_or(AvalancheError::InvalidWidth(width))?;
+        let max = 12 + 2 * el;
+        if rounds > max {
+            return Err(AvalancheError::TooManyRounds { width, rounds, max });
+        }
+        Ok(())
+    }
+
+    /// Computes the output bits of `input`.
+    fn evaluate(&self, input: &[u8]) -> BitString {
+        match self {
+            Target::Permutation { width, rounds } => {
+                let mut s = BitString::from(input.to_vec());
+                keccak_p(*width, *rounds, &mut s);
+                s
+            }
+            Target::Sha3 { sha3_variant, rounds, .. } => {
+                let mut n = BitString::from(input.to_vec());
+                n.push(0);
+                n.push(1);
+                keccak_sponge(1600, *rounds, sha3_variant.capacity(), &n, sha3_variant.output_len())
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Display, Error)]
+pub enum AvalancheError {
+    #[display("invalid width {_0}, expected 25, 50, 100, 200, 400, 800 or 1600")]
+    InvalidWidth(#[error(not(source))] usize),
+    #[display("{rounds} rounds requested, KECCAK-p[{width}] has at most {max}")]
+    TooManyRounds { width: usize, rounds: usize, max: usize },
+    #[display("the message must not be empty")]
+    EmptyMessage,
+    #[display("at least one sample is needed")]
+    NoSamples,
+}
+
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AvalancheReport {
+    pub target: Target,
+    /// Number of random inputs, each flipped at every bit.
+    pub samples: usize,
+    /// `distances[d]`: number of flips that changed exactly d output bits.
+    pub distances: Vec<u64>,
+    /// `flips[i * output_bits + j]`: number of samples in which flipping input bit i
+    /// flipped output bit j.
+    flips: Vec<u32>,
+}
+
+impl AvalancheReport {
+    /// Probability that output bit j flips when input bit i is flipped.
+    pub fn flip_probability(&self, i: usize, j: usize) -> f64 {
+        let output_bits = self.target.output_bits();
+        assert!(i < self.target.input_bits() && j < output_bits);
+        self.flips[i * output_bits + j] as f64 / self.samples as f64
+    }
+
+    /// Mean output Hamming distance, ideally half the output bits.
+    pub fn mean_distance(&self) -> f64 {
+        let (count, sum) = self.distances.iter().enumerate()
+            .fold((0u64, 0u64), |(count, sum), (d, &n)| (count + n, sum + d as u64 * n));
+        sum as f64 / count as f64
+    }
+
+    /// Standard deviation of the output Hamming distance, ideally sqrt(output bits) / 2.
+    pub fn distance_std_dev(&self) -> f64 {
+        let mean = self.mean_distance();
+        let (count, sum) = self.distances.iter().enumerate()
+            .fold((0u64, 0f64), |(count, sum), (d, &n)| (count + n, sum + n as f64 * (d as f64 - mean).powi(2)));
+        (sum / count as f64).sqrt()
+    }
+
+    /// Largest deviation |p - 0.5| over the entries of the SAC matrix.
+    pub fn max_sac_deviation(&self) -> f64 {
+        self.sac_deviations().fold(0.0, f64::max)
+    }
+
+    /// Mean deviation |p - 0.5| over the entries of the SAC matrix.
+    pub fn mean_sac_deviation(&self) -> f64 {
+        self.sac_deviations().sum::<f64>() / self.flips.len() as f64
+    }
+
+    fn sac_deviations(&self) -> impl Iterator<Item = f64> + use<'_> {
+        self.flips.iter().map(|&n| (n as f64 / self.samples as f64 - 0.5).abs())
+    }
+
+    /// Writes the SAC matrix as CSV: a header row "input_bit,0,1,...", then a row per input
+    /// bit i with the probabilities of each output bit j.
+    pub fn write_csv(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "input_bit")?;
+        for j in 0..self.target.output_bits() {
+            write!(out, ",{j}")?;
+        }
+        writeln!(out)?;
+        for i in 0..self.target.input_bits() {
+            write!(out, "{i}")?;
+            for j in 0..self.target.output_bits() {
+                write!(out, ",{:.4}", self.flip_probability(i, j))?;
+            }
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the SAC matrix as a binary PGM (P5) heatmap: a row per input bit and a column
+    /// per output bit, from black for p = 0 to white for p = 1, so that a well-mixed
+    /// function gives an even grey.
+    pub fn write_pgm(&self, out: &mut impl Write) -> io::Result<()> {
+        let (width, height) = (self.target.output_bits(), self.target.input_bits());
+        write!(out, "P5\n{width} {height}\n255\n")?;
+        let mut row = vec![0u8; width];
+        for i in 0..height {
+            for (j, pixel) in row.iter_mut().enumerate() {
+                *pixel = (self.flip_probability(i, j) * 255.0).round() as u8;
+            }
+            out.write_all(&row)?;
+        }
+        Ok(())
+    }
+}
+
+/// Mean SAC deviation |p - 0.5| of an ideal random function estimated from `samples` inputs,
+/// E|X / n - 1/2| for X ~ Binomial(n, 1/2): the floor that `mean_sac_deviation` approaches
+/// once the rounds mix well, which only more samples lower.
+pub fn random_sac_deviation(samples: usize) -> f64 {
+    let n = samples as f64;
+    // the probabilities C(n, k) / 2^n are computed as logarithms, which do not underflow
+    let mut ln_probability = -n * core::f64::consts::LN_2;
+    let mut mean = 0.0;
+    for k in 0..=samples {
+        if k > 0 {
+            ln_probability += ((samples - k + 1) as f64 / k as f64).ln();
+        }
+        mean += ln_probability.exp() * (k as f64 / n - 0.5).abs();
+    }
+    mean
+}
+
+/// Flips every input bit of `samples` random inputs drawn from `seed`, and collects the
+/// output differences.
+pub fn analyze(target: Target, samples: usize, seed: u64) -> Result<AvalancheReport, AvalancheError> {
+    target.check()?;
+    if samples == 0 {
+        return Err(AvalancheError::NoSamples);
+    }
+    let (input_bits, output_bits) = (target.input_bits(), target.output_bits());
+    let mut distances = vec![0u64; output_bits + 1];
+    let mut flips = vec![0u32; input_bits * output_bits];
+    let mut rng = SplitMix64::new(seed);
+    let mut input = vec![0u8; input_bits];
+    for _ in 0..samples {
+        for bit in input.iter_mut() {
+            *bit = (rng.next_u64() & 1) as u8;
+        }
+        let output = target.evaluate(&input);
+        for i in 0..input_bits {
+            input[i] ^= 1;
+            let flipped = target.evaluate(&input);
+            input[i] ^= 1;
+            let mut distance = 0;
+            for j in 0..output_bits {
+                if output[j] != flipped[j] {
+                    flips[i * output_bits + j] += 1;
+                    distance += 1;
+                }
+            }
+            distances[distance] += 1;
+        }
+    }
+    Ok(AvalancheReport { target, samples, distances, flips })
+}
+
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zero_rounds() {
+        // without any round, flipping bit i flips exactly output bit i
+        let report = analyze(Target::Permutation { width: 25, rounds: 0 }, 3, 1).unwrap();
+        assert_eq!(3 * 25, report.distances[1]);
+        assert_eq!(1.0, report.mean_distance());
+        assert_eq!(0.0, report.distance_std_dev());
+        assert_eq!(1.0, report.flip_probability(7, 7));
+        assert_eq!(0.0, report.flip_probability(7, 8));
+        assert_eq!(0.5, report.max_sac_deviation());
+    }
+
+    #[test]
+    fn test_diffusion_grows_with_rounds() {
+        let one_round = analyze(Target::Permutation { width: 200, rounds: 1 }, 4, 7).unwrap();
+        let full = analyze(Target::Permutation { width: 200, rounds: 18 }, 4, 7).unwrap();
+        // one round stays local: theta spreads a bit to 11 positions, chi to at most 3 each
+        assert!(one_round.mean_distance() <= 33.0, "{}", one_round.mean_distance());
+        assert!((full.mean_distance() - 100.0).abs() < 5.0, "{}", full.mean_distance());
+        assert!(one_round.mean_sac_deviation() > full.mean_sac_deviation());
+    }
+
+    #[test]
+    fn test_sha3() {
+        let report = analyze(Target::Sha3 { sha3_variant: Sha3Variant::SHA3_224, rounds: 24, message_len: 2 }, 2, 3).unwrap();
+        assert_eq!(2 * 16, report.distances.iter().sum::<u64>());
+        assert!((report.mean_distance() - 112.0).abs() < 15.0, "{}", report.mean_distance());
+    }
+
+    #[test]
+    fn test_random_sac_deviation() {
+        assert_eq!(0.5, random_sac_deviation(1));
+        assert_eq!(0.25, random_sac_deviation(2));
+        assert!((random_sac_deviation(8) - 0.13671875).abs() < 1e-12);
+        // the normal approximation sqrt(1 / (2 pi n)) for many samples
+        let n = 10_000.0;
+        assert!((random_sac_deviation(10_000) - (1.0 / (2.0 * core::f64::consts::PI * n)).sqrt()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_invalid_targets() {
+        assert_eq!(Err(AvalancheError::InvalidWidth(64)), analyze(Target::Permutation { width: 64, rounds: 1 }, 1, 0));
+        assert_eq!(Err(AvalancheError::TooManyRounds { width: 200, rounds: 19, max: 18 }),
+            analyze(Target::Permutation { width: 200, rounds: 19 }, 1, 0));
+        assert_eq!(Err(AvalancheError::EmptyMessage),
+            analyze(Target::Sha3 { sha3_variant: Sha3Variant::SHA3_256, rounds: 24, message_len: 0 }, 1, 0));
+        assert_eq!(Err(AvalancheError::NoSamples), analyze(Target::Permutation { width: 25, rounds: 1 }, 0, 0));
+    }
+
+    #[test]
+    fn test_exports() {
+        let report = analyze(Target::Permutation { width: 25, rounds: 0 }, 1, 1).unwrap();
+        let mut csv = Vec::new();
+        report.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(1 + 25, lines.len());
+        assert!(lines[0].starts_with("input_bit,0,1,2,"));
+        assert!(lines[1].starts_with("0,1.0000,0.0000,"));
+
+        let mut pgm = Vec::new();
+        report.write_pgm(&mut pgm).unwrap();
+        let header = b"P5\n25 25\n255\n";
+        assert_eq!(header, &pgm[..header.len()]);
+        assert_eq!(header.len() + 25 * 25, pgm.len());
+        assert_eq!([255, 0], pgm[header.len()..][..2]);
+    }
+}
@@ -0,0 +1,429 @@
+//! Throughput benchmarks of the permutation and of the hashing front-ends, with warm-up,
+//! repeated measurements and order statistics, so that regressions can be tracked from run
+//! to run.
+//!
+//! A front-end is timed over a whole message, from the construction of the hasher to the
+//! digest; messages of any size up to `MAX_MESSAGE_LEN` are fed from a single zeroed buffer
+//! of `CHUNK_LEN` bytes, so that measuring a 1 GiB message does not allocate 1 GiB. Each
+//! repetition runs the operation enough times to last `min_repetition_time`, and reports
+//! the mean time of one operation; the statistics are taken over the repetitions.
+//!
+//! The batch front-ends hash `BATCH_LEN` messages one after the other through `sha3_into`,
+//! as a server hashing many short requests would; their messages are slices of the chunk,
+//! so they are at most `CHUNK_LEN` bytes long.
+
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::sha3::constants::KECCAK_B;
+use crate::sha3::constants::KECCAK_NR;
+use crate::sha3::json::JsonValue;
+use crate::sha3::sha3::Sha3;
+use crate::sha3::sha3::Shake;
+use crate::sha3::sha3::keccak_p;
+use crate::sha3::sha3::sha3_into;
+use crate::sha3::sp800_185::Kmac;
+use crate::sha3::types::Sha3Variant;
+use crate::sha3::types::ShakeVariant;
+
+
+/// Messages are fed to the hashers in chunks of this size.
+pub const CHUNK_LEN: usize = 64 * 1024;
+
+/// Largest message size accepted by `bench`: 1 GiB.
+pub const MAX_MESSAGE_LEN: u64 = 1 << 30;
+
+/// Messages hashed in one operation of a batch front-end.
+pub const BATCH_LEN: u64 = 16;
+
+/// Key of the KMAC front-ends, one block long at most for both variants.
+const KMAC_KEY: [u8; 32] = [0x4b; 32];
+
+
+/// An implementation of KECCAK-p[1600, 24].
+///
+/// Only the bit-level reference of FIPS 202 exists so far; an optimized permutation gets a
+/// variant here, so that every front-end is measured on it as well.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Backend {
+    Reference,
+}
+
+impl Backend {
+    pub const ALL: [Backend; 1] = [Backend::Reference];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Backend::Reference => "reference",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Backend> {
+        Backend::ALL.into_iter().find(|backend| backend.name() == name)
+    }
+}
+
+/// What is timed: a bare permutation, or a hash function over a whole message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Frontend {
+    /// One call of KECCAK-p[1600, 24] on a 200-byte state; the message size is ignored.
+    Permutation,
+    Sha3(Sha3Variant),
+    /// SHAKE with an output of twice the security strength.
+    Shake(ShakeVariant),
+    /// KMAC with a 32-byte key and an output of twice the security strength.
+    Kmac(ShakeVariant),
+    /// `BATCH_LEN` messages of the given size, each hashed with `sha3_into`.
+    Batch(Sha3Variant),
+}
+
+impl Frontend {
+    pub const ALL: [Frontend; 13] = [
+        Frontend::Permutation,
+        Frontend::Sha3(Sha3Variant::SHA3_224),
+        Frontend::Sha3(Sha3Variant::SHA3_256),
+        Frontend::Sha3(Sha3Variant::SHA3_384),
+        Frontend::Sha3(Sha3Variant::SHA3_512),
+        Frontend::Shake(ShakeVariant::SHAKE128),
+        Frontend::Shake(ShakeVariant::SHAKE256),
+        Frontend::Kmac(ShakeVariant::SHAKE128),
+        Frontend::Kmac(ShakeVariant::SHAKE256),
+        Frontend::Batch(Sha3Variant::SHA3_224),
+        Frontend::Batch(Sha3Variant::SHA3_256),
+        Frontend::Batch(Sha3Variant::SHA3_384),
+        Frontend::Batch(Sha3Variant::SHA3_512),
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Frontend::Permutation => "permutation",
+            Frontend::Sha3(Sha3Variant::SHA3_224) => "sha3-224",
+            Frontend::Sha3(Sha3Variant::SHA3_256) => "sha3-256",
+            Frontend::Sha3(Sha3Variant::SHA3_384) => "sha3-384",
+            Frontend::Sha3(Sha3Variant::SHA3_512) => "sha3-512",
+            Frontend::Shake(ShakeVariant::SHAKE128) => "shake128",
+            Frontend::Shake(ShakeVariant::SHAKE256) => "shake256",
+            Frontend::Kmac(ShakeVariant::SHAKE128) => "kmac128",
+            Frontend::Kmac(ShakeVariant::SHAKE256) => "kmac256",
+            Frontend::Batch(Sha3Variant::SHA3_224) => "batch-sha3-224",
+            Frontend::Batch(Sha3Variant::SHA3_256) => "batch-sha3-256",
+            Frontend::Batch(Sha3Variant::SHA3_384) => "batch-sha3-384",
+            Frontend::Batch(Sha3Variant::SHA3_512) => "batch-sha3-512",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Frontend> {
+        Frontend::ALL.into_iter().find(|frontend| frontend.name() == name)
+    }
+
+    /// Capacity c of the sponge, in bits.
+    fn capacity(&self) -> usize {
+        match self {
+            Frontend::Permutation => 0,
+            Frontend::Sha3(sha3_variant) | Frontend::Batch(sha3_variant) => sha3_variant.capacity(),
+            Frontend::Shake(shake_variant) | Frontend::Kmac(shake_variant) => shake_variant.capacity(),
+        }
+    }
+
+    /// Output length in bytes; the whole state for the permutation.
+    fn output_len(&self) -> usize {
+        match self {
+            Frontend::Permutation => KECCAK_B / 8,
+            Frontend::Sha3(sha3_variant) | Frontend::Batch(sha3_variant) => sha3_variant.output_len() / 8,
+            Frontend::Shake(shake_variant) | Frontend::Kmac(shake_variant) => shake_variant.capacity() / 8,
+        }
+    }
+
+    /// Number of KECCAK-p calls for a message of `message_len` bytes: the padded message
+    /// M || suffix || pad10*1 is a multiple of the rate r, and the output fits in one block.
+    /// KMAC absorbs two more blocks, the encoded function name and the padded key, and
+    /// appends right_encode(L), 3 bytes for these output lengths, to the message. A batch
+    /// hashes `BATCH_LEN` messages.
+    pub fn permutations(&self, message_len: u64) -> u64 {
+        let rate = (KECCAK_B - self.capacity()) as u64;
+        match self {
+            Frontend::Permutation => 1,
+            Frontend::Sha3(_) => (8 * message_len + 2 + 2).div_ceil(rate),
+            Frontend::Batch(_) => BATCH_LEN * (8 * message_len + 2 + 2).div_ceil(rate),
+            Frontend::Shake(_) => (8 * message_len + 4 + 2).div_ceil(rate),
+            Frontend::Kmac(_) => 2 + (8 * (message_len + 3) + 2 + 2).div_ceil(rate),
+        }
+    }
+
+    /// Bytes hashed by one operation on messages of `message_len` bytes.
+    pub fn input_len(&self, message_len: u64) -> u64 {
+        match self {
+            Frontend::Batch(_) => BATCH_LEN * message_len,
+            _ => message_len,
+        }
+    }
+
+    /// Largest message size, in bytes.
+    pub fn max_message_len(&self) -> u64 {
+        match self {
+            Frontend::Batch(_) => CHUNK_LEN as u64,
+            _ => MAX_MESSAGE_LEN,
+        }
+    }
+
+    /// Processes a message of `message_len` bytes, taken from repetitions of `chunk`.
+    fn run(&self, backend: Backend, message_len: u64, chunk: &[u8], out: &mut [u8]) {
+        match backend {
+            Backend::Reference => self.run_reference(message_len, chunk, out),
+        }
+    }
+
+    fn run_reference(&self, message_len: u64, chunk: &[u8], out: &mut [u8]) {
+        let out = &mut out[..self.output_len()];
+        match self {
+            Frontend::Permutation => {
+                let mut s = [0u8; KECCAK_B];
+                keccak_p(KECCAK_B, KECCAK_NR, &mut s);
+                out[0] = s[0];
+            }
+            Frontend::Sha3(sha3_variant) => {
+                let mut sha3 = Sha3::new(*sha3_variant);
+                feed(message_len, chunk, |bytes| sha3.update(bytes));
+                sha3.digest_into(out);
+            }
+            Frontend::Shake(shake_variant) => {
+                let mut shake = Shake::new(*shake_variant);
+                feed(message_len, chunk, |bytes| shake.update(bytes));
+                shake.finalize_xof().squeeze(out);
+            }
+            Frontend::Kmac(shake_variant) => {
+                let mut kmac = Kmac::new(*shake_variant, &KMAC_KEY, b"");
+                feed(message_len, chunk, |bytes| kmac.update(bytes));
+                kmac.finalize_into(out);
+            }
+            Frontend::Batch(sha3_variant) => {
+                let message = &chunk[..message_len as usize];
+                for _ in 0..BATCH_LEN {
+                    sha3_into(*sha3_variant, message, out);
+                }
+            }
+        }
+    }
+}
+
+fn feed(message_len: u64, chunk: &[u8], mut update: impl FnMut(&[u8])) {
+    let mut remaining = message_len;
+    while remaining > 0 {
+        let len = remaining.min(chunk.len() as u64) as usize;
+        update(&chunk[..len]);
+        remaining -= len as u64;
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BenchOptions {
+    /// Unmeasured runs before the repetitions, which also calibrate their length.
+    pub warmup: usize,
+    pub repetitions: usize,
+    /// A repetition runs the operation until at least this much time has passed.
+    pub min_repetition_time: Duration,
+}
+
+impl Default for BenchOptions {
+    fn default() -> Self {
+        BenchOptions {
+            warmup: 1,
+            repetitions: 5,
+            min_repetition_time: Duration::from_millis(10),
+        }
+    }
+}
+
+/// Order statistics of the time of one operation over the repetitions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Stats {
+    pub min: Duration,
+    pub median: Duration,
+    pub p90: Duration,
+    pub max: Duration,
+}
+
+impl Stats {
+    /// Statistics of the samples, which must not be empty.
+    pub fn from_samples(samples: &[Duration]) -> Stats {
+        let mut sorted = samples.to_vec();
+        sorted.sort();
+        Stats {
+            min: sorted[0],
+            median: percentile(&sorted, 50),
+            p90: percentile(&sorted, 90),
+            max: sorted[sorted.len() - 1],
+        }
+    }
+}
+
+/// The nearest-rank `p`-th percentile of sorted samples: the smallest sample that is greater
+/// than or equal to p percent of them.
+pub fn percentile(sorted: &[Duration], p: usize) -> Duration {
+    assert!(!sorted.is_empty() && p <= 100);
+    let rank = (p * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BenchResult {
+    pub backend: Backend,
+    pub frontend: Frontend,
+    /// Message size in bytes, of each message of a batch; the state size, 200, for the
+    /// permutation.
+    pub message_len: u64,
+    /// Operations per repetition.
+    pub iterations: u64,
+    pub stats: Stats,
+}
+
+impl BenchResult {
+    pub fn permutations(&self) -> u64 {
+        self.frontend.permutations(self.message_len)
+    }
+
+    /// Bytes hashed by one operation.
+    pub fn input_len(&self) -> u64 {
+        self.frontend.input_len(self.message_len)
+    }
+
+    /// Throughput in hashed bytes per second, from the median time.
+    pub fn bytes_per_second(&self) -> f64 {
+        self.input_len() as f64 / self.stats.median.as_secs_f64()
+    }
+
+    pub fn permutations_per_second(&self) -> f64 {
+        self.permutations() as f64 / self.stats.median.as_secs_f64()
+    }
+
+    /// Estimate of the clock cycles per hashed byte from the median time, assuming that the
+    /// clock ran at `cpu_hz` throughout; no cycle counter is read. None for an empty message.
+    pub fn estimated_cycles_per_byte(&self, cpu_hz: f64) -> Option<f64> {
+        (self.input_len() > 0).then(|| self.stats.median.as_secs_f64() * cpu_hz / self.input_len() as f64)
+    }
+
+    /// The result as a JSON object; times are in nanoseconds.
+    pub fn to_json(&self) -> JsonValue {
+        let nanos = |duration: Duration| JsonValue::Number(duration.as_nanos() as f64);
+        JsonValue::Object(vec![
+            ("backend".to_string(), self.backend.name().into()),
+            ("frontend".to_string(), self.frontend.name().into()),
+            ("message_bytes".to_string(), self.message_len.into()),
+            ("input_bytes".to_string(), self.input_len().into()),
+            ("iterations".to_string(), self.iterations.into()),
+            ("permutations".to_string(), self.permutations().into()),
+            ("min_ns".to_string(), nanos(self.stats.min)),
+            ("median_ns".to_string(), nanos(self.stats.median)),
+            ("p90_ns".to_string(), nanos(self.stats.p90)),
+            ("max_ns".to_string(), nanos(self.stats.max)),
+            ("bytes_per_second".to_string(), JsonValue::Number(self.bytes_per_second().round())),
+            ("permutations_per_second".to_string(), JsonValue::Number(self.permutations_per_second().round())),
+        ])
+    }
+}
+
+/// Measures `frontend` on `backend` for messages of `message_len` bytes.
+pub fn bench(backend: Backend, frontend: Frontend, message_len: u64, options: &BenchOptions) -> BenchResult {
+    assert!(message_len <= frontend.max_message_len());
+    assert!(options.repetitions > 0);
+    let message_len = match frontend {
+        Frontend::Permutation => (KECCAK_B / 8) as u64,
+        _ => message_len,
+    };
+    let chunk = vec![0u8; CHUNK_LEN];
+    let mut out = [0u8; KECCAK_B / 8];
+
+    // the last warm-up run estimates the time of one operation
+    let mut estimate = Duration::ZERO;
+    for _ in 0..options.warmup {
+        let start = Instant::now();
+        frontend.run(backend, message_len, &chunk, &mut out);
+        estimate = start.elapsed();
+    }
+    let iterations = if estimate.is_zero() {
+        1
+    } else {
+        (options.min_repetition_time.as_nanos() / estimate.as_nanos()).clamp(1, u64::MAX as u128) as u64
+    };
+
+    let mut samples = Vec::with_capacity(options.repetitions);
+    for _ in 0..options.repetitions {
+        let start = Instant::now();
+        for _ in 0..iterations {
+            frontend.run(backend, message_len, &chunk, &mut out);
+        }
+        samples.push(Duration::from_nanos((start.elapsed().as_nanos() / iterations as u128) as u64));
+    }
+    std::hint::black_box(&out);
+    BenchResult { backend, frontend, message_len, iterations, stats: Stats::from_samples(&samples) }
+}
+
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentile() {
+        let samples: Vec<Duration> = [5, 1, 4, 2, 3].into_iter().map(Duration::from_millis).collect();
+        let stats = Stats::from_samples(&samples);
+        assert_eq!(Duration::from_millis(1), stats.min);
+        assert_eq!(Duration::from_millis(3), stats.median);
+        assert_eq!(Duration::from_millis(5), stats.p90);
+        assert_eq!(Duration::from_millis(5), stats.max);
+
+        let sorted: Vec<Duration> = (1..=10).map(Duration::from_millis).collect();
+        assert_eq!(Duration::from_millis(5), percentile(&sorted, 50));
+        assert_eq!(Duration::from_millis(9), percentile(&sorted, 90));
+        assert_eq!(Duration::from_millis(1), percentile(&sorted, 0));
+        assert_eq!(Duration::from_millis(10), percentile(&sorted, 100));
+    }
+
+    #[test]
+    fn test_permutations() {
+        // SHA3-256: r = 1088 bits, 136 bytes; the suffix and the padding take 4 bits
+        let sha3_256 = Frontend::Sha3(Sha3Variant::SHA3_256);
+        assert_eq!(1, sha3_256.permutations(0));
+        assert_eq!(1, sha3_256.permutations(135));
+        assert_eq!(2, sha3_256.permutations(136));
+        // SHAKE128: r = 168 bytes; the suffix and the padding take 6 bits
+        let shake128 = Frontend::Shake(ShakeVariant::SHAKE128);
+        assert_eq!(1, shake128.permutations(167));
+        assert_eq!(2, shake128.permutations(168));
+        assert_eq!(3, Frontend::Kmac(ShakeVariant::SHAKE128).permutations(0));
+        assert_eq!(1, Frontend::Permutation.permutations(1 << 20));
+        assert_eq!(2 * BATCH_LEN, Frontend::Batch(Sha3Variant::SHA3_256).permutations(136));
+    }
+
+    #[test]
+    fn test_names() {
+        for frontend in Frontend::ALL {
+            assert_eq!(Some(frontend), Frontend::from_name(frontend.name()));
+        }
+        assert_eq!(Some(Backend::Reference), Backend::from_name("reference"));
+        assert_eq!(None, Frontend::from_name("md5"));
+    }
+
+    #[test]
+    fn test_bench() {
+        let options = BenchOptions { warmup: 1, repetitions: 3, min_repetition_time: Duration::ZERO };
+        let result = bench(Backend::Reference, Frontend::Sha3(Sha3Variant::SHA3_256), 200, &options);
+        assert_eq!(200, result.message_len);
+        assert_eq!(1, result.iterations);
+        assert_eq!(2, result.permutations());
+        assert!(result.stats.min <= result.stats.median && result.stats.median <= result.stats.max);
+        assert!(result.bytes_per_second() > 0.0);
+        assert!(result.estimated_cycles_per_byte(1e9).unwrap() > 0.0);
+
+        let batch = bench(Backend::Reference, Frontend::Batch(Sha3Variant::SHA3_512), 64, &options);
+        assert_eq!((64, BATCH_LEN * 64), (batch.message_len, batch.input_len()));
+        assert_eq!(BATCH_LEN, batch.permutations());
+
+        let result = bench(Backend::Reference, Frontend::Permutation, 0, &options);
+        assert_eq!(200, result.message_len);
+        let json = result.to_json();
+        assert_eq!(Some("permutation"), json.get("frontend").and_then(JsonValue::as_str));
+        assert_eq!(Some(1), json.get("permutations").and_then(JsonValue::as_u64));
+    }
+}
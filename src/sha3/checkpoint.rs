@@ -0,0 +1,257 @@
+//! Versioned byte format to export the state of an incremental hasher while it is
+//! absorbing, and to resume hashing from it later, possibly on another machine.
+//!
+//! Layout (integers are little-endian):
+//!
+//! | offset | size | content                                       |
+//! |--------|------|-----------------------------------------------|
+//! | 0      | 4    | magic "K3CP"                                  |
+//! | 4      | 1    | format version (1)                            |
+//! | 5      | 1    | algorithm id, see `CheckpointKind::id`        |
+//! | 6      | 8    | number of bytes absorbed so far               |
+//! | 14     | 200  | Keccak state S, as bytes                      |
+//! | 214    | 2    | length n of the buffered partial block        |
+//! | 216    | n    | buffered bytes, n < r / 8                     |
+
+use derive_more::{Display, Error};
+
+use crate::sha3::constants::KECCAK_B;
+use crate::sha3::types::Sha3Variant;
+use crate::sha3::types::ShakeVariant;
+
+
+pub const CHECKPOINT_MAGIC: [u8; 4] = *b"K3CP";
+
+pub const CHECKPOINT_VERSION: u8 = 1;
+
+/// Size of the Keccak state S in bytes.
+pub const CHECKPOINT_STATE_LEN: usize = KECCAK_B / 8;
+
+const HEADER_LEN: usize = 4 + 1 + 1 + 8;
+
+/// Largest checkpoint, reached for SHAKE128 whose rate is 168 bytes.
+pub const CHECKPOINT_MAX_LEN: usize = HEADER_LEN + CHECKPOINT_STATE_LEN + 2 + 168;
+
+
+/// The algorithm whose state is stored in a checkpoint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CheckpointKind {
+    Sha3(Sha3Variant),
+    Shake(ShakeVariant),
+}
+
+impl CheckpointKind {
+    pub fn id(&self) -> u8 {
+        match self {
+            CheckpointKind::Sha3(Sha3Variant::SHA3_224) => 0x01,
+            CheckpointKind::Sha3(Sha3Variant::SHA3_256) => 0x02,
+            CheckpointKind::Sha3(Sha3Variant::SHA3_384) => 0x03,
+            CheckpointKind::Sha3(Sha3Variant::SHA3_512) => 0x04,
+            CheckpointKind::Shake(ShakeVariant::SHAKE128) => 0x11,
+            CheckpointKind::Shake(ShakeVariant::SHAKE256) => 0x12,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<CheckpointKind> {
+        match id {
+            0x01 => Some(CheckpointKind::Sha3(Sha3Variant::SHA3_224)),
+            0x02 => Some(CheckpointKind::Sha3(Sha3Variant::SHA3_256)),
+            0x03 => Some(CheckpointKind::Sha3(Sha3Variant::SHA3_384)),
+            0x04 => Some(CheckpointKind::Sha3(Sha3Variant::SHA3_512)),
+            0x11 => Some(CheckpointKind::Shake(ShakeVariant::SHAKE128)),
+            0x12 => Some(CheckpointKind::Shake(ShakeVariant::SHAKE256)),
+            _ => None,
+        }
+    }
+
+    /// Capacity c of the underlying KECCAK[c].
+    pub fn capacity(&self) -> usize {
+        match self {
+            CheckpointKind::Sha3(sha3_variant) => sha3_variant.capacity(),
+            CheckpointKind::Shake(shake_variant) => shake_variant.capacity(),
+        }
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Display, Error)]
+pub enum CheckpointError {
+    #[display("checkpoint is truncated")]
+    Truncated,
+    #[display("checkpoint has trailing bytes")]
+    TrailingBytes,
+    #[display("not a checkpoint (bad magic)")]
+    BadMagic,
+    #[display("unsupported checkpoint version {_0}")]
+    UnsupportedVersion(#[error(not(source))] u8),
+    #[display("unknown algorithm id {_0:#04x}")]
+    UnknownAlgorithm(#[error(not(source))] u8),
+    #[display("checkpoint is for {_0:?}, which does not match the hasher")]
+    WrongAlgorithm(#[error(not(source))] CheckpointKind),
+    #[display("buffered block length does not match the absorbed length")]
+    InconsistentLength,
+}
+
+
+/// Content of a checkpoint, borrowed from the blob it was parsed from.
+pub(crate) struct CheckpointFields<'a> {
+    pub kind: CheckpointKind,
+    pub absorbed: u64,
+    pub state: &'a [u8],
+    pub buffered: &'a [u8],
+}
+
+/// Writes the checkpoint to `out` and returns its length;
+/// `out` must be at least `CHECKPOINT_MAX_LEN` bytes long.
+pub(crate) fn encode_checkpoint(fields: &CheckpointFields, out: &mut [u8]) -> usize {
+    assert_eq!(fields.state.len(), CHECKPOINT_STATE_LEN);
+    let len = HEADER_LEN + CHECKPOINT_STATE_LEN + 2 + fields.buffered.len();
+    assert!(out.len() >= len);
+
+    out[0..4].copy_from_slice(&CHECKPOINT_MAGIC);
+    out[4] = CHECKPOINT_VERSION;
+    out[5] = fields.kind.id();
+    out[6..14].copy_from_slice(&fields.absorbed.to_le_bytes());
+    out[HEADER_LEN..HEADER_LEN + CHECKPOINT_STATE_LEN].copy_from_slice(fields.state);
+    let pos = HEADER_LEN + CHECKPOINT_STATE_LEN;
+    out[pos..pos + 2].copy_from_slice(&(fields.buffered.len() as u16).to_le_bytes());
+    out[pos + 2..len].copy_from_slice(fields.buffered);
+    len
+}
+
+/// Parses a checkpoint and checks that the buffered block is consistent with the algorithm.
+pub(crate) fn decode_checkpoint(blob: &[u8]) -> Result<CheckpointFields<'_>, CheckpointError> {
+    let kind = checkpoint_kind(blob)?;
+    if blob.len() < HEADER_LEN + CHECKPOINT_STATE_LEN + 2 {
+        return Err(CheckpointError::Truncated);
+    }
+    let absorbed = u64::from_le_bytes(blob[6..14].try_into().unwrap());
+    let state = &blob[HEADER_LEN..HEADER_LEN + CHECKPOINT_STATE_LEN];
+    let pos = HEADER_LEN + CHECKPOINT_STATE_LEN;
+    let buffered_len = u16::from_le_bytes([blob[pos], blob[pos + 1]]) as usize;
+    let buffered = match blob.get(pos + 2..pos + 2 + buffered_len) {
+        Some(buffered) => buffered,
+        None => return Err(CheckpointError::Truncated),
+    };
+    if blob.len() > pos + 2 + buffered_len {
+        return Err(CheckpointError::TrailingBytes);
+    }
+
+    let rate_bytes = (KECCAK_B - kind.capacity()) as u64 / 8;
+    if buffered_len as u64 != absorbed % rate_bytes {
+        return Err(CheckpointError::InconsistentLength);
+    }
+    Ok(CheckpointFields {
+        kind,
+        absorbed,
+        state,
+        buffered,
+    })
+}
+
+/// Returns the algorithm a checkpoint was taken from, so that the right hasher can be resumed.
+pub fn checkpoint_kind(blob: &[u8]) -> Result<CheckpointKind, CheckpointError> {
+    if blob.len() < HEADER_LEN {
+        return Err(CheckpointError::Truncated);
+    }
+    if blob[0..4] != CHECKPOINT_MAGIC {
+        return Err(CheckpointError::BadMagic);
+    }
+    if blob[4] != CHECKPOINT_VERSION {
+        return Err(CheckpointError::UnsupportedVersion(blob[4]));
+    }
+    CheckpointKind::from_id(blob[5]).ok_or(CheckpointError::UnknownAlgorithm(blob[5]))
+}
+
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::sha3::sha3::Sha3;
+    use crate::sha3::sha3::Shake;
+    use crate::sha3::sha3::sha3_family;
+    use crate::sha3::sha3::shake256;
+
+    fn message(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 31 + 7) as u8).collect()
+    }
+
+    #[test]
+    fn test_resume_sha3_from_checkpoint() {
+        let msg = message(400);
+        for sha3_variant in [Sha3Variant::SHA3_224, Sha3Variant::SHA3_256, Sha3Variant::SHA3_384, Sha3Variant::SHA3_512] {
+            let expected = sha3_family(&msg, sha3_variant.capacity(), sha3_variant.output_len());
+            for split in [0, 1, 71, 72, 136, 137, 400] {
+                let mut sha3 = Sha3::new(sha3_variant);
+                sha3.update(&msg[..split]);
+                let blob = sha3.checkpoint();
+                assert_eq!(Ok(CheckpointKind::Sha3(sha3_variant)), checkpoint_kind(&blob));
+
+                let mut resumed = Sha3::from_checkpoint(&blob).unwrap();
+                resumed.update(&msg[split..]);
+                assert_eq!(expected.as_slice(), resumed.digest().as_slice());
+            }
+        }
+    }
+
+    #[test]
+    fn test_resume_shake_from_checkpoint() {
+        let msg = message(300);
+        let expected = shake256(&msg, 512);
+
+        let mut shake = Shake::new(ShakeVariant::SHAKE256);
+        shake.update(&msg[..200]);
+        let mut blob = [0u8; CHECKPOINT_MAX_LEN];
+        let len = shake.write_checkpoint(&mut blob);
+
+        let mut resumed = Shake::from_checkpoint(&blob[..len]).unwrap();
+        resumed.update(&msg[200..]);
+        let mut output = [0u8; 64];
+        resumed.finalize_xof().squeeze(&mut output);
+        assert_eq!(expected.as_slice(), output);
+    }
+
+    #[test]
+    fn test_fork_cloned_hasher() {
+        let mut prefix = Sha3::new(Sha3Variant::SHA3_256);
+        prefix.update(b"common prefix, ");
+        let mut fork = prefix.clone();
+        prefix.update(b"first");
+        fork.update(b"second");
+        assert_eq!(sha3_family(b"common prefix, first", 512, 256).as_slice(), prefix.digest().as_slice());
+        assert_eq!(sha3_family(b"common prefix, second", 512, 256).as_slice(), fork.digest().as_slice());
+    }
+
+    #[test]
+    fn test_invalid_checkpoints() {
+        let mut sha3 = Sha3::new(Sha3Variant::SHA3_256);
+        sha3.update(b"abc");
+        let blob = sha3.checkpoint().to_vec();
+
+        assert_eq!(Err(CheckpointError::Truncated), Sha3::from_checkpoint(&blob[..5]).map(|_| ()));
+        assert_eq!(Err(CheckpointError::Truncated), Sha3::from_checkpoint(&blob[..blob.len() - 1]).map(|_| ()));
+
+        let mut trailing = blob.clone();
+        trailing.push(0);
+        assert_eq!(Err(CheckpointError::TrailingBytes), Sha3::from_checkpoint(&trailing).map(|_| ()));
+
+        let mut bad_magic = blob.clone();
+        bad_magic[0] ^= 1;
+        assert_eq!(Err(CheckpointError::BadMagic), Sha3::from_checkpoint(&bad_magic).map(|_| ()));
+
+        let mut bad_version = blob.clone();
+        bad_version[4] = 2;
+        assert_eq!(Err(CheckpointError::UnsupportedVersion(2)), Sha3::from_checkpoint(&bad_version).map(|_| ()));
+
+        let mut bad_id = blob.clone();
+        bad_id[5] = 0x7f;
+        assert_eq!(Err(CheckpointError::UnknownAlgorithm(0x7f)), Sha3::from_checkpoint(&bad_id).map(|_| ()));
+
+        let mut bad_length = blob.clone();
+        bad_length[6] = 4;
+        assert_eq!(Err(CheckpointError::InconsistentLength), Sha3::from_checkpoint(&bad_length).map(|_| ()));
+
+        assert_eq!(Err(CheckpointError::WrongAlgorithm(CheckpointKind::Sha3(Sha3Variant::SHA3_256))),
+            Shake::from_checkpoint(&blob).map(|_| ()));
+    }
+}
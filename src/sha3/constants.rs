@@ -23,6 +23,9 @@ pub const KECCAK_CONSTANTS: [[usize;3];7] = [
 
 pub const KECCAK_B: usize = 1600;
 
+/// Lane size w for the largest width b=1600; the state types are sized for it.
+pub const KECCAK_MAX_W: usize = 64;
+
 pub const KECCAK_NR: usize = 24;
 
 pub fn get_w_from_b(b: usize) -> usize {
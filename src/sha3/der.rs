@@ -0,0 +1,523 @@
+//! DER encoding of the `DigestInfo` of PKCS #1 (RFC 8017, Sec. 9.2), e.g. to hand a SHA3
+//! digest to an RSA PKCS #1 v1.5 signer:
+//!
+//! ```text
+//! DigestInfo ::= SEQUENCE {
+//!     digestAlgorithm  AlgorithmIdentifier,
+//!     digest           OCTET STRING }
+//! AlgorithmIdentifier ::= SEQUENCE {
+//!     algorithm        OBJECT IDENTIFIER,
+//!     parameters       ANY DEFINED BY algorithm OPTIONAL }
+//! ```
+//!
+//! Every algorithm of the registry with an OID can be encoded: SHA3, SHAKE (RFC 8702),
+//! HMAC-SHA3 and KMAC, whose parameters are
+//!
+//! ```text
+//! KMACwithSHAKE128-params ::= SEQUENCE {
+//!     kMACOutputLength     INTEGER DEFAULT 256,
+//!     customizationString  OCTET STRING DEFAULT '' }
+//! ```
+//!
+//! with a default output length of 512 for KMAC256. Decoding only accepts DER: definite
+//! minimal lengths, minimal integers and OID arcs, no default values and no trailing bytes.
+
+#[cfg(feature = "alloc")]
+use alloc::vec;
+
+use derive_more::{Display, Error};
+
+use crate::sha3::registry::Algorithm;
+use crate::sha3::registry::Oid;
+#[cfg(feature = "alloc")]
+use crate::sha3::types::ByteString;
+
+
+const TAG_INTEGER: u8 = 0x02;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_NULL: u8 = 0x05;
+const TAG_OID: u8 = 0x06;
+const TAG_SEQUENCE: u8 = 0x30;
+
+/// Most arcs in an OID accepted by the decoder.
+const MAX_OID_ARCS: usize = 16;
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Display, Error)]
+pub enum DerError {
+    #[display("DER data is truncated")]
+    Truncated,
+    #[display("DER data has trailing bytes")]
+    TrailingBytes,
+    #[display("expected tag {_0:#04x}, found {_1:#04x}")]
+    UnexpectedTag(#[error(not(source))] u8, u8),
+    #[display("length is not in minimal definite form")]
+    NonCanonicalLength,
+    #[display("integer is not minimally encoded")]
+    NonCanonicalInteger,
+    #[display("invalid or non-minimal object identifier")]
+    InvalidOid,
+    #[display("unknown algorithm OID")]
+    UnknownAlgorithm,
+    #[display("{_0} has no OID")]
+    NoOid(#[error(not(source))] Algorithm),
+    #[display("invalid parameters for {_0}")]
+    InvalidParameters(#[error(not(source))] Algorithm),
+    #[display("digest of {found} bytes, expected {expected}")]
+    InvalidDigestLength { expected: usize, found: usize },
+    #[display("output buffer is too small")]
+    BufferTooSmall,
+}
+
+
+/// The `parameters` field of the AlgorithmIdentifier.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Parameters<'a> {
+    Absent,
+    Null,
+    /// KMAC parameters; `None` stands for a field left to its default.
+    Kmac {
+        output_len: Option<usize>,
+        customization: Option<&'a [u8]>,
+    },
+}
+
+/// A DigestInfo, borrowing the digest (and the KMAC customization string).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DigestInfo<'a> {
+    pub algorithm: Algorithm,
+    pub parameters: Parameters<'a>,
+    pub digest: &'a [u8],
+}
+
+impl<'a> DigestInfo<'a> {
+    /// Uses the usual parameters: NULL for SHA3, as in the DigestInfo prefixes of PKCS #1
+    /// v1.5 signatures, and absent for the other algorithms.
+    pub fn new(algorithm: Algorithm, digest: &'a [u8]) -> Self {
+        let parameters = match algorithm {
+            Algorithm::Sha3(_) => Parameters::Null,
+            _ => Parameters::Absent,
+        };
+        DigestInfo { algorithm, parameters, digest }
+    }
+
+    /// Length of the digest in bytes implied by the algorithm and its parameters.
+    pub fn expected_digest_len(&self) -> usize {
+        match self.parameters {
+            Parameters::Kmac { output_len: Some(output_len), .. } => output_len / 8,
+            _ => self.algorithm.output_len() / 8,
+        }
+    }
+
+    fn check(&self) -> Result<(), DerError> {
+        let is_valid = match (self.algorithm, self.parameters) {
+            (Algorithm::Sha3(_), Parameters::Absent | Parameters::Null) => true,
+            (Algorithm::Shake(_) | Algorithm::HmacSha3(_), Parameters::Absent) => true,
+            (Algorithm::Kmac(_) | Algorithm::KmacXof(_), Parameters::Absent) => true,
+            (Algorithm::Kmac(_) | Algorithm::KmacXof(_), Parameters::Kmac { output_len, customization }) => {
+                // DER leaves out default values
+                output_len.is_none_or(|len| len != self.algorithm.output_len() && len > 0 && len.is_multiple_of(8))
+                    && customization.is_none_or(|s| !s.is_empty())
+            }
+            _ => false,
+        };
+        if !is_valid {
+            return Err(DerError::InvalidParameters(self.algorithm));
+        }
+        let expected = self.expected_digest_len();
+        if self.digest.len() != expected {
+            return Err(DerError::InvalidDigestLength { expected, found: self.digest.len() });
+        }
+        Ok(())
+    }
+
+    /// Writes the DER encoding to the start of `out` and returns its length.
+    pub fn encode(&self, out: &mut [u8]) -> Result<usize, DerError> {
+        let oid = self.algorithm.oid().ok_or(DerError::NoOid(self.algorithm))?;
+        self.check()?;
+        let mut writer = DerWriter::new(out);
+        writer.prepend(self.digest)?;
+        writer.prepend_header(TAG_OCTET_STRING, self.digest.len())?;
+
+        let algorithm_end = writer.len();
+        match self.parameters {
+            Parameters::Absent => {}
+            Parameters::Null => writer.prepend_header(TAG_NULL, 0)?,
+            Parameters::Kmac { output_len, customization } => {
+                let params_end = writer.len();
+                if let Some(customization) = customization {
+                    writer.prepend(customization)?;
+                    writer.prepend_header(TAG_OCTET_STRING, customization.len())?;
+                }
+                if let Some(output_len) = output_len {
+                    writer.prepend_integer(output_len as u64)?;
+                }
+                writer.prepend_header(TAG_SEQUENCE, writer.len() - params_end)?;
+            }
+        }
+        writer.prepend_oid(oid)?;
+        writer.prepend_header(TAG_SEQUENCE, writer.len() - algorithm_end)?;
+        writer.prepend_header(TAG_SEQUENCE, writer.len())?;
+        Ok(writer.finish())
+    }
+
+    #[cfg(feature = "alloc")]
+    pub fn to_der(&self) -> Result<ByteString, DerError> {
+        let mut out = vec![0u8; self.digest.len() + self.parameters_len_bound() + 64];
+        let len = self.encode(&mut out)?;
+        out.truncate(len);
+        Ok(ByteString::from(out))
+    }
+
+    #[cfg(feature = "alloc")]
+    fn parameters_len_bound(&self) -> usize {
+        match self.parameters {
+            Parameters::Kmac { customization: Some(s), .. } => s.len(),
+            _ => 0,
+        }
+    }
+
+    /// Parses a DER-encoded DigestInfo; the whole input must be consumed.
+    pub fn decode(der: &'a [u8]) -> Result<DigestInfo<'a>, DerError> {
+        let mut reader = DerReader::new(der);
+        let mut digest_info = DerReader::new(reader.read(TAG_SEQUENCE)?);
+        reader.finish()?;
+
+        let mut algorithm_identifier = DerReader::new(digest_info.read(TAG_SEQUENCE)?);
+        let algorithm = decode_oid(algorithm_identifier.read(TAG_OID)?)?;
+        let parameters = match algorithm_identifier.peek_tag() {
+            None => Parameters::Absent,
+            Some(TAG_NULL) => {
+                if !algorithm_identifier.read(TAG_NULL)?.is_empty() {
+                    return Err(DerError::InvalidParameters(algorithm));
+                }
+                Parameters::Null
+            }
+            Some(TAG_SEQUENCE) => {
+                let mut params = DerReader::new(algorithm_identifier.read(TAG_SEQUENCE)?);
+                let output_len = match params.peek_tag() {
+                    Some(TAG_INTEGER) => Some(params.read_integer()?),
+                    _ => None,
+                };
+                let customization = match params.peek_tag() {
+                    Some(_) => Some(params.read(TAG_OCTET_STRING)?),
+                    None => None,
+                };
+                params.finish()?;
+                let output_len = output_len
+                    .map(|len| usize::try_from(len).map_err(|_| DerError::InvalidParameters(algorithm)))
+                    .transpose()?;
+                Parameters::Kmac { output_len, customization }
+            }
+            Some(_) => return Err(DerError::InvalidParameters(algorithm)),
+        };
+        algorithm_identifier.finish()?;
+
+        let digest = digest_info.read(TAG_OCTET_STRING)?;
+        digest_info.finish()?;
+
+        let digest_info = DigestInfo { algorithm, parameters, digest };
+        digest_info.check()?;
+        Ok(digest_info)
+    }
+}
+
+
+/// Maps the content octets of an OID to a registered algorithm.
+fn decode_oid(content: &[u8]) -> Result<Algorithm, DerError> {
+    let mut arcs = [0u32; MAX_OID_ARCS];
+    let mut n = 0;
+    let mut value: u32 = 0;
+    let mut is_start = true;
+    for &byte in content {
+        // the first octet of an arc cannot be 0x80 (a leading zero)
+        if is_start && byte == 0x80 {
+            return Err(DerError::InvalidOid);
+        }
+        value = value.checked_mul(128).ok_or(DerError::InvalidOid)? | u32::from(byte & 0x7f);
+        is_start = byte & 0x80 == 0;
+        if is_start {
+            if n == 0 {
+                // the first subidentifier holds the first two arcs, 40 * X + Y
+                let first = (value / 40).min(2);
+                arcs[0] = first;
+                arcs[1] = value - 40 * first;
+                n = 2;
+            } else if n < MAX_OID_ARCS {
+                arcs[n] = value;
+                n += 1;
+            } else {
+                return Err(DerError::UnknownAlgorithm);
+            }
+            value = 0;
+        }
+    }
+    if n == 0 || !is_start {
+        return Err(DerError::InvalidOid);
+    }
+    Algorithm::from_oid(&arcs[..n]).ok_or(DerError::UnknownAlgorithm)
+}
+
+
+/// Fills a buffer from its end, so that the length of each value is known when its
+/// header is written.
+struct DerWriter<'o> {
+    out: &'o mut [u8],
+    start: usize,
+}
+
+impl<'o> DerWriter<'o> {
+    fn new(out: &'o mut [u8]) -> Self {
+        let start = out.len();
+        DerWriter { out, start }
+    }
+
+    /// Number of bytes written so far.
+    fn len(&self) -> usize {
+        self.out.len() - self.start
+    }
+
+    fn prepend(&mut self, bytes: &[u8]) -> Result<(), DerError> {
+        if bytes.len() > self.start {
+            return Err(DerError::BufferTooSmall);
+        }
+        self.start -= bytes.len();
+        self.out[self.start..self.start + bytes.len()].copy_from_slice(bytes);
+        Ok(())
+    }
+
+    fn prepend_header(&mut self, tag: u8, len: usize) -> Result<(), DerError> {
+        if len < 0x80 {
+            return self.prepend(&[tag, len as u8]);
+        }
+        let len_bytes = (len as u64).to_be_bytes();
+        let skip = len_bytes.iter().take_while(|byte| **byte == 0).count();
+        self.prepend(&len_bytes[skip..])?;
+        self.prepend(&[tag, 0x80 | (8 - skip) as u8])
+    }
+
+    fn prepend_integer(&mut self, value: u64) -> Result<(), DerError> {
+        let bytes = value.to_be_bytes();
+        let skip = bytes.iter().take_while(|byte| **byte == 0).count().min(7);
+        let content_end = self.len();
+        self.prepend(&bytes[skip..])?;
+        // a leading zero keeps the integer positive
+        if bytes[skip] & 0x80 != 0 {
+            self.prepend(&[0])?;
+        }
+        self.prepend_header(TAG_INTEGER, self.len() - content_end)
+    }
+
+    fn prepend_oid(&mut self, oid: Oid) -> Result<(), DerError> {
+        let content_end = self.len();
+        let arcs = oid.0;
+        for (i, arc) in arcs.iter().enumerate().skip(1).rev() {
+            let mut value = if i == 1 { 40 * arcs[0] + arc } else { *arc };
+            self.prepend(&[(value & 0x7f) as u8])?;
+            value >>= 7;
+            while value > 0 {
+                self.prepend(&[0x80 | (value & 0x7f) as u8])?;
+                value >>= 7;
+            }
+        }
+        self.prepend_header(TAG_OID, self.len() - content_end)
+    }
+
+    /// Moves the encoding to the start of the buffer and returns its length.
+    fn finish(self) -> usize {
+        let len = self.len();
+        self.out.copy_within(self.start.., 0);
+        len
+    }
+}
+
+
+struct DerReader<'a> {
+    input: &'a [u8],
+}
+
+impl<'a> DerReader<'a> {
+    fn new(input: &'a [u8]) -> Self {
+        DerReader { input }
+    }
+
+    fn peek_tag(&self) -> Option<u8> {
+        self.input.first().copied()
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], DerError> {
+        if n > self.input.len() {
+            return Err(DerError::Truncated);
+        }
+        let (taken, rest) = self.input.split_at(n);
+        self.input = rest;
+        Ok(taken)
+    }
+
+    /// Reads a value with the given tag and returns its content octets.
+    fn read(&mut self, tag: u8) -> Result<&'a [u8], DerError> {
+        let found = self.take(1)?[0];
+        if found != tag {
+            return Err(DerError::UnexpectedTag(tag, found));
+        }
+        let first = self.take(1)?[0];
+        let len = if first < 0x80 {
+            usize::from(first)
+        } else {
+            let n = usize::from(first & 0x7f);
+            // 0x80 is the indefinite form, which DER forbids
+            if n == 0 || n > core::mem::size_of::<usize>() {
+                return Err(DerError::NonCanonicalLength);
+            }
+            let bytes = self.take(n)?;
+            if bytes[0] == 0 {
+                return Err(DerError::NonCanonicalLength);
+            }
+            let len = bytes.iter().fold(0usize, |len, byte| (len << 8) | usize::from(*byte));
+            if len < 0x80 {
+                return Err(DerError::NonCanonicalLength);
+            }
+            len
+        };
+        self.take(len)
+    }
+
+    /// Reads a non-negative INTEGER that fits in a u64.
+    fn read_integer(&mut self) -> Result<u64, DerError> {
+        let content = self.read(TAG_INTEGER)?;
+        match content {
+            [] => Err(DerError::Truncated),
+            [byte, ..] if byte & 0x80 != 0 => Err(DerError::NonCanonicalInteger),
+            [0, next, ..] if next & 0x80 == 0 => Err(DerError::NonCanonicalInteger),
+            _ => {
+                let content = if content[0] == 0 && content.len() > 1 { &content[1..] } else { content };
+                if content.len() > 8 {
+                    return Err(DerError::NonCanonicalInteger);
+                }
+                Ok(content.iter().fold(0u64, |value, byte| (value << 8) | u64::from(*byte)))
+            }
+        }
+    }
+
+    fn finish(self) -> Result<(), DerError> {
+        match self.input.is_empty() {
+            true => Ok(()),
+            false => Err(DerError::TrailingBytes),
+        }
+    }
+}
+
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::sha3::sha3::sha3_256;
+    use crate::sha3::types::Sha3Variant;
+    use crate::sha3::types::ShakeVariant;
+
+    const SHA3_256: Algorithm = Algorithm::Sha3(Sha3Variant::SHA3_256);
+
+    #[test]
+    fn test_sha3_256_matches_the_pkcs1_prefix() {
+        // the DigestInfo prefix for SHA3-256 used by OpenSSL for PKCS #1 v1.5 signatures
+        let digest = sha3_256(b"abc");
+        let der = DigestInfo::new(SHA3_256, &digest).to_der().unwrap();
+        assert_eq!("3031300d06096086480165030402080500042\
+                    03a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532", hex::encode(&der[..]));
+        assert_eq!(DigestInfo::new(SHA3_256, &digest), DigestInfo::decode(&der).unwrap());
+    }
+
+    #[test]
+    fn test_round_trips() {
+        let digest = [0xa5u8; 200];
+        for algorithm in Algorithm::ALL {
+            let digest_info = DigestInfo::new(algorithm, &digest[..algorithm.output_len() / 8]);
+            match algorithm.oid() {
+                Some(_) => {
+                    let der = digest_info.to_der().unwrap();
+                    assert_eq!(digest_info, DigestInfo::decode(&der).unwrap(), "{algorithm}");
+                }
+                None => assert_eq!(Err(DerError::NoOid(algorithm)), digest_info.to_der()),
+            }
+        }
+
+        let kmac = Algorithm::Kmac(ShakeVariant::SHAKE128);
+        let customization = [b'x'; 300];
+        for (output_len, customization) in [(None, Some(&b"My Tagged Application"[..])), (Some(1600), None), (Some(8), Some(&customization[..]))] {
+            let digest_info = DigestInfo {
+                algorithm: kmac,
+                parameters: Parameters::Kmac { output_len, customization },
+                digest: &digest[..output_len.unwrap_or(256) / 8],
+            };
+            let der = digest_info.to_der().unwrap();
+            assert_eq!(digest_info, DigestInfo::decode(&der).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_kmac_parameters() {
+        // KMAC256 with kMACOutputLength 128 and customizationString "S"
+        let der = hex::decode("30283014060960864801650304021430070202008004015304\
+                               1000000000000000000000000000000000").unwrap();
+        let digest_info = DigestInfo::decode(&der).unwrap();
+        assert_eq!(Algorithm::Kmac(ShakeVariant::SHAKE256), digest_info.algorithm);
+        assert_eq!(Parameters::Kmac { output_len: Some(128), customization: Some(b"S") }, digest_info.parameters);
+        assert_eq!(&[0u8; 16], digest_info.digest);
+        assert_eq!(der, digest_info.to_der().unwrap().to_vec());
+
+        // DER leaves out the default output length
+        let digest = [0u8; 64];
+        let default_len = DigestInfo {
+            algorithm: Algorithm::Kmac(ShakeVariant::SHAKE256),
+            parameters: Parameters::Kmac { output_len: Some(512), customization: None },
+            digest: &digest,
+        };
+        assert_eq!(Err(DerError::InvalidParameters(default_len.algorithm)), default_len.to_der());
+        let mut explicit_default = der.clone();
+        explicit_default[19..21].copy_from_slice(&[0x02, 0x00]);
+        assert_eq!(Err(DerError::InvalidParameters(default_len.algorithm)), DigestInfo::decode(&explicit_default));
+    }
+
+    #[test]
+    fn test_rejects_non_canonical_der() {
+        let digest = sha3_256(b"abc");
+        let der = DigestInfo::new(SHA3_256, &digest).to_der().unwrap().to_vec();
+        let tampered = |offset: usize, removed: usize, replacement: &[u8]| {
+            let mut bytes = der.clone();
+            bytes.splice(offset..offset + removed, replacement.iter().copied());
+            DigestInfo::decode(&bytes).map(|_| ())
+        };
+        // long form for a short length, with or without a leading zero, and the indefinite form
+        assert_eq!(Err(DerError::NonCanonicalLength), tampered(1, 1, &[0x81, 0x31]));
+        assert_eq!(Err(DerError::NonCanonicalLength), tampered(1, 1, &[0x82, 0x00, 0x31]));
+        assert_eq!(Err(DerError::NonCanonicalLength), tampered(1, 1, &[0x80]));
+        // an OID arc with a leading 0x80 octet
+        assert_eq!(Err(DerError::InvalidOid), tampered(0, 17, &hex::decode("3032300e060a608648016503040280080500").unwrap()));
+        // trailing bytes after the DigestInfo, and inside it
+        assert_eq!(Err(DerError::TrailingBytes), tampered(der.len(), 0, &[0x05, 0x00]));
+        let mut inner_trailing = der.clone();
+        inner_trailing[1] = 0x33;
+        inner_trailing.extend_from_slice(&[0x05, 0x00]);
+        assert_eq!(Err(DerError::TrailingBytes), DigestInfo::decode(&inner_trailing).map(|_| ()));
+        // a truncated encoding, and a digest of the wrong length
+        assert_eq!(Err(DerError::Truncated), tampered(der.len() - 1, 1, &[]));
+        let mut short_digest = der[..der.len() - 1].to_vec();
+        short_digest[1] = 0x30;
+        short_digest[18] = 0x1f;
+        assert_eq!(Err(DerError::InvalidDigestLength { expected: 32, found: 31 }), DigestInfo::decode(&short_digest).map(|_| ()));
+        // an unknown OID (SHA-256), and parameters that SHAKE does not take
+        assert_eq!(Err(DerError::UnknownAlgorithm), tampered(14, 1, &[0x01]));
+        let shake = DigestInfo { parameters: Parameters::Null, ..DigestInfo::new(Algorithm::Shake(ShakeVariant::SHAKE128), &digest) };
+        assert_eq!(Err(DerError::InvalidParameters(shake.algorithm)), shake.to_der());
+    }
+
+    #[test]
+    fn test_integers() {
+        for integer in ["0203000100", "020180", "0200"] {
+            let bytes = hex::decode(integer).unwrap();
+            assert!(DerReader::new(&bytes).read_integer().is_err(), "{integer}");
+        }
+        assert_eq!(Ok(0), DerReader::new(&[0x02, 0x01, 0x00]).read_integer());
+        assert_eq!(Ok(0x80), DerReader::new(&[0x02, 0x02, 0x00, 0x80]).read_integer());
+    }
+}
@@ -0,0 +1,484 @@
+//! Generator of test vectors for Keccak instances that NIST does not publish vectors for:
+//! reduced-round Keccak, widths below 1600, unusual SHAKE output lengths and the legacy
+//! Keccak submission (no domain-separation suffix, as in Ethereum's Keccak-256).
+//!
+//! A seed and a parameter grid are expanded into one file per Keccak instance, written both
+//! in the .rsp layout of `test_vectors/SHA3` and as an ACVP prompt/expected-results pair.
+//! Instances that are a full-width SHA3 hash are first cross-checked against the NIST files,
+//! and nothing is written if any of them disagrees.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+use derive_more::{Display, Error};
+
+use crate::sha3::constants::KECCAK_B;
+use crate::sha3::constants::KECCAK_CONSTANTS;
+use crate::sha3::constants::KECCAK_NR;
+use crate::sha3::json::JsonValue;
+use crate::sha3::rng::SplitMix64;
+use crate::sha3::rsp::RspError;
+use crate::sha3::rsp::RspRecordKind;
+use crate::sha3::rsp::parse_rsp;
+use crate::sha3::sha3::keccak_sponge;
+use crate::sha3::types::BitString;
+use crate::sha3::types::ByteString;
+use crate::sha3::types::Sha3Variant;
+use crate::sha3::utils::bitstring_to_bytestr;
+use crate::sha3::utils::bytestr_to_bitstring;
+
+
+/// Domain-separation bits appended to the message before padding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Suffix {
+    /// "01", fixed-output SHA3 hashes.
+    Sha3,
+    /// "1111", SHAKE XOFs.
+    Shake,
+    /// No suffix, the Keccak submission before FIPS 202.
+    Keccak,
+}
+
+impl Suffix {
+    fn bits(&self) -> &'static [u8] {
+        match self {
+            Suffix::Sha3 => &[0, 1],
+            Suffix::Shake => &[1, 1, 1, 1],
+            Suffix::Keccak => &[],
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Suffix::Sha3 => "SHA3",
+            Suffix::Shake => "SHAKE",
+            Suffix::Keccak => "Keccak",
+        }
+    }
+}
+
+
+/// KECCAK[c] over KECCAK-p[b, nr], with a suffix.
+///
+/// Fixed-output instances (SHA3 and legacy Keccak suffixes) output d = c / 2 bits as in
+/// FIPS 202; SHAKE instances are generated for each requested output length.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeccakInstance {
+    pub width: usize,
+    pub rounds: usize,
+    pub capacity: usize,
+    pub suffix: Suffix,
+}
+
+impl KeccakInstance {
+    fn is_full(&self) -> bool {
+        self.width == KECCAK_B && self.rounds == KECCAK_NR
+    }
+
+    /// The SHA3 variant this instance computes, if any.
+    pub fn sha3_variant(&self) -> Option<Sha3Variant> {
+        if !self.is_full() || self.suffix != Suffix::Sha3 {
+            return None;
+        }
+        [Sha3Variant::SHA3_224, Sha3Variant::SHA3_256, Sha3Variant::SHA3_384, Sha3Variant::SHA3_512]
+            .into_iter()
+            .find(|sha3_variant| sha3_variant.capacity() == self.capacity)
+    }
+
+    /// Name used in the .rsp header, e.g. "SHA3-256", "SHAKE128", "Keccak-256",
+    /// or "Keccak-p[800,22]-c256-SHA3" for non-standard instances.
+    pub fn name(&self) -> String {
+        let d = self.capacity / 2;
+        match (self.is_full(), self.suffix, self.capacity) {
+            (true, Suffix::Sha3, 448 | 512 | 768 | 1024) => format!("SHA3-{d}"),
+            (true, Suffix::Shake, 256 | 512) => format!("SHAKE{d}"),
+            (true, Suffix::Keccak, 448 | 512 | 768 | 1024) => format!("Keccak-{d}"),
+            _ => format!("Keccak-p[{},{}]-c{}-{}", self.width, self.rounds, self.capacity, self.suffix.label()),
+        }
+    }
+
+    /// Name used in ACVP files, which differs from the .rsp one for SHAKE ("SHAKE-128").
+    pub fn acvp_name(&self) -> String {
+        match (self.is_full(), self.suffix, self.capacity) {
+            (true, Suffix::Shake, 256 | 512) => format!("SHAKE-{}", self.capacity / 2),
+            _ => self.name(),
+        }
+    }
+
+    /// File name without extension: the name with the characters outside [A-Za-z0-9-] replaced.
+    fn file_stem(&self) -> String {
+        self.name().chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' }).collect()
+    }
+
+    fn check(&self) -> Result<(), String> {
+        let Some(el) = KECCAK_CONSTANTS.iter().find(|tuple| tuple[2] == self.width).map(|tuple| tuple[0]) else {
+            return Err(format!("width {} is not 25 * 2^l", self.width));
+        };
+        if self.rounds == 0 || self.rounds > 12 + 2 * el {
+            return Err(format!("{} rounds are not in 1..={} for width {}", self.rounds, 12 + 2 * el, self.width));
+        }
+        if self.capacity == 0 || self.capacity + 2 > self.width {
+            return Err(format!("capacity {} leaves no room for a rate of at least 2 bits", self.capacity));
+        }
+        if self.suffix != Suffix::Shake && !self.capacity.is_multiple_of(16) {
+            return Err(format!("capacity {} does not give a whole number of output bytes", self.capacity));
+        }
+        Ok(())
+    }
+
+    /// KECCAK[c](M || suffix, d), with d a multiple of 8.
+    pub fn hash(&self, m: &[u8], d: usize) -> ByteString {
+        assert!(d.is_multiple_of(8));
+        let mut n = bytestr_to_bitstring(m);
+        n.extend_from_slice(self.suffix.bits());
+        let output: BitString = keccak_sponge(self.width, self.rounds, self.capacity, &n, d);
+        bitstring_to_bytestr(&output)
+    }
+}
+
+
+/// Parameter grid: every combination of width, rounds, capacity and suffix is an instance.
+pub struct GeneratorGrid {
+    pub widths: Vec<usize>,
+    /// An empty list means the full 12 + 2l rounds of each width.
+    pub rounds: Vec<usize>,
+    pub capacities: Vec<usize>,
+    pub suffixes: Vec<Suffix>,
+    /// Output lengths of the SHAKE instances, in bits.
+    pub output_lens: Vec<usize>,
+    /// Message lengths, in bytes.
+    pub msg_lens: Vec<usize>,
+}
+
+impl GeneratorGrid {
+    /// The valid instances of the grid; combinations such as a capacity larger than the width
+    /// are left out, as a grid usually spans several widths.
+    pub fn instances(&self) -> Vec<KeccakInstance> {
+        let mut instances = Vec::new();
+        for &width in &self.widths {
+            let full_rounds = KECCAK_CONSTANTS.iter().find(|tuple| tuple[2] == width).map(|tuple| 12 + 2 * tuple[0]);
+            let rounds = if self.rounds.is_empty() { full_rounds.into_iter().collect() } else { self.rounds.clone() };
+            for &rounds in &rounds {
+                for &capacity in &self.capacities {
+                    for &suffix in &self.suffixes {
+                        let instance = KeccakInstance { width, rounds, capacity, suffix };
+                        if instance.check().is_ok() && !instances.contains(&instance) {
+                            instances.push(instance);
+                        }
+                    }
+                }
+            }
+        }
+        instances
+    }
+}
+
+
+#[derive(Debug, Display, Error)]
+pub enum GeneratorError {
+    #[display("invalid grid: {_0}")]
+    InvalidGrid(#[error(not(source))] String),
+    #[display("{}: {_1}", _0.display())]
+    Io(PathBuf, #[error(source)] io::Error),
+    #[display("{}: {_1}", _0.display())]
+    Rsp(PathBuf, #[error(source)] RspError),
+    #[display("{}: {_1} disagrees with the NIST record at line {_2}", _0.display())]
+    CrossCheckFailed(PathBuf, #[error(not(source))] String, usize),
+}
+
+
+/// Vectors of one Keccak instance: a section per output length, each with the same messages.
+pub struct VectorFile {
+    pub instance: KeccakInstance,
+    pub seed: u64,
+    /// (d in bits, [(message, output)])
+    pub sections: Vec<(usize, Vec<(ByteString, ByteString)>)>,
+}
+
+impl VectorFile {
+    pub fn generate(instance: KeccakInstance, grid: &GeneratorGrid, seed: u64) -> Self {
+        let mut rng = SplitMix64::new(seed);
+        let msgs: Vec<ByteString> = grid.msg_lens.iter()
+            .map(|&len| {
+                let mut msg = ByteString::from(vec![0u8; len]);
+                rng.fill_bytes(&mut msg);
+                msg
+            })
+            .collect();
+        let output_lens = match instance.suffix {
+            Suffix::Shake => grid.output_lens.clone(),
+            Suffix::Sha3 | Suffix::Keccak => vec![instance.capacity / 2],
+        };
+        let sections = output_lens.into_iter()
+            .map(|d| (d, msgs.iter().map(|msg| (msg.clone(), instance.hash(msg, d))).collect()))
+            .collect();
+        VectorFile { instance, seed, sections }
+    }
+
+    /// The .rsp layout of the NIST files: header comments, then `[L = d]` sections of
+    /// Len/Msg/MD records (`[Outputlen = d]` and `Output` for SHAKE instances).
+    pub fn to_rsp(&self) -> String {
+        let instance = &self.instance;
+        let (section_name, output_name) = match instance.suffix {
+            Suffix::Shake => ("Outputlen", "Output"),
+            Suffix::Sha3 | Suffix::Keccak => ("L", "MD"),
+        };
+        let mut rsp = format!("#  crypto-playground test-vector generator\n\
+            #  \"{} Generated\" information for \"seed {}\"\n\
+            #  KECCAK-p[{}, {}], capacity {}, suffix {}\n\
+            #  Length values represented in bits\n",
+            instance.name(), self.seed, instance.width, instance.rounds, instance.capacity, instance.suffix.label());
+        for (d, records) in &self.sections {
+            rsp += &format!("\n[{section_name} = {d}]\n");
+            for (msg, output) in records {
+                // as in the NIST files, the empty message is written as "00"
+                let msg_hex = if msg.is_empty() { "00".to_string() } else { hex::encode(msg.as_slice()) };
+                rsp += &format!("\nLen = {}\nMsg = {msg_hex}\n{output_name} = {}\n", 8 * msg.len(), hex::encode(output.as_slice()));
+            }
+        }
+        rsp
+    }
+
+    /// ACVP prompt and expected results, with one AFT test group per output length.
+    pub fn to_acvp(&self) -> (JsonValue, JsonValue) {
+        let mut prompt_groups = Vec::new();
+        let mut expected_groups = Vec::new();
+        let mut tc_id = 1usize;
+        for (tg_id, (d, records)) in self.sections.iter().enumerate() {
+            let mut prompt_tests = Vec::new();
+            let mut expected_tests = Vec::new();
+            for (msg, output) in records {
+                let mut prompt_test = vec![
+                    ("tcId".to_string(), JsonValue::from(tc_id)),
+                    ("msg".to_string(), JsonValue::from(hex::encode_upper(msg.as_slice()))),
+                    ("len".to_string(), JsonValue::from(8 * msg.len())),
+                ];
+                if self.instance.suffix == Suffix::Shake {
+                    prompt_test.push(("outLen".to_string(), JsonValue::from(*d)));
+                }
+                prompt_tests.push(JsonValue::Object(prompt_test));
+                expected_tests.push(JsonValue::Object(vec![
+                    ("tcId".to_string(), JsonValue::from(tc_id)),
+                    ("md".to_string(), JsonValue::from(hex::encode_upper(output.as_slice()))),
+                ]));
+                tc_id += 1;
+            }
+            prompt_groups.push(JsonValue::Object(vec![
+                ("tgId".to_string(), JsonValue::from(tg_id + 1)),
+                ("testType".to_string(), JsonValue::from("AFT")),
+                ("tests".to_string(), JsonValue::Array(prompt_tests)),
+            ]));
+            expected_groups.push(JsonValue::Object(vec![
+                ("tgId".to_string(), JsonValue::from(tg_id + 1)),
+                ("tests".to_string(), JsonValue::Array(expected_tests)),
+            ]));
+        }
+        let vector_set = |test_groups: Vec<JsonValue>| JsonValue::Array(vec![
+            JsonValue::Object(vec![("acvVersion".to_string(), JsonValue::from("1.0"))]),
+            JsonValue::Object(vec![
+                ("vsId".to_string(), JsonValue::from(self.seed)),
+                ("algorithm".to_string(), JsonValue::from(self.instance.acvp_name())),
+                ("revision".to_string(), JsonValue::from("1.0")),
+                ("isSample".to_string(), JsonValue::from(true)),
+                ("testGroups".to_string(), JsonValue::Array(test_groups)),
+            ]),
+        ]);
+        (vector_set(prompt_groups), vector_set(expected_groups))
+    }
+}
+
+
+/// Checks a full-width SHA3 instance against the byte-oriented records of the NIST ShortMsg
+/// and LongMsg files found in `nist_dir`, e.g. `test_vectors/SHA3`. Returns the number of records checked.
+pub fn cross_check(instance: &KeccakInstance, sha3_variant: Sha3Variant, nist_dir: &Path) -> Result<usize, GeneratorError> {
+    let mut checked = 0;
+    for test_name in ["ShortMsg", "LongMsg"] {
+        let path = nist_dir.join(format!("SHA3_{}{test_name}.rsp", sha3_variant.output_len()));
+        let content = fs::read_to_string(&path)
+            .map_err(|e| GeneratorError::Io(path.clone(), e))?;
+        let rsp = parse_rsp(&content)
+            .map_err(|e| GeneratorError::Rsp(path.clone(), e))?;
+        for record in rsp.records() {
+            if let RspRecordKind::Message { len, msg, md } = &record.kind
+                && len.is_multiple_of(8) {
+                if instance.hash(msg, sha3_variant.output_len()) != *md {
+                    return Err(GeneratorError::CrossCheckFailed(path, instance.name(), record.line));
+                }
+                checked += 1;
+            }
+        }
+    }
+    Ok(checked)
+}
+
+/// Generates the vectors of every instance of the grid, and writes `rsp/<name>Generated.rsp`
+/// and `acvp/<name>/{prompt,expectedResults}.json` under `out_dir`. Returns the written paths.
+///
+/// Full-width SHA3 instances are cross-checked against the NIST files in `nist_dir` first;
+/// nothing is written unless they all agree.
+pub fn write_vectors(out_dir: &Path, grid: &GeneratorGrid, seed: u64, nist_dir: &Path) -> Result<Vec<PathBuf>, GeneratorError> {
+    let instances = grid.instances();
+    if instances.is_empty() {
+        return Err(GeneratorError::InvalidGrid("no valid Keccak instance".to_string()));
+    }
+    if grid.msg_lens.is_empty() {
+        return Err(GeneratorError::InvalidGrid("no message length".to_string()));
+    }
+    if let Some(d) = grid.output_lens.iter().find(|d| !d.is_multiple_of(8)) {
+        return Err(GeneratorError::InvalidGrid(format!("output length {d} is not a multiple of 8")));
+    }
+    if instances.iter().any(|instance| instance.suffix == Suffix::Shake) && grid.output_lens.is_empty() {
+        return Err(GeneratorError::InvalidGrid("no output length for the SHAKE instances".to_string()));
+    }
+
+    for instance in &instances {
+        if let Some(sha3_variant) = instance.sha3_variant() {
+            cross_check(instance, sha3_variant, nist_dir)?;
+        }
+    }
+    let files: Vec<VectorFile> = instances.into_iter()
+        .map(|instance| VectorFile::generate(instance, grid, seed))
+        .collect();
+
+    let write = |path: PathBuf, content: String| {
+        fs::create_dir_all(path.parent().unwrap())
+            .and_then(|_| fs::write(&path, content))
+            .map_err(|e| GeneratorError::Io(path.clone(), e))
+            .map(|_| path)
+    };
+    let mut paths = Vec::new();
+    for file in &files {
+        let stem = file.instance.file_stem();
+        paths.push(write(out_dir.join("rsp").join(format!("{stem}Generated.rsp")), file.to_rsp())?);
+        let (prompt, expected_results) = file.to_acvp();
+        let acvp_dir = out_dir.join("acvp").join(&stem);
+        paths.push(write(acvp_dir.join("prompt.json"), prompt.to_pretty_string() + "\n")?);
+        paths.push(write(acvp_dir.join("expectedResults.json"), expected_results.to_pretty_string() + "\n")?);
+    }
+    Ok(paths)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha3::acvp::AcvpOptions;
+    use crate::sha3::acvp::run_acvp_dir;
+    use crate::sha3::kat::KatOptions;
+    use crate::sha3::kat::run_rsp_file;
+
+    fn nist_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("test_vectors/SHA3")
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("crypto-playground-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn instance(width: usize, rounds: usize, capacity: usize, suffix: Suffix) -> KeccakInstance {
+        KeccakInstance { width, rounds, capacity, suffix }
+    }
+
+    #[test]
+    fn test_non_standard_instances() {
+        // expected values computed with an independent Python implementation of KECCAK-p[b, nr]
+        let cases = [
+            (instance(1600, 24, 512, Suffix::Keccak), &b""[..], 256, "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"),
+            (instance(1600, 12, 512, Suffix::Sha3), b"abc", 256, "50e16cd9619525ba39414b290ec6dd64f9850a87ca41b68b447372000f836728"),
+            (instance(800, 22, 256, Suffix::Sha3), b"abc", 128, "83d1e652dd8240071f38e03499500fe4"),
+            (instance(400, 20, 144, Suffix::Sha3), b"abc", 72, "0ee01e0f09df1c7714"),
+            (instance(25, 12, 8, Suffix::Shake), &[1, 2], 40, "f6e8b842d9"),
+        ];
+        for (instance, msg, d, expected) in cases {
+            assert_eq!(hex::decode(expected).unwrap(), instance.hash(msg, d).as_slice(), "{}", instance.name());
+        }
+        let msg: Vec<u8> = (0..40).collect();
+        assert_eq!(hex::decode("af07bb69b0ffb6902776e2a6b81ab49991ae6346058c65de88").unwrap(),
+            instance(200, 18, 100, Suffix::Shake).hash(&msg, 200).as_slice());
+    }
+
+    #[test]
+    fn test_names_and_grid() {
+        assert_eq!("SHA3-256", instance(1600, 24, 512, Suffix::Sha3).name());
+        assert_eq!("SHAKE128", instance(1600, 24, 256, Suffix::Shake).name());
+        assert_eq!("SHAKE-128", instance(1600, 24, 256, Suffix::Shake).acvp_name());
+        assert_eq!("Keccak-256", instance(1600, 24, 512, Suffix::Keccak).name());
+        assert_eq!("Keccak-p[800,22]-c256-SHA3", instance(800, 22, 256, Suffix::Sha3).name());
+        assert_eq!("Keccak-p_800_22_-c256-SHA3", instance(800, 22, 256, Suffix::Sha3).file_stem());
+
+        let grid = GeneratorGrid {
+            widths: vec![200, 1600, 1000],
+            rounds: vec![],
+            capacities: vec![64, 512],
+            suffixes: vec![Suffix::Sha3],
+            output_lens: vec![],
+            msg_lens: vec![0],
+        };
+        // width 1000 is not 25 * 2^l, and c = 512 does not fit in 200 bits
+        assert_eq!(vec![instance(200, 18, 64, Suffix::Sha3), instance(1600, 24, 64, Suffix::Sha3), instance(1600, 24, 512, Suffix::Sha3)],
+            grid.instances());
+    }
+
+    #[test]
+    fn test_write_vectors() {
+        let grid = GeneratorGrid {
+            widths: vec![800, 1600],
+            rounds: vec![12, 22, 24],
+            capacities: vec![256, 512],
+            suffixes: vec![Suffix::Sha3, Suffix::Shake],
+            output_lens: vec![8, 1000],
+            msg_lens: vec![0, 1, 71, 137],
+        };
+        let out_dir = temp_dir("generator");
+        let paths = write_vectors(&out_dir, &grid, 42, &nist_dir()).unwrap();
+        // 800-bit width: rounds 12 and 22, 2 capacities, 2 suffixes; 1600-bit width: 3 rounds
+        assert_eq!(3 * (2 * 2 * 2 + 3 * 2 * 2), paths.len());
+
+        let rsp = fs::read_to_string(out_dir.join("rsp/SHA3-256Generated.rsp")).unwrap();
+        assert!(rsp.contains("#  \"SHA3-256 Generated\" information for \"seed 42\"\n"), "{rsp}");
+        let shake = fs::read_to_string(out_dir.join("rsp/Keccak-p_800_12_-c256-SHAKEGenerated.rsp")).unwrap();
+        assert!(shake.contains("\n[Outputlen = 1000]\n"), "{shake}");
+
+        // the standard instances can be run by the known-answer runners
+        for (name, sections) in [("SHA3-256", 1), ("SHAKE128", 2), ("SHAKE256", 2)] {
+            let report = run_rsp_file(&out_dir.join(format!("rsp/{name}Generated.rsp")), &KatOptions::default()).unwrap();
+            assert!(report.is_success(), "{report}");
+            assert_eq!(sections * grid.msg_lens.len(), report.passed, "{report}");
+        }
+        for name in ["SHA3-256", "SHAKE128"] {
+            let reports = run_acvp_dir(&out_dir.join("acvp").join(name), &AcvpOptions::default()).unwrap();
+            assert!(reports[0].is_success() && reports[0].passed() > 0, "{}", reports[0]);
+        }
+        fs::remove_dir_all(&out_dir).unwrap();
+    }
+
+    #[test]
+    fn test_cross_check_failure_writes_nothing() {
+        let nist_copy = temp_dir("generator-nist");
+        fs::create_dir_all(&nist_copy).unwrap();
+        for test_name in ["ShortMsg", "LongMsg"] {
+            let file_name = format!("SHA3_224{test_name}.rsp");
+            let content = fs::read_to_string(nist_dir().join(&file_name)).unwrap();
+            // corrupt the digest of the empty message
+            let content = content.replacen("MD = 6b4e", "MD = 6b4f", 1);
+            fs::write(nist_copy.join(file_name), content).unwrap();
+        }
+        let grid = GeneratorGrid {
+            widths: vec![1600],
+            rounds: vec![],
+            capacities: vec![448],
+            suffixes: vec![Suffix::Sha3],
+            output_lens: vec![],
+            msg_lens: vec![3],
+        };
+        let out_dir = temp_dir("generator-out");
+        let err = write_vectors(&out_dir, &grid, 1, &nist_copy).err().unwrap();
+        assert!(matches!(err, GeneratorError::CrossCheckFailed(_, ref name, 9) if name == "SHA3-224"), "{err}");
+        assert!(!out_dir.exists());
+        fs::remove_dir_all(&nist_copy).unwrap();
+    }
+}
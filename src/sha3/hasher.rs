@@ -0,0 +1,155 @@
+use core::hash::BuildHasher;
+use core::hash::Hasher;
+#[cfg(feature = "std")]
+use std::collections::hash_map::RandomState;
+
+use crate::sha3::secret::Secret;
+use crate::sha3::secret::Zeroize;
+use crate::sha3::sha3::SHA3_MAX_DIGEST_BYTES;
+use crate::sha3::sha3::Sha3;
+use crate::sha3::types::Sha3Variant;
+
+
+/// Adapter that exposes an (optionally keyed) SHA3 hasher through `std::hash::Hasher`.
+///
+/// The key is absorbed before any data, prefixed with its length; since SHA3 is not
+/// susceptible to length extension, this is enough to obtain a keyed hash.
+/// `finish` returns the first 8 bytes of the digest as a little-endian u64.
+#[derive(Clone)]
+pub struct Sha3Hasher {
+    sha3: Sha3,
+}
+
+impl Sha3Hasher {
+    pub fn new(sha3_variant: Sha3Variant) -> Self {
+        Sha3Hasher {
+            sha3: Sha3::new(sha3_variant),
+        }
+    }
+
+    pub fn with_key(sha3_variant: Sha3Variant, key: &[u8]) -> Self {
+        let mut hasher = Sha3Hasher::new(sha3_variant);
+        hasher.sha3.update(&(key.len() as u64).to_le_bytes());
+        hasher.sha3.update(key);
+        hasher
+    }
+}
+
+impl Default for Sha3Hasher {
+    fn default() -> Self {
+        Sha3Hasher::new(Sha3Variant::SHA3_256)
+    }
+}
+
+impl Hasher for Sha3Hasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.sha3.update(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        let mut digest = [0u8; SHA3_MAX_DIGEST_BYTES];
+        let digest_len = self.sha3.variant().output_len() / 8;
+        self.sha3.clone().digest_into(&mut digest[..digest_len]);
+        let mut first_bytes = [0u8; 8];
+        first_bytes.copy_from_slice(&digest[..8]);
+        digest.zeroize();
+        u64::from_le_bytes(first_bytes)
+    }
+}
+
+
+pub const SHA3_BUILD_HASHER_KEY_LEN: usize = 32;
+
+/// `BuildHasher` producing `Sha3Hasher`s keyed with the same key, e.g. for `HashMap`.
+#[derive(Clone)]
+pub struct Sha3BuildHasher {
+    sha3_variant: Sha3Variant,
+    key: Secret<[u8; SHA3_BUILD_HASHER_KEY_LEN]>,
+}
+
+impl Sha3BuildHasher {
+    /// Uses a random key taken from the seeds of std's `RandomState`.
+    #[cfg(feature = "std")]
+    pub fn new(sha3_variant: Sha3Variant) -> Self {
+        let mut key = [0u8; SHA3_BUILD_HASHER_KEY_LEN];
+        for (i, chunk) in key.chunks_mut(8).enumerate() {
+            let mut random_hasher = RandomState::new().build_hasher();
+            random_hasher.write_usize(i);
+            chunk.copy_from_slice(&random_hasher.finish().to_le_bytes());
+        }
+        let build_hasher = Sha3BuildHasher::with_key(sha3_variant, key);
+        key.zeroize();
+        build_hasher
+    }
+
+    /// Uses a fixed key, so that hashes are reproducible across runs.
+    pub fn with_key(sha3_variant: Sha3Variant, key: [u8; SHA3_BUILD_HASHER_KEY_LEN]) -> Self {
+        Sha3BuildHasher {
+            sha3_variant,
+            key: Secret::new(key),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for Sha3BuildHasher {
+    fn default() -> Self {
+        Sha3BuildHasher::new(Sha3Variant::SHA3_256)
+    }
+}
+
+impl BuildHasher for Sha3BuildHasher {
+    type Hasher = Sha3Hasher;
+
+    fn build_hasher(&self) -> Sha3Hasher {
+        Sha3Hasher::with_key(self.sha3_variant, self.key.expose_secret())
+    }
+}
+
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn test_unkeyed_hasher_matches_digest() {
+        let mut hasher = Sha3Hasher::default();
+        hasher.write(b"Te");
+        hasher.write(b"st");
+        // first 8 bytes of SHA3-256("Test")
+        let digest = hex::decode("c0a5cca43b8aa79eb50e3464bc839dd6").unwrap();
+        let expected = u64::from_le_bytes(digest[..8].try_into().unwrap());
+        assert_eq!(expected, hasher.finish());
+        // finish does not consume the hasher
+        assert_eq!(expected, hasher.finish());
+    }
+
+    #[test]
+    fn test_keyed_hashers() {
+        let key = [7u8; SHA3_BUILD_HASHER_KEY_LEN];
+        let h0 = Sha3BuildHasher::with_key(Sha3Variant::SHA3_256, key).hash_one("abc");
+        let h1 = Sha3BuildHasher::with_key(Sha3Variant::SHA3_256, key).hash_one("abc");
+        assert_eq!(h0, h1);
+
+        let h2 = Sha3BuildHasher::with_key(Sha3Variant::SHA3_256, [8u8; SHA3_BUILD_HASHER_KEY_LEN]).hash_one("abc");
+        assert_ne!(h0, h2);
+    }
+
+    #[test]
+    fn test_key_is_not_debug_printed() {
+        let build_hasher = Sha3BuildHasher::with_key(Sha3Variant::SHA3_256, [0x42u8; SHA3_BUILD_HASHER_KEY_LEN]);
+        assert_eq!("Secret([REDACTED])", format!("{:?}", build_hasher.key));
+    }
+
+    #[test]
+    fn test_hash_map() {
+        let mut map = HashMap::with_hasher(Sha3BuildHasher::default());
+        map.insert("one", 1);
+        map.insert("two", 2);
+        assert_eq!(Some(&1), map.get("one"));
+        assert_eq!(Some(&2), map.get("two"));
+        assert_eq!(None, map.get("three"));
+    }
+}
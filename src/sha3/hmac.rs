@@ -0,0 +1,144 @@
+//! HMAC (FIPS 198-1) instantiated with the SHA3 hashes, whose block size B is the rate r / 8.
+
+#[cfg(feature = "alloc")]
+use alloc::vec;
+
+use crate::sha3::constants::KECCAK_B;
+use crate::sha3::secret::Zeroize;
+use crate::sha3::secret::ct_eq;
+use crate::sha3::sha3::SHA3_MAX_DIGEST_BYTES;
+use crate::sha3::sha3::Sha3;
+use crate::sha3::sha3::sha3_into;
+#[cfg(feature = "alloc")]
+use crate::sha3::types::ByteString;
+use crate::sha3::types::Sha3Variant;
+
+
+/// Largest block size of HMAC-SHA3 (HMAC-SHA3-224), in bytes.
+const HMAC_MAX_BLOCK_BYTES: usize = 144;
+
+/// Block size B of HMAC-SHA3 in bytes.
+pub fn hmac_block_len(sha3_variant: Sha3Variant) -> usize {
+    (KECCAK_B - sha3_variant.capacity()) / 8
+}
+
+/// Incremental HMAC-SHA3: HMAC(K, text) = H((K0 xor opad) || H((K0 xor ipad) || text)).
+///
+/// Both hashers are keyed when the HMAC is created, so the key itself is not kept.
+#[derive(Clone)]
+pub struct HmacSha3 {
+    inner: Sha3,
+    outer: Sha3,
+}
+
+impl HmacSha3 {
+    /// Keys longer than B bytes are hashed first; shorter keys, including the empty key,
+    /// are padded with zeros.
+    pub fn new(sha3_variant: Sha3Variant, key: &[u8]) -> Self {
+        let block_len = hmac_block_len(sha3_variant);
+        let mut k0 = [0u8; HMAC_MAX_BLOCK_BYTES];
+        if key.len() > block_len {
+            sha3_into(sha3_variant, key, &mut k0[..sha3_variant.output_len() / 8]);
+        } else {
+            k0[..key.len()].copy_from_slice(key);
+        }
+
+        let mut pad = [0u8; HMAC_MAX_BLOCK_BYTES];
+        let mut keyed = |pad_byte: u8| {
+            for (p, k) in pad.iter_mut().zip(k0.iter()) {
+                *p = k ^ pad_byte;
+            }
+            let mut sha3 = Sha3::new(sha3_variant);
+            sha3.update(&pad[..block_len]);
+            sha3
+        };
+        let hmac = HmacSha3 {
+            inner: keyed(0x36),
+            outer: keyed(0x5c),
+        };
+        k0.zeroize();
+        pad.zeroize();
+        hmac
+    }
+
+    pub fn variant(&self) -> Sha3Variant {
+        self.inner.variant()
+    }
+
+    pub fn update(&mut self, bytestr: &[u8]) {
+        self.inner.update(bytestr);
+    }
+
+    /// Writes the full tag to `out`, which must be `output_len() / 8` bytes long.
+    pub fn finalize_into(mut self, out: &mut [u8]) {
+        let mut inner_digest = [0u8; SHA3_MAX_DIGEST_BYTES];
+        let digest_len = self.variant().output_len() / 8;
+        self.inner.digest_into(&mut inner_digest[..digest_len]);
+        self.outer.update(&inner_digest[..digest_len]);
+        self.outer.digest_into(out);
+        inner_digest.zeroize();
+    }
+
+    /// Checks, in constant time, a tag that may be truncated to its leftmost `tag.len()` bytes.
+    /// Empty tags never verify; the caller must check that `tag` is not shorter than its policy allows.
+    pub fn verify_truncated(self, tag: &[u8]) -> bool {
+        let mut mac = [0u8; SHA3_MAX_DIGEST_BYTES];
+        let digest_len = self.variant().output_len() / 8;
+        self.finalize_into(&mut mac[..digest_len]);
+        let is_valid = !tag.is_empty() && tag.len() <= digest_len && ct_eq(&mac[..tag.len()], tag);
+        mac.zeroize();
+        is_valid
+    }
+
+    /// Checks a full-length tag in constant time.
+    pub fn verify(self, tag: &[u8]) -> bool {
+        tag.len() == self.variant().output_len() / 8 && self.verify_truncated(tag)
+    }
+}
+
+#[cfg(feature = "alloc")]
+pub fn hmac_sha3(sha3_variant: Sha3Variant, key: &[u8], text: &[u8]) -> ByteString {
+    let mut hmac = HmacSha3::new(sha3_variant, key);
+    hmac.update(text);
+    let mut tag = ByteString::from(vec![0u8; sha3_variant.output_len() / 8]);
+    hmac.finalize_into(&mut tag);
+    tag
+}
+
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hmac_sha3() {
+        // NIST example for HMAC-SHA3-256; the others were computed with Python's hmac module
+        let key: Vec<u8> = (0..32).collect();
+        assert_eq!(hex::decode("4fe8e202c4f058e8dddc23d8c34e467343e23555e24fc2f025d598f558f67205").unwrap(),
+            hmac_sha3(Sha3Variant::SHA3_256, &key, b"Sample message for keylen<blocklen").as_slice());
+        assert_eq!(hex::decode("1b9044e0d5bb4ef944bc00f1b26c483ac3e222f4640935d089a49083").unwrap(),
+            hmac_sha3(Sha3Variant::SHA3_224, b"", b"").as_slice());
+        // key of exactly B bytes, and message spanning several blocks
+        let key: Vec<u8> = (0..104).collect();
+        assert_eq!(hex::decode("bdf5cdd38b85653bb233f17d128211a5a7965c26fa40a2b92b3be4245c2477428745101e1b8d9b81da1e9d8748f32d24").unwrap(),
+            hmac_sha3(Sha3Variant::SHA3_384, &key, &[b'x'; 300]).as_slice());
+        // key longer than B bytes, which is hashed first
+        let key: Vec<u8> = (0..200).collect();
+        assert_eq!(hex::decode("eba5b7668e85748ab6d5f4800f48c292a5085820904091cda307f8431ef37763680ddeed39f4aa9b262f1aa8691e2331563eb0169aaa1249575a4ad17dbd6c53").unwrap(),
+            hmac_sha3(Sha3Variant::SHA3_512, &key, b"Sample message for keylen>blocklen").as_slice());
+    }
+
+    #[test]
+    fn test_verify() {
+        let tag = hmac_sha3(Sha3Variant::SHA3_256, b"key", b"text");
+        let mut hmac = HmacSha3::new(Sha3Variant::SHA3_256, b"key");
+        hmac.update(b"text");
+        assert!(hmac.clone().verify(&tag));
+        assert!(!hmac.clone().verify(&tag[..16]));
+        assert!(hmac.clone().verify_truncated(&tag[..16]));
+        assert!(!hmac.clone().verify_truncated(&[]));
+        let mut modified = tag.clone();
+        modified[31] ^= 0x80;
+        assert!(!hmac.verify(&modified));
+    }
+}
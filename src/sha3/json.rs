@@ -0,0 +1,494 @@
+//! Minimal JSON reader and writer (RFC 8259) for the test-vector files, so that the crate
+//! does not need a serialization framework. Objects keep the order of their members.
+
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::Write;
+
+use derive_more::{Display, Error};
+
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    /// Returns the value of the first member named `key`, if this is an object.
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            JsonValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// Returns the number if it is a non-negative integer that fits exactly in an f64.
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            JsonValue::Number(n) if *n >= 0.0 && *n <= (1u64 << 53) as f64 && (*n as u64) as f64 == *n => Some(*n as u64),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[JsonValue]> {
+        match self {
+            JsonValue::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&[(String, JsonValue)]> {
+        match self {
+            JsonValue::Object(members) => Some(members),
+            _ => None,
+        }
+    }
+
+    /// Serializes the value with one member or element per line, indented by 2 spaces.
+    pub fn to_pretty_string(&self) -> String {
+        let mut out = String::new();
+        self.write_pretty(&mut out, 0);
+        out
+    }
+
+    fn write_pretty(&self, out: &mut String, indent: usize) {
+        let newline = |out: &mut String, indent: usize| {
+            out.push('\n');
+            for _ in 0..indent {
+                out.push_str("  ");
+            }
+        };
+        match self {
+            JsonValue::Array(values) if !values.is_empty() => {
+                out.push('[');
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    newline(out, indent + 1);
+                    value.write_pretty(out, indent + 1);
+                }
+                newline(out, indent);
+                out.push(']');
+            }
+            JsonValue::Object(members) if !members.is_empty() => {
+                out.push('{');
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    newline(out, indent + 1);
+                    write_string(out, key).unwrap();
+                    out.push_str(": ");
+                    value.write_pretty(out, indent + 1);
+                }
+                newline(out, indent);
+                out.push('}');
+            }
+            value => {
+                write!(out, "{value}").unwrap();
+            }
+        }
+    }
+}
+
+impl From<&str> for JsonValue {
+    fn from(s: &str) -> Self {
+        JsonValue::String(s.to_string())
+    }
+}
+
+impl From<String> for JsonValue {
+    fn from(s: String) -> Self {
+        JsonValue::String(s)
+    }
+}
+
+impl From<bool> for JsonValue {
+    fn from(b: bool) -> Self {
+        JsonValue::Bool(b)
+    }
+}
+
+impl From<u64> for JsonValue {
+    fn from(n: u64) -> Self {
+        JsonValue::Number(n as f64)
+    }
+}
+
+impl From<usize> for JsonValue {
+    fn from(n: usize) -> Self {
+        JsonValue::Number(n as f64)
+    }
+}
+
+/// Compact serialization, without any whitespace.
+impl fmt::Display for JsonValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonValue::Null => f.write_str("null"),
+            JsonValue::Bool(b) => write!(f, "{b}"),
+            JsonValue::Number(n) if n.is_finite() => write!(f, "{n}"),
+            JsonValue::Number(_) => f.write_str("null"),
+            JsonValue::String(s) => write_string(f, s),
+            JsonValue::Array(values) => {
+                f.write_char('[')?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                f.write_char(']')
+            }
+            JsonValue::Object(members) => {
+                f.write_char('{')?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+fn write_string(out: &mut impl Write, s: &str) -> fmt::Result {
+    out.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => out.write_str("\\\"")?,
+            '\\' => out.write_str("\\\\")?,
+            '\n' => out.write_str("\\n")?,
+            '\r' => out.write_str("\\r")?,
+            '\t' => out.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32)?,
+            c => out.write_char(c)?,
+        }
+    }
+    out.write_char('"')
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Display, Error)]
+#[display("byte {offset}: {kind}")]
+pub struct JsonError {
+    /// Byte offset in the input at which parsing failed.
+    pub offset: usize,
+    #[error(not(source))]
+    pub kind: JsonErrorKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Display)]
+pub enum JsonErrorKind {
+    #[display("unexpected end of input")]
+    UnexpectedEnd,
+    #[display("unexpected character")]
+    UnexpectedCharacter,
+    #[display("invalid number")]
+    InvalidNumber,
+    #[display("invalid escape sequence")]
+    InvalidEscape,
+    #[display("trailing characters after the value")]
+    TrailingCharacters,
+    #[display("nesting is too deep")]
+    TooDeep,
+}
+
+/// Deepest nesting of arrays and objects accepted by `parse_json`.
+const MAX_DEPTH: usize = 128;
+
+pub fn parse_json(input: &str) -> Result<JsonValue, JsonError> {
+    let mut parser = Parser { input, pos: 0 };
+    let value = parser.parse_value(0)?;
+    parser.skip_whitespace();
+    if parser.pos < input.len() {
+        return Err(parser.error(JsonErrorKind::TrailingCharacters));
+    }
+    Ok(value)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, kind: JsonErrorKind) -> JsonError {
+        JsonError { offset: self.pos, kind }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), JsonError> {
+        match self.peek() {
+            Some(b) if b == byte => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(_) => Err(self.error(JsonErrorKind::UnexpectedCharacter)),
+            None => Err(self.error(JsonErrorKind::UnexpectedEnd)),
+        }
+    }
+
+    fn expect_literal(&mut self, literal: &str, value: JsonValue) -> Result<JsonValue, JsonError> {
+        if self.input[self.pos..].starts_with(literal) {
+            self.pos += literal.len();
+            Ok(value)
+        } else {
+            Err(self.error(JsonErrorKind::UnexpectedCharacter))
+        }
+    }
+
+    fn parse_value(&mut self, depth: usize) -> Result<JsonValue, JsonError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error(JsonErrorKind::TooDeep));
+        }
+        self.skip_whitespace();
+        match self.peek() {
+            None => Err(self.error(JsonErrorKind::UnexpectedEnd)),
+            Some(b'n') => self.expect_literal("null", JsonValue::Null),
+            Some(b't') => self.expect_literal("true", JsonValue::Bool(true)),
+            Some(b'f') => self.expect_literal("false", JsonValue::Bool(false)),
+            Some(b'"') => Ok(JsonValue::String(self.parse_string()?)),
+            Some(b'[') => self.parse_array(depth),
+            Some(b'{') => self.parse_object(depth),
+            Some(b'-' | b'0'..=b'9') => self.parse_number(),
+            Some(_) => Err(self.error(JsonErrorKind::UnexpectedCharacter)),
+        }
+    }
+
+    fn parse_array(&mut self, depth: usize) -> Result<JsonValue, JsonError> {
+        self.expect(b'[')?;
+        let mut values = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(JsonValue::Array(values));
+        }
+        loop {
+            values.push(self.parse_value(depth + 1)?);
+            self.skip_whitespace();
+            if self.peek() == Some(b',') {
+                self.pos += 1;
+            } else {
+                self.expect(b']')?;
+                return Ok(JsonValue::Array(values));
+            }
+        }
+    }
+
+    fn parse_object(&mut self, depth: usize) -> Result<JsonValue, JsonError> {
+        self.expect(b'{')?;
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(JsonValue::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.parse_string()?;
+            self.skip_whitespace();
+            self.expect(b':')?;
+            members.push((key, self.parse_value(depth + 1)?));
+            self.skip_whitespace();
+            if self.peek() == Some(b',') {
+                self.pos += 1;
+            } else {
+                self.expect(b'}')?;
+                return Ok(JsonValue::Object(members));
+            }
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<JsonValue, JsonError> {
+        let start = self.pos;
+        while let Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9') = self.peek() {
+            self.pos += 1;
+        }
+        let number = &self.input[start..self.pos];
+        match number.parse::<f64>() {
+            Ok(n) if is_json_number(number) => Ok(JsonValue::Number(n)),
+            _ => Err(JsonError { offset: start, kind: JsonErrorKind::InvalidNumber }),
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, JsonError> {
+        self.expect(b'"')?;
+        let mut s = String::new();
+        loop {
+            let c = match self.input[self.pos..].chars().next() {
+                Some(c) => c,
+                None => return Err(self.error(JsonErrorKind::UnexpectedEnd)),
+            };
+            match c {
+                '"' => {
+                    self.pos += 1;
+                    return Ok(s);
+                }
+                '\\' => {
+                    self.pos += 1;
+                    s.push(self.parse_escape()?);
+                }
+                c if (c as u32) < 0x20 => return Err(self.error(JsonErrorKind::UnexpectedCharacter)),
+                c => {
+                    self.pos += c.len_utf8();
+                    s.push(c);
+                }
+            }
+        }
+    }
+
+    fn parse_escape(&mut self) -> Result<char, JsonError> {
+        let c = match self.peek() {
+            Some(c) => c,
+            None => return Err(self.error(JsonErrorKind::UnexpectedEnd)),
+        };
+        self.pos += 1;
+        match c {
+            b'"' => Ok('"'),
+            b'\\' => Ok('\\'),
+            b'/' => Ok('/'),
+            b'b' => Ok('\u{8}'),
+            b'f' => Ok('\u{c}'),
+            b'n' => Ok('\n'),
+            b'r' => Ok('\r'),
+            b't' => Ok('\t'),
+            b'u' => {
+                let high = self.parse_hex4()?;
+                let code = if (0xD800..0xDC00).contains(&high) {
+                    // a high surrogate must be followed by an escaped low surrogate
+                    if !self.input[self.pos..].starts_with("\\u") {
+                        return Err(self.error(JsonErrorKind::InvalidEscape));
+                    }
+                    self.pos += 2;
+                    let low = self.parse_hex4()?;
+                    if !(0xDC00..0xE000).contains(&low) {
+                        return Err(self.error(JsonErrorKind::InvalidEscape));
+                    }
+                    0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+                } else {
+                    high
+                };
+                char::from_u32(code).ok_or(self.error(JsonErrorKind::InvalidEscape))
+            }
+            _ => Err(self.error(JsonErrorKind::InvalidEscape)),
+        }
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, JsonError> {
+        let hex = self.input.get(self.pos..self.pos + 4)
+            .ok_or(self.error(JsonErrorKind::UnexpectedEnd))?;
+        if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(self.error(JsonErrorKind::InvalidEscape));
+        }
+        self.pos += 4;
+        Ok(u32::from_str_radix(hex, 16).unwrap())
+    }
+}
+
+/// Checks the number grammar of RFC 8259, which is stricter than the one of `f64::from_str`
+/// (e.g. "01", "1." and "+1" are not JSON numbers).
+fn is_json_number(number: &str) -> bool {
+    let digits = |s: &str| s.len() - s.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let mut rest = number.strip_prefix('-').unwrap_or(number);
+    let int_len = digits(rest);
+    if int_len == 0 || (int_len > 1 && rest.starts_with('0')) {
+        return false;
+    }
+    rest = &rest[int_len..];
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let fraction_len = digits(fraction);
+        if fraction_len == 0 {
+            return false;
+        }
+        rest = &fraction[fraction_len..];
+    }
+    if let Some(exponent) = rest.strip_prefix(['e', 'E']) {
+        let exponent = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+        let exponent_len = digits(exponent);
+        if exponent_len == 0 {
+            return false;
+        }
+        rest = &exponent[exponent_len..];
+    }
+    rest.is_empty()
+}
+
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_values() {
+        let value = parse_json(r#" { "a": [1, -2.5e1, true, null], "b": {"c": "d\"é😀"}, "e": [] } "#).unwrap();
+        let a = value.get("a").unwrap().as_array().unwrap();
+        assert_eq!(Some(1), a[0].as_u64());
+        assert_eq!(JsonValue::Number(-25.0), a[1]);
+        assert_eq!(Some(true), a[2].as_bool());
+        assert_eq!(JsonValue::Null, a[3]);
+        assert_eq!(Some("d\"é😀"), value.get("b").unwrap().get("c").unwrap().as_str());
+        assert_eq!(Some(&[][..]), value.get("e").unwrap().as_array());
+        assert_eq!(None, value.get("f"));
+    }
+
+    #[test]
+    fn test_serialize_round_trip() {
+        let input = r#"{"name":"tab\tquote\"","list":[1,2.5,false,null,{}],"empty":[]}"#;
+        let value = parse_json(input).unwrap();
+        assert_eq!(input, value.to_string());
+        assert_eq!(value, parse_json(&value.to_pretty_string()).unwrap());
+        assert_eq!("{\n  \"a\": [\n    1\n  ]\n}", parse_json(r#"{"a":[1]}"#).unwrap().to_pretty_string());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let kind = |input: &str| parse_json(input).unwrap_err().kind;
+        assert_eq!(JsonErrorKind::UnexpectedEnd, kind("[1, 2"));
+        assert_eq!(JsonErrorKind::UnexpectedCharacter, kind("[1; 2]"));
+        assert_eq!(JsonErrorKind::InvalidNumber, kind("01"));
+        assert_eq!(JsonErrorKind::InvalidNumber, kind("1."));
+        assert_eq!(JsonErrorKind::InvalidNumber, kind("1e"));
+        assert_eq!(JsonErrorKind::InvalidNumber, kind("-"));
+        assert_eq!(JsonValue::Number(-0.5e-3), parse_json("-0.5E-3").unwrap());
+        assert_eq!(JsonErrorKind::InvalidEscape, kind(r#""\x""#));
+        assert_eq!(JsonErrorKind::InvalidEscape, kind(r#""\ud83d""#));
+        assert_eq!(JsonErrorKind::TrailingCharacters, kind("{} {}"));
+        assert_eq!(JsonErrorKind::TooDeep, kind(&"[".repeat(200)));
+        assert_eq!(JsonError { offset: 4, kind: JsonErrorKind::UnexpectedCharacter }, parse_json("[1, x]").unwrap_err());
+    }
+}
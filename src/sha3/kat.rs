@@ -0,0 +1,277 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+use derive_more::{Display, Error};
+
+use crate::sha3::monte::MONTE_CHECKPOINTS;
+use crate::sha3::monte::MonteCarlo;
+use crate::sha3::registry::Algorithm;
+use crate::sha3::rsp::RspError;
+use crate::sha3::rsp::RspFile;
+use crate::sha3::rsp::RspRecordKind;
+use crate::sha3::rsp::parse_rsp;
+use crate::sha3::sha3::sha3_family;
+use crate::sha3::sha3::shake_family;
+use crate::sha3::types::ByteString;
+
+
+/// Whether known-answer files can be run against `algorithm`: SHA3 and SHAKE.
+pub fn is_kat_algorithm(algorithm: &Algorithm) -> bool {
+    matches!(algorithm, Algorithm::Sha3(_) | Algorithm::Shake(_))
+}
+
+/// Maps the names used in the CAVP headers, e.g. "SHA3-256" or "SHAKE128", which are the
+/// registry names.
+pub fn algorithm_from_name(name: &str) -> Option<Algorithm> {
+    Algorithm::from_name(name).filter(is_kat_algorithm)
+}
+
+/// Hashes a byte-oriented message; `d` is only used by SHAKE.
+fn hash(algorithm: Algorithm, msg: &[u8], d: usize) -> ByteString {
+    match algorithm {
+        Algorithm::Sha3(sha3_variant) => sha3_family(msg, sha3_variant.capacity(), sha3_variant.output_len()),
+        Algorithm::Shake(shake_variant) => shake_family(msg, shake_variant.capacity(), d),
+        _ => unreachable!("{algorithm} has no known-answer files"),
+    }
+}
+
+
+pub struct KatOptions {
+    /// Number of Monte Carlo checkpoints to check per file; each costs 1000 hashes.
+    pub monte_checkpoints: usize,
+}
+
+impl Default for KatOptions {
+    fn default() -> Self {
+        KatOptions {
+            monte_checkpoints: MONTE_CHECKPOINTS,
+        }
+    }
+}
+
+
+#[derive(Debug, Display, Error)]
+pub enum KatError {
+    #[display("{}: {_1}", _0.display())]
+    Io(PathBuf, #[error(source)] io::Error),
+    #[display("{}: {_1}", _0.display())]
+    Rsp(PathBuf, #[error(source)] RspError),
+    #[display("{}: missing algorithm", _0.display())]
+    MissingAlgorithm(#[error(not(source))] PathBuf),
+    #[display("{}: unsupported algorithm '{_1}'", _0.display())]
+    UnsupportedAlgorithm(PathBuf, #[error(not(source))] String),
+}
+
+
+/// Outcome of running one .rsp file.
+pub struct FileReport {
+    pub path: PathBuf,
+    pub algorithm: Algorithm,
+    pub test_name: Option<String>,
+    pub passed: usize,
+    pub failed: usize,
+    /// Records that are not checked, e.g. bit-oriented messages, SHAKE Monte Carlo tests,
+    /// or Monte Carlo checkpoints beyond `KatOptions::monte_checkpoints` or after a divergence.
+    pub skipped: usize,
+    /// Lines of the records that failed.
+    pub failures: Vec<usize>,
+    /// COUNT of the first Monte Carlo checkpoint which diverged.
+    pub monte_divergence: Option<usize>,
+}
+
+impl FileReport {
+    pub fn is_success(&self) -> bool {
+        self.failed == 0
+    }
+}
+
+impl fmt::Display for FileReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let file_name = self.path.file_name().unwrap_or_default().to_string_lossy();
+        write!(f, "{file_name}: passed {}, failed {}, skipped {}", self.passed, self.failed, self.skipped)?;
+        if !self.failures.is_empty() {
+            write!(f, " (failing records at lines {:?})", self.failures)?;
+        }
+        if let Some(count) = self.monte_divergence {
+            write!(f, " (Monte Carlo test diverged at COUNT = {count})")?;
+        }
+        Ok(())
+    }
+}
+
+
+/// Runs every record of a parsed file against the algorithm named in its header.
+pub fn run_rsp(path: &Path, rsp: &RspFile, options: &KatOptions) -> Result<FileReport, KatError> {
+    let name = rsp.algorithm.as_deref().ok_or_else(|| KatError::MissingAlgorithm(path.to_path_buf()))?;
+    let algorithm = algorithm_from_name(name)
+        .ok_or_else(|| KatError::UnsupportedAlgorithm(path.to_path_buf(), name.to_string()))?;
+
+    let mut report = FileReport {
+        path: path.to_path_buf(),
+        algorithm,
+        test_name: rsp.test_name.clone(),
+        passed: 0,
+        failed: 0,
+        skipped: 0,
+        failures: Vec::new(),
+        monte_divergence: None,
+    };
+    let mut monte = None;
+    let mut monte_checked = 0;
+
+    for record in rsp.records() {
+        let is_match = match &record.kind {
+            RspRecordKind::Message { len, msg, md } => {
+                // only byte-oriented messages can be hashed with this crate
+                if !len.is_multiple_of(8) {
+                    report.skipped += 1;
+                    continue;
+                }
+                hash(algorithm, msg, 8 * md.len()) == *md
+            }
+            RspRecordKind::VariableOutput { output_len, msg, output, .. } => {
+                hash(algorithm, msg, *output_len) == *output
+            }
+            RspRecordKind::Seed { seed } => {
+                if let Algorithm::Sha3(_) = algorithm {
+                    monte = Some(MonteCarlo::new(seed, |m: &[u8]| hash(algorithm, m, 0)));
+                    monte_checked = 0;
+                }
+                continue;
+            }
+            RspRecordKind::MonteCheckpoint { count, md, .. } => {
+                match monte.as_mut() {
+                    Some(monte) if monte_checked < options.monte_checkpoints && report.monte_divergence.is_none() => {
+                        monte_checked += 1;
+                        let is_match = monte.next().unwrap() == *md;
+                        if !is_match {
+                            report.monte_divergence = Some(*count);
+                        }
+                        is_match
+                    }
+                    _ => {
+                        report.skipped += 1;
+                        continue;
+                    }
+                }
+            }
+        };
+        if is_match {
+            report.passed += 1;
+        } else {
+            report.failed += 1;
+            report.failures.push(record.line);
+        }
+    }
+    Ok(report)
+}
+
+pub fn run_rsp_file(path: &Path, options: &KatOptions) -> Result<FileReport, KatError> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| KatError::Io(path.to_path_buf(), e))?;
+    let rsp = parse_rsp(&content)
+        .map_err(|e| KatError::Rsp(path.to_path_buf(), e))?;
+    run_rsp(path, &rsp, options)
+}
+
+/// Runs all .rsp files found under `dir`, in sorted order.
+pub fn run_rsp_dir(dir: &Path, options: &KatOptions) -> Result<Vec<FileReport>, KatError> {
+    find_rsp_files(dir)?.iter().map(|path| run_rsp_file(path, options)).collect()
+}
+
+/// Lists the .rsp files found under `dir`, in sorted order.
+pub fn find_rsp_files(dir: &Path) -> Result<Vec<PathBuf>, KatError> {
+    let mut paths = Vec::new();
+    collect_rsp_files(dir, &mut paths)?;
+    paths.sort();
+    Ok(paths)
+}
+
+fn collect_rsp_files(dir: &Path, paths: &mut Vec<PathBuf>) -> Result<(), KatError> {
+    let entries = fs::read_dir(dir)
+        .map_err(|e| KatError::Io(dir.to_path_buf(), e))?;
+    for entry in entries {
+        let path = entry
+            .map_err(|e| KatError::Io(dir.to_path_buf(), e))?
+            .path();
+        if path.is_dir() {
+            collect_rsp_files(&path, paths)?;
+        } else if path.extension().is_some_and(|ext| ext == "rsp") {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::sha3::types::Sha3Variant;
+    use crate::sha3::types::ShakeVariant;
+
+    use super::*;
+
+    #[test]
+    fn test_all_rsp_files() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("test_vectors");
+        // the Monte Carlo tests are limited to their first 2 checkpoints to keep this test fast
+        let options = KatOptions { monte_checkpoints: 2 };
+        let reports = run_rsp_dir(&dir, &options).unwrap();
+        assert_eq!(12, reports.len());
+        for report in &reports {
+            assert!(report.is_success(), "{report}");
+            assert!(report.passed > 0, "{report}");
+        }
+    }
+
+    #[test]
+    fn test_failures_are_reported() {
+        let content = "#  \"SHA3-256 ShortMsg\" information for \"test\"\n\n[L = 256]\n\n\
+            Len = 0\nMsg = 00\nMD = a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a\n\n\
+            Len = 8\nMsg = e9\nMD = 00d04dd1e6cfc29a4460d521796852f25d9ef8d28b44ee91ff5b759d72c1e6d6\n";
+        let rsp = parse_rsp(content).unwrap();
+        let report = run_rsp(Path::new("test.rsp"), &rsp, &KatOptions::default()).unwrap();
+        assert_eq!((1, 1, 0), (report.passed, report.failed, report.skipped));
+        assert_eq!(vec![9], report.failures);
+        assert_eq!("test.rsp: passed 1, failed 1, skipped 0 (failing records at lines [9])", report.to_string());
+    }
+
+    #[test]
+    fn test_monte_divergence_is_reported() {
+        let content = "#  \"SHA3-256 Monte\" information for \"test\"\n\n[L = 256]\n\n\
+            Seed = aa64f7245e2177c654eb4de360da8761a516fdc7578c3498c5e582e096b8730c\n\n\
+            COUNT = 0\nMD = 225cbac2be6f329d94228c5360a1c177bc495a761c442a1771b1d18555c309a5\n\n\
+            COUNT = 1\nMD = 06d364a1b1ced3dbbce6380093fb1ac77221abcee30faf16546ffad8fe1eef8c\n\n\
+            COUNT = 2\nMD = 8d81a67598ff73e2305ed53b1e6d58c799a1d1908abf81a15eab4bfd35b96e51\n";
+        let rsp = parse_rsp(content).unwrap();
+        let report = run_rsp(Path::new("monte.rsp"), &rsp, &KatOptions::default()).unwrap();
+        assert_eq!((1, 1, 1), (report.passed, report.failed, report.skipped));
+        assert_eq!(Some(1), report.monte_divergence);
+    }
+
+    #[test]
+    fn test_unsupported_algorithm() {
+        let rsp = parse_rsp("#  \"SHA-1 ShortMsg\" information for \"test\"\n").unwrap();
+        let err = run_rsp(Path::new("sha1.rsp"), &rsp, &KatOptions::default()).err().unwrap();
+        assert_eq!("sha1.rsp: unsupported algorithm 'SHA-1'", err.to_string());
+
+        let rsp = parse_rsp("[L = 256]\n").unwrap();
+        let err = run_rsp(Path::new("no-header.rsp"), &rsp, &KatOptions::default()).err().unwrap();
+        assert_eq!("no-header.rsp: missing algorithm", err.to_string());
+    }
+
+    #[test]
+    fn test_algorithm_names() {
+        for algorithm in [
+            Algorithm::Sha3(Sha3Variant::SHA3_224), Algorithm::Sha3(Sha3Variant::SHA3_512),
+            Algorithm::Shake(ShakeVariant::SHAKE128), Algorithm::Shake(ShakeVariant::SHAKE256),
+        ] {
+            assert_eq!(Some(algorithm), algorithm_from_name(algorithm.name()));
+        }
+        // in the registry, but without CAVP files
+        assert_eq!(None, algorithm_from_name("KMAC128"));
+    }
+}
@@ -1,4 +1,47 @@
 pub mod constants;
 pub mod types;
 mod utils;
-pub mod sha3;
\ No newline at end of file
+#[cfg(feature = "std")]
+mod rng;
+#[allow(clippy::module_inception)]
+pub mod sha3;
+pub mod hasher;
+pub mod checkpoint;
+pub mod sp800_185;
+pub mod hmac;
+pub mod selftest;
+pub mod registry;
+pub mod der;
+#[cfg(feature = "alloc")]
+pub mod rsp;
+#[cfg(feature = "alloc")]
+pub mod json;
+#[cfg(feature = "alloc")]
+pub mod monte;
+#[cfg(feature = "alloc")]
+pub mod pkcs1;
+#[cfg(feature = "alloc")]
+pub mod multibase;
+#[cfg(feature = "alloc")]
+pub mod multihash;
+#[cfg(feature = "alloc")]
+pub mod trace;
+#[cfg(feature = "alloc")]
+pub mod visualize;
+#[cfg(feature = "std")]
+pub mod kat;
+#[cfg(feature = "std")]
+pub mod acvp;
+#[cfg(feature = "std")]
+pub mod wycheproof;
+#[cfg(feature = "std")]
+pub mod generator;
+#[cfg(feature = "std")]
+pub mod tree;
+#[cfg(feature = "std")]
+pub mod bench;
+#[cfg(feature = "std")]
+pub mod avalanche;
+#[cfg(all(test, feature = "std"))]
+mod properties;
+pub mod secret;
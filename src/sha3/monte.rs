@@ -0,0 +1,219 @@
+use crate::sha3::types::ByteString;
+
+
+/// Number of chained hashes between two checkpoints of the SHA3 Monte Carlo test.
+pub const MONTE_ITERATIONS: usize = 1000;
+
+/// Number of checkpoints in the CAVP Monte files.
+pub const MONTE_CHECKPOINTS: usize = 100;
+
+
+/// The SHA3 Monte Carlo test (MCT) of the SHA3VS:
+///
+/// ```text
+/// MD[0] = Seed
+/// For j from 0 to 99:
+///     For i from 1 to 1000:
+///         MD[i] = SHA3(MD[i-1])
+///     MD[0] = MD[1000], which is the checkpoint "COUNT = j"
+/// ```
+///
+/// The iterator yields the successive checkpoints. `hash` is the SHA3 implementation under test,
+/// so that the same procedure can check both the one-shot and the incremental code paths.
+pub struct MonteCarlo<F: FnMut(&[u8]) -> ByteString> {
+    md: ByteString,
+    hash: F,
+}
+
+impl<F: FnMut(&[u8]) -> ByteString> MonteCarlo<F> {
+    pub fn new(seed: &[u8], hash: F) -> Self {
+        MonteCarlo {
+            md: ByteString::from(seed.to_vec()),
+            hash,
+        }
+    }
+}
+
+impl<F: FnMut(&[u8]) -> ByteString> Iterator for MonteCarlo<F> {
+    type Item = ByteString;
+
+    fn next(&mut self) -> Option<ByteString> {
+        for _ in 0..MONTE_ITERATIONS {
+            self.md = (self.hash)(&self.md);
+        }
+        Some(self.md.clone())
+    }
+}
+
+
+/// The SHAKE Monte Carlo test of the SHA3VS and of ACVP, in which each output length is
+/// taken from the previous output:
+///
+/// ```text
+/// Outputlen = maxoutlen, rounded down to whole bytes
+/// Output[0] = Msg
+/// For j from 0 to 99:
+///     For i from 1 to 1000:
+///         Msg[i] = the leftmost 128 bits of Output[i-1], padded with zeros to 128 bits
+///         Output[i] = SHAKE(Msg[i], Outputlen)
+///         Range = maxoutbytes - minoutbytes + 1
+///         Outputlen = 8 * (minoutbytes + (the rightmost 16 bits of Output[i] mod Range))
+///     Output[0] = Output[1000], which is the checkpoint j
+/// ```
+///
+/// The lengths are in bits. `shake` is the SHAKE implementation under test, called with the
+/// message and the output length in bits.
+pub struct ShakeMonteCarlo<F: FnMut(&[u8], usize) -> ByteString> {
+    output: ByteString,
+    min_out_bytes: usize,
+    range: usize,
+    out_bytes: usize,
+    shake: F,
+}
+
+impl<F: FnMut(&[u8], usize) -> ByteString> ShakeMonteCarlo<F> {
+    /// Panics unless `min_out_len` is at least 16 bits and at most `max_out_len`.
+    pub fn new(seed: &[u8], min_out_len: usize, max_out_len: usize, shake: F) -> Self {
+        assert!(16 <= min_out_len && min_out_len <= max_out_len);
+        let min_out_bytes = min_out_len / 8;
+        let max_out_bytes = max_out_len / 8;
+        ShakeMonteCarlo {
+            output: ByteString::from(seed.to_vec()),
+            min_out_bytes,
+            range: max_out_bytes - min_out_bytes + 1,
+            out_bytes: max_out_bytes,
+            shake,
+        }
+    }
+}
+
+impl<F: FnMut(&[u8], usize) -> ByteString> Iterator for ShakeMonteCarlo<F> {
+    type Item = ByteString;
+
+    fn next(&mut self) -> Option<ByteString> {
+        for _ in 0..MONTE_ITERATIONS {
+            let mut msg = [0u8; 16];
+            let len = self.output.len().min(msg.len());
+            msg[..len].copy_from_slice(&self.output[..len]);
+            self.output = (self.shake)(&msg, 8 * self.out_bytes);
+            let rightmost = u16::from_be_bytes([self.output[self.output.len() - 2], self.output[self.output.len() - 1]]);
+            self.out_bytes = self.min_out_bytes + rightmost as usize % self.range;
+        }
+        Some(self.output.clone())
+    }
+}
+
+
+/// The first checkpoint at which an implementation diverged from the expected values.
+#[derive(Debug, PartialEq, Eq)]
+pub struct MonteDivergence {
+    pub count: usize,
+    pub expected: ByteString,
+    pub computed: ByteString,
+}
+
+/// Runs the Monte Carlo test from `seed` against the `expected` checkpoints, given in order
+/// from COUNT = 0. Returns the number of checked checkpoints, or the first diverging one.
+pub fn check_monte<F: FnMut(&[u8]) -> ByteString>(seed: &[u8], expected: &[ByteString], hash: F) -> Result<usize, MonteDivergence> {
+    check_checkpoints(expected, MonteCarlo::new(seed, hash))
+}
+
+/// Runs the SHAKE Monte Carlo test like `check_monte`. A checkpoint of the wrong length
+/// diverges like one with the wrong bytes.
+pub fn check_shake_monte<F: FnMut(&[u8], usize) -> ByteString>(seed: &[u8], min_out_len: usize, max_out_len: usize, expected: &[ByteString], shake: F) -> Result<usize, MonteDivergence> {
+    check_checkpoints(expected, ShakeMonteCarlo::new(seed, min_out_len, max_out_len, shake))
+}
+
+fn check_checkpoints(expected: &[ByteString], computed: impl Iterator<Item = ByteString>) -> Result<usize, MonteDivergence> {
+    for (count, (expected_md, computed_md)) in expected.iter().zip(computed).enumerate() {
+        if expected_md.as_slice() != computed_md.as_slice() {
+            return Err(MonteDivergence {
+                count,
+                expected: expected_md.clone(),
+                computed: computed_md,
+            });
+        }
+    }
+    Ok(expected.len())
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::sha3::rsp::RspRecordKind;
+    use crate::sha3::rsp::parse_rsp;
+    use crate::sha3::sha3::Sha3;
+    use crate::sha3::sha3::sha3_family;
+    use crate::sha3::types::Sha3Variant;
+
+    const VARIANTS: [(Sha3Variant, &str); 4] = [
+        (Sha3Variant::SHA3_224, "SHA3_224Monte.rsp"),
+        (Sha3Variant::SHA3_256, "SHA3_256Monte.rsp"),
+        (Sha3Variant::SHA3_384, "SHA3_384Monte.rsp"),
+        (Sha3Variant::SHA3_512, "SHA3_512Monte.rsp"),
+    ];
+
+    /// Reads the seed and the expected checkpoints of a bundled Monte file.
+    fn read_monte_file(file_name: &str) -> (ByteString, Vec<ByteString>) {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("test_vectors/SHA3").join(file_name);
+        let rsp = parse_rsp(&std::fs::read_to_string(path).unwrap()).unwrap();
+        let mut seed = None;
+        let mut checkpoints = Vec::new();
+        for record in rsp.records() {
+            match &record.kind {
+                RspRecordKind::Seed { seed: s } => seed = Some(s.clone()),
+                RspRecordKind::MonteCheckpoint { count, md, .. } => {
+                    assert_eq!(checkpoints.len(), *count);
+                    checkpoints.push(md.clone());
+                }
+                _ => panic!("unexpected record in {file_name}"),
+            }
+        }
+        assert_eq!(MONTE_CHECKPOINTS, checkpoints.len());
+        (seed.unwrap(), checkpoints)
+    }
+
+    /// Runs the first `checkpoints` checkpoints of every variant with both SHA3 code paths:
+    /// the one-shot `sha3_family` and the incremental `Sha3` sponge.
+    fn check_monte_files(checkpoints: usize) {
+        for (sha3_variant, file_name) in VARIANTS {
+            let (seed, expected) = read_monte_file(file_name);
+            let expected = &expected[..checkpoints];
+
+            let one_shot = |m: &[u8]| sha3_family(m, sha3_variant.capacity(), sha3_variant.output_len());
+            assert_eq!(Ok(checkpoints), check_monte(&seed, expected, one_shot), "one-shot {sha3_variant:?}");
+
+            let incremental = |m: &[u8]| {
+                let mut sha3 = Sha3::new(sha3_variant);
+                sha3.update(m);
+                sha3.digest()
+            };
+            assert_eq!(Ok(checkpoints), check_monte(&seed, expected, incremental), "incremental {sha3_variant:?}");
+        }
+    }
+
+    #[test]
+    fn test_monte_first_checkpoint() {
+        check_monte_files(1);
+    }
+
+    // 100 000 hashes per variant and code path, takes a few minutes
+    #[test]
+    #[ignore]
+    fn test_monte_all_checkpoints() {
+        check_monte_files(MONTE_CHECKPOINTS);
+    }
+
+    #[test]
+    fn test_monte_divergence_is_reported() {
+        let (seed, mut expected) = read_monte_file("SHA3_256Monte.rsp");
+        expected.truncate(3);
+        expected[1][0] ^= 1;
+        let one_shot = |m: &[u8]| sha3_family(m, 512, 256);
+        let divergence = check_monte(&seed, &expected, one_shot).err().unwrap();
+        assert_eq!(1, divergence.count);
+        assert_eq!(expected[1].as_slice(), divergence.expected.as_slice());
+    }
+}
@@ -0,0 +1,257 @@
+//! Multibase strings (https://github.com/multiformats/multibase): binary data in a text
+//! encoding, prefixed with a character naming the encoding. Only the lowercase forms are
+//! produced and accepted:
+//!
+//! | prefix | encoding                                        |
+//! |--------|-------------------------------------------------|
+//! | f      | base16, lowercase                               |
+//! | b      | base32, RFC 4648 lowercase alphabet, no padding |
+//! | z      | base58btc, the Bitcoin alphabet                 |
+//!
+//! Decoding is strict: no padding, whitespace, or non-zero bits after the last base32 byte.
+
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use derive_more::{Display, Error};
+
+use crate::sha3::types::ByteString;
+
+
+const BASE32_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+const BASE58_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Multibase {
+    Base16,
+    Base32,
+    Base58Btc,
+}
+
+impl Multibase {
+    pub fn prefix(&self) -> char {
+        match self {
+            Multibase::Base16 => 'f',
+            Multibase::Base32 => 'b',
+            Multibase::Base58Btc => 'z',
+        }
+    }
+
+    pub fn from_prefix(prefix: char) -> Option<Multibase> {
+        match prefix {
+            'f' => Some(Multibase::Base16),
+            'b' => Some(Multibase::Base32),
+            'z' => Some(Multibase::Base58Btc),
+            _ => None,
+        }
+    }
+
+    /// Encodes `data` without the prefix.
+    pub fn encode_raw(&self, data: &[u8]) -> String {
+        match self {
+            Multibase::Base16 => hex::encode(data),
+            Multibase::Base32 => base32_encode(data),
+            Multibase::Base58Btc => base58_encode(data),
+        }
+    }
+
+    /// Decodes a string without the prefix.
+    pub fn decode_raw(&self, text: &str) -> Result<ByteString, MultibaseError> {
+        match self {
+            Multibase::Base16 => {
+                if text.bytes().any(|c| c.is_ascii_uppercase()) {
+                    return Err(MultibaseError::InvalidEncoding(*self));
+                }
+                hex::decode(text)
+                    .map(ByteString::from)
+                    .map_err(|_| MultibaseError::InvalidEncoding(*self))
+            }
+            Multibase::Base32 => base32_decode(text).ok_or(MultibaseError::InvalidEncoding(*self)),
+            Multibase::Base58Btc => base58_decode(text).ok_or(MultibaseError::InvalidEncoding(*self)),
+        }
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Display, Error)]
+pub enum MultibaseError {
+    #[display("empty multibase string")]
+    Empty,
+    #[display("unsupported multibase prefix '{_0}'")]
+    UnsupportedPrefix(#[error(not(source))] char),
+    #[display("invalid {_0:?} string")]
+    InvalidEncoding(#[error(not(source))] Multibase),
+}
+
+
+/// Encodes `data` with the prefix of `base`, e.g. "bmfrgg" for "abc" in base32.
+pub fn encode(base: Multibase, data: &[u8]) -> String {
+    let mut text = String::new();
+    text.push(base.prefix());
+    text.push_str(&base.encode_raw(data));
+    text
+}
+
+/// Decodes a prefixed string, returning the encoding it was in and the data.
+pub fn decode(text: &str) -> Result<(Multibase, ByteString), MultibaseError> {
+    let prefix = text.chars().next().ok_or(MultibaseError::Empty)?;
+    let base = Multibase::from_prefix(prefix).ok_or(MultibaseError::UnsupportedPrefix(prefix))?;
+    let data = base.decode_raw(&text[prefix.len_utf8()..])?;
+    Ok((base, data))
+}
+
+
+fn base32_encode(data: &[u8]) -> String {
+    let mut text = String::with_capacity((8 * data.len()).div_ceil(5));
+    let mut acc = 0u16;
+    let mut acc_bits = 0;
+    for &byte in data {
+        acc = (acc << 8) | u16::from(byte);
+        acc_bits += 8;
+        while acc_bits >= 5 {
+            acc_bits -= 5;
+            text.push(char::from(BASE32_ALPHABET[usize::from((acc >> acc_bits) & 0x1f)]));
+        }
+    }
+    if acc_bits > 0 {
+        text.push(char::from(BASE32_ALPHABET[usize::from((acc << (5 - acc_bits)) & 0x1f)]));
+    }
+    text
+}
+
+fn base32_decode(text: &str) -> Option<ByteString> {
+    // a trailing group of 1, 3 or 6 characters cannot come from whole bytes
+    if matches!(text.len() % 8, 1 | 3 | 6) {
+        return None;
+    }
+    let mut data = ByteString::with_capacity(5 * text.len() / 8);
+    let mut acc = 0u16;
+    let mut acc_bits = 0;
+    for c in text.bytes() {
+        let value = BASE32_ALPHABET.iter().position(|&a| a == c)?;
+        acc = (acc << 5) | value as u16;
+        acc_bits += 5;
+        if acc_bits >= 8 {
+            acc_bits -= 8;
+            data.push((acc >> acc_bits) as u8);
+        }
+        acc &= (1 << acc_bits) - 1;
+    }
+    // the bits left over only pad the last character
+    if acc != 0 {
+        return None;
+    }
+    Some(data)
+}
+
+fn base58_encode(data: &[u8]) -> String {
+    let zeros = data.iter().take_while(|&&byte| byte == 0).count();
+    // digits in base 58, least significant first
+    let mut digits: Vec<u8> = Vec::with_capacity(data.len() * 138 / 100 + 1);
+    for &byte in &data[zeros..] {
+        let mut carry = u32::from(byte);
+        for digit in digits.iter_mut() {
+            carry += u32::from(*digit) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    let mut text = String::with_capacity(zeros + digits.len());
+    for _ in 0..zeros {
+        text.push('1');
+    }
+    for &digit in digits.iter().rev() {
+        text.push(char::from(BASE58_ALPHABET[usize::from(digit)]));
+    }
+    text
+}
+
+fn base58_decode(text: &str) -> Option<ByteString> {
+    let zeros = text.bytes().take_while(|&c| c == b'1').count();
+    // bytes, least significant first
+    let mut bytes: Vec<u8> = Vec::with_capacity(text.len() * 733 / 1000 + 1);
+    for c in text.bytes().skip(zeros) {
+        let mut carry = BASE58_ALPHABET.iter().position(|&a| a == c)? as u32;
+        for byte in bytes.iter_mut() {
+            carry += u32::from(*byte) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+    let mut data = ByteString::from(vec![0u8; zeros]);
+    data.extend(bytes.iter().rev());
+    Some(data)
+}
+
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rfc4648_base32() {
+        // RFC 4648, Sec. 10, lowercase and without padding
+        let vectors = [
+            ("", ""),
+            ("f", "my"),
+            ("fo", "mzxq"),
+            ("foo", "mzxw6"),
+            ("foob", "mzxw6yq"),
+            ("fooba", "mzxw6ytb"),
+            ("foobar", "mzxw6ytboi"),
+        ];
+        for (data, text) in vectors {
+            assert_eq!(text, base32_encode(data.as_bytes()));
+            assert_eq!(data.as_bytes(), &base32_decode(text).unwrap()[..]);
+        }
+    }
+
+    #[test]
+    fn test_base58btc() {
+        // from the multibase test suite
+        assert_eq!("zStV1DL6CwTryKyV", encode(Multibase::Base58Btc, b"hello world"));
+        assert_eq!("z117paNL19xttacUY", encode(Multibase::Base58Btc, b"\x00\x00yes mani !"));
+        assert_eq!("z11", encode(Multibase::Base58Btc, &[0, 0]));
+        assert_eq!("z", encode(Multibase::Base58Btc, &[]));
+        for text in ["zStV1DL6CwTryKyV", "z117paNL19xttacUY", "z11", "z"] {
+            let (base, data) = decode(text).unwrap();
+            assert_eq!(Multibase::Base58Btc, base);
+            assert_eq!(text, encode(base, &data));
+        }
+    }
+
+    #[test]
+    fn test_prefixes() {
+        assert_eq!("f796573206d616e692021", encode(Multibase::Base16, b"yes mani !"));
+        assert_eq!("bpfsxgidnmfxgsibb", encode(Multibase::Base32, b"yes mani !"));
+        assert_eq!(Ok((Multibase::Base32, ByteString::from(b"yes mani !".to_vec()))), decode("bpfsxgidnmfxgsibb"));
+        assert_eq!(Err(MultibaseError::Empty), decode(""));
+        assert_eq!(Err(MultibaseError::UnsupportedPrefix('m')), decode("meWVzIG1hbmkgIQ"));
+        assert_eq!(Err(MultibaseError::UnsupportedPrefix('B')), decode("BPFSXGIDNMFXGSIBB"));
+    }
+
+    #[test]
+    fn test_strict_decoding() {
+        // padding, uppercase, impossible lengths and non-zero trailing bits
+        for text in ["bmy======", "bMY", "bmzx", "bm", "bmz", "bmzxw6y"] {
+            assert_eq!(Err(MultibaseError::InvalidEncoding(Multibase::Base32)), decode(text), "{text}");
+        }
+        // '0', 'O', 'I' and 'l' are not in the alphabet
+        for text in ["z0", "zO", "zI", "zl", "zStV1DL6 CwTryKyV"] {
+            assert_eq!(Err(MultibaseError::InvalidEncoding(Multibase::Base58Btc)), decode(text), "{text}");
+        }
+        for text in ["f0", "f0A", "fzz"] {
+            assert_eq!(Err(MultibaseError::InvalidEncoding(Multibase::Base16)), decode(text), "{text}");
+        }
+    }
+}
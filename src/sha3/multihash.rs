@@ -0,0 +1,396 @@
+//! Multihash (https://github.com/multiformats/multihash): a digest prefixed with the
+//! multicodec code of its hash function and its length, both as unsigned varints.
+//!
+//! | code | name       | digest                          |
+//! |------|------------|---------------------------------|
+//! | 0x14 | sha3-512   | SHA3-512                        |
+//! | 0x15 | sha3-384   | SHA3-384                        |
+//! | 0x16 | sha3-256   | SHA3-256                        |
+//! | 0x17 | sha3-224   | SHA3-224                        |
+//! | 0x18 | shake-128  | SHAKE128, 32 bytes by default   |
+//! | 0x19 | shake-256  | SHAKE256, 64 bytes by default   |
+//! | 0x1a | keccak-224 | Keccak-224, without a suffix    |
+//! | 0x1b | keccak-256 | Keccak-256, as used by Ethereum |
+//! | 0x1c | keccak-384 | Keccak-384                      |
+//! | 0x1d | keccak-512 | Keccak-512                      |
+//!
+//! Digests of the fixed-output functions may be truncated, SHAKE digests can have any length,
+//! but no digest is shorter than `MIN_DIGEST_LEN` bytes: an empty or very short digest would
+//! match almost any blob.
+//! The varints follow the multiformats rules: at most 9 bytes and no redundant zero bytes.
+
+use alloc::string::String;
+use alloc::vec;
+
+use derive_more::{Display, Error, From};
+
+use crate::sha3::multibase;
+use crate::sha3::multibase::Multibase;
+use crate::sha3::multibase::MultibaseError;
+use crate::sha3::registry::Algorithm;
+use crate::sha3::registry::HasherOptions;
+use crate::sha3::secret::ct_eq;
+use crate::sha3::types::ByteString;
+use crate::sha3::types::Sha3Variant;
+use crate::sha3::types::ShakeVariant;
+
+
+/// Longest unsigned varint allowed by the multiformats specification.
+const MAX_VARINT_LEN: usize = 9;
+
+/// Shortest digest accepted, in bytes: 128 bits, the lowest security strength of the functions.
+pub const MIN_DIGEST_LEN: usize = 16;
+
+
+/// Multicodec codes and names of the registry algorithms that have one.
+const CODES: [(Algorithm, u64, &str); 10] = [
+    (Algorithm::Sha3(Sha3Variant::SHA3_512), 0x14, "sha3-512"),
+    (Algorithm::Sha3(Sha3Variant::SHA3_384), 0x15, "sha3-384"),
+    (Algorithm::Sha3(Sha3Variant::SHA3_256), 0x16, "sha3-256"),
+    (Algorithm::Sha3(Sha3Variant::SHA3_224), 0x17, "sha3-224"),
+    (Algorithm::Shake(ShakeVariant::SHAKE128), 0x18, "shake-128"),
+    (Algorithm::Shake(ShakeVariant::SHAKE256), 0x19, "shake-256"),
+    (Algorithm::Keccak(Sha3Variant::SHA3_224), 0x1a, "keccak-224"),
+    (Algorithm::Keccak(Sha3Variant::SHA3_256), 0x1b, "keccak-256"),
+    (Algorithm::Keccak(Sha3Variant::SHA3_384), 0x1c, "keccak-384"),
+    (Algorithm::Keccak(Sha3Variant::SHA3_512), 0x1d, "keccak-512"),
+];
+
+
+/// A hash function of the registry that has a multicodec code.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MultihashCode(Algorithm);
+
+impl MultihashCode {
+    /// Returns the code of `algorithm`, if it has one.
+    pub fn new(algorithm: Algorithm) -> Option<MultihashCode> {
+        CODES.iter()
+            .any(|(coded, ..)| *coded == algorithm)
+            .then_some(MultihashCode(algorithm))
+    }
+
+    /// The algorithms that have a code, in the order of the codes.
+    pub fn all() -> impl Iterator<Item = MultihashCode> {
+        CODES.iter().map(|(algorithm, ..)| MultihashCode(*algorithm))
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.0
+    }
+
+    fn entry(&self) -> &'static (Algorithm, u64, &'static str) {
+        CODES.iter()
+            .find(|(algorithm, ..)| *algorithm == self.0)
+            .expect("MultihashCode is only built for the algorithms of CODES")
+    }
+
+    pub fn code(&self) -> u64 {
+        self.entry().1
+    }
+
+    pub fn from_code(code: u64) -> Option<MultihashCode> {
+        MultihashCode::all().find(|c| c.code() == code)
+    }
+
+    /// Name in the multicodec table, e.g. "sha3-256" or "shake-128".
+    pub fn name(&self) -> &'static str {
+        self.entry().2
+    }
+
+    pub fn from_name(name: &str) -> Option<MultihashCode> {
+        MultihashCode::all().find(|c| c.name() == name)
+    }
+
+    /// Digest length in bytes used by `Multihash::hash`: the full output of the fixed-output
+    /// functions, and twice the security strength for SHAKE.
+    pub fn default_digest_len(&self) -> usize {
+        self.0.output_len() / 8
+    }
+
+    /// Longest digest in bytes, if the output length is fixed.
+    pub fn max_digest_len(&self) -> Option<usize> {
+        (!self.0.is_xof()).then(|| self.default_digest_len())
+    }
+
+    /// Hashes `blob` and keeps the first `digest_len` bytes of the output.
+    pub fn hash(&self, blob: &[u8], digest_len: usize) -> ByteString {
+        assert!(digest_len >= MIN_DIGEST_LEN);
+        let output_len = match self.max_digest_len() {
+            Some(max_len) => {
+                assert!(digest_len <= max_len);
+                max_len
+            }
+            None => digest_len,
+        };
+        let mut hasher = self.0.new_hasher(&HasherOptions::default());
+        hasher.update(blob);
+        let mut digest = ByteString::from(vec![0u8; output_len]);
+        hasher.finalize_into(&mut digest);
+        digest.truncate(digest_len);
+        digest
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq, Eq, Display, Error, From)]
+pub enum MultihashError {
+    #[display("multihash is truncated")]
+    #[from(ignore)]
+    Truncated,
+    #[display("bytes after the digest")]
+    #[from(ignore)]
+    TrailingBytes,
+    #[display("invalid unsigned varint")]
+    #[from(ignore)]
+    InvalidVarint,
+    #[display("unknown multihash code {_0:#x}")]
+    #[from(ignore)]
+    UnknownCode(#[error(not(source))] u64),
+    #[display("{} digests are at most {max} bytes long, found {found}", code.name())]
+    #[from(ignore)]
+    DigestTooLong { code: MultihashCode, max: usize, found: usize },
+    #[display("{} digests are at least {min} bytes long, found {found}", code.name())]
+    #[from(ignore)]
+    DigestTooShort { code: MultihashCode, min: usize, found: usize },
+    #[display("{_0}")]
+    Multibase(MultibaseError),
+}
+
+
+/// A digest together with the hash function that produced it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Multihash {
+    code: MultihashCode,
+    digest: ByteString,
+}
+
+impl Multihash {
+    /// Wraps a digest computed elsewhere, e.g. by `sha3_256` or a `Sha3` hasher, possibly
+    /// truncated to no less than `MIN_DIGEST_LEN` bytes.
+    pub fn new(code: MultihashCode, digest: &[u8]) -> Result<Multihash, MultihashError> {
+        if digest.len() < MIN_DIGEST_LEN {
+            return Err(MultihashError::DigestTooShort { code, min: MIN_DIGEST_LEN, found: digest.len() });
+        }
+        if let Some(max) = code.max_digest_len()
+            && digest.len() > max
+        {
+            return Err(MultihashError::DigestTooLong { code, max, found: digest.len() });
+        }
+        Ok(Multihash { code, digest: ByteString::from(digest.to_vec()) })
+    }
+
+    /// Hashes `blob` with the default digest length of `code`.
+    pub fn hash(code: MultihashCode, blob: &[u8]) -> Multihash {
+        let digest = code.hash(blob, code.default_digest_len());
+        Multihash { code, digest }
+    }
+
+    pub fn code(&self) -> MultihashCode {
+        self.code
+    }
+
+    pub fn digest(&self) -> &[u8] {
+        &self.digest
+    }
+
+    /// Whether `blob` hashes to this digest, comparing in constant time.
+    pub fn verify(&self, blob: &[u8]) -> bool {
+        ct_eq(&self.code.hash(blob, self.digest.len()), &self.digest)
+    }
+
+    /// Returns code || length || digest.
+    pub fn to_bytes(&self) -> ByteString {
+        let mut out = ByteString::with_capacity(2 * MAX_VARINT_LEN + self.digest.len());
+        write_varint(self.code.code(), &mut out);
+        write_varint(self.digest.len() as u64, &mut out);
+        out.extend_from_slice(&self.digest);
+        out
+    }
+
+    /// Parses a multihash that spans all of `bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Multihash, MultihashError> {
+        let (multihash, len) = Multihash::read(bytes)?;
+        if len != bytes.len() {
+            return Err(MultihashError::TrailingBytes);
+        }
+        Ok(multihash)
+    }
+
+    /// Parses the multihash at the start of `bytes`, e.g. inside a CID, and returns it
+    /// with the number of bytes it spans.
+    pub fn read(bytes: &[u8]) -> Result<(Multihash, usize), MultihashError> {
+        let (code, code_len) = read_varint(bytes)?;
+        let code = MultihashCode::from_code(code).ok_or(MultihashError::UnknownCode(code))?;
+        let (digest_len, len_len) = read_varint(&bytes[code_len..])?;
+        let start = code_len + len_len;
+        let digest = usize::try_from(digest_len)
+            .ok()
+            .and_then(|digest_len| bytes.get(start..start.checked_add(digest_len)?))
+            .ok_or(MultihashError::Truncated)?;
+        let multihash = Multihash::new(code, digest)?;
+        Ok((multihash, start + digest.len()))
+    }
+
+    /// The multihash as a multibase string, e.g. "bcyqd..." in base32 for a SHA3-256 digest.
+    pub fn to_multibase(&self, base: Multibase) -> String {
+        multibase::encode(base, &self.to_bytes())
+    }
+
+    pub fn from_multibase(text: &str) -> Result<Multihash, MultihashError> {
+        let (_, bytes) = multibase::decode(text)?;
+        Multihash::from_bytes(&bytes)
+    }
+}
+
+
+fn write_varint(mut value: u64, out: &mut ByteString) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Returns the value of the varint at the start of `bytes` and its length.
+fn read_varint(bytes: &[u8]) -> Result<(u64, usize), MultihashError> {
+    let mut value = 0u64;
+    for (i, &byte) in bytes.iter().enumerate().take(MAX_VARINT_LEN) {
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            // a zero last byte would make the encoding longer than needed
+            if byte == 0 && i > 0 {
+                return Err(MultihashError::InvalidVarint);
+            }
+            return Ok((value, i + 1));
+        }
+    }
+    if bytes.len() < MAX_VARINT_LEN {
+        Err(MultihashError::Truncated)
+    } else {
+        Err(MultihashError::InvalidVarint)
+    }
+}
+
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::sha3::sha3::sha3_256;
+    use crate::sha3::sha3::shake128;
+
+    use super::*;
+
+    fn multihash_code(algorithm: Algorithm) -> MultihashCode {
+        MultihashCode::new(algorithm).unwrap()
+    }
+
+    #[test]
+    fn test_known_multihashes() {
+        // digests of "abc" from Python's hashlib; Keccak-256 from Ethereum
+        let vectors = [
+            (multihash_code(Algorithm::Sha3(Sha3Variant::SHA3_256)), "16203a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532"),
+            (multihash_code(Algorithm::Sha3(Sha3Variant::SHA3_512)), "1440b751850b1a57168a5693cd924b6b096e08f621827444f70d884f5d0240d2712e10e116e9192af3c91a7ec57647e3934057340b4cf408d5a56592f8274eec53f0"),
+            (multihash_code(Algorithm::Shake(ShakeVariant::SHAKE128)), "18205881092dd818bf5cf8a3ddb793fbcba74097d5c526a6d35f97b83351940f2cc8"),
+            (multihash_code(Algorithm::Shake(ShakeVariant::SHAKE256)), "1940483366601360a8771c6863080cc4114d8db44530f8f1e1ee4f94ea37e78b5739d5a15bef186a5386c75744c0527e1faa9f8726e462a12a4feb06bd8801e751e4"),
+            (multihash_code(Algorithm::Keccak(Sha3Variant::SHA3_256)), "1b204e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45"),
+        ];
+        for (code, expected) in vectors {
+            let multihash = Multihash::hash(code, b"abc");
+            assert_eq!(expected, hex::encode(&multihash.to_bytes()[..]), "{}", code.name());
+            assert_eq!(Ok(multihash.clone()), Multihash::from_bytes(&hex::decode(expected).unwrap()));
+            assert!(multihash.verify(b"abc"));
+            assert!(!multihash.verify(b"abd"));
+        }
+    }
+
+    #[test]
+    fn test_multibase_forms() {
+        let multihash = Multihash::hash(multihash_code(Algorithm::Sha3(Sha3Variant::SHA3_256)), b"abc");
+        let base32 = "bcyqdvgc5u5h6ejnsarobolll2oil3bk7bbxd5hkslndl7ysfcfbrkmq";
+        let base58 = "zW1dPidZ6r5gZPoADdz6TDXv967KaD93Y9LEtYS9QLo8m7F";
+        assert_eq!(base32, multihash.to_multibase(Multibase::Base32));
+        assert_eq!(base58, multihash.to_multibase(Multibase::Base58Btc));
+        assert_eq!(Ok(multihash.clone()), Multihash::from_multibase(base32));
+        assert_eq!(Ok(multihash), Multihash::from_multibase(base58));
+
+        let keccak = Multihash::from_multibase("zcZtawSMzruvBMygbzekLShaZoJctTYyH8L7CFcztU9V4be").unwrap();
+        assert_eq!(multihash_code(Algorithm::Keccak(Sha3Variant::SHA3_256)), keccak.code());
+        assert!(keccak.verify(b"abc"));
+
+        assert_eq!(
+            Err(MultihashError::Multibase(MultibaseError::UnsupportedPrefix('m'))),
+            Multihash::from_multibase("mFiA6mF2nT+IlsgRcFy1r05C9hV8IbD6dUltGv+JFEUMVMg"),
+        );
+    }
+
+    #[test]
+    fn test_truncated_and_long_digests() {
+        let code = multihash_code(Algorithm::Sha3(Sha3Variant::SHA3_256));
+        let truncated = Multihash::new(code, &sha3_256(b"abc")[..20]).unwrap();
+        assert_eq!("1614", hex::encode(&truncated.to_bytes()[..2]));
+        assert!(truncated.verify(b"abc"));
+        assert_eq!(
+            Err(MultihashError::DigestTooLong { code, max: 32, found: 33 }),
+            Multihash::new(code, &[0u8; 33]),
+        );
+        assert_eq!(
+            Err(MultihashError::DigestTooShort { code, min: 16, found: 0 }),
+            Multihash::new(code, &[]),
+        );
+        assert!(Multihash::new(code, &sha3_256(b"abc")[..16]).unwrap().verify(b"abc"));
+
+        // SHAKE digests longer than 127 bytes need a two-byte length
+        let shake = multihash_code(Algorithm::Shake(ShakeVariant::SHAKE128));
+        let long = Multihash::new(shake, &shake128(b"abc", 8 * 200)).unwrap();
+        assert_eq!("18c801", hex::encode(&long.to_bytes()[..3]));
+        assert!(long.verify(b"abc"));
+        let text = long.to_multibase(Multibase::Base58Btc);
+        assert_eq!(Ok(long), Multihash::from_multibase(&text));
+    }
+
+    #[test]
+    fn test_malformed_multihashes() {
+        let short = |found| MultihashError::DigestTooShort { code: multihash_code(Algorithm::Sha3(Sha3Variant::SHA3_256)), min: 16, found };
+        let cases: [(&str, MultihashError); 11] = [
+            ("", MultihashError::Truncated),
+            ("16", MultihashError::Truncated),
+            ("1603abcd", MultihashError::Truncated),
+            ("1610000000000000000000000000000000000000", MultihashError::TrailingBytes),
+            // an empty digest would verify any blob
+            ("1600", short(0)),
+            ("1601ab", short(1)),
+            ("160f000000000000000000000000000000", short(15)),
+            ("12020000", MultihashError::UnknownCode(0x12)),
+            ("968000", MultihashError::InvalidVarint),
+            ("1680808080808080808001", MultihashError::InvalidVarint),
+            ("1721000000000000000000000000000000000000000000000000000000000000000000", MultihashError::DigestTooLong {
+                code: multihash_code(Algorithm::Sha3(Sha3Variant::SHA3_224)), max: 28, found: 33,
+            }),
+        ];
+        for (bytes, error) in cases {
+            assert_eq!(Err(error), Multihash::from_bytes(&hex::decode(bytes).unwrap()), "{bytes}");
+        }
+    }
+
+    #[test]
+    fn test_read_prefix() {
+        let mut bytes = Multihash::hash(multihash_code(Algorithm::Keccak(Sha3Variant::SHA3_512)), b"").to_bytes();
+        assert_eq!(66, bytes.len());
+        bytes.extend_from_slice(b"rest");
+        let (multihash, len) = Multihash::read(&bytes).unwrap();
+        assert_eq!(66, len);
+        assert!(multihash.verify(b""));
+    }
+
+    #[test]
+    fn test_codes_and_names() {
+        for code in MultihashCode::all() {
+            assert_eq!(Some(code), MultihashCode::from_code(code.code()));
+            assert_eq!(Some(code), MultihashCode::from_name(code.name()));
+            assert_eq!(Some(code), MultihashCode::new(code.algorithm()));
+        }
+        assert_eq!(10, MultihashCode::all().count());
+        assert_eq!(None, MultihashCode::new(Algorithm::Kmac(ShakeVariant::SHAKE128)));
+        assert_eq!(None, MultihashCode::from_code(0x13));
+        assert_eq!(None, MultihashCode::from_name("SHA3-256"));
+    }
+}
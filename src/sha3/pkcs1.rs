@@ -0,0 +1,402 @@
+//! The encoding layer of RSA from PKCS #1 (RFC 8017): EMSA-PSS for signatures and
+//! EME-OAEP for encryption, with a SHA3 hash and either MGF1 over SHA3 or SHAKE as the mask
+//! generation function (RFC 8702). The RSA operations themselves are left to the caller.
+//!
+//! With SHAKE as the hash, as in the RSASSA-PSS-SHAKE128/256 of RFC 8702, the digest is
+//! 32 bytes for SHAKE128 and 64 bytes for SHAKE256.
+
+use alloc::vec;
+use core::hint::black_box;
+
+use derive_more::{Display, Error};
+
+use crate::sha3::secret::Zeroize;
+use crate::sha3::secret::ct_eq;
+use crate::sha3::sha3::SHA3_MAX_DIGEST_BYTES;
+use crate::sha3::sha3::Sha3;
+use crate::sha3::sha3::Shake;
+use crate::sha3::types::ByteString;
+use crate::sha3::types::Sha3Variant;
+use crate::sha3::types::ShakeVariant;
+
+
+/// Hash used to compute mHash and H in PSS, or lHash in OAEP.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pkcs1Hash {
+    Sha3(Sha3Variant),
+    Shake(ShakeVariant),
+}
+
+impl Pkcs1Hash {
+    /// Digest length hLen in bytes.
+    pub fn digest_len(&self) -> usize {
+        match self {
+            Pkcs1Hash::Sha3(sha3_variant) => sha3_variant.output_len() / 8,
+            Pkcs1Hash::Shake(shake_variant) => shake_variant.capacity() / 8,
+        }
+    }
+
+    /// Hashes the concatenation of `parts` into `out`, which must be `digest_len()` bytes long.
+    fn hash(&self, parts: &[&[u8]], out: &mut [u8]) {
+        match self {
+            Pkcs1Hash::Sha3(sha3_variant) => {
+                let mut sha3 = Sha3::new(*sha3_variant);
+                parts.iter().for_each(|part| sha3.update(part));
+                sha3.digest_into(out);
+            }
+            Pkcs1Hash::Shake(shake_variant) => {
+                let mut shake = Shake::new(*shake_variant);
+                parts.iter().for_each(|part| shake.update(part));
+                shake.finalize_xof().squeeze(out);
+            }
+        }
+    }
+}
+
+/// Mask generation function.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mgf {
+    /// MGF1 (RFC 8017, Appendix B.2.1) over a SHA3 hash.
+    Mgf1(Sha3Variant),
+    /// SHAKE with an output of the length of the mask (RFC 8702).
+    Shake(ShakeVariant),
+}
+
+impl Mgf {
+    /// XORs the mask of `out.len()` bytes derived from `seed` into `out`.
+    pub fn apply_mask(&self, seed: &[u8], out: &mut [u8]) {
+        let mut block = [0u8; SHA3_MAX_DIGEST_BYTES];
+        match self {
+            Mgf::Mgf1(sha3_variant) => {
+                let digest_len = sha3_variant.output_len() / 8;
+                for (counter, chunk) in out.chunks_mut(digest_len).enumerate() {
+                    let mut sha3 = Sha3::new(*sha3_variant);
+                    sha3.update(seed);
+                    sha3.update(&(counter as u32).to_be_bytes());
+                    sha3.digest_into(&mut block[..digest_len]);
+                    xor_into(chunk, &block);
+                }
+            }
+            Mgf::Shake(shake_variant) => {
+                let mut shake = Shake::new(*shake_variant);
+                shake.update(seed);
+                let mut reader = shake.finalize_xof();
+                for chunk in out.chunks_mut(block.len()) {
+                    reader.squeeze(&mut block[..chunk.len()]);
+                    xor_into(chunk, &block);
+                }
+            }
+        }
+        block.zeroize();
+    }
+}
+
+fn xor_into(out: &mut [u8], mask: &[u8]) {
+    for (byte, mask_byte) in out.iter_mut().zip(mask) {
+        *byte ^= mask_byte;
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Display, Error)]
+pub enum EncodingError {
+    #[display("modulus is too short for the parameters")]
+    ModulusTooShort,
+    #[display("message is too long")]
+    MessageTooLong,
+    #[display("the OAEP seed must be {_0} bytes long")]
+    InvalidSeedLength(#[error(not(source))] usize),
+}
+
+/// The only error of verification and decoding, so that failures cannot be told apart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Display, Error)]
+#[display("decoding error")]
+pub struct DecodingError;
+
+
+/// The hash and the mask generation function of PSS or OAEP.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pkcs1Params {
+    pub hash: Pkcs1Hash,
+    pub mgf: Mgf,
+}
+
+impl Pkcs1Params {
+    /// SHA3 as the hash, and MGF1 with the same hash.
+    pub fn sha3(sha3_variant: Sha3Variant) -> Self {
+        Pkcs1Params {
+            hash: Pkcs1Hash::Sha3(sha3_variant),
+            mgf: Mgf::Mgf1(sha3_variant),
+        }
+    }
+
+    /// SHAKE as both the hash and the mask generation function, as in RFC 8702.
+    pub fn shake(shake_variant: ShakeVariant) -> Self {
+        Pkcs1Params {
+            hash: Pkcs1Hash::Shake(shake_variant),
+            mgf: Mgf::Shake(shake_variant),
+        }
+    }
+
+    /// EMSA-PSS-ENCODE (RFC 8017, Sec. 9.1.1) for a modulus of `mod_bits` bits: returns EM,
+    /// of emLen = ceil((mod_bits - 1) / 8) bytes.
+    pub fn pss_encode(&self, message: &[u8], salt: &[u8], mod_bits: usize) -> Result<ByteString, EncodingError> {
+        let h_len = self.hash.digest_len();
+        let em_bits = mod_bits.saturating_sub(1);
+        let em_len = em_bits.div_ceil(8);
+        if em_len < h_len + salt.len() + 2 {
+            return Err(EncodingError::ModulusTooShort);
+        }
+
+        let mut m_hash = [0u8; SHA3_MAX_DIGEST_BYTES];
+        self.hash.hash(&[message], &mut m_hash[..h_len]);
+        let db_len = em_len - h_len - 1;
+        let mut em = vec![0u8; em_len];
+        // H = Hash(0x00 * 8 || mHash || salt)
+        self.hash.hash(&[&[0u8; 8], &m_hash[..h_len], salt], &mut em[db_len..em_len - 1]);
+        em[em_len - 1] = 0xbc;
+
+        // DB = PS || 0x01 || salt, masked with MGF(H)
+        let (db, rest) = em.split_at_mut(db_len);
+        db[db_len - salt.len() - 1] = 0x01;
+        db[db_len - salt.len()..].copy_from_slice(salt);
+        self.mgf.apply_mask(&rest[..h_len], db);
+        db[0] &= 0xff >> (8 * em_len - em_bits);
+        Ok(ByteString::from(em))
+    }
+
+    /// EMSA-PSS-VERIFY (RFC 8017, Sec. 9.1.2) with a salt of `salt_len` bytes; `em` is the
+    /// output of the RSA verification primitive as an octet string of emLen bytes.
+    pub fn pss_verify(&self, message: &[u8], em: &[u8], salt_len: usize, mod_bits: usize) -> Result<(), DecodingError> {
+        let h_len = self.hash.digest_len();
+        let em_bits = mod_bits.saturating_sub(1);
+        let em_len = em_bits.div_ceil(8);
+        if em.len() != em_len || em_len < h_len + salt_len + 2 {
+            return Err(DecodingError);
+        }
+
+        let db_len = em_len - h_len - 1;
+        let top_bits_mask = 0xffu8 >> (8 * em_len - em_bits);
+        let mut is_valid = ct_byte_eq(em[em_len - 1], 0xbc) & ct_byte_eq(em[0] & !top_bits_mask, 0);
+
+        let h = &em[db_len..em_len - 1];
+        let mut db = em[..db_len].to_vec();
+        self.mgf.apply_mask(h, &mut db);
+        db[0] &= top_bits_mask;
+        let ps_len = db_len - salt_len - 1;
+        for &byte in &db[..ps_len] {
+            is_valid &= ct_byte_eq(byte, 0);
+        }
+        is_valid &= ct_byte_eq(db[ps_len], 0x01);
+
+        let mut m_hash = [0u8; SHA3_MAX_DIGEST_BYTES];
+        let mut h_expected = [0u8; SHA3_MAX_DIGEST_BYTES];
+        self.hash.hash(&[message], &mut m_hash[..h_len]);
+        self.hash.hash(&[&[0u8; 8], &m_hash[..h_len], &db[ps_len + 1..]], &mut h_expected[..h_len]);
+        is_valid &= if ct_eq(h, &h_expected[..h_len]) { 0xff } else { 0 };
+        db.zeroize();
+        match black_box(is_valid) {
+            0xff => Ok(()),
+            _ => Err(DecodingError),
+        }
+    }
+
+    /// EME-OAEP encoding (RFC 8017, Sec. 7.1.1, step 2) for a modulus of `k` bytes, with a
+    /// random `seed` of hLen bytes: returns EM, of `k` bytes.
+    pub fn oaep_encode(&self, message: &[u8], label: &[u8], seed: &[u8], k: usize) -> Result<ByteString, EncodingError> {
+        let h_len = self.hash.digest_len();
+        if seed.len() != h_len {
+            return Err(EncodingError::InvalidSeedLength(h_len));
+        }
+        if k < 2 * h_len + 2 {
+            return Err(EncodingError::ModulusTooShort);
+        }
+        if message.len() > k - 2 * h_len - 2 {
+            return Err(EncodingError::MessageTooLong);
+        }
+
+        // EM = 0x00 || maskedSeed || maskedDB, with DB = lHash || PS || 0x01 || M
+        let mut em = vec![0u8; k];
+        let (masked_seed, db) = em[1..].split_at_mut(h_len);
+        self.hash.hash(&[label], &mut db[..h_len]);
+        let db_len = db.len();
+        db[db_len - message.len() - 1] = 0x01;
+        db[db_len - message.len()..].copy_from_slice(message);
+        self.mgf.apply_mask(seed, db);
+        masked_seed.copy_from_slice(seed);
+        self.mgf.apply_mask(db, masked_seed);
+        Ok(ByteString::from(em))
+    }
+
+    /// EME-OAEP decoding (RFC 8017, Sec. 7.1.2, step 3) of the `k` bytes output by the RSA
+    /// decryption primitive. The checks on the content of EM run in constant time, and
+    /// every failure returns the same error.
+    pub fn oaep_decode(&self, em: &[u8], label: &[u8], k: usize) -> Result<ByteString, DecodingError> {
+        let h_len = self.hash.digest_len();
+        if em.len() != k || k < 2 * h_len + 2 {
+            return Err(DecodingError);
+        }
+
+        let mut seed = em[1..1 + h_len].to_vec();
+        let mut db = em[1 + h_len..].to_vec();
+        self.mgf.apply_mask(&db, &mut seed);
+        self.mgf.apply_mask(&seed, &mut db);
+
+        let mut l_hash = [0u8; SHA3_MAX_DIGEST_BYTES];
+        self.hash.hash(&[label], &mut l_hash[..h_len]);
+        let mut is_valid = ct_byte_eq(em[0], 0);
+        is_valid &= if ct_eq(&db[..h_len], &l_hash[..h_len]) { 0xff } else { 0 };
+
+        // finds the 0x01 after PS without branching on the content of DB
+        let mut is_looking = 0xffu8;
+        let mut separator = 0usize;
+        for (i, &byte) in db.iter().enumerate().skip(h_len) {
+            let is_separator = is_looking & ct_byte_eq(byte, 0x01);
+            separator |= i & usize::from(is_separator & 1).wrapping_neg();
+            is_valid &= !is_looking | is_separator | ct_byte_eq(byte, 0);
+            is_looking &= !is_separator;
+        }
+        is_valid &= !is_looking;
+
+        let result = match black_box(is_valid) {
+            0xff => Ok(ByteString::from(db[separator + 1..].to_vec())),
+            _ => Err(DecodingError),
+        };
+        seed.zeroize();
+        db.zeroize();
+        result
+    }
+}
+
+/// 0xff if `a == b`, 0 otherwise, without branching.
+fn ct_byte_eq(a: u8, b: u8) -> u8 {
+    let diff = u16::from(a ^ b);
+    (diff.wrapping_sub(1) >> 8) as u8
+}
+
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    // EM recovered from a PSS signature and an OAEP ciphertext made by OpenSSL with
+    // SHA3-256 and MGF1-SHA3-256, for moduli of 1024 and 1030 bits; the salt and seed
+    // were then recovered from EM
+    const OPENSSL_PSS: [(usize, &str, &str); 2] = [
+        (1024,
+         "6b30794a89efe46e100d6152d129c200dde5c28da50c65d2465609f0011c222985b1bbe4bb2c2ce7856de11f569bda45\
+          d48fbc94a42dfb394e49b1fbe16d8cd6367a54ed6fd29f804eb392b11ee9a5f6ea02165a6b08bb370eb090079a3b9417\
+          bddfc2a546f2ee58ebb2371eb2b699c44a9d1d6a8e05eabf9047d78034075cbc",
+         "02f1b6fa7bc338d6cc38acd993fd35b49d99e083d9000f30b629b1e8a1d54630"),
+        (1030,
+         "0dbc112a2f5d6bcbed2159db210f08ee7ab37e82f4ec2fe27cd7d5879029a335f9113e84b80ba15b390b99d86dd498bd\
+          843dd595044a145d4288d40e0cc85f778a557b650af4b505478c3f6b144cf057ddd1fe1bdcdfc1cf456da6b9539ba817\
+          35727af69fa822993e5fbcefc632555a40f64d6d6a82ccd1462f1a4f283d29c6bc",
+         "e23cfa55258fe785088d5d8fef0598feb41de5ce35f04465820ad73863dd9740"),
+    ];
+    const OPENSSL_OAEP: [(usize, &str, &str); 2] = [
+        (1024,
+         "002a23ee270f6dbb000b07c0bd109307dacbb6c5187eb0ece185ab18f7cd71dc5fff0ae7dab73e836a4fcc8a947811f0\
+          a108b7c42595eda070ac09fa2151ee9aa783f9696838013dcd7b342fdf36b6760c7209c172ed348ef10b768319d85397\
+          dcd54168d5ce01434af9017e509dd78657c92caac4e1c889856d02f759f79764",
+         "97249647d3d3f1065ed3f29a7a4ff0c7077971b87d5588fad265400d8bb05321"),
+        (1030,
+         "0004c864f94b88a8dc674239ead02540d746b63dd298e12faee73a7c80ab998ebfa56dd92d35e535c4256014f44bacff\
+          61989eaee5e6008922a029aa66a752704a8aee2cc1984b1a9b205a3f3dea5c38f135bc3986dae19e23a17031c5be163b\
+          d814b55371db12f2daaa296c2cb0d0d2f7be3e0dfe543cb7a1b051769bc56aac55",
+         "b45204d6de4db22e51a084310b956f9eb3ddc5710fa68a98d8fe25b2324e7c5d"),
+    ];
+
+    #[test]
+    fn test_pss_openssl_examples() {
+        let params = Pkcs1Params::sha3(Sha3Variant::SHA3_256);
+        let message = b"SHA3 with RSASSA-PSS";
+        for (mod_bits, em, salt) in OPENSSL_PSS {
+            let em = hex::decode(em).unwrap();
+            let salt = hex::decode(salt).unwrap();
+            assert_eq!(Ok(()), params.pss_verify(message, &em, 32, mod_bits));
+            assert_eq!(em, params.pss_encode(message, &salt, mod_bits).unwrap().to_vec());
+            assert_eq!(Err(DecodingError), params.pss_verify(b"SHA3 with RSASSA-PSS.", &em, 32, mod_bits));
+        }
+    }
+
+    #[test]
+    fn test_oaep_openssl_examples() {
+        let params = Pkcs1Params::sha3(Sha3Variant::SHA3_256);
+        for (mod_bits, em, seed) in OPENSSL_OAEP {
+            let k = mod_bits.div_ceil(8);
+            let em = hex::decode(em).unwrap();
+            let seed = hex::decode(seed).unwrap();
+            assert_eq!(b"OAEP message", &params.oaep_decode(&em, b"label", k).unwrap()[..]);
+            assert_eq!(em, params.oaep_encode(b"OAEP message", b"label", &seed, k).unwrap().to_vec());
+            assert_eq!(Err(DecodingError), params.oaep_decode(&em, b"other label", k));
+        }
+    }
+
+    #[test]
+    fn test_shake_examples() {
+        // computed with Python's hashlib, following RFC 8702 for PSS
+        let pss = Pkcs1Params::shake(ShakeVariant::SHAKE128);
+        let salt: Vec<u8> = (0..32).collect();
+        let em = pss.pss_encode(b"SHAKE with RSASSA-PSS", &salt, 1030).unwrap();
+        assert_eq!("162cb85e0cb8f5ad10e5580ecfeff9f8427cc8a7d472a1758360d404ac0d3fb3fa4ada394eade313edeb06475d36ca86\
+                    6f9d9fca50d0e84a73bf97305b3e2764e29844417c64c655add6de40ec4239221ce035d5d32553dc6d17f50f273c80cb\
+                    0e8d4f8779002721ae385c321598226077ff9d800022e04065f94263a9a0624abc", hex::encode(&em[..]));
+        assert_eq!(Ok(()), pss.pss_verify(b"SHAKE with RSASSA-PSS", &em, 32, 1030));
+
+        let oaep = Pkcs1Params::shake(ShakeVariant::SHAKE256);
+        let seed: Vec<u8> = (0..64).collect();
+        let em = oaep.oaep_encode(b"OAEP with SHAKE", b"", &seed, 160).unwrap();
+        assert_eq!("002cbd0b54b387d864a501825af8f4dd04937039fe761198bc6497d2070cd67c121e97e5ca730916de300474e3204d3f\
+                    c82906556e3142813a498bbf4df454be0533e75548a91a31155c6afe880e4f233deae98edda3c3bc202962c412124c1c\
+                    6634c0fb9df312b72dcaf696e08d3dd48c3e2de8693810c44c728aede9a18d9ccbb67c4edd0f08d084310d0130165dba\
+                    a19f350164fd549b39558dd7e0c2587f", hex::encode(&em[..]));
+        assert_eq!(b"OAEP with SHAKE", &oaep.oaep_decode(&em, b"", 160).unwrap()[..]);
+    }
+
+    #[test]
+    fn test_round_trips() {
+        let all_params = [
+            Pkcs1Params::sha3(Sha3Variant::SHA3_224),
+            Pkcs1Params::sha3(Sha3Variant::SHA3_512),
+            Pkcs1Params::shake(ShakeVariant::SHAKE256),
+            Pkcs1Params { hash: Pkcs1Hash::Sha3(Sha3Variant::SHA3_384), mgf: Mgf::Shake(ShakeVariant::SHAKE128) },
+        ];
+        for params in all_params {
+            let h_len = params.hash.digest_len();
+            for mod_bits in [2048usize, 2049, 2055] {
+                let k = mod_bits.div_ceil(8);
+                for salt_len in [0, 20, h_len] {
+                    let salt = vec![0x5a; salt_len];
+                    let em = params.pss_encode(b"message", &salt, mod_bits).unwrap();
+                    assert_eq!(Ok(()), params.pss_verify(b"message", &em, salt_len, mod_bits), "{params:?} {mod_bits} {salt_len}");
+                }
+                let seed = vec![0xa5; h_len];
+                for message in [&b""[..], b"message", &vec![7; k - 2 * h_len - 2]] {
+                    let em = params.oaep_encode(message, b"L", &seed, k).unwrap();
+                    assert_eq!(message, &params.oaep_decode(&em, b"L", k).unwrap()[..]);
+                }
+                assert_eq!(Err(EncodingError::MessageTooLong), params.oaep_encode(&vec![7; k - 2 * h_len - 1], b"L", &seed, k));
+            }
+        }
+    }
+
+    #[test]
+    fn test_every_corruption_is_rejected_alike() {
+        let params = Pkcs1Params::sha3(Sha3Variant::SHA3_256);
+        let em = params.oaep_encode(b"secret", b"", &[1; 32], 128).unwrap();
+        for i in 0..em.len() {
+            let mut corrupted = em.to_vec();
+            corrupted[i] ^= 0x01;
+            assert_eq!(Err(DecodingError), params.oaep_decode(&corrupted, b"", 128), "byte {i}");
+        }
+        let em = params.pss_encode(b"message", &[2; 32], 1024).unwrap();
+        for i in 0..em.len() {
+            let mut corrupted = em.to_vec();
+            corrupted[i] ^= 0x80;
+            assert_eq!(Err(DecodingError), params.pss_verify(b"message", &corrupted, 32, 1024), "byte {i}");
+        }
+        assert_eq!(Err(EncodingError::ModulusTooShort), params.pss_encode(b"message", &[0; 32], 520));
+        assert_eq!(Err(EncodingError::InvalidSeedLength(32)), params.oaep_encode(b"", b"", &[0; 31], 128));
+    }
+}
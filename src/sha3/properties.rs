@@ -0,0 +1,347 @@
+//! Seeded randomized tests of the incremental hashers against the one-shot reference.
+//!
+//! Each case is derived from a seed: a variant, a message whose length clusters around
+//! multiples of the rate, a random partition of the message into `update` calls (empty
+//! ones included) and, for SHAKE, a random partition of the output into `squeeze` calls.
+//! The result must match `sha3_family`, `shake_family` or, for bit-oriented messages,
+//! `keccak_sponge`. The lengths r-2, r-1, r and r+1, in bits and in bytes, are always tested.
+//!
+//! A failing case is shrunk to a minimal reproducer, printed along with its seed;
+//! `SHA3_PROPERTY_SEED=<seed>` reruns a single seed.
+
+use std::env;
+use std::fmt;
+
+use crate::sha3::constants::KECCAK_B;
+use crate::sha3::constants::KECCAK_NR;
+use crate::sha3::rng::SplitMix64;
+use crate::sha3::sha3::Sha3;
+use crate::sha3::sha3::Shake;
+use crate::sha3::sha3::ShakeReader;
+use crate::sha3::sha3::Sponge;
+use crate::sha3::sha3::keccak_sponge;
+use crate::sha3::sha3::sha3_family;
+use crate::sha3::sha3::shake_family;
+use crate::sha3::types::BitString;
+use crate::sha3::types::Sha3Variant;
+use crate::sha3::types::ShakeVariant;
+use crate::sha3::utils::bitstring_to_bytestr;
+
+
+const RANDOM_CASES: u64 = 200;
+const BASE_SEED: u64 = 0x5ba3_0000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Algorithm {
+    Sha3(Sha3Variant),
+    Shake(ShakeVariant),
+}
+
+const ALGORITHMS: [Algorithm; 6] = [
+    Algorithm::Sha3(Sha3Variant::SHA3_224),
+    Algorithm::Sha3(Sha3Variant::SHA3_256),
+    Algorithm::Sha3(Sha3Variant::SHA3_384),
+    Algorithm::Sha3(Sha3Variant::SHA3_512),
+    Algorithm::Shake(ShakeVariant::SHAKE128),
+    Algorithm::Shake(ShakeVariant::SHAKE256),
+];
+
+impl Algorithm {
+    fn capacity(&self) -> usize {
+        match self {
+            Algorithm::Sha3(sha3_variant) => sha3_variant.capacity(),
+            Algorithm::Shake(shake_variant) => shake_variant.capacity(),
+        }
+    }
+
+    /// The rate r in bits.
+    fn rate(&self) -> usize {
+        KECCAK_B - self.capacity()
+    }
+
+    fn suffix(&self) -> &'static [u8] {
+        match self {
+            Algorithm::Sha3(_) => &[0, 1],
+            Algorithm::Shake(_) => &[1, 1, 1, 1],
+        }
+    }
+}
+
+
+/// A message is either bytes, hashed through the public hashers, or bits (one per u8),
+/// absorbed directly into a `Sponge`.
+#[derive(Clone, Debug)]
+struct Case {
+    algorithm: Algorithm,
+    message: Vec<u8>,
+    is_bits: bool,
+    /// Lengths of the `update` calls, in elements of `message`.
+    chunks: Vec<usize>,
+    /// Lengths of the `squeeze` calls, in bytes; a single digest for SHA3.
+    squeezes: Vec<usize>,
+}
+
+impl fmt::Display for Case {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unit = if self.is_bits { "bits" } else { "bytes" };
+        let message: String = match self.is_bits {
+            true => self.message.iter().map(|bit| char::from(b'0' + bit)).collect(),
+            false => hex::encode(&self.message),
+        };
+        write!(f, "{:?}, {} {unit} [{message}], updates {:?}, squeezes {:?}",
+            self.algorithm, self.message.len(), self.chunks, self.squeezes)
+    }
+}
+
+fn below(rng: &mut SplitMix64, n: usize) -> usize {
+    (rng.next_u64() % n as u64) as usize
+}
+
+/// Splits `len` into random chunks, with a bias towards short and empty ones.
+fn random_partition(rng: &mut SplitMix64, len: usize) -> Vec<usize> {
+    let mut chunks = Vec::new();
+    let mut remaining = len;
+    while remaining > 0 {
+        let chunk = match below(rng, 4) {
+            0 => 0,
+            1 => below(rng, remaining.min(8) + 1),
+            2 => below(rng, remaining + 1),
+            _ => remaining,
+        };
+        chunks.push(chunk);
+        remaining -= chunk;
+    }
+    chunks
+}
+
+impl Case {
+    /// A case with a random message of `len` elements.
+    fn with_len(rng: &mut SplitMix64, algorithm: Algorithm, len: usize, is_bits: bool) -> Case {
+        let mut message = vec![0u8; len];
+        rng.fill_bytes(&mut message);
+        if is_bits {
+            message.iter_mut().for_each(|bit| *bit &= 1);
+        }
+        let chunks = random_partition(rng, len);
+        let squeezes = match algorithm {
+            Algorithm::Sha3(sha3_variant) => vec![sha3_variant.output_len() / 8],
+            Algorithm::Shake(_) => {
+                let output_len = 1 + below(rng, 3 * algorithm.rate() / 8);
+                random_partition(rng, output_len)
+            }
+        };
+        Case { algorithm, message, is_bits, chunks, squeezes }
+    }
+
+    /// A random case, with a length within a few elements of a multiple of the rate
+    /// half of the time.
+    fn from_seed(seed: u64) -> Case {
+        let mut rng = SplitMix64::new(seed);
+        let algorithm = ALGORITHMS[below(&mut rng, ALGORITHMS.len())];
+        let is_bits = below(&mut rng, 4) == 0;
+        let rate = if is_bits { algorithm.rate() } else { algorithm.rate() / 8 };
+        let len = match below(&mut rng, 2) {
+            0 => (below(&mut rng, 3) * rate + below(&mut rng, 9)).saturating_sub(4),
+            _ => below(&mut rng, 3 * rate),
+        };
+        Case::with_len(&mut rng, algorithm, len, is_bits)
+    }
+
+    fn output_len(&self) -> usize {
+        self.squeezes.iter().sum()
+    }
+
+    fn expected(&self) -> Vec<u8> {
+        let c = self.algorithm.capacity();
+        let d = 8 * self.output_len();
+        match (self.is_bits, self.algorithm) {
+            (false, Algorithm::Sha3(_)) => sha3_family(&self.message, c, d).to_vec(),
+            (false, Algorithm::Shake(_)) => shake_family(&self.message, c, d).to_vec(),
+            (true, algorithm) => {
+                let mut n = BitString::from(self.message.clone());
+                algorithm.suffix().iter().for_each(|bit| n.push(*bit));
+                bitstring_to_bytestr(&keccak_sponge(KECCAK_B, KECCAK_NR, c, &n, d)).to_vec()
+            }
+        }
+    }
+
+    fn actual(&self) -> Vec<u8> {
+        let chunks = self.chunks.iter().scan(0, |start, len| {
+            let chunk = &self.message[*start..*start + len];
+            *start += len;
+            Some(chunk)
+        });
+        let mut out = vec![0u8; self.output_len()];
+        match (self.is_bits, self.algorithm) {
+            (false, Algorithm::Sha3(sha3_variant)) => {
+                let mut sha3 = Sha3::new(sha3_variant);
+                chunks.for_each(|chunk| sha3.update(chunk));
+                sha3.digest_into(&mut out);
+            }
+            (false, Algorithm::Shake(shake_variant)) => {
+                let mut shake = Shake::new(shake_variant);
+                chunks.for_each(|chunk| shake.update(chunk));
+                self.squeeze(shake.finalize_xof(), &mut out);
+            }
+            (true, algorithm) => {
+                let mut sponge = Sponge::new(algorithm.capacity());
+                chunks.for_each(|chunk| sponge.absorb_bits(chunk));
+                sponge.absorb_bits(algorithm.suffix());
+                sponge.pad();
+                self.squeeze(ShakeReader::from_sponge(sponge), &mut out);
+            }
+        }
+        out
+    }
+
+    fn squeeze(&self, mut reader: ShakeReader, out: &mut [u8]) {
+        let mut start = 0;
+        for len in &self.squeezes {
+            reader.squeeze(&mut out[start..start + len]);
+            start += len;
+        }
+    }
+
+    /// Smaller variants of the case, from the most to the least aggressive: shorter
+    /// messages, obtained by cutting down one `update` at a time so that the others keep
+    /// their length, fewer updates, a shorter output, then zeroed elements.
+    fn shrink_candidates(&self) -> Vec<Case> {
+        let mut candidates = Vec::new();
+        if !self.message.is_empty() {
+            let mut case = self.clone();
+            case.message.clear();
+            case.chunks.clear();
+            candidates.push(case);
+        }
+        let mut start = 0;
+        for (i, &len) in self.chunks.iter().enumerate() {
+            for new_len in [0, len / 2, len.saturating_sub(1)] {
+                if new_len < len {
+                    let mut case = self.clone();
+                    case.message.drain(start + new_len..start + len);
+                    case.chunks[i] = new_len;
+                    candidates.push(case);
+                }
+            }
+            if len > 0 {
+                let mut case = self.clone();
+                case.message.remove(start);
+                case.chunks[i] -= 1;
+                candidates.push(case);
+            }
+            start += len;
+        }
+        for i in 1..self.chunks.len() {
+            let mut case = self.clone();
+            let merged = case.chunks.remove(i);
+            case.chunks[i - 1] += merged;
+            candidates.push(case);
+        }
+        if let Algorithm::Shake(_) = self.algorithm
+            && self.output_len() > 1 {
+            let mut case = self.clone();
+            case.squeezes = fit_partition(&self.squeezes, self.output_len() - 1);
+            candidates.push(case);
+        }
+        for i in 0..self.message.len() {
+            if self.message[i] != 0 {
+                let mut case = self.clone();
+                case.message[i] = 0;
+                candidates.push(case);
+            }
+        }
+        candidates
+    }
+}
+
+/// Cuts `chunks` down so that they add up to `len`.
+fn fit_partition(chunks: &[usize], len: usize) -> Vec<usize> {
+    let mut remaining = len;
+    let mut fitted = Vec::new();
+    for &chunk in chunks {
+        if remaining == 0 {
+            break;
+        }
+        fitted.push(chunk.min(remaining));
+        remaining -= chunk.min(remaining);
+    }
+    fitted
+}
+
+/// Greedily replaces `case` by its first failing candidate until none fails.
+fn shrink(mut case: Case, fails: impl Fn(&Case) -> bool) -> Case {
+    while let Some(smaller) = case.shrink_candidates().into_iter().find(|candidate| fails(candidate)) {
+        case = smaller;
+    }
+    case
+}
+
+fn is_consistent(case: &Case) -> bool {
+    case.actual() == case.expected()
+}
+
+fn check(seed: u64, case: Case) {
+    if !is_consistent(&case) {
+        let minimal = shrink(case.clone(), |case| !is_consistent(case));
+        panic!("seed {seed:#x}: streaming and one-shot outputs differ\n  case: {case}\n  shrunk to: {minimal}\n  streaming {}\n  one-shot  {}",
+            hex::encode(minimal.actual()), hex::encode(minimal.expected()));
+    }
+}
+
+
+#[test]
+fn test_random_partitions() {
+    let seeds = match env::var("SHA3_PROPERTY_SEED") {
+        Ok(seed) => {
+            let seed = seed.trim_start_matches("0x");
+            vec![u64::from_str_radix(seed, 16).expect("SHA3_PROPERTY_SEED is a hex seed")]
+        }
+        Err(_) => (BASE_SEED..BASE_SEED + RANDOM_CASES).collect(),
+    };
+    for seed in seeds {
+        check(seed, Case::from_seed(seed));
+    }
+}
+
+#[test]
+fn test_lengths_around_the_rate() {
+    for (i, algorithm) in ALGORITHMS.into_iter().enumerate() {
+        for is_bits in [false, true] {
+            let rate = if is_bits { algorithm.rate() } else { algorithm.rate() / 8 };
+            for len in [rate - 2, rate - 1, rate, rate + 1] {
+                // a single update, then a random partition
+                let seed = BASE_SEED ^ ((i as u64) << 32 | (is_bits as u64) << 31 | len as u64);
+                let mut rng = SplitMix64::new(seed);
+                let mut case = Case::with_len(&mut rng, algorithm, len, is_bits);
+                check(seed, case.clone());
+                case.chunks = vec![len];
+                check(seed, case);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_shrinking() {
+    // a fake property that fails for messages of at least 10 bytes with a nonzero
+    // byte past the fifth one, whatever the partition
+    let fails = |case: &Case| case.message.len() >= 10 && case.message[5..].iter().any(|byte| *byte != 0);
+    let seed = (BASE_SEED..).find(|seed| fails(&Case::from_seed(*seed))).unwrap();
+    let case = Case::from_seed(seed);
+    let minimal = shrink(case.clone(), fails);
+
+    assert_eq!(10, minimal.message.len());
+    assert_eq!(1, minimal.message.iter().filter(|byte| **byte != 0).count());
+    assert!(minimal.chunks.len() <= 1);
+    assert_eq!(case.algorithm, minimal.algorithm);
+    if let Algorithm::Shake(_) = minimal.algorithm {
+        assert_eq!(1, minimal.output_len());
+    }
+}
+
+#[test]
+fn test_fit_partition() {
+    assert_eq!(vec![3, 0, 2], fit_partition(&[3, 0, 4, 5], 5));
+    assert_eq!(Vec::<usize>::new(), fit_partition(&[3, 4], 0));
+    assert_eq!(vec![3, 4], fit_partition(&[3, 4], 7));
+}
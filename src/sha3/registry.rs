@@ -0,0 +1,471 @@
+//! Registry of the algorithms of the crate, looked up by canonical name or by OID, with
+//! their parameters and a hasher behind `Box<dyn DynHasher>` to select them at runtime.
+
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+#[cfg(feature = "alloc")]
+use alloc::vec;
+use core::fmt;
+
+use crate::sha3::constants::KECCAK_B;
+#[cfg(feature = "alloc")]
+use crate::sha3::hmac::HmacSha3;
+#[cfg(feature = "alloc")]
+use crate::sha3::sha3::Keccak;
+#[cfg(feature = "alloc")]
+use crate::sha3::sha3::Sha3;
+#[cfg(feature = "alloc")]
+use crate::sha3::sha3::Shake;
+#[cfg(feature = "alloc")]
+use crate::sha3::sp800_185::CShake;
+#[cfg(feature = "alloc")]
+use crate::sha3::sp800_185::Kmac;
+#[cfg(feature = "alloc")]
+use crate::sha3::sp800_185::ParallelHash;
+#[cfg(feature = "alloc")]
+use crate::sha3::sp800_185::TupleHash;
+#[cfg(feature = "alloc")]
+use crate::sha3::types::ByteString;
+use crate::sha3::types::Sha3Variant;
+use crate::sha3::types::ShakeVariant;
+
+
+/// An object identifier, as its arcs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Oid(pub &'static [u32]);
+
+/// Dotted decimal notation, e.g. "2.16.840.1.101.3.4.2.8".
+impl fmt::Display for Oid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, arc) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ".")?;
+            }
+            write!(f, "{arc}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The arcs of the OIDs in the NIST hashAlgs arc, 2.16.840.1.101.3.4.2.
+macro_rules! hash_algs_oid {
+    ($arc:literal) => {
+        Oid(&[2, 16, 840, 1, 101, 3, 4, 2, $arc])
+    };
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Algorithm {
+    Sha3(Sha3Variant),
+    Shake(ShakeVariant),
+    CShake(ShakeVariant),
+    Kmac(ShakeVariant),
+    KmacXof(ShakeVariant),
+    TupleHash(ShakeVariant),
+    TupleHashXof(ShakeVariant),
+    ParallelHash(ShakeVariant),
+    ParallelHashXof(ShakeVariant),
+    HmacSha3(Sha3Variant),
+    /// The Keccak submission that predates FIPS 202, padded without the SHA3 suffix.
+    Keccak(Sha3Variant),
+}
+
+impl Algorithm {
+    /// Every algorithm of the crate.
+    pub const ALL: [Algorithm; 28] = [
+        Algorithm::Sha3(Sha3Variant::SHA3_224),
+        Algorithm::Sha3(Sha3Variant::SHA3_256),
+        Algorithm::Sha3(Sha3Variant::SHA3_384),
+        Algorithm::Sha3(Sha3Variant::SHA3_512),
+        Algorithm::Shake(ShakeVariant::SHAKE128),
+        Algorithm::Shake(ShakeVariant::SHAKE256),
+        Algorithm::CShake(ShakeVariant::SHAKE128),
+        Algorithm::CShake(ShakeVariant::SHAKE256),
+        Algorithm::Kmac(ShakeVariant::SHAKE128),
+        Algorithm::Kmac(ShakeVariant::SHAKE256),
+        Algorithm::KmacXof(ShakeVariant::SHAKE128),
+        Algorithm::KmacXof(ShakeVariant::SHAKE256),
+        Algorithm::TupleHash(ShakeVariant::SHAKE128),
+        Algorithm::TupleHash(ShakeVariant::SHAKE256),
+        Algorithm::TupleHashXof(ShakeVariant::SHAKE128),
+        Algorithm::TupleHashXof(ShakeVariant::SHAKE256),
+        Algorithm::ParallelHash(ShakeVariant::SHAKE128),
+        Algorithm::ParallelHash(ShakeVariant::SHAKE256),
+        Algorithm::ParallelHashXof(ShakeVariant::SHAKE128),
+        Algorithm::ParallelHashXof(ShakeVariant::SHAKE256),
+        Algorithm::HmacSha3(Sha3Variant::SHA3_224),
+        Algorithm::HmacSha3(Sha3Variant::SHA3_256),
+        Algorithm::HmacSha3(Sha3Variant::SHA3_384),
+        Algorithm::HmacSha3(Sha3Variant::SHA3_512),
+        Algorithm::Keccak(Sha3Variant::SHA3_224),
+        Algorithm::Keccak(Sha3Variant::SHA3_256),
+        Algorithm::Keccak(Sha3Variant::SHA3_384),
+        Algorithm::Keccak(Sha3Variant::SHA3_512),
+    ];
+
+    /// Looks up a canonical name (see `name`), ignoring ASCII case.
+    pub fn from_name(name: &str) -> Option<Algorithm> {
+        Algorithm::ALL.into_iter().find(|algorithm| algorithm.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn from_oid(oid: &[u32]) -> Option<Algorithm> {
+        Algorithm::ALL.into_iter().find(|algorithm| algorithm.oid().is_some_and(|o| o.0 == oid))
+    }
+
+    /// Looks up an OID in dotted decimal notation.
+    pub fn from_dotted_oid(oid: &str) -> Option<Algorithm> {
+        Algorithm::ALL.into_iter().find(|algorithm| algorithm.oid().is_some_and(|o| {
+            let mut arcs = oid.split('.');
+            o.0.iter().all(|arc| arcs.next().and_then(|a| a.parse::<u32>().ok()) == Some(*arc))
+                && arcs.next().is_none()
+        }))
+    }
+
+    /// The name used in the NIST publications, e.g. "SHA3-256", "KMACXOF128" or
+    /// "HMAC-SHA3-512", and in the Keccak submission, e.g. "Keccak-256".
+    pub fn name(&self) -> &'static str {
+        match self {
+            Algorithm::Sha3(Sha3Variant::SHA3_224) => "SHA3-224",
+            Algorithm::Sha3(Sha3Variant::SHA3_256) => "SHA3-256",
+            Algorithm::Sha3(Sha3Variant::SHA3_384) => "SHA3-384",
+            Algorithm::Sha3(Sha3Variant::SHA3_512) => "SHA3-512",
+            Algorithm::Shake(ShakeVariant::SHAKE128) => "SHAKE128",
+            Algorithm::Shake(ShakeVariant::SHAKE256) => "SHAKE256",
+            Algorithm::CShake(ShakeVariant::SHAKE128) => "cSHAKE128",
+            Algorithm::CShake(ShakeVariant::SHAKE256) => "cSHAKE256",
+            Algorithm::Kmac(ShakeVariant::SHAKE128) => "KMAC128",
+            Algorithm::Kmac(ShakeVariant::SHAKE256) => "KMAC256",
+            Algorithm::KmacXof(ShakeVariant::SHAKE128) => "KMACXOF128",
+            Algorithm::KmacXof(ShakeVariant::SHAKE256) => "KMACXOF256",
+            Algorithm::TupleHash(ShakeVariant::SHAKE128) => "TupleHash128",
+            Algorithm::TupleHash(ShakeVariant::SHAKE256) => "TupleHash256",
+            Algorithm::TupleHashXof(ShakeVariant::SHAKE128) => "TupleHashXOF128",
+            Algorithm::TupleHashXof(ShakeVariant::SHAKE256) => "TupleHashXOF256",
+            Algorithm::ParallelHash(ShakeVariant::SHAKE128) => "ParallelHash128",
+            Algorithm::ParallelHash(ShakeVariant::SHAKE256) => "ParallelHash256",
+            Algorithm::ParallelHashXof(ShakeVariant::SHAKE128) => "ParallelHashXOF128",
+            Algorithm::ParallelHashXof(ShakeVariant::SHAKE256) => "ParallelHashXOF256",
+            Algorithm::HmacSha3(Sha3Variant::SHA3_224) => "HMAC-SHA3-224",
+            Algorithm::HmacSha3(Sha3Variant::SHA3_256) => "HMAC-SHA3-256",
+            Algorithm::HmacSha3(Sha3Variant::SHA3_384) => "HMAC-SHA3-384",
+            Algorithm::HmacSha3(Sha3Variant::SHA3_512) => "HMAC-SHA3-512",
+            Algorithm::Keccak(Sha3Variant::SHA3_224) => "Keccak-224",
+            Algorithm::Keccak(Sha3Variant::SHA3_256) => "Keccak-256",
+            Algorithm::Keccak(Sha3Variant::SHA3_384) => "Keccak-384",
+            Algorithm::Keccak(Sha3Variant::SHA3_512) => "Keccak-512",
+        }
+    }
+
+    /// The OID assigned by NIST under hashAlgs, if any: cSHAKE, TupleHash, ParallelHash
+    /// and Keccak have none.
+    pub fn oid(&self) -> Option<Oid> {
+        match self {
+            Algorithm::Sha3(Sha3Variant::SHA3_224) => Some(hash_algs_oid!(7)),
+            Algorithm::Sha3(Sha3Variant::SHA3_256) => Some(hash_algs_oid!(8)),
+            Algorithm::Sha3(Sha3Variant::SHA3_384) => Some(hash_algs_oid!(9)),
+            Algorithm::Sha3(Sha3Variant::SHA3_512) => Some(hash_algs_oid!(10)),
+            Algorithm::Shake(ShakeVariant::SHAKE128) => Some(hash_algs_oid!(11)),
+            Algorithm::Shake(ShakeVariant::SHAKE256) => Some(hash_algs_oid!(12)),
+            Algorithm::HmacSha3(Sha3Variant::SHA3_224) => Some(hash_algs_oid!(13)),
+            Algorithm::HmacSha3(Sha3Variant::SHA3_256) => Some(hash_algs_oid!(14)),
+            Algorithm::HmacSha3(Sha3Variant::SHA3_384) => Some(hash_algs_oid!(15)),
+            Algorithm::HmacSha3(Sha3Variant::SHA3_512) => Some(hash_algs_oid!(16)),
+            Algorithm::Kmac(ShakeVariant::SHAKE128) => Some(hash_algs_oid!(19)),
+            Algorithm::Kmac(ShakeVariant::SHAKE256) => Some(hash_algs_oid!(20)),
+            Algorithm::KmacXof(ShakeVariant::SHAKE128) => Some(hash_algs_oid!(21)),
+            Algorithm::KmacXof(ShakeVariant::SHAKE256) => Some(hash_algs_oid!(22)),
+            _ => None,
+        }
+    }
+
+    /// Capacity c of the underlying sponge, in bits.
+    pub fn capacity(&self) -> usize {
+        match self {
+            Algorithm::Sha3(sha3_variant)
+            | Algorithm::HmacSha3(sha3_variant)
+            | Algorithm::Keccak(sha3_variant) => sha3_variant.capacity(),
+            Algorithm::Shake(shake_variant)
+            | Algorithm::CShake(shake_variant)
+            | Algorithm::Kmac(shake_variant)
+            | Algorithm::KmacXof(shake_variant)
+            | Algorithm::TupleHash(shake_variant)
+            | Algorithm::TupleHashXof(shake_variant)
+            | Algorithm::ParallelHash(shake_variant)
+            | Algorithm::ParallelHashXof(shake_variant) => shake_variant.capacity(),
+        }
+    }
+
+    /// Rate r = 1600 - c of the underlying sponge, in bits.
+    pub fn rate(&self) -> usize {
+        KECCAK_B - self.capacity()
+    }
+
+    /// Output length in bits; for the XOFs, and for KMAC, TupleHash and ParallelHash whose
+    /// output length is a parameter, the usual default of twice the security strength.
+    pub fn output_len(&self) -> usize {
+        match self {
+            Algorithm::Sha3(sha3_variant)
+            | Algorithm::HmacSha3(sha3_variant)
+            | Algorithm::Keccak(sha3_variant) => sha3_variant.output_len(),
+            _ => 2 * self.security_strength(),
+        }
+    }
+
+    /// Security strength in bits: d/2 (the collision resistance) for SHA3-d and Keccak-d,
+    /// and c/2 for the other functions, i.e. 128 or 256 for the SHAKE-based ones and d for
+    /// HMAC-SHA3-d (given a key of at least that many bits).
+    pub fn security_strength(&self) -> usize {
+        match self {
+            Algorithm::Sha3(sha3_variant) | Algorithm::Keccak(sha3_variant) => sha3_variant.output_len() / 2,
+            _ => self.capacity() / 2,
+        }
+    }
+
+    /// Whether the output can be of any length; KMAC, TupleHash and ParallelHash take
+    /// any length too, but their output depends on it.
+    pub fn is_xof(&self) -> bool {
+        matches!(self,
+            Algorithm::Shake(_)
+            | Algorithm::CShake(_)
+            | Algorithm::KmacXof(_)
+            | Algorithm::TupleHashXof(_)
+            | Algorithm::ParallelHashXof(_))
+    }
+
+    pub fn is_keyed(&self) -> bool {
+        matches!(self, Algorithm::Kmac(_) | Algorithm::KmacXof(_) | Algorithm::HmacSha3(_))
+    }
+
+    /// Creates a hasher; the options that do not apply to the algorithm are ignored.
+    #[cfg(feature = "alloc")]
+    pub fn new_hasher(&self, options: &HasherOptions) -> Box<dyn DynHasher> {
+        let state = match *self {
+            Algorithm::Sha3(sha3_variant) => HasherState::Sha3(Sha3::new(sha3_variant)),
+            Algorithm::Shake(shake_variant) => HasherState::Shake(Shake::new(shake_variant)),
+            Algorithm::CShake(shake_variant) => HasherState::CShake(
+                CShake::new(shake_variant, options.function_name, options.customization)),
+            Algorithm::Kmac(shake_variant) | Algorithm::KmacXof(shake_variant) => HasherState::Kmac(
+                Kmac::new(shake_variant, options.key, options.customization)),
+            Algorithm::TupleHash(shake_variant) | Algorithm::TupleHashXof(shake_variant) => HasherState::TupleHash(
+                TupleHash::new(shake_variant, options.customization)),
+            Algorithm::ParallelHash(shake_variant) | Algorithm::ParallelHashXof(shake_variant) => HasherState::ParallelHash(
+                ParallelHash::new(shake_variant, options.block_size, options.customization)),
+            Algorithm::HmacSha3(sha3_variant) => HasherState::HmacSha3(HmacSha3::new(sha3_variant, options.key)),
+            Algorithm::Keccak(sha3_variant) => HasherState::Keccak(Keccak::new(sha3_variant)),
+        };
+        Box::new(RegistryHasher { algorithm: *self, state })
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+
+/// Default block size B of ParallelHash, in bytes.
+pub const DEFAULT_PARALLEL_HASH_BLOCK_SIZE: usize = 8192;
+
+/// Parameters of the algorithms that take more than a message.
+#[derive(Clone, Copy, Debug)]
+pub struct HasherOptions<'a> {
+    /// Key of KMAC and HMAC-SHA3.
+    pub key: &'a [u8],
+    /// Customization string S of the SP 800-185 functions.
+    pub customization: &'a [u8],
+    /// Function name N of cSHAKE.
+    pub function_name: &'a [u8],
+    /// Block size B of ParallelHash, in bytes.
+    pub block_size: usize,
+}
+
+impl Default for HasherOptions<'_> {
+    fn default() -> Self {
+        HasherOptions {
+            key: &[],
+            customization: &[],
+            function_name: &[],
+            block_size: DEFAULT_PARALLEL_HASH_BLOCK_SIZE,
+        }
+    }
+}
+
+
+/// Object-safe interface to the hashers of every algorithm.
+#[cfg(feature = "alloc")]
+pub trait DynHasher {
+    fn algorithm(&self) -> Algorithm;
+
+    /// Absorbs more of the message; for TupleHash, each call adds one item to the tuple.
+    fn update(&mut self, bytes: &[u8]);
+
+    /// Writes the output to `out`. For SHA3, Keccak and HMAC-SHA3 it must be `output_len() / 8`
+    /// bytes long; the other algorithms take any length.
+    fn finalize_into(self: Box<Self>, out: &mut [u8]);
+
+    fn box_clone(&self) -> Box<dyn DynHasher>;
+
+    /// Returns an output of the default length `output_len()`.
+    fn finalize(self: Box<Self>) -> ByteString {
+        let mut out = vec![0u8; self.algorithm().output_len() / 8];
+        self.finalize_into(&mut out);
+        ByteString::from(out)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Clone for Box<dyn DynHasher> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[derive(Clone)]
+enum HasherState {
+    Sha3(Sha3),
+    Shake(Shake),
+    CShake(CShake),
+    Kmac(Kmac),
+    TupleHash(TupleHash),
+    ParallelHash(ParallelHash),
+    HmacSha3(HmacSha3),
+    Keccak(Keccak),
+}
+
+#[cfg(feature = "alloc")]
+#[derive(Clone)]
+struct RegistryHasher {
+    algorithm: Algorithm,
+    state: HasherState,
+}
+
+#[cfg(feature = "alloc")]
+impl DynHasher for RegistryHasher {
+    fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        match &mut self.state {
+            HasherState::Sha3(sha3) => sha3.update(bytes),
+            HasherState::Shake(shake) => shake.update(bytes),
+            HasherState::CShake(cshake) => cshake.update(bytes),
+            HasherState::Kmac(kmac) => kmac.update(bytes),
+            HasherState::TupleHash(tuple_hash) => tuple_hash.push_item(bytes),
+            HasherState::ParallelHash(parallel_hash) => parallel_hash.update(bytes),
+            HasherState::HmacSha3(hmac) => hmac.update(bytes),
+            HasherState::Keccak(keccak) => keccak.update(bytes),
+        }
+    }
+
+    fn finalize_into(self: Box<Self>, out: &mut [u8]) {
+        let is_xof = self.algorithm.is_xof();
+        match self.state {
+            HasherState::Sha3(sha3) => sha3.digest_into(out),
+            HasherState::Shake(shake) => shake.finalize_xof().squeeze(out),
+            HasherState::CShake(cshake) => cshake.finalize_xof().squeeze(out),
+            HasherState::Kmac(kmac) if is_xof => kmac.finalize_xof().squeeze(out),
+            HasherState::Kmac(kmac) => kmac.finalize_into(out),
+            HasherState::TupleHash(tuple_hash) if is_xof => tuple_hash.finalize_xof().squeeze(out),
+            HasherState::TupleHash(tuple_hash) => tuple_hash.finalize_into(out),
+            HasherState::ParallelHash(parallel_hash) if is_xof => parallel_hash.finalize_xof().squeeze(out),
+            HasherState::ParallelHash(parallel_hash) => parallel_hash.finalize_into(out),
+            HasherState::HmacSha3(hmac) => hmac.finalize_into(out),
+            HasherState::Keccak(keccak) => keccak.digest_into(out),
+        }
+    }
+
+    fn box_clone(&self) -> Box<dyn DynHasher> {
+        Box::new(self.clone())
+    }
+}
+
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::sha3::hmac::hmac_sha3;
+    use crate::sha3::sha3::keccak_family;
+    use crate::sha3::sha3::sha3_family;
+    use crate::sha3::sha3::shake_family;
+    use crate::sha3::sp800_185::cshake;
+    use crate::sha3::sp800_185::kmac;
+    use crate::sha3::sp800_185::kmac_xof;
+    use crate::sha3::sp800_185::parallel_hash;
+    use crate::sha3::sp800_185::parallel_hash_xof;
+    use crate::sha3::sp800_185::tuple_hash;
+    use crate::sha3::sp800_185::tuple_hash_xof;
+
+    #[test]
+    fn test_names_and_oids_round_trip() {
+        // the functions exported by sha3, sp800_185 and hmac, with one entry per variant
+        let names = [
+            "SHA3-224", "SHA3-256", "SHA3-384", "SHA3-512", "SHAKE128", "SHAKE256",
+            "cSHAKE128", "cSHAKE256", "KMAC128", "KMAC256", "KMACXOF128", "KMACXOF256",
+            "TupleHash128", "TupleHash256", "TupleHashXOF128", "TupleHashXOF256",
+            "ParallelHash128", "ParallelHash256", "ParallelHashXOF128", "ParallelHashXOF256",
+            "HMAC-SHA3-224", "HMAC-SHA3-256", "HMAC-SHA3-384", "HMAC-SHA3-512",
+            "Keccak-224", "Keccak-256", "Keccak-384", "Keccak-512",
+        ];
+        assert_eq!(names, Algorithm::ALL.map(|algorithm| algorithm.name()));
+        for algorithm in Algorithm::ALL {
+            assert_eq!(Some(algorithm), Algorithm::from_name(algorithm.name()));
+            assert_eq!(Some(algorithm), Algorithm::from_name(&algorithm.name().to_lowercase()));
+            if let Some(oid) = algorithm.oid() {
+                assert_eq!(Some(algorithm), Algorithm::from_oid(oid.0));
+                assert_eq!(Some(algorithm), Algorithm::from_dotted_oid(&oid.to_string()));
+            }
+        }
+        assert_eq!(Some(Algorithm::Sha3(Sha3Variant::SHA3_256)), Algorithm::from_dotted_oid("2.16.840.1.101.3.4.2.8"));
+        assert_eq!(None, Algorithm::from_dotted_oid("2.16.840.1.101.3.4.2.8.1"));
+        assert_eq!(None, Algorithm::from_dotted_oid("2.16.840.1.101.3.4.2"));
+        assert_eq!(None, Algorithm::from_name("SHA3-257"));
+    }
+
+    #[test]
+    fn test_metadata() {
+        let sha3_256 = Algorithm::from_name("SHA3-256").unwrap();
+        assert_eq!((1088, 512, 256, 128, false), (sha3_256.rate(), sha3_256.capacity(), sha3_256.output_len(), sha3_256.security_strength(), sha3_256.is_xof()));
+        let shake128 = Algorithm::from_name("SHAKE128").unwrap();
+        assert_eq!((1344, 256, 256, 128, true), (shake128.rate(), shake128.capacity(), shake128.output_len(), shake128.security_strength(), shake128.is_xof()));
+        let kmac256 = Algorithm::from_name("KMAC256").unwrap();
+        assert_eq!((1088, 512, 512, 256, false, true), (kmac256.rate(), kmac256.capacity(), kmac256.output_len(), kmac256.security_strength(), kmac256.is_xof(), kmac256.is_keyed()));
+        let hmac = Algorithm::from_name("HMAC-SHA3-384").unwrap();
+        assert_eq!((832, 384, 384, true), (hmac.rate(), hmac.output_len(), hmac.security_strength(), hmac.is_keyed()));
+        let keccak256 = Algorithm::from_name("keccak-256").unwrap();
+        assert_eq!((1088, 512, 256, 128, false, false), (keccak256.rate(), keccak256.capacity(), keccak256.output_len(), keccak256.security_strength(), keccak256.is_xof(), keccak256.is_keyed()));
+    }
+
+    #[test]
+    fn test_dyn_hashers_match_one_shot_functions() {
+        let key = b"key";
+        let s = b"custom";
+        let options = HasherOptions { key, customization: s, function_name: b"fn", block_size: 3 };
+        let m = b"The quick brown fox";
+        for algorithm in Algorithm::ALL {
+            let mut hasher = algorithm.new_hasher(&options);
+            hasher.update(&m[..5]);
+            let copy = hasher.clone();
+            hasher.update(&m[5..]);
+            let len = algorithm.output_len();
+            let expected = match algorithm {
+                Algorithm::Sha3(_) => sha3_family(m, algorithm.capacity(), len),
+                Algorithm::Shake(_) => shake_family(m, algorithm.capacity(), len),
+                Algorithm::CShake(v) => cshake(v, m, len, b"fn", s),
+                Algorithm::Kmac(v) => kmac(v, key, m, len, s),
+                Algorithm::KmacXof(v) => kmac_xof(v, key, m, len, s),
+                Algorithm::TupleHash(v) => tuple_hash(v, &[&m[..5], &m[5..]], len, s),
+                Algorithm::TupleHashXof(v) => tuple_hash_xof(v, &[&m[..5], &m[5..]], len, s),
+                Algorithm::ParallelHash(v) => parallel_hash(v, m, 3, len, s),
+                Algorithm::ParallelHashXof(v) => parallel_hash_xof(v, m, 3, len, s),
+                Algorithm::HmacSha3(v) => hmac_sha3(v, key, m),
+                Algorithm::Keccak(_) => keccak_family(m, algorithm.capacity(), len),
+            };
+            assert_eq!(expected, hasher.finalize(), "{algorithm}");
+            assert_eq!(algorithm, copy.algorithm());
+        }
+    }
+}
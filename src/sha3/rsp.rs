@@ -0,0 +1,357 @@
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use derive_more::{Display, Error};
+
+use crate::sha3::types::ByteString;
+
+
+/// A parsed NIST CAVP response (.rsp) file.
+///
+/// The files start with a header of `#` comments, e.g.
+///     #  "SHA3-256 ShortMsg" information for "SHA3AllBytes1-28-16"
+/// followed by sections such as `[L = 256]`, each holding records separated by blank lines.
+pub struct RspFile {
+    pub header: Vec<String>,
+    /// Algorithm and test names found in the header, e.g. ("SHA3-256", "ShortMsg").
+    pub algorithm: Option<String>,
+    pub test_name: Option<String>,
+    pub sections: Vec<RspSection>,
+}
+
+/// A section opened by one or more bracketed lines such as `[L = 256]`.
+pub struct RspSection {
+    pub params: Vec<RspParam>,
+    pub records: Vec<RspRecord>,
+}
+
+/// A bracketed line: `[L = 256]` has a value, `[Tested for Output of byte oriented messages]` does not.
+pub struct RspParam {
+    pub name: String,
+    pub value: Option<String>,
+}
+
+/// A record together with the line at which it starts (1-based).
+pub struct RspRecord {
+    pub line: usize,
+    pub kind: RspRecordKind,
+}
+
+pub enum RspRecordKind {
+    /// `Len = .. / Msg = .. / MD = ..` (`Output` instead of `MD` for SHAKE).
+    /// `msg` holds the ceil(len / 8) bytes of the message, so the "00" given for Len = 0 is dropped.
+    Message { len: usize, msg: ByteString, md: ByteString },
+    /// `COUNT = .. / Outputlen = .. / Msg = .. / Output = ..` (SHAKE variable output tests).
+    VariableOutput { count: usize, output_len: usize, msg: ByteString, output: ByteString },
+    /// `Seed = ..` starting a Monte Carlo test (a lone `Msg = ..` in the SHAKE Monte files).
+    Seed { seed: ByteString },
+    /// `COUNT = .. / MD = ..` checkpoint of a Monte Carlo test (`Outputlen` and `Output` for SHAKE).
+    MonteCheckpoint { count: usize, output_len: Option<usize>, md: ByteString },
+}
+
+
+#[derive(Debug, Clone, PartialEq, Eq, Display, Error)]
+#[display("line {line}: {kind}")]
+pub struct RspError {
+    pub line: usize,
+    #[error(not(source))]
+    pub kind: RspErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Display)]
+pub enum RspErrorKind {
+    #[display("expected 'name = value'")]
+    MissingEquals,
+    #[display("unterminated section header")]
+    UnterminatedSection,
+    #[display("invalid hex string in field '{_0}'")]
+    InvalidHex(String),
+    #[display("invalid number in field '{_0}'")]
+    InvalidNumber(String),
+    #[display("duplicate field '{_0}'")]
+    DuplicateField(String),
+    #[display("missing field '{_0}'")]
+    MissingField(String),
+    #[display("unknown record with fields {_0}")]
+    UnknownRecord(String),
+    #[display("Len = {len} does not match a message of {msg_bytes} bytes")]
+    LengthMismatch { len: usize, msg_bytes: usize },
+}
+
+
+/// The `name = value` lines of the record being parsed.
+struct Fields {
+    line: usize,
+    fields: Vec<(String, String, usize)>,
+}
+
+impl Fields {
+    fn take(&mut self, name: &str) -> Option<(String, usize)> {
+        let pos = self.fields.iter().position(|(n, _, _)| n == name)?;
+        let (_, value, line) = self.fields.remove(pos);
+        Some((value, line))
+    }
+
+    fn has(&self, name: &str) -> bool {
+        self.fields.iter().any(|(n, _, _)| n == name)
+    }
+
+    fn error(&self, line: usize, kind: RspErrorKind) -> RspError {
+        RspError { line, kind }
+    }
+
+    fn number(&mut self, name: &str) -> Result<usize, RspError> {
+        let (value, line) = self.take(name)
+            .ok_or_else(|| self.error(self.line, RspErrorKind::MissingField(name.to_string())))?;
+        value.parse()
+            .map_err(|_| self.error(line, RspErrorKind::InvalidNumber(name.to_string())))
+    }
+
+    fn hex(&mut self, name: &str) -> Result<ByteString, RspError> {
+        let (value, line) = self.take(name)
+            .ok_or_else(|| self.error(self.line, RspErrorKind::MissingField(name.to_string())))?;
+        hex::decode(&value)
+            .map(ByteString::from)
+            .map_err(|_| self.error(line, RspErrorKind::InvalidHex(name.to_string())))
+    }
+
+    /// Digests are called `MD` in the SHA3 files and `Output` in the SHAKE files.
+    fn digest(&mut self) -> Result<ByteString, RspError> {
+        if self.has("Output") {
+            self.hex("Output")
+        } else {
+            self.hex("MD")
+        }
+    }
+
+    fn into_record(mut self) -> Result<RspRecord, RspError> {
+        let line = self.line;
+        let kind = if self.has("Len") {
+            let len = self.number("Len")?;
+            let mut msg = self.hex("Msg")?;
+            let msg_bytes = len.div_ceil(8);
+            // Len = 0 comes with "Msg = 00"
+            if msg.len() != msg_bytes && !(len == 0 && msg.len() == 1) {
+                return Err(self.error(line, RspErrorKind::LengthMismatch { len, msg_bytes: msg.len() }));
+            }
+            msg.truncate(msg_bytes);
+            let md = self.digest()?;
+            RspRecordKind::Message { len, msg, md }
+        } else if self.has("COUNT") {
+            let count = self.number("COUNT")?;
+            if self.has("Msg") {
+                let output_len = self.number("Outputlen")?;
+                let msg = self.hex("Msg")?;
+                let output = self.hex("Output")?;
+                RspRecordKind::VariableOutput { count, output_len, msg, output }
+            } else {
+                let output_len = match self.has("Outputlen") {
+                    true => Some(self.number("Outputlen")?),
+                    false => None,
+                };
+                let md = self.digest()?;
+                RspRecordKind::MonteCheckpoint { count, output_len, md }
+            }
+        } else if self.has("Seed") {
+            RspRecordKind::Seed { seed: self.hex("Seed")? }
+        } else if self.has("Msg") && self.fields.len() == 1 {
+            RspRecordKind::Seed { seed: self.hex("Msg")? }
+        } else {
+            let names: Vec<&str> = self.fields.iter().map(|(n, _, _)| n.as_str()).collect();
+            return Err(self.error(line, RspErrorKind::UnknownRecord(names.join(", "))));
+        };
+
+        if let Some((name, _, field_line)) = self.fields.first() {
+            return Err(self.error(*field_line, RspErrorKind::UnknownRecord(name.clone())));
+        }
+        Ok(RspRecord { line, kind })
+    }
+}
+
+
+/// Splits "name = value" around the first '='.
+fn split_assignment(s: &str) -> Option<(String, String)> {
+    let (name, value) = s.split_once('=')?;
+    Some((name.trim().to_string(), value.trim().to_string()))
+}
+
+/// Extracts ("SHA3-256", "ShortMsg") from `#  "SHA3-256 ShortMsg" information for "..."`.
+fn parse_info_line(comment: &str) -> Option<(String, String)> {
+    let (quoted, rest) = comment.trim().strip_prefix('"')?.split_once('"')?;
+    if !rest.trim_start().starts_with("information") {
+        return None;
+    }
+    let (algorithm, test_name) = quoted.rsplit_once(' ')?;
+    Some((algorithm.to_string(), test_name.to_string()))
+}
+
+pub fn parse_rsp(content: &str) -> Result<RspFile, RspError> {
+    let mut rsp = RspFile {
+        header: Vec::new(),
+        algorithm: None,
+        test_name: None,
+        sections: Vec::new(),
+    };
+    let mut current: Option<Fields> = None;
+
+    for (i, raw_line) in content.lines().enumerate() {
+        let line_no = i + 1;
+        let line = raw_line.trim();
+
+        // a record ends at the first line which is not a 'name = value' line
+        if (line.is_empty() || line.starts_with('#') || line.starts_with('['))
+            && let Some(fields) = current.take() {
+            rsp.push_record(fields.into_record()?);
+        }
+
+        if line.is_empty() {
+            continue;
+        } else if let Some(comment) = line.strip_prefix('#') {
+            if rsp.sections.is_empty() {
+                rsp.header.push(comment.trim().to_string());
+                if rsp.algorithm.is_none()
+                    && let Some((algorithm, test_name)) = parse_info_line(comment) {
+                    rsp.algorithm = Some(algorithm);
+                    rsp.test_name = Some(test_name);
+                }
+            }
+        } else if let Some(bracketed) = line.strip_prefix('[') {
+            let inner = bracketed.strip_suffix(']')
+                .ok_or(RspError { line: line_no, kind: RspErrorKind::UnterminatedSection })?;
+            let param = match split_assignment(inner) {
+                Some((name, value)) => RspParam { name, value: Some(value) },
+                None => RspParam { name: inner.trim().to_string(), value: None },
+            };
+            // consecutive bracketed lines belong to the same section
+            match rsp.sections.last_mut() {
+                Some(section) if section.records.is_empty() => section.params.push(param),
+                _ => rsp.sections.push(RspSection { params: vec![param], records: Vec::new() }),
+            }
+        } else {
+            let (name, value) = split_assignment(line)
+                .ok_or(RspError { line: line_no, kind: RspErrorKind::MissingEquals })?;
+            let fields = current.get_or_insert(Fields { line: line_no, fields: Vec::new() });
+            if fields.has(&name) {
+                return Err(RspError { line: line_no, kind: RspErrorKind::DuplicateField(name) });
+            }
+            fields.fields.push((name, value, line_no));
+        }
+    }
+
+    if let Some(fields) = current.take() {
+        rsp.push_record(fields.into_record()?);
+    }
+    Ok(rsp)
+}
+
+impl RspSection {
+    /// Returns the value of a bracketed parameter, e.g. param("L") for `[L = 256]`.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params.iter()
+            .find(|param| param.name == name)
+            .and_then(|param| param.value.as_deref())
+    }
+}
+
+impl RspFile {
+    /// Adds a record to the last section; records before any bracketed line get a section without params.
+    fn push_record(&mut self, record: RspRecord) {
+        if self.sections.is_empty() {
+            self.sections.push(RspSection { params: Vec::new(), records: Vec::new() });
+        }
+        self.sections.last_mut().unwrap().records.push(record);
+    }
+
+    pub fn records(&self) -> impl Iterator<Item = &RspRecord> {
+        self.sections.iter().flat_map(|section| section.records.iter())
+    }
+}
+
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    const SHORT_MSG: &str = "\
+#  CAVS 19.0
+#  \"SHA3-256 ShortMsg\" information for \"SHA3AllBytes1-28-16\"
+#  Length values represented in bits
+
+[L = 256]
+
+Len = 0
+Msg = 00
+MD = a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a
+
+Len = 8
+Msg = e9
+MD = f0d04dd1e6cfc29a4460d521796852f25d9ef8d28b44ee91ff5b759d72c1e6d6
+";
+
+    #[test]
+    fn test_parse_short_msg() {
+        let rsp = parse_rsp(SHORT_MSG).unwrap();
+        assert_eq!(Some("SHA3-256"), rsp.algorithm.as_deref());
+        assert_eq!(Some("ShortMsg"), rsp.test_name.as_deref());
+        assert_eq!(3, rsp.header.len());
+        assert_eq!(1, rsp.sections.len());
+        assert_eq!(Some("256"), rsp.sections[0].param("L"));
+
+        let records: Vec<&RspRecord> = rsp.records().collect();
+        assert_eq!(2, records.len());
+        assert_eq!(7, records[0].line);
+        match &records[0].kind {
+            RspRecordKind::Message { len, msg, md } => {
+                assert_eq!(0, *len);
+                assert!(msg.is_empty());
+                assert_eq!(32, md.len());
+            }
+            _ => panic!("expected a message record"),
+        }
+        match &records[1].kind {
+            RspRecordKind::Message { len, msg, .. } => {
+                assert_eq!(8, *len);
+                assert_eq!(&[0xe9], msg.as_slice());
+            }
+            _ => panic!("expected a message record"),
+        }
+    }
+
+    #[test]
+    fn test_parse_monte_and_variable_output() {
+        let monte = "[L = 224]\n\nSeed = 0102\n\nCOUNT = 0\nMD = aabb\n\nCOUNT = 1\nMD = ccdd\n";
+        let rsp = parse_rsp(monte).unwrap();
+        let records: Vec<&RspRecord> = rsp.records().collect();
+        assert!(matches!(&records[0].kind, RspRecordKind::Seed { seed } if seed.as_slice() == [1, 2]));
+        assert!(matches!(&records[2].kind, RspRecordKind::MonteCheckpoint { count: 1, output_len: None, md } if md.as_slice() == [0xcc, 0xdd]));
+
+        let variable_out = "[Tested for Output of byte oriented messages]\n[Input Length = 128]\n\n\
+            COUNT = 0\nOutputlen = 16\nMsg = 00ff\nOutput = 1234\n";
+        let rsp = parse_rsp(variable_out).unwrap();
+        assert_eq!(1, rsp.sections.len());
+        assert_eq!(2, rsp.sections[0].params.len());
+        assert_eq!(Some("128"), rsp.sections[0].param("Input Length"));
+        let records: Vec<&RspRecord> = rsp.records().collect();
+        assert!(matches!(&records[0].kind, RspRecordKind::VariableOutput { count: 0, output_len: 16, .. }));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = parse_rsp("[L = 256]\n\nLen = 8\nMsg = zz\nMD = 00\n").err().unwrap();
+        assert_eq!(RspError { line: 4, kind: RspErrorKind::InvalidHex("Msg".to_string()) }, err);
+
+        let err = parse_rsp("Len = 16\nMsg = 00\nMD = 00\n").err().unwrap();
+        assert_eq!(RspError { line: 1, kind: RspErrorKind::LengthMismatch { len: 16, msg_bytes: 1 } }, err);
+
+        let err = parse_rsp("Len = 8\nMsg = 00\n").err().unwrap();
+        assert_eq!(RspError { line: 1, kind: RspErrorKind::MissingField("MD".to_string()) }, err);
+
+        let err = parse_rsp("[L = 256\n").err().unwrap();
+        assert_eq!(RspError { line: 1, kind: RspErrorKind::UnterminatedSection }, err);
+
+        let err = parse_rsp("Len 8\n").err().unwrap();
+        assert_eq!("line 1: expected 'name = value'", err.to_string());
+    }
+}
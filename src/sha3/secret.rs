@@ -0,0 +1,146 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::fmt;
+use core::ptr;
+use core::sync::atomic::Ordering;
+use core::sync::atomic::compiler_fence;
+
+
+/// Types whose content can be wiped, e.g. because it holds key material.
+///
+/// The writes are volatile, so that the compiler cannot drop them as dead stores
+/// when the value is not read afterwards (typically, right before it is dropped).
+pub trait Zeroize {
+    fn zeroize(&mut self);
+}
+
+impl Zeroize for u8 {
+    fn zeroize(&mut self) {
+        // SAFETY: self is a valid, aligned and exclusive reference.
+        unsafe { ptr::write_volatile(self, 0) };
+    }
+}
+
+impl<T: Zeroize> Zeroize for [T] {
+    fn zeroize(&mut self) {
+        for el in self.iter_mut() {
+            el.zeroize();
+        }
+        compiler_fence(Ordering::SeqCst);
+    }
+}
+
+impl<T: Zeroize, const N: usize> Zeroize for [T; N] {
+    fn zeroize(&mut self) {
+        self.as_mut_slice().zeroize();
+    }
+}
+
+/// Wipes the elements, then empties the vector.
+#[cfg(feature = "alloc")]
+impl<T: Zeroize> Zeroize for Vec<T> {
+    fn zeroize(&mut self) {
+        self.as_mut_slice().zeroize();
+        self.clear();
+    }
+}
+
+
+/// Wrapper for secret values such as keys: the value is wiped on drop
+/// and is never shown by `Debug`.
+pub struct Secret<T: Zeroize>(T);
+
+impl<T: Zeroize> Secret<T> {
+    pub fn new(value: T) -> Self {
+        Secret(value)
+    }
+
+    pub fn expose_secret(&self) -> &T {
+        &self.0
+    }
+
+    /// Gives write access, e.g. to read a key straight into a buffer that is wiped on drop.
+    pub fn expose_secret_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: Zeroize + Clone> Clone for Secret<T> {
+    fn clone(&self) -> Self {
+        Secret(self.0.clone())
+    }
+}
+
+impl<T: Zeroize> From<T> for Secret<T> {
+    fn from(value: T) -> Self {
+        Secret::new(value)
+    }
+}
+
+impl<T: Zeroize> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret([REDACTED])")
+    }
+}
+
+impl<T: Zeroize> Drop for Secret<T> {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+
+/// Compares two byte strings in time that depends on their lengths but not on their
+/// content, e.g. to check a MAC tag without revealing the position of the first mismatch.
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut diff = 0u8;
+    for (x, y) in a.iter().zip(b) {
+        // SAFETY: diff is a valid, aligned local; the volatile read keeps the loop from
+        // being turned into an early exit.
+        diff = unsafe { ptr::read_volatile(&(diff | (x ^ y))) };
+    }
+    diff == 0
+}
+
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zeroize_array() {
+        let mut key = [0xAAu8; 32];
+        key.zeroize();
+        assert_eq!([0u8; 32], key);
+
+        let mut slices = [[[1u8; 5]; 5]; 4];
+        slices.zeroize();
+        assert_eq!([[[0u8; 5]; 5]; 4], slices);
+    }
+
+    #[test]
+    fn test_zeroize_vec() {
+        let mut key = vec![0xAAu8; 32];
+        key.zeroize();
+        assert!(key.is_empty());
+    }
+
+    #[test]
+    fn test_ct_eq() {
+        assert!(ct_eq(b"", b""));
+        assert!(ct_eq(b"tag", b"tag"));
+        assert!(!ct_eq(b"tag", b"tab"));
+        assert!(!ct_eq(b"tag", b"tags"));
+    }
+
+    #[test]
+    fn test_secret_debug_is_redacted() {
+        let secret = Secret::new(*b"my secret key");
+        let debug = format!("{secret:?}");
+        assert_eq!("Secret([REDACTED])", debug);
+        assert_eq!(b"my secret key", secret.expose_secret());
+    }
+}
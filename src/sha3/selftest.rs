@@ -0,0 +1,453 @@
+//! Power-on and continuous self-tests, in the style of FIPS 140.
+//!
+//! `self_test` runs known-answer tests (KATs) compiled into the binary: one on the
+//! KECCAK-f[1600] permutation, one for each `Sha3Variant` and SHAKE variant, and one for
+//! cSHAKE, KMAC, TupleHash, ParallelHash and each HMAC-SHA3. If it has not been called
+//! explicitly, it runs on the first use of the permutation.
+//!
+//! Every permutation goes through `keccak_p`, which checks the status first: once a KAT has
+//! failed, the module is in the error state and every call that would permute a state, and
+//! so every call that would output a digest, panics. Running `self_test` again is the only
+//! way out of the error state. While it runs, other threads that use the permutation wait
+//! for it to finish; only the thread running the KATs gets through. Without `std`, threads
+//! cannot be told apart, and a permutation during the self-test is taken to be one of its
+//! own.
+//!
+//! As a continuous test, the permutation KAT is run again every
+//! `CONTINUOUS_CHECK_INTERVAL` permutations.
+//!
+//! With the `fault-injection` feature, `inject_fault` corrupts the output of a chosen KAT,
+//! and `hold_self_test` keeps a self-test in the running state, so that the error state and
+//! the waiting threads can be tested.
+
+#[cfg(feature = "std")]
+use core::cell::Cell;
+#[cfg(feature = "fault-injection")]
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::AtomicU8;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering;
+
+use derive_more::{Display, Error};
+
+use crate::sha3::constants::KECCAK_B;
+use crate::sha3::constants::KECCAK_NR;
+use crate::sha3::hmac::HmacSha3;
+use crate::sha3::secret::ct_eq;
+use crate::sha3::sha3::Sha3;
+use crate::sha3::sha3::Shake;
+use crate::sha3::sha3::keccak_p_unchecked;
+use crate::sha3::sp800_185::CShake;
+use crate::sha3::sp800_185::Kmac;
+use crate::sha3::sp800_185::ParallelHash;
+use crate::sha3::sp800_185::TupleHash;
+use crate::sha3::types::Sha3Variant;
+use crate::sha3::types::ShakeVariant;
+use crate::sha3::utils::bits_to_bytes;
+
+
+/// Number of permutations between two runs of the permutation KAT.
+pub const CONTINUOUS_CHECK_INTERVAL: usize = 1 << 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SelfTestStatus {
+    /// No self-test has run yet; it runs on the first use of the permutation.
+    Untested,
+    Running,
+    Operational,
+    /// A KAT failed: every call that uses the permutation panics.
+    Error,
+}
+
+const UNTESTED: u8 = 0;
+const RUNNING: u8 = 1;
+const OPERATIONAL: u8 = 2;
+const ERROR: u8 = 3;
+
+static STATUS: AtomicU8 = AtomicU8::new(UNTESTED);
+static PERMUTATIONS: AtomicUsize = AtomicUsize::new(0);
+
+pub fn status() -> SelfTestStatus {
+    match STATUS.load(Ordering::Acquire) {
+        UNTESTED => SelfTestStatus::Untested,
+        RUNNING => SelfTestStatus::Running,
+        OPERATIONAL => SelfTestStatus::Operational,
+        _ => SelfTestStatus::Error,
+    }
+}
+
+
+/// The KAT that failed first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Display, Error)]
+#[display("self-test failed: {name} does not match its known answer")]
+pub struct SelfTestError {
+    #[error(not(source))]
+    pub name: &'static str,
+}
+
+/// A known-answer test: `run` fills an output of the length of `expected`.
+struct Kat {
+    name: &'static str,
+    expected: &'static str,
+    run: fn(&mut [u8]),
+}
+
+/// KECCAK-f[1600] applied to the all-zero state.
+const PERMUTATION_KAT: Kat = Kat {
+    name: "KECCAK-f[1600]",
+    expected: "e7dde140798f25f18a47c033f9ccd584eea95aa61e2698d54d49806f304715bd\
+               57d05362054e288bd46f8e7f2da497ffc44746a4a0e5fe90762e19d60cda5b8c\
+               9c05191bf7a630ad64fc8fd0b75a933035d617233fa95aeb0321710d26e6a6a9\
+               5f55cfdb167ca58126c84703cd31b8439f56a5111a2ff20161aed9215a63e505\
+               f270c98cf2febe641166c47b95703661cb0ed04f555a7cb8c832cf1c8ae83e8c\
+               14263aae22790c94e409c5a224f94118c26504e72635f5163ba1307fe944f675\
+               49a2ec5c7bfff1ea",
+    run: |out| {
+        let mut s = [0u8; KECCAK_B];
+        keccak_p_unchecked(KECCAK_B, KECCAK_NR, &mut s);
+        bits_to_bytes(&s, out);
+    },
+};
+
+fn sha3_abc(sha3_variant: Sha3Variant, out: &mut [u8]) {
+    let mut sha3 = Sha3::new(sha3_variant);
+    sha3.update(b"abc");
+    sha3.digest_into(out);
+}
+
+fn shake_abc(shake_variant: ShakeVariant, out: &mut [u8]) {
+    let mut shake = Shake::new(shake_variant);
+    shake.update(b"abc");
+    shake.finalize_xof().squeeze(out);
+}
+
+/// Key and message of the NIST HMAC-SHA3 examples with a key shorter than the block.
+const HMAC_KEY: [u8; 32] = {
+    let mut key = [0u8; 32];
+    let mut i = 0;
+    while i < key.len() {
+        key[i] = i as u8;
+        i += 1;
+    }
+    key
+};
+const HMAC_MSG: &[u8] = b"Sample message for keylen<blocklen";
+
+fn hmac_sha3(sha3_variant: Sha3Variant, out: &mut [u8]) {
+    let mut hmac = HmacSha3::new(sha3_variant, &HMAC_KEY);
+    hmac.update(HMAC_MSG);
+    hmac.finalize_into(out);
+}
+
+/// The KATs on the algorithms, run after the permutation KAT. The SP 800-185 ones are
+/// Sample #1 of the NIST examples of each function.
+const ALGORITHM_KATS: [Kat; 14] = [
+    Kat {
+        name: "SHA3-224",
+        expected: "e642824c3f8cf24ad09234ee7d3c766fc9a3a5168d0c94ad73b46fdf",
+        run: |out| sha3_abc(Sha3Variant::SHA3_224, out),
+    },
+    Kat {
+        name: "SHA3-256",
+        expected: "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532",
+        run: |out| sha3_abc(Sha3Variant::SHA3_256, out),
+    },
+    Kat {
+        name: "SHA3-384",
+        expected: "ec01498288516fc926459f58e2c6ad8df9b473cb0fc08c2596da7cf0e49be4b2\
+                   98d88cea927ac7f539f1edf228376d25",
+        run: |out| sha3_abc(Sha3Variant::SHA3_384, out),
+    },
+    Kat {
+        name: "SHA3-512",
+        expected: "b751850b1a57168a5693cd924b6b096e08f621827444f70d884f5d0240d2712e\
+                   10e116e9192af3c91a7ec57647e3934057340b4cf408d5a56592f8274eec53f0",
+        run: |out| sha3_abc(Sha3Variant::SHA3_512, out),
+    },
+    Kat {
+        name: "SHAKE128",
+        expected: "5881092dd818bf5cf8a3ddb793fbcba74097d5c526a6d35f97b83351940f2cc8",
+        run: |out| shake_abc(ShakeVariant::SHAKE128, out),
+    },
+    Kat {
+        name: "SHAKE256",
+        expected: "483366601360a8771c6863080cc4114d8db44530f8f1e1ee4f94ea37e78b5739\
+                   d5a15bef186a5386c75744c0527e1faa9f8726e462a12a4feb06bd8801e751e4",
+        run: |out| shake_abc(ShakeVariant::SHAKE256, out),
+    },
+    Kat {
+        name: "cSHAKE128",
+        expected: "c1c36925b6409a04f1b504fcbca9d82b4017277cb5ed2b2065fc1d3814d5aaf5",
+        run: |out| {
+            let mut cshake = CShake::new(ShakeVariant::SHAKE128, b"", b"Email Signature");
+            cshake.update(&[0x00, 0x01, 0x02, 0x03]);
+            cshake.finalize_xof().squeeze(out);
+        },
+    },
+    Kat {
+        name: "KMAC128",
+        expected: "e5780b0d3ea6f7d3a429c5706aa43a00fadbd7d49628839e3187243f456ee14e",
+        run: |out| {
+            let mut key = [0u8; 32];
+            for (i, byte) in key.iter_mut().enumerate() {
+                *byte = 0x40 + i as u8;
+            }
+            let mut kmac = Kmac::new(ShakeVariant::SHAKE128, &key, b"");
+            kmac.update(&[0x00, 0x01, 0x02, 0x03]);
+            kmac.finalize_into(out);
+        },
+    },
+    Kat {
+        name: "TupleHash128",
+        expected: "c5d8786c1afb9b82111ab34b65b2c0048fa64e6d48e263264ce1707d3ffc8ed1",
+        run: |out| {
+            let mut tuple_hash = TupleHash::new(ShakeVariant::SHAKE128, b"");
+            tuple_hash.push_item(&[0x00, 0x01, 0x02]);
+            tuple_hash.push_item(&[0x10, 0x11, 0x12, 0x13, 0x14, 0x15]);
+            tuple_hash.finalize_into(out);
+        },
+    },
+    Kat {
+        name: "ParallelHash128",
+        expected: "ba8dc1d1d979331d3f813603c67f72609ab5e44b94a0b8f9af46514454a2b4f5",
+        run: |out| {
+            let mut parallel_hash = ParallelHash::new(ShakeVariant::SHAKE128, 8, b"");
+            for block in [0x00u8, 0x10, 0x20] {
+                for i in 0..8 {
+                    parallel_hash.update(&[block + i]);
+                }
+            }
+            parallel_hash.finalize_into(out);
+        },
+    },
+    Kat {
+        name: "HMAC-SHA3-224",
+        expected: "7bf598119c2788783550195d105f6956986e0076bd2097e10c979c89",
+        run: |out| hmac_sha3(Sha3Variant::SHA3_224, out),
+    },
+    Kat {
+        name: "HMAC-SHA3-256",
+        expected: "4fe8e202c4f058e8dddc23d8c34e467343e23555e24fc2f025d598f558f67205",
+        run: |out| hmac_sha3(Sha3Variant::SHA3_256, out),
+    },
+    Kat {
+        name: "HMAC-SHA3-384",
+        expected: "0c3b82c4b2d0c728dd73e65460d605e3e3f0f1740516225c17478a32d6d3bbb8\
+                   ddd8ae2af6543c3c62da12d9b7cd3766",
+        run: |out| hmac_sha3(Sha3Variant::SHA3_384, out),
+    },
+    Kat {
+        name: "HMAC-SHA3-512",
+        expected: "45c37e949cce1eb50ccf6c96439c06e25f4a4416a99a8a8959593aefb8ef584e\
+                   b0704dc5855faae16196792f4437cdef36d8467b037303ecf62584a4ccc18ddf",
+        run: |out| hmac_sha3(Sha3Variant::SHA3_512, out),
+    },
+];
+
+/// Names of all KATs, in the order in which `self_test` runs them.
+pub fn kat_names() -> impl Iterator<Item = &'static str> {
+    core::iter::once(PERMUTATION_KAT.name).chain(ALGORITHM_KATS.iter().map(|kat| kat.name))
+}
+
+fn run_kat(kat: &Kat) -> Result<(), SelfTestError> {
+    let mut expected = [0u8; KECCAK_B / 8];
+    let mut actual = [0u8; KECCAK_B / 8];
+    let len = kat.expected.len() / 2;
+    hex::decode_to_slice(kat.expected, &mut expected[..len]).expect("embedded KATs are valid hex");
+    (kat.run)(&mut actual[..len]);
+    if is_fault_injected(kat.name) {
+        actual[0] ^= 1;
+    }
+    if ct_eq(&actual[..len], &expected[..len]) {
+        Ok(())
+    } else {
+        Err(SelfTestError { name: kat.name })
+    }
+}
+
+#[cfg(feature = "std")]
+std::thread_local! {
+    /// Whether this thread runs the KATs of a self-test.
+    static IN_SELF_TEST: Cell<bool> = const { Cell::new(false) };
+}
+
+#[cfg(feature = "std")]
+fn is_self_test_thread() -> bool {
+    IN_SELF_TEST.with(Cell::get)
+}
+
+#[cfg(not(feature = "std"))]
+fn is_self_test_thread() -> bool {
+    true
+}
+
+/// Lets other threads run while this one waits.
+fn pause() {
+    #[cfg(feature = "std")]
+    std::thread::yield_now();
+    #[cfg(not(feature = "std"))]
+    core::hint::spin_loop();
+}
+
+fn wait_for_self_test() {
+    #[cfg(feature = "fault-injection")]
+    WAITS.fetch_add(1, Ordering::Relaxed);
+    pause();
+}
+
+/// Marks the current thread as the one running the KATs, and moves the module to the error
+/// state if a KAT panics.
+struct SelfTestGuard;
+
+impl SelfTestGuard {
+    fn new() -> Self {
+        #[cfg(feature = "std")]
+        IN_SELF_TEST.with(|flag| flag.set(true));
+        SelfTestGuard
+    }
+}
+
+impl Drop for SelfTestGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "std")]
+        IN_SELF_TEST.with(|flag| flag.set(false));
+        let _ = STATUS.compare_exchange(RUNNING, ERROR, Ordering::AcqRel, Ordering::Acquire);
+    }
+}
+
+/// Runs all KATs once the caller has moved the module to the running state.
+fn run_self_test() -> Result<(), SelfTestError> {
+    let _guard = SelfTestGuard::new();
+    #[cfg(feature = "fault-injection")]
+    while HOLD.load(Ordering::Acquire) {
+        pause();
+    }
+    let result = core::iter::once(&PERMUTATION_KAT)
+        .chain(ALGORITHM_KATS.iter())
+        .try_for_each(run_kat);
+    STATUS.store(if result.is_ok() { OPERATIONAL } else { ERROR }, Ordering::Release);
+    result
+}
+
+/// Runs all KATs, stopping at the first mismatch, and moves the module to the operational
+/// or the error state accordingly. If another thread is running a self-test, waits for it to
+/// finish first.
+pub fn self_test() -> Result<(), SelfTestError> {
+    loop {
+        let current = STATUS.load(Ordering::Acquire);
+        if current == RUNNING && !is_self_test_thread() {
+            wait_for_self_test();
+        } else if STATUS.compare_exchange(current, RUNNING, Ordering::AcqRel, Ordering::Acquire).is_ok() {
+            return run_self_test();
+        }
+    }
+}
+
+/// Called by `keccak_p` before each permutation: runs the power-on self-test on first use,
+/// the permutation KAT every `CONTINUOUS_CHECK_INTERVAL` permutations, waits while another
+/// thread runs a self-test, and panics in the error state.
+pub(crate) fn check_permutation() {
+    loop {
+        match STATUS.load(Ordering::Acquire) {
+            OPERATIONAL => {
+                let count = PERMUTATIONS.fetch_add(1, Ordering::Relaxed);
+                if count % CONTINUOUS_CHECK_INTERVAL == CONTINUOUS_CHECK_INTERVAL - 1
+                    && let Err(e) = run_kat(&PERMUTATION_KAT) {
+                    STATUS.store(ERROR, Ordering::Release);
+                    panic!("{e}");
+                }
+                return;
+            }
+            RUNNING if is_self_test_thread() => return,
+            RUNNING => wait_for_self_test(),
+            UNTESTED => {
+                let is_first = STATUS
+                    .compare_exchange(UNTESTED, RUNNING, Ordering::AcqRel, Ordering::Acquire)
+                    .is_ok();
+                if is_first {
+                    if let Err(e) = run_self_test() {
+                        panic!("{e}");
+                    }
+                    return;
+                }
+            }
+            _ => panic!("the SHA-3 module is in the error state after a failed self-test"),
+        }
+    }
+}
+
+
+#[cfg(feature = "fault-injection")]
+static INJECTED_FAULT: AtomicUsize = AtomicUsize::new(0);
+
+/// Makes the KAT called `name` (one of `kat_names()`) see a corrupted output from now on,
+/// or removes the fault with `None`. Returns false if there is no KAT called `name`.
+#[cfg(feature = "fault-injection")]
+pub fn inject_fault(name: Option<&str>) -> bool {
+    let index = match name {
+        None => 0,
+        Some(name) => match kat_names().position(|kat_name| kat_name == name) {
+            Some(i) => i + 1,
+            None => return false,
+        },
+    };
+    INJECTED_FAULT.store(index, Ordering::Release);
+    true
+}
+
+#[cfg(feature = "fault-injection")]
+fn is_fault_injected(name: &str) -> bool {
+    match INJECTED_FAULT.load(Ordering::Acquire) {
+        0 => false,
+        index => kat_names().nth(index - 1) == Some(name),
+    }
+}
+
+#[cfg(not(feature = "fault-injection"))]
+fn is_fault_injected(_name: &str) -> bool {
+    false
+}
+
+#[cfg(feature = "fault-injection")]
+static HOLD: AtomicBool = AtomicBool::new(false);
+
+#[cfg(feature = "fault-injection")]
+static WAITS: AtomicUsize = AtomicUsize::new(0);
+
+/// Keeps the self-tests in the running state, before their first KAT, until called again
+/// with false.
+#[cfg(feature = "fault-injection")]
+pub fn hold_self_test(hold: bool) {
+    HOLD.store(hold, Ordering::Release);
+}
+
+/// Number of times a thread has waited for another one to finish a self-test.
+#[cfg(feature = "fault-injection")]
+pub fn self_test_waits() -> usize {
+    WAITS.load(Ordering::Relaxed)
+}
+
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_self_test() {
+        assert_eq!(Ok(()), self_test());
+        assert_eq!(SelfTestStatus::Operational, status());
+    }
+
+    #[test]
+    fn test_kats_are_well_formed() {
+        let names: Vec<_> = kat_names().collect();
+        assert_eq!(15, names.len());
+        for (i, name) in names.iter().enumerate() {
+            assert!(!names[..i].contains(name), "duplicate KAT {name}");
+        }
+        for kat in core::iter::once(&PERMUTATION_KAT).chain(ALGORITHM_KATS.iter()) {
+            assert!(kat.expected.len() % 2 == 0 && kat.expected.len() <= KECCAK_B / 4, "{}", kat.name);
+            assert!(hex::decode(kat.expected).is_ok(), "{}", kat.name);
+        }
+    }
+}
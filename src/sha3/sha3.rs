@@ -1,31 +1,61 @@
-
+#[cfg(feature = "alloc")]
+use alloc::vec;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io;
+
+#[cfg(feature = "alloc")]
+use crate::sha3::checkpoint::CHECKPOINT_MAX_LEN;
+use crate::sha3::checkpoint::CHECKPOINT_STATE_LEN;
+use crate::sha3::checkpoint::CheckpointError;
+use crate::sha3::checkpoint::CheckpointFields;
+use crate::sha3::checkpoint::CheckpointKind;
+use crate::sha3::checkpoint::decode_checkpoint;
+use crate::sha3::checkpoint::encode_checkpoint;
 use crate::sha3::constants::KECCAK_B;
+use crate::sha3::constants::KECCAK_MAX_W;
 use crate::sha3::constants::KECCAK_NR;
 use crate::sha3::constants::RHO_OFFSETS;
 use crate::sha3::constants::get_el_from_b;
-use crate::sha3::constants::get_w_from_b;
+use crate::sha3::secret::Zeroize;
+use crate::sha3::selftest::check_permutation;
+#[cfg(feature = "alloc")]
 use crate::sha3::types::ByteString;
 use crate::sha3::types::Sha3Variant;
+use crate::sha3::types::ShakeVariant;
 use crate::sha3::types::State;
+#[cfg(feature = "alloc")]
 use crate::sha3::types::BitString;
 use crate::sha3::types::new_plane;
-use crate::sha3::types::new_state;
+#[cfg(feature = "alloc")]
 use crate::sha3::utils::bitstring_to_bytestr;
+use crate::sha3::utils::bits_to_bytes;
 use crate::sha3::utils::bitstring_to_state;
+#[cfg(feature = "alloc")]
 use crate::sha3::utils::bytestr_to_bitstring;
+use crate::sha3::utils::bytes_to_bits;
+#[cfg(feature = "alloc")]
 use crate::sha3::utils::concat_bitstrings;
-use crate::sha3::utils::debug_state_as_bytes;
-use crate::sha3::utils::debug_state_as_lanes_of_integers;
+#[cfg(feature = "alloc")]
 use crate::sha3::utils::new_bitstring;
-use crate::sha3::utils::prepend_zero;
-use crate::sha3::utils::state_to_bitstring;
+use crate::sha3::utils::state_to_bits;
+#[cfg(feature = "alloc")]
 use crate::sha3::utils::trunc;
+#[cfg(feature = "alloc")]
 use crate::sha3::utils::xor_bitstrings;
 
 
+/// Largest digest produced by the SHA3 family (SHA3-512), in bytes.
+pub const SHA3_MAX_DIGEST_BYTES: usize = 64;
+
+/// Incremental SHA3 hasher: the message can be fed in pieces via `update`.
+#[derive(Clone)]
 pub struct Sha3 {
     sha3_variant: Sha3Variant,
+    sponge: Sponge,
     is_finalized: bool,
+    digest: [u8; SHA3_MAX_DIGEST_BYTES],
 }
 
 
@@ -33,32 +63,415 @@ impl Sha3 {
     pub fn new(sha3_variant: Sha3Variant) -> Self {
         Sha3 {
             sha3_variant,
+            sponge: Sponge::new(sha3_variant.capacity()),
             is_finalized: false,
+            digest: [0u8; SHA3_MAX_DIGEST_BYTES],
         }
     }
 
-    pub fn update(&mut self, bytestr: &ByteString) {
+    pub fn variant(&self) -> Sha3Variant {
+        self.sha3_variant
+    }
+
+    pub fn update(&mut self, bytestr: &[u8]) {
         assert!(!self.is_finalized);
-        todo!()
+        self.sponge.absorb_bytes(bytestr);
     }
 
+    /// Appends the "01" suffix, pads the last block and squeezes the digest.
     pub fn finalize(&mut self) {
         assert!(!self.is_finalized);
         self.is_finalized = true;
-        todo!()
+        self.sponge.absorb_bits(&[0, 1]);
+        self.sponge.pad();
+        let digest_len = self.sha3_variant.output_len() / 8;
+        self.sponge.squeeze(&mut self.digest[..digest_len]);
+    }
+
+    /// Writes the digest to `out`, which must be exactly `output_len() / 8` bytes long,
+    /// finalizing the hasher first if needed.
+    pub fn digest_into(mut self, out: &mut [u8]) {
+        if !self.is_finalized {
+            self.finalize();
+        }
+        assert_eq!(out.len(), self.sha3_variant.output_len() / 8);
+        out.copy_from_slice(&self.digest[..out.len()]);
+    }
+
+    /// Returns the digest, finalizing the hasher first if needed.
+    #[cfg(feature = "alloc")]
+    pub fn digest(self) -> ByteString {
+        let mut digest = ByteString::from(vec![0u8; self.sha3_variant.output_len() / 8]);
+        self.digest_into(&mut digest);
+        digest
+    }
+
+    /// Writes a checkpoint of the hasher (see the `checkpoint` module) to `out`,
+    /// which must hold at least `CHECKPOINT_MAX_LEN` bytes, and returns its length.
+    pub fn write_checkpoint(&self, out: &mut [u8]) -> usize {
+        assert!(!self.is_finalized);
+        self.sponge.write_checkpoint(CheckpointKind::Sha3(self.sha3_variant), out)
+    }
+
+    #[cfg(feature = "alloc")]
+    pub fn checkpoint(&self) -> ByteString {
+        checkpoint_to_bytestr(|out| self.write_checkpoint(out))
+    }
+
+    /// Resumes hashing from a checkpoint taken from any SHA3 variant.
+    pub fn from_checkpoint(blob: &[u8]) -> Result<Sha3, CheckpointError> {
+        let fields = decode_checkpoint(blob)?;
+        match fields.kind {
+            CheckpointKind::Sha3(sha3_variant) => Ok(Sha3 {
+                sha3_variant,
+                sponge: Sponge::from_checkpoint(&fields),
+                is_finalized: false,
+                digest: [0u8; SHA3_MAX_DIGEST_BYTES],
+            }),
+            kind => Err(CheckpointError::WrongAlgorithm(kind)),
+        }
+    }
+}
+
+impl Drop for Sha3 {
+    fn drop(&mut self) {
+        self.digest.zeroize();
+    }
+}
+
+#[cfg(feature = "std")]
+impl io::Write for Sha3 {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
     }
 
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+
+/// Incremental hasher for the Keccak submission that predates FIPS 202 (e.g. Ethereum's
+/// Keccak-256): the same sponges as SHA3, but the message is padded without a suffix.
+#[derive(Clone)]
+pub struct Keccak {
+    sha3_variant: Sha3Variant,
+    sponge: Sponge,
+}
+
+impl Keccak {
+    /// The variant only selects the capacity and digest length, e.g. SHA3_256 for Keccak-256.
+    pub fn new(sha3_variant: Sha3Variant) -> Self {
+        Keccak {
+            sha3_variant,
+            sponge: Sponge::new(sha3_variant.capacity()),
+        }
+    }
+
+    pub fn variant(&self) -> Sha3Variant {
+        self.sha3_variant
+    }
+
+    pub fn update(&mut self, bytestr: &[u8]) {
+        self.sponge.absorb_bytes(bytestr);
+    }
+
+    /// Pads the last block and writes the digest to `out`, which must be exactly
+    /// `output_len() / 8` bytes long.
+    pub fn digest_into(mut self, out: &mut [u8]) {
+        assert_eq!(out.len(), self.sha3_variant.output_len() / 8);
+        self.sponge.pad();
+        self.sponge.squeeze(out);
+    }
+
+    #[cfg(feature = "alloc")]
     pub fn digest(self) -> ByteString {
-        todo!()
+        let mut digest = ByteString::from(vec![0u8; self.sha3_variant.output_len() / 8]);
+        self.digest_into(&mut digest);
+        digest
+    }
+}
+
+#[cfg(feature = "std")]
+impl io::Write for Keccak {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+
+/// Incremental SHAKE hasher; once all input is absorbed, `finalize_xof` turns it into
+/// a `ShakeReader` from which an output of arbitrary length can be read.
+#[derive(Clone)]
+pub struct Shake {
+    shake_variant: ShakeVariant,
+    sponge: Sponge,
+}
+
+impl Shake {
+    pub fn new(shake_variant: ShakeVariant) -> Self {
+        Shake {
+            shake_variant,
+            sponge: Sponge::new(shake_variant.capacity()),
+        }
+    }
+
+    pub fn variant(&self) -> ShakeVariant {
+        self.shake_variant
+    }
+
+    pub fn update(&mut self, bytestr: &[u8]) {
+        self.sponge.absorb_bytes(bytestr);
+    }
+
+    /// Writes a checkpoint of the hasher (see the `checkpoint` module) to `out`,
+    /// which must hold at least `CHECKPOINT_MAX_LEN` bytes, and returns its length.
+    pub fn write_checkpoint(&self, out: &mut [u8]) -> usize {
+        self.sponge.write_checkpoint(CheckpointKind::Shake(self.shake_variant), out)
+    }
+
+    #[cfg(feature = "alloc")]
+    pub fn checkpoint(&self) -> ByteString {
+        checkpoint_to_bytestr(|out| self.write_checkpoint(out))
+    }
+
+    /// Resumes absorbing from a checkpoint taken from any SHAKE variant.
+    pub fn from_checkpoint(blob: &[u8]) -> Result<Shake, CheckpointError> {
+        let fields = decode_checkpoint(blob)?;
+        match fields.kind {
+            CheckpointKind::Shake(shake_variant) => Ok(Shake {
+                shake_variant,
+                sponge: Sponge::from_checkpoint(&fields),
+            }),
+            kind => Err(CheckpointError::WrongAlgorithm(kind)),
+        }
+    }
+
+    /// Appends the "1111" suffix and pads the last block.
+    pub fn finalize_xof(mut self) -> ShakeReader {
+        self.sponge.absorb_bits(&[1, 1, 1, 1]);
+        self.sponge.pad();
+        ShakeReader::from_sponge(self.sponge)
+    }
+}
+
+#[cfg(feature = "alloc")]
+fn checkpoint_to_bytestr(write_checkpoint: impl Fn(&mut [u8]) -> usize) -> ByteString {
+    let mut out = [0u8; CHECKPOINT_MAX_LEN];
+    let len = write_checkpoint(&mut out);
+    let checkpoint = ByteString::from(out[..len].to_vec());
+    out.zeroize();
+    checkpoint
+}
+
+#[cfg(feature = "std")]
+impl io::Write for Shake {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+
+/// Squeezing phase of a SHAKE sponge, or of one of the SP 800-185 XOFs built on it;
+/// reads of any size can be mixed.
+#[derive(Clone)]
+pub struct ShakeReader {
+    sponge: Sponge,
+}
+
+impl ShakeReader {
+    /// Wraps a sponge that has been padded and is ready to squeeze.
+    pub(crate) fn from_sponge(sponge: Sponge) -> Self {
+        ShakeReader { sponge }
+    }
+
+    /// Fills `out` with the next `out.len()` bytes of the output stream.
+    pub fn squeeze(&mut self, out: &mut [u8]) {
+        self.sponge.squeeze(out);
+    }
+
+    /// Discards the next `len` bytes of the output stream; only the permutations are run.
+    pub fn skip(&mut self, len: u64) {
+        self.sponge.skip(len);
+    }
+}
+
+#[cfg(feature = "std")]
+impl io::Read for ShakeReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.squeeze(buf);
+        Ok(buf.len())
+    }
+}
+
+
+/// SPONGE[KECCAK-p[1600, 24], pad10*1, 1600 – c] (Alg. 8) split into its absorbing and
+/// squeezing phases, so that the input does not need to be available all at once.
+///
+/// Bits are buffered until a full block of r bits is collected; `absorbed` counts
+/// the message bytes. When squeezing, `squeezed` counts the bytes of Trunc_r(S)
+/// already handed out.
+#[derive(Clone)]
+pub(crate) struct Sponge {
+    rate: usize,
+    s: [u8; KECCAK_B],
+    buffer: [u8; KECCAK_B],
+    buffered: usize,
+    absorbed: u64,
+    is_squeezing: bool,
+    squeezed: usize,
+}
+
+impl Sponge {
+    pub(crate) fn new(keccak_c: usize) -> Self {
+        assert!(KECCAK_B > keccak_c);
+        let rate = KECCAK_B - keccak_c;
+        Sponge {
+            rate,
+            s: [0u8; KECCAK_B],
+            buffer: [0u8; KECCAK_B],
+            buffered: 0,
+            absorbed: 0,
+            is_squeezing: false,
+            squeezed: 0,
+        }
+    }
+
+    pub(crate) fn absorb_bytes(&mut self, bytes: &[u8]) {
+        let mut bits = [0u8; 8];
+        for byte in bytes {
+            bytes_to_bits(&[*byte], &mut bits);
+            self.absorb_bits(&bits);
+        }
+        bits.zeroize();
+        self.absorbed += bytes.len() as u64;
+    }
+
+    pub(crate) fn absorb_bits(&mut self, bits: &[u8]) {
+        assert!(!self.is_squeezing);
+        for &bit in bits {
+            self.buffer[self.buffered] = bit;
+            self.buffered += 1;
+            if self.buffered == self.rate {
+                self.absorb_block();
+            }
+        }
+    }
+
+    /// Step 6 of SPONGE for a single block Pi: S = f(S xor (Pi || 0^c)).
+    fn absorb_block(&mut self) {
+        for i in 0..self.rate {
+            self.s[i] ^= self.buffer[i];
+        }
+        keccak_p(KECCAK_B, KECCAK_NR, &mut self.s);
+        self.buffered = 0;
+    }
+
+    /// Appends pad10*1 to the buffered bits and switches to squeezing.
+    pub(crate) fn pad(&mut self) {
+        let j = pad101_zeros(self.rate, self.buffered);
+        self.absorb_bits(&[1]);
+        for _ in 0..j {
+            self.absorb_bits(&[0]);
+        }
+        self.absorb_bits(&[1]);
+        assert_eq!(self.buffered, 0);
+        self.is_squeezing = true;
+    }
+
+    fn write_checkpoint(&self, kind: CheckpointKind, out: &mut [u8]) -> usize {
+        assert!(!self.is_squeezing);
+        // only whole bytes are absorbed before the suffix is appended
+        assert!(self.buffered.is_multiple_of(8));
+        let buffered_len = self.buffered / 8;
+        let mut state = [0u8; CHECKPOINT_STATE_LEN];
+        let mut buffered = [0u8; KECCAK_B / 8];
+        bits_to_bytes(&self.s, &mut state);
+        bits_to_bytes(&self.buffer[..self.buffered], &mut buffered[..buffered_len]);
+
+        let fields = CheckpointFields {
+            kind,
+            absorbed: self.absorbed,
+            state: &state,
+            buffered: &buffered[..buffered_len],
+        };
+        let len = encode_checkpoint(&fields, out);
+        state.zeroize();
+        buffered.zeroize();
+        len
+    }
+
+    fn from_checkpoint(fields: &CheckpointFields) -> Self {
+        let mut sponge = Sponge::new(fields.kind.capacity());
+        let buffered = 8 * fields.buffered.len();
+        bytes_to_bits(fields.state, &mut sponge.s);
+        bytes_to_bits(fields.buffered, &mut sponge.buffer[..buffered]);
+        sponge.buffered = buffered;
+        sponge.absorbed = fields.absorbed;
+        sponge
+    }
+
+    /// Wipes the state and the buffered bits.
+    fn zeroize(&mut self) {
+        self.s.zeroize();
+        self.buffer.zeroize();
+        self.buffered = 0;
+    }
+
+    /// Fills `out` with the next bytes of Z (Steps 7-10 of SPONGE): the bytes of Trunc_r(S)
+    /// are handed out first, then S is updated with f once they are used up.
+    pub(crate) fn squeeze(&mut self, out: &mut [u8]) {
+        assert!(self.is_squeezing);
+        assert!(self.rate.is_multiple_of(8));
+        for byte in out.iter_mut() {
+            if self.squeezed == self.rate / 8 {
+                keccak_p(KECCAK_B, KECCAK_NR, &mut self.s);
+                self.squeezed = 0;
+            }
+            let bits = &self.s[8*self.squeezed..8*self.squeezed + 8];
+            bits_to_bytes(bits, core::slice::from_mut(byte));
+            self.squeezed += 1;
+        }
+    }
+
+    pub(crate) fn skip(&mut self, mut len: u64) {
+        assert!(self.is_squeezing);
+        let block_len = self.rate / 8;
+        while len > 0 {
+            if self.squeezed == block_len {
+                keccak_p(KECCAK_B, KECCAK_NR, &mut self.s);
+                self.squeezed = 0;
+            }
+            let step = len.min((block_len - self.squeezed) as u64);
+            self.squeezed += step as usize;
+            len -= step;
+        }
+    }
+}
+
+impl Drop for Sponge {
+    fn drop(&mut self) {
+        self.zeroize();
     }
 }
 
 /// 1st transformation function (Alg 1., p.11)
-fn theta(a: &mut State) {
+pub(crate) fn theta(a: &mut State) {
     let w = a.len();
 
     //Step 1.
-    let mut c = new_plane(w);
+    let mut c = new_plane();
     for x in 0..5 {
         for z in 0..w {
             c[z][x] = a[z][x][0] ^ a[z][x][1] ^ a[z][x][2] ^ a[z][x][3] ^ a[z][x][4];
@@ -66,10 +479,10 @@ fn theta(a: &mut State) {
     }
 
     // Step 2.
-    let mut d = new_plane(w);
+    let mut d = new_plane();
     for x in 0..5 {
         for z in 0..w {
-            d[z][x] = c[z][(x+4) % 5] ^ c[(z-1+w) % w][(x+1) % 5];
+            d[z][x] = c[z][(x+4) % 5] ^ c[(z+w-1) % w][(x+1) % 5];
         }
     }
 
@@ -81,40 +494,44 @@ fn theta(a: &mut State) {
             }
         }
     }        
+
+    c.zeroize();
+    d.zeroize();
 }
 
 
 /// 2nd transformation function (Alg 2., p.12)
-fn rho(a: &State) -> State {
+///
+/// The state is updated in place: each lane is copied to a temporary lane,
+/// which is wiped afterwards, instead of building a whole new state A'.
+pub(crate) fn rho(a: &mut State) {
     let w = a.len();
-    let mut cache = new_state(w);
-    
-    //Step 1.
-    for z in 0..w {
-        cache[z][0][0] = a[z][0][0];
-    }
+    let mut lane = [0u8; KECCAK_MAX_W];
+
+    //Step 1. A'[0,0,z] = A[0,0,z], i.e. lane (0,0) is left as is
 
     //Step 2.
     let (mut x, mut y) = (1, 0);
     
     //Step 3.  For t from 0 to 23:
     for _t in 0..24 {
+        for z in 0..w {
+            lane[z] = a[z][x][y];
+        }
         for z in 0..w {            
-            // z1 = z - (t+1)*(t+2)/2 mod w            
-            // for sha3 with w=1600, this is equivalent to the line below
-            let z1 = (z - RHO_OFFSETS[x][y] + w) % w;
-            cache[z][x][y] = a[z1][x][y];
+            // z1 = z - (t+1)*(t+2)/2 mod w, where (t+1)*(t+2)/2 is tabulated in RHO_OFFSETS
+            let z1 = (z + w - RHO_OFFSETS[x][y] % w) % w;
+            a[z][x][y] = lane[z1];
         }
         (x, y) = (y, (2*x + 3*y) % 5);
     }
 
-    // Step 3. Return A1
-    cache
+    lane.zeroize();
 }
 
 
 /// 3rd transformation (Alg 3.)
-fn pi(a: &mut State) {
+pub(crate) fn pi(a: &mut State) {
     let w = a.len();
     let mut cache = [[0u8; 5]; 5];
     for z in 0..w {
@@ -129,10 +546,11 @@ fn pi(a: &mut State) {
             }
         }
     }
+    cache.zeroize();
 }
 
 /// 4th transformation function (Alg 4.)
-fn chi(a: &mut State) {
+pub(crate) fn chi(a: &mut State) {
     let w = a.len();
     let mut cache = [0u8; 5];
     for z in 0..w {
@@ -148,6 +566,7 @@ fn chi(a: &mut State) {
             }
         }
     }
+    cache.zeroize();
 }
 
 
@@ -159,25 +578,27 @@ fn chi(a: &mut State) {
 fn rc_fun(t: usize) -> u8 {
     
     // Step 1.
-    if t % 255 == 0 {
+    if t.is_multiple_of(255) {
         return 1
     }
     
     // Step 2.   R = 1 0 0 0 0 0 0 0   (8 bits with R[0]=1, rest are 0s).
-    let mut r = BitString::with_capacity(8);
-    r.push(1);
-    for _i in 1..8 {
-        r.push(0);
-    }
+    //   one extra position is kept for the bit R[8] appearing in Step 3.
+    let mut r = [0u8; 9];
+    r[0] = 1;
 
     // Step 3.  For i from 1 to t mod 255, let: <...>
     for _ in 1..(t % 255)+1 {
-        r = prepend_zero(&r);
-        r[0] = r[0] ^ r[8];
-        r[4] = r[4] ^ r[8];
-        r[5] = r[5] ^ r[8];
-        r[6] = r[6] ^ r[8];
-        r = trunc(8, &r);
+        // R = 0 || R
+        for i in (1..9).rev() {
+            r[i] = r[i-1];
+        }
+        r[0] = 0;
+        r[0] ^= r[8];
+        r[4] ^= r[8];
+        r[5] ^= r[8];
+        r[6] ^= r[8];
+        // R = Trunc8[R] : R[8] is simply overwritten in the next iteration
     }
 
     // Step 4. Return R[0]
@@ -186,13 +607,13 @@ fn rc_fun(t: usize) -> u8 {
 
 
 /// 5th transformation (Alg 6.)
-fn iota(a: &mut State, ir: usize, el: usize) {
+pub(crate) fn iota(a: &mut State, ir: usize, el: usize) {
     let w = a.len();
     
     //Step 1. skip as we modify "a" in place
 
     //Step 2.
-    let mut rc = new_bitstring(w);
+    let mut rc = [0u8; KECCAK_MAX_W];
 
     // Step 3. For j from 0 to l, let RC[2**j – 1] = rc(j + 7ir)
     for j in 0..(el+1) {
@@ -203,20 +624,16 @@ fn iota(a: &mut State, ir: usize, el: usize) {
     for z in 0..w {
         a[z][0][0] ^= rc[z];
     }
-    
 }
 
 /// Rnd function (see page 16, Sec. 3.3, of the specs).
 ///    we explicitly specify "el" as input here
-fn rnd(a: &mut State, ir: usize, el: usize) -> State {
-    // println!("\nRound {ir}\n");
+fn rnd(a: &mut State, ir: usize, el: usize) {
     theta(a);
-    let mut a2 = rho(&a);
-    pi(&mut a2);
-    chi(&mut a2);
-    iota(&mut a2, ir, el);
-    // debug_state_as_bytes("After Iota", &A5);
-    a2
+    rho(a);
+    pi(a);
+    chi(a);
+    iota(a, ir, el);
 }
 
 // Alg 7.
@@ -227,26 +644,30 @@ fn rnd(a: &mut State, ir: usize, el: usize) -> State {
 //
 // nr : number of rounds
 //
-// s : an input string of length b; represented as an array of bytes
-fn keccak_p(b: usize, nr: usize, s: &BitString) -> BitString {    
-    //let w = get_w_from_b(b);
+// s : an input string of length b; represented as an array of bits,
+//     which is replaced by the output string S'
+//
+// Each call first goes through the self-test checks (see the `selftest` module).
+pub fn keccak_p(b: usize, nr: usize, s: &mut [u8]) {
+    check_permutation();
+    keccak_p_unchecked(b, nr, s);
+}
+
+/// KECCAK-p[b, nr] without the self-test checks, for the permutation KAT itself.
+pub(crate) fn keccak_p_unchecked(b: usize, nr: usize, s: &mut [u8]) {
     let el = get_el_from_b(b);
     assert_eq!(b, s.len());
 
     // Step 1. Convert S to A
     let mut a = bitstring_to_state(s);
 
-    // debug_state_as_bytes("keccak_p / Step 1 / A", &A);
-    // debug_state_as_lanes_of_integers("keccak_p / Step 1 / A", &A);
-
     // Step 2.   ir  from (12 + 2 el – nr) to  (12 + 2 el – 1)
     for ir in (12 + 2 * el - nr)..(12 + 2 * el) {
-        a = rnd(&mut a, ir, el);
+        rnd(&mut a, ir, el);
     }    
     // Step 3. Convert A to S' of length b
-    let s1 = state_to_bitstring(&a);
-    assert_eq!(s1.len(), b);
-    s1
+    state_to_bits(&a, s);
+    a.zeroize();
 }
 
 // we hardcode Sponge function here, as their paramters are mostly fixed (except "c") as per Sec.5.2
@@ -256,11 +677,17 @@ fn keccak_p(b: usize, nr: usize, s: &BitString) -> BitString {
 //            SPONGE[f, pad, r](N, d)
 //
 // c : 
+#[cfg(feature = "alloc")]
 fn keccak(keccak_c: usize, n_bitstr: &BitString, d: usize) -> BitString {
     // hardcoded for SHA3
-    let b = KECCAK_B;
-    let nr = KECCAK_NR;
+    keccak_sponge(KECCAK_B, KECCAK_NR, keccak_c, n_bitstr, d)
+}
 
+/// SPONGE[KECCAK-p[b, nr], pad10*1, b – c](N, d) for any width b and number of rounds nr,
+/// e.g. to generate vectors for reduced-round or smaller Keccak instances.
+#[cfg(feature = "alloc")]
+pub fn keccak_sponge(b: usize, nr: usize, keccak_c: usize, n_bitstr: &BitString, d: usize) -> BitString {
+    assert!(nr <= 12 + 2 * get_el_from_b(b));
     assert!(b > keccak_c);
     let r: usize = b - keccak_c;
 
@@ -288,37 +715,45 @@ fn keccak(keccak_c: usize, n_bitstr: &BitString, d: usize) -> BitString {
     let mut s = new_bitstring(b);
 
     //Step 6.  For i from 0 to n-1, let <..>
-    for i in 0..n {
+    // the copies of the message and of the state are wiped before they are dropped
+    for pi in ps {
         let zero = new_bitstring(c);
-        let pi_zero = concat_bitstrings(&ps[i], &zero);
-        let f_input = xor_bitstrings(&s, &pi_zero);
-        s = keccak_p(b, nr, &f_input);
+        let mut pi_zero = concat_bitstrings(pi, &zero);
+        let next = xor_bitstrings(&s, &pi_zero);
+        pi_zero.zeroize();
+        s.zeroize();
+        s = next;
+        keccak_p(b, nr, &mut s);
     }
+    p.zeroize();
     
     //Step 7.
     let mut z = BitString::new(); // can we estimate the max capacity?
     
     loop {
         //Step 8.
-        z = concat_bitstrings(&z, &trunc(r, &s));
+        let mut block = trunc(r, &s);
+        let next = concat_bitstrings(&z, &block);
+        block.zeroize();
+        z.zeroize();
+        z = next;
 
         //Step 9.
         if d <= z.len() {
-            return trunc(d, &z)
+            let output = trunc(d, &z);
+            z.zeroize();
+            s.zeroize();
+            return output
         }
 
         //Step 10. update S and go to Step 8
-        s = keccak_p(b, nr, &s);
+        keccak_p(b, nr, &mut s);
     }
 
 }
 
 
 
-fn keccak_init(){
-    todo!()
-}
-
 /// Alg. 9
 /// output a string of the form 10*1
 ///   x: positive
@@ -327,10 +762,9 @@ fn keccak_init(){
 ///   it is defined as 1 || 0^j || 1,
 ///   where j = -m -2 mod x
 /// 
-fn pad101(x: usize,  m: usize) -> BitString {
-    assert!(x>=2); // we explicitly exclude the case x=1
-    let m1 = m % x;  // m1 \in [0, x-1]
-    let j = (2*x-m1-2) % x;  // it always holds j>=0 assuming x>=2
+#[cfg(feature = "alloc")]
+pub(crate) fn pad101(x: usize,  m: usize) -> BitString {
+    let j = pad101_zeros(x, m);
     let mut res = BitString::new();
     res.push(1);
     for _ in 0..j {
@@ -340,56 +774,114 @@ fn pad101(x: usize,  m: usize) -> BitString {
     res
 }
 
+/// Returns the number j of zeros in pad10*1, i.e. j = -m -2 mod x.
+fn pad101_zeros(x: usize,  m: usize) -> usize {
+    assert!(x>=2); // we explicitly exclude the case x=1
+    let m1 = m % x;  // m1 \in [0, x-1]
+    (2*x-m1-2) % x  // it always holds j>=0 assuming x>=2
+}
+
 // two-bit suffixes are applied to M in the sha3 family of functions
+#[cfg(feature = "alloc")]
 pub fn sha3_family(m: &[u8], keccak_c: usize, keccak_d: usize) -> ByteString {
     let mut n = bytestr_to_bitstring(m);
     n.push(0);
     n.push(1);
-    let digest_bits = keccak(keccak_c, &n, keccak_d);
-    let digest_bytes = bitstring_to_bytestr(&digest_bits);
-    digest_bytes
+    let mut digest_bits = keccak(keccak_c, &n, keccak_d);
+    n.zeroize();
+    let digest = bitstring_to_bytestr(&digest_bits);
+    digest_bits.zeroize();
+    digest
 }
 
+#[cfg(feature = "alloc")]
 pub fn sha3_224(m:  &[u8]) -> ByteString {
     sha3_family(m, 448, 224)
 }
 
+#[cfg(feature = "alloc")]
 /// The function is defined as follows: SHA3-256(M) = KECCAK [512] (M || 01, 256)
 pub fn sha3_256(m: &[u8]) -> ByteString {
     sha3_family(m, 512, 256)
 }
 
+#[cfg(feature = "alloc")]
 pub fn sha3_384(m: &[u8]) -> ByteString {
     sha3_family(m,  768, 384)
 }
 
+#[cfg(feature = "alloc")]
 pub fn sha3_512(m: &[u8]) -> ByteString {
     sha3_family(m,  1024, 512)
 }
 
+// four-bit suffixes are applied to M in the SHAKE family of functions (Sec. 6.2)
+#[cfg(feature = "alloc")]
+pub fn shake_family(m: &[u8], keccak_c: usize, d: usize) -> ByteString {
+    assert!(d.is_multiple_of(8));
+    let mut n = bytestr_to_bitstring(m);
+    n.push(1);
+    n.push(1);
+    n.push(1);
+    n.push(1);
+    let mut output_bits = keccak(keccak_c, &n, d);
+    n.zeroize();
+    let output = bitstring_to_bytestr(&output_bits);
+    output_bits.zeroize();
+    output
+}
+
+#[cfg(feature = "alloc")]
+/// SHAKE128(M, d) = KECCAK[256] (M || 1111, d), where d is the output length in bits.
+pub fn shake128(m: &[u8], d: usize) -> ByteString {
+    shake_family(m, 256, d)
+}
+
+#[cfg(feature = "alloc")]
+/// SHAKE256(M, d) = KECCAK[512] (M || 1111, d), where d is the output length in bits.
+pub fn shake256(m: &[u8], d: usize) -> ByteString {
+    shake_family(m, 512, d)
+}
+
+// the Keccak submission pads M directly, without a suffix
+#[cfg(feature = "alloc")]
+pub fn keccak_family(m: &[u8], keccak_c: usize, keccak_d: usize) -> ByteString {
+    let mut n = bytestr_to_bitstring(m);
+    let mut digest_bits = keccak(keccak_c, &n, keccak_d);
+    n.zeroize();
+    let digest = bitstring_to_bytestr(&digest_bits);
+    digest_bits.zeroize();
+    digest
+}
+
+#[cfg(feature = "alloc")]
+/// Keccak-256(M) = KECCAK[512] (M, 256), as used by Ethereum.
+pub fn keccak_256(m: &[u8]) -> ByteString {
+    keccak_family(m, 512, 256)
+}
+
+/// Computes the digest of M without allocating; `out` must be `output_len() / 8` bytes long.
+pub fn sha3_into(sha3_variant: Sha3Variant, m: &[u8], out: &mut [u8]) {
+    let mut sha3 = Sha3::new(sha3_variant);
+    sha3.update(m);
+    sha3.digest_into(out);
+}
 
-#[cfg(test)]
+
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use crate::sha3::{types::Sha3Variant};
 
     use super::*;
 
-    fn get_timestamp() -> Duration {
-        SystemTime::now().duration_since(UNIX_EPOCH).unwrap()
-    }
-    
     fn test_sha3_on_input(bytes: &[u8], expected_digest: &str, sha3_variant: &Sha3Variant){
-        let timestamp_start = get_timestamp();
         let computed_digest = match sha3_variant {
             Sha3Variant::SHA3_224 => sha3_224(bytes),
             Sha3Variant::SHA3_256 => sha3_256(bytes),
             Sha3Variant::SHA3_384 => sha3_384(bytes),
             Sha3Variant::SHA3_512 => sha3_512(bytes),
         };
-        let computed_digest_hex = hex::encode(&computed_digest.as_slice());
-        let duration = get_timestamp() - timestamp_start;
-        println!("Execution time of sha3 function: {duration:?}");
-        
+        let computed_digest_hex = hex::encode(computed_digest.as_slice());
         //println!("Digest for bytes {bytes:?} : {computed_digest}");
         assert_eq!(&expected_digest.to_lowercase(), &computed_digest_hex.to_lowercase());
     }    
@@ -410,83 +902,138 @@ mod tests {
         test_sha3_on_input(&hex::decode("e9").unwrap(), "f0d04dd1e6cfc29a4460d521796852f25d9ef8d28b44ee91ff5b759d72c1e6d6", &Sha3Variant::SHA3_256);
     }
 
-
-    use std::{fs::read_to_string, time::{Duration, SystemTime, UNIX_EPOCH}};
-    fn read_lines(filename: &str) -> Vec<String> {
-        read_to_string(filename)
-            .unwrap()  // panic on possible file-reading errors
-            .lines()  // split the string into an iterator of string slices
-            .map(String::from)  // make each slice into a string
-            .collect()  // gather them together into a vector
-    }
-    
-    fn test_rsp_file(filename: &str, sha3_variant: &Sha3Variant){
-        let lines = read_lines(filename);
-        let n = lines.len();                
-        println!("file read {filename} -> {n} lines");
-
-        let mut number_of_tests = 0;
-        for i in 0..n-2 {
-            let line = &lines[i];
-            if line.starts_with("Len") {
-                number_of_tests += 1;
-                let len_bytes: usize = line.split("=").skip(1).next().unwrap().trim().parse().unwrap();
-                
-                let line_msg = &lines[i+1];
-                let msg_hex = line_msg.split("=").skip(1).next().unwrap().trim();
-                let msg_len = msg_hex.len();
-                if len_bytes > 0 {  // len 0 test has input msg as 00.
-                    assert_eq!(msg_len * 4, len_bytes);
-                }
-                
-                let line_md = &lines[i+2];
-                let md = line_md.split("=").skip(1).next().unwrap().trim();
-
-                let msg_hex_to_show = match msg_hex.len() {
-                    ..17 => msg_hex.to_string(),
-                    17.. => msg_hex[..4].to_string() + "..." + &msg_hex[msg_hex.len()-4..]
-                };
-                println!("Found test #{number_of_tests}: Len = {len_bytes}, Msg [of len {msg_len}] = {msg_hex_to_show}");
-                println!("expected MD = '{md}'");
-
-                let decoded_input = hex::decode(msg_hex).unwrap();
-                // this takes care of len 0 test case that has input msg as 00 (msg len and Len mismatch).
-                let decoded_input_corrected = &decoded_input[0..(len_bytes/8)];
-                test_sha3_on_input(&decoded_input_corrected, md, sha3_variant);
+    #[test]
+    fn test_incremental_matches_one_shot(){
+        let msg: Vec<u8> = (0..300u32).map(|i| (i * 7) as u8).collect();
+        for sha3_variant in [Sha3Variant::SHA3_224, Sha3Variant::SHA3_256, Sha3Variant::SHA3_384, Sha3Variant::SHA3_512] {
+            let expected = sha3_family(&msg, sha3_variant.capacity(), sha3_variant.output_len());
+            let mut sha3 = Sha3::new(sha3_variant);
+            for chunk in msg.chunks(53) {
+                sha3.update(chunk);
             }
+            assert_eq!(expected.as_slice(), sha3.digest().as_slice());
         }
     }
 
+    #[test]
+    fn test_sha3_into(){
+        let mut digest = [0u8; 32];
+        sha3_into(Sha3Variant::SHA3_256, &[], &mut digest);
+        assert_eq!("a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a", hex::encode(digest));
+    }
 
     #[test]
-    fn test_rsp_224_file(){
-        test_rsp_file("test_vectors/SHA3/SHA3_224ShortMsg.rsp", &Sha3Variant::SHA3_224);
+    fn test_io_copy_into_sha3(){
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/test/test_file.txt");
+        let data = std::fs::read(path).unwrap();
+        let mut file = std::fs::File::open(path).unwrap();
+        let mut sha3 = Sha3::new(Sha3Variant::SHA3_256);
+        std::io::copy(&mut file, &mut sha3).unwrap();
+        assert_eq!(sha3_256(&data).as_slice(), sha3.digest().as_slice());
     }
 
     #[test]
-    fn test_rsp_256_file(){
-        test_rsp_file("test_vectors/SHA3/SHA3_256ShortMsg.rsp", &Sha3Variant::SHA3_256);
+    fn test_shake(){
+        assert_eq!("7f9c2ba4e88f827d616045507605853ed73b8093f6efbc88eb1a6eacfa66ef26", hex::encode(shake128(&[], 256).as_slice()));
+        assert_eq!("46b9dd2b0ba88d13233b3feb743eeb243fcd52ea62b81b82b50c27646ed5762fd75dc4ddd8c0f200cb05019d67b592f6fc821c49479ab48640292eacb3b7c4be",
+            hex::encode(shake256(&[], 512).as_slice()));
     }
 
     #[test]
-    fn test_rsp_256_long_file(){
-        test_rsp_file("test_vectors/SHA3/SHA3_256LongMsg.rsp", &Sha3Variant::SHA3_256);
+    fn test_shake_reader(){
+        use std::io::Read;
+        // 500 bytes span several SHAKE128 output blocks of 168 bytes
+        let expected = shake128(b"abc", 500 * 8);
+
+        let mut shake = Shake::new(ShakeVariant::SHAKE128);
+        shake.update(b"a");
+        shake.update(b"bc");
+        let mut reader = shake.finalize_xof();
+        let mut output = vec![0u8; 500];
+        let (head, tail) = output.split_at_mut(13);
+        reader.read_exact(head).unwrap();
+        reader.read_exact(tail).unwrap();
+        assert_eq!(expected.as_slice(), output.as_slice());
     }
 
     #[test]
-    fn test_rsp_384_file(){
-        test_rsp_file("test_vectors/SHA3/SHA3_384ShortMsg.rsp", &Sha3Variant::SHA3_384);
+    fn test_shake_reader_skip(){
+        let expected = shake128(b"abc", 1000 * 8);
+        for (first, skipped) in [(0, 0), (0, 168), (5, 163), (13, 400), (100, 336)] {
+            let mut reader = Shake::new(ShakeVariant::SHAKE128);
+            reader.update(b"abc");
+            let mut reader = reader.finalize_xof();
+            let mut head = vec![0u8; first];
+            reader.squeeze(&mut head);
+            reader.skip(skipped as u64);
+            let mut tail = vec![0u8; 1000 - first - skipped];
+            reader.squeeze(&mut tail);
+            assert_eq!(&expected[..first], head.as_slice());
+            assert_eq!(&expected[first + skipped..], tail.as_slice());
+        }
     }
 
     #[test]
-    fn test_rsp_512_file(){
-        test_rsp_file("test_vectors/SHA3/SHA3_512ShortMsg.rsp", &Sha3Variant::SHA3_512);
+    fn test_sponge_zeroize(){
+        let mut sponge = Sponge::new(512);
+        // one full block of 136 bytes is absorbed into S, the rest stays buffered
+        sponge.absorb_bytes(&[0xA5u8; 150]);
+        assert!(sponge.s.iter().any(|&bit| bit != 0));
+        assert!(sponge.buffer.iter().any(|&bit| bit != 0));
+
+        // what Drop runs
+        sponge.zeroize();
+        assert!(sponge.s.iter().all(|&bit| bit == 0));
+        assert!(sponge.buffer.iter().all(|&bit| bit == 0));
+        assert_eq!(0, sponge.buffered);
+    }
+
+    #[test]
+    fn test_keccak_256(){
+        assert_eq!("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470", hex::encode(keccak_256(&[]).as_slice()));
+        assert_eq!("4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45", hex::encode(keccak_256(b"abc").as_slice()));
+
+        let msg: Vec<u8> = (0..300u32).map(|i| (i * 7) as u8).collect();
+        let mut keccak = Keccak::new(Sha3Variant::SHA3_256);
+        for chunk in msg.chunks(61) {
+            keccak.update(chunk);
+        }
+        assert_eq!(keccak_256(&msg), keccak.digest());
+    }
+
+    #[test]
+    fn test_keccak_p_zero_state(){
+        let mut s = [0u8; KECCAK_B];
+        keccak_p(KECCAK_B, KECCAK_NR, &mut s);
+        let mut bytes = [0u8; KECCAK_B / 8];
+        bits_to_bytes(&s, &mut bytes);
+        // first lane of KECCAK-f[1600] applied to the all-zero state
+        assert_eq!("e7dde140798f25f1", hex::encode(&bytes[..8]));
+    }
+
+    #[test]
+    fn test_theta_wraps_z(){
+        use crate::sha3::types::new_state;
+
+        // a 1 in column (x, z) = (1, w - 1) flips the columns (2, w - 1) and (0, 0), the latter
+        // through the z - 1 of D[x, z] = C[x - 1, z] xor C[x + 1, z - 1] at z = 0
+        for w in [1, 2, 4, 8, 16, 32, 64] {
+            let mut a = new_state(w);
+            a[w - 1][1][0] = 1;
+            theta(&mut a);
+            for y in 0..5 {
+                assert_eq!(1, a[0][0][y], "w = {w}");
+                assert_eq!(1, a[w - 1][2][y], "w = {w}");
+            }
+            let ones: usize = (0..w).map(|z| a[z].iter().flatten().map(|&bit| bit as usize).sum::<usize>()).sum();
+            assert_eq!(11, ones, "w = {w}");
+        }
     }
 
     #[test]
     fn test_read_file(){
         use std::fs;
-        let filename = "test/test_file.txt";
+        let filename = concat!(env!("CARGO_MANIFEST_DIR"), "/test/test_file.txt");
         let data = fs::read(filename).unwrap();
         let data_bytes = data.len();
         let computed_digest = hex::encode(sha3_256(&data).as_slice()).to_lowercase();
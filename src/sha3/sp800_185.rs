@@ -0,0 +1,508 @@
+//! The SHA-3 derived functions of NIST SP 800-185: cSHAKE, KMAC, TupleHash and ParallelHash.
+//!
+//! Each function comes in a 128 and a 256 flavour, selected by the `ShakeVariant` of the
+//! underlying cSHAKE (e.g. KMAC256 is built on cSHAKE256). Lengths L are in bits and must
+//! be multiples of 8, as only byte-oriented inputs and outputs are supported.
+
+#[cfg(feature = "alloc")]
+use alloc::vec;
+#[cfg(feature = "std")]
+use std::io;
+
+use crate::sha3::constants::KECCAK_B;
+use crate::sha3::sha3::Shake;
+use crate::sha3::sha3::ShakeReader;
+use crate::sha3::sha3::Sponge;
+use crate::sha3::secret::Zeroize;
+use crate::sha3::secret::ct_eq;
+#[cfg(feature = "alloc")]
+use crate::sha3::types::ByteString;
+use crate::sha3::types::ShakeVariant;
+
+
+/// Encoding of an integer by `left_encode` or `right_encode` (Sec. 2.3.1): the big-endian
+/// bytes of the integer, with their count prepended or appended.
+#[derive(Clone, Copy, Debug)]
+pub struct EncodedInteger {
+    bytes: [u8; 9],
+    len: usize,
+}
+
+impl core::ops::Deref for EncodedInteger {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+/// Big-endian bytes of x without leading zeros, but at least one byte.
+fn integer_bytes(x: u64) -> ([u8; 8], usize) {
+    let n = (8 - x.leading_zeros() as usize / 8).max(1);
+    (x.to_be_bytes(), n)
+}
+
+/// left_encode(x) = n || x_1 || ... || x_n, where x_1 is the most significant byte.
+pub fn left_encode(x: u64) -> EncodedInteger {
+    let (be, n) = integer_bytes(x);
+    let mut bytes = [0u8; 9];
+    bytes[0] = n as u8;
+    bytes[1..=n].copy_from_slice(&be[8 - n..]);
+    EncodedInteger { bytes, len: n + 1 }
+}
+
+/// right_encode(x) = x_1 || ... || x_n || n.
+pub fn right_encode(x: u64) -> EncodedInteger {
+    let (be, n) = integer_bytes(x);
+    let mut bytes = [0u8; 9];
+    bytes[..n].copy_from_slice(&be[8 - n..]);
+    bytes[n] = n as u8;
+    EncodedInteger { bytes, len: n + 1 }
+}
+
+/// Rate of the cSHAKE sponge in bytes, which is the padding width w used by bytepad.
+fn rate_bytes(shake_variant: ShakeVariant) -> usize {
+    (KECCAK_B - shake_variant.capacity()) / 8
+}
+
+/// Absorbs encode_string(S) = left_encode(len(S)) || S.
+fn absorb_encoded_string(sponge: &mut Sponge, s: &[u8]) {
+    sponge.absorb_bytes(&left_encode(8 * s.len() as u64));
+    sponge.absorb_bytes(s);
+}
+
+/// Absorbs bytepad(encode_string(S_1) || ... || encode_string(S_k), w), i.e. the encoded
+/// strings prefixed by left_encode(w) and followed by zeros up to a multiple of w bytes.
+fn absorb_bytepad(sponge: &mut Sponge, strings: &[&[u8]], w: usize) {
+    let prefix = left_encode(w as u64);
+    sponge.absorb_bytes(&prefix);
+    let mut len = prefix.len();
+    for s in strings {
+        absorb_encoded_string(sponge, s);
+        len += left_encode(8 * s.len() as u64).len() + s.len();
+    }
+    for _ in 0..(w - len % w) % w {
+        sponge.absorb_bytes(&[0]);
+    }
+}
+
+
+/// Incremental cSHAKE (Sec. 3): SHAKE with a function-name string N and a customization
+/// string S. When both are empty, cSHAKE is SHAKE itself.
+#[derive(Clone)]
+pub struct CShake {
+    shake_variant: ShakeVariant,
+    sponge: Sponge,
+    is_shake: bool,
+}
+
+impl CShake {
+    pub fn new(shake_variant: ShakeVariant, function_name: &[u8], customization: &[u8]) -> Self {
+        let mut sponge = Sponge::new(shake_variant.capacity());
+        let is_shake = function_name.is_empty() && customization.is_empty();
+        if !is_shake {
+            absorb_bytepad(&mut sponge, &[function_name, customization], rate_bytes(shake_variant));
+        }
+        CShake {
+            shake_variant,
+            sponge,
+            is_shake,
+        }
+    }
+
+    pub fn variant(&self) -> ShakeVariant {
+        self.shake_variant
+    }
+
+    pub fn update(&mut self, bytestr: &[u8]) {
+        self.sponge.absorb_bytes(bytestr);
+    }
+
+    /// Appends the "00" suffix (or "1111" when this is plain SHAKE) and pads the last block.
+    pub fn finalize_xof(mut self) -> ShakeReader {
+        if self.is_shake {
+            self.sponge.absorb_bits(&[1, 1, 1, 1]);
+        } else {
+            self.sponge.absorb_bits(&[0, 0]);
+        }
+        self.sponge.pad();
+        ShakeReader::from_sponge(self.sponge)
+    }
+}
+
+#[cfg(feature = "std")]
+impl io::Write for CShake {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+
+/// Longest tag accepted by `Kmac::verify`, in bytes.
+pub const KMAC_MAX_VERIFY_BYTES: usize = 128;
+
+/// Incremental KMAC (Sec. 4):
+/// KMAC(K, X, L, S) = cSHAKE(bytepad(encode_string(K), w) || X || right_encode(L), L, "KMAC", S).
+///
+/// The key only lives in the sponge state, which is wiped on drop.
+#[derive(Clone)]
+pub struct Kmac {
+    cshake: CShake,
+}
+
+impl Kmac {
+    pub fn new(shake_variant: ShakeVariant, key: &[u8], customization: &[u8]) -> Self {
+        let mut cshake = CShake::new(shake_variant, b"KMAC", customization);
+        absorb_bytepad(&mut cshake.sponge, &[key], rate_bytes(shake_variant));
+        Kmac { cshake }
+    }
+
+    pub fn variant(&self) -> ShakeVariant {
+        self.cshake.variant()
+    }
+
+    pub fn update(&mut self, bytestr: &[u8]) {
+        self.cshake.update(bytestr);
+    }
+
+    /// Writes the tag to `out`; its length L is bound into the tag.
+    pub fn finalize_into(mut self, out: &mut [u8]) {
+        self.cshake.update(&right_encode(8 * out.len() as u64));
+        self.cshake.finalize_xof().squeeze(out);
+    }
+
+    /// Checks a tag of L = 8 * `tag.len()` bits in constant time. Since L is bound into the
+    /// output, a tag truncated from a longer KMAC output does not verify; the caller must
+    /// check that `tag` has the expected length.
+    pub fn verify(self, tag: &[u8]) -> bool {
+        if tag.len() > KMAC_MAX_VERIFY_BYTES {
+            return false;
+        }
+        let mut mac = [0u8; KMAC_MAX_VERIFY_BYTES];
+        self.finalize_into(&mut mac[..tag.len()]);
+        let is_valid = ct_eq(&mac[..tag.len()], tag);
+        mac.zeroize();
+        is_valid
+    }
+
+    /// KMACXOF: L is encoded as 0, so that the output can be read to any length.
+    pub fn finalize_xof(mut self) -> ShakeReader {
+        self.cshake.update(&right_encode(0));
+        self.cshake.finalize_xof()
+    }
+}
+
+
+/// Incremental TupleHash (Sec. 5): hashes a sequence of strings, such that
+/// ("ab", "c") and ("a", "bc") give unrelated outputs.
+#[derive(Clone)]
+pub struct TupleHash {
+    cshake: CShake,
+}
+
+impl TupleHash {
+    pub fn new(shake_variant: ShakeVariant, customization: &[u8]) -> Self {
+        TupleHash {
+            cshake: CShake::new(shake_variant, b"TupleHash", customization),
+        }
+    }
+
+    pub fn variant(&self) -> ShakeVariant {
+        self.cshake.variant()
+    }
+
+    /// Appends one element to the tuple; unlike `update` elsewhere, each call is a
+    /// separate string.
+    pub fn push_item(&mut self, item: &[u8]) {
+        absorb_encoded_string(&mut self.cshake.sponge, item);
+    }
+
+    pub fn finalize_into(mut self, out: &mut [u8]) {
+        self.cshake.update(&right_encode(8 * out.len() as u64));
+        self.cshake.finalize_xof().squeeze(out);
+    }
+
+    /// TupleHashXOF: L is encoded as 0.
+    pub fn finalize_xof(mut self) -> ShakeReader {
+        self.cshake.update(&right_encode(0));
+        self.cshake.finalize_xof()
+    }
+}
+
+
+/// Largest chaining value of ParallelHash (ParallelHash256), in bytes.
+const PARALLEL_HASH_MAX_CHAINING_BYTES: usize = 64;
+
+/// Incremental ParallelHash (Sec. 6): the input is split into blocks of B bytes, each
+/// block is hashed with cSHAKE(X_i, 2 * security strength, "", ""), and the chaining
+/// values are hashed with cSHAKE(..., "ParallelHash", S).
+///
+/// The blocks are hashed one after the other here; the output is the same as with a
+/// parallel implementation.
+#[derive(Clone)]
+pub struct ParallelHash {
+    cshake: CShake,
+    block_size: usize,
+    block: Shake,
+    block_filled: usize,
+    blocks: u64,
+}
+
+impl ParallelHash {
+    /// `block_size` is B, in bytes.
+    pub fn new(shake_variant: ShakeVariant, block_size: usize, customization: &[u8]) -> Self {
+        assert!(block_size > 0);
+        let mut cshake = CShake::new(shake_variant, b"ParallelHash", customization);
+        cshake.update(&left_encode(block_size as u64));
+        ParallelHash {
+            cshake,
+            block_size,
+            block: Shake::new(shake_variant),
+            block_filled: 0,
+            blocks: 0,
+        }
+    }
+
+    pub fn variant(&self) -> ShakeVariant {
+        self.cshake.variant()
+    }
+
+    pub fn update(&mut self, mut bytestr: &[u8]) {
+        while !bytestr.is_empty() {
+            let n = bytestr.len().min(self.block_size - self.block_filled);
+            self.block.update(&bytestr[..n]);
+            self.block_filled += n;
+            bytestr = &bytestr[n..];
+            if self.block_filled == self.block_size {
+                self.absorb_chaining_value();
+            }
+        }
+    }
+
+    /// Hashes the current block and absorbs its chaining value.
+    fn absorb_chaining_value(&mut self) {
+        let shake_variant = self.cshake.variant();
+        let block = core::mem::replace(&mut self.block, Shake::new(shake_variant));
+        let mut chaining_value = [0u8; PARALLEL_HASH_MAX_CHAINING_BYTES];
+        let len = shake_variant.capacity() / 8;
+        block.finalize_xof().squeeze(&mut chaining_value[..len]);
+        self.cshake.update(&chaining_value[..len]);
+        chaining_value.zeroize();
+        self.block_filled = 0;
+        self.blocks += 1;
+    }
+
+    /// Hashes the last, partial block, then absorbs right_encode(n) || right_encode(L).
+    fn finish(&mut self, output_bits: u64) {
+        if self.block_filled > 0 {
+            self.absorb_chaining_value();
+        }
+        self.cshake.update(&right_encode(self.blocks));
+        self.cshake.update(&right_encode(output_bits));
+    }
+
+    pub fn finalize_into(mut self, out: &mut [u8]) {
+        self.finish(8 * out.len() as u64);
+        self.cshake.finalize_xof().squeeze(out);
+    }
+
+    /// ParallelHashXOF: L is encoded as 0.
+    pub fn finalize_xof(mut self) -> ShakeReader {
+        self.finish(0);
+        self.cshake.finalize_xof()
+    }
+}
+
+
+#[cfg(feature = "alloc")]
+fn squeeze_bits(mut reader: ShakeReader, l: usize) -> ByteString {
+    assert!(l.is_multiple_of(8));
+    let mut output = ByteString::from(vec![0u8; l / 8]);
+    reader.squeeze(&mut output);
+    output
+}
+
+#[cfg(feature = "alloc")]
+fn finalize_bits(l: usize, finalize_into: impl FnOnce(&mut [u8])) -> ByteString {
+    assert!(l.is_multiple_of(8));
+    let mut output = ByteString::from(vec![0u8; l / 8]);
+    finalize_into(&mut output);
+    output
+}
+
+/// cSHAKE(X, L, N, S)
+#[cfg(feature = "alloc")]
+pub fn cshake(shake_variant: ShakeVariant, x: &[u8], l: usize, n: &[u8], s: &[u8]) -> ByteString {
+    let mut cshake = CShake::new(shake_variant, n, s);
+    cshake.update(x);
+    squeeze_bits(cshake.finalize_xof(), l)
+}
+
+#[cfg(feature = "alloc")]
+pub fn cshake128(x: &[u8], l: usize, n: &[u8], s: &[u8]) -> ByteString {
+    cshake(ShakeVariant::SHAKE128, x, l, n, s)
+}
+
+#[cfg(feature = "alloc")]
+pub fn cshake256(x: &[u8], l: usize, n: &[u8], s: &[u8]) -> ByteString {
+    cshake(ShakeVariant::SHAKE256, x, l, n, s)
+}
+
+/// KMAC(K, X, L, S)
+#[cfg(feature = "alloc")]
+pub fn kmac(shake_variant: ShakeVariant, k: &[u8], x: &[u8], l: usize, s: &[u8]) -> ByteString {
+    let mut kmac = Kmac::new(shake_variant, k, s);
+    kmac.update(x);
+    finalize_bits(l, |out| kmac.finalize_into(out))
+}
+
+#[cfg(feature = "alloc")]
+pub fn kmac128(k: &[u8], x: &[u8], l: usize, s: &[u8]) -> ByteString {
+    kmac(ShakeVariant::SHAKE128, k, x, l, s)
+}
+
+#[cfg(feature = "alloc")]
+pub fn kmac256(k: &[u8], x: &[u8], l: usize, s: &[u8]) -> ByteString {
+    kmac(ShakeVariant::SHAKE256, k, x, l, s)
+}
+
+/// KMACXOF(K, X, L, S)
+#[cfg(feature = "alloc")]
+pub fn kmac_xof(shake_variant: ShakeVariant, k: &[u8], x: &[u8], l: usize, s: &[u8]) -> ByteString {
+    let mut kmac = Kmac::new(shake_variant, k, s);
+    kmac.update(x);
+    squeeze_bits(kmac.finalize_xof(), l)
+}
+
+/// TupleHash(X, L, S), where X is the tuple of strings `items`.
+#[cfg(feature = "alloc")]
+pub fn tuple_hash(shake_variant: ShakeVariant, items: &[&[u8]], l: usize, s: &[u8]) -> ByteString {
+    let mut tuple_hash = TupleHash::new(shake_variant, s);
+    for item in items {
+        tuple_hash.push_item(item);
+    }
+    finalize_bits(l, |out| tuple_hash.finalize_into(out))
+}
+
+#[cfg(feature = "alloc")]
+pub fn tuple_hash128(items: &[&[u8]], l: usize, s: &[u8]) -> ByteString {
+    tuple_hash(ShakeVariant::SHAKE128, items, l, s)
+}
+
+#[cfg(feature = "alloc")]
+pub fn tuple_hash256(items: &[&[u8]], l: usize, s: &[u8]) -> ByteString {
+    tuple_hash(ShakeVariant::SHAKE256, items, l, s)
+}
+
+/// TupleHashXOF(X, L, S)
+#[cfg(feature = "alloc")]
+pub fn tuple_hash_xof(shake_variant: ShakeVariant, items: &[&[u8]], l: usize, s: &[u8]) -> ByteString {
+    let mut tuple_hash = TupleHash::new(shake_variant, s);
+    for item in items {
+        tuple_hash.push_item(item);
+    }
+    squeeze_bits(tuple_hash.finalize_xof(), l)
+}
+
+/// ParallelHash(X, B, L, S), with the block size B in bytes.
+#[cfg(feature = "alloc")]
+pub fn parallel_hash(shake_variant: ShakeVariant, x: &[u8], b: usize, l: usize, s: &[u8]) -> ByteString {
+    let mut parallel_hash = ParallelHash::new(shake_variant, b, s);
+    parallel_hash.update(x);
+    finalize_bits(l, |out| parallel_hash.finalize_into(out))
+}
+
+#[cfg(feature = "alloc")]
+pub fn parallel_hash128(x: &[u8], b: usize, l: usize, s: &[u8]) -> ByteString {
+    parallel_hash(ShakeVariant::SHAKE128, x, b, l, s)
+}
+
+#[cfg(feature = "alloc")]
+pub fn parallel_hash256(x: &[u8], b: usize, l: usize, s: &[u8]) -> ByteString {
+    parallel_hash(ShakeVariant::SHAKE256, x, b, l, s)
+}
+
+/// ParallelHashXOF(X, B, L, S)
+#[cfg(feature = "alloc")]
+pub fn parallel_hash_xof(shake_variant: ShakeVariant, x: &[u8], b: usize, l: usize, s: &[u8]) -> ByteString {
+    let mut parallel_hash = ParallelHash::new(shake_variant, b, s);
+    parallel_hash.update(x);
+    squeeze_bits(parallel_hash.finalize_xof(), l)
+}
+
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::sha3::sha3::shake128;
+
+    // The expected values below are the samples published by NIST for SP 800-185.
+
+    fn range(start: u8, end: u8) -> Vec<u8> {
+        (start..end).collect()
+    }
+
+    #[test]
+    fn test_encode_integers() {
+        assert_eq!(&[1, 0], &*left_encode(0));
+        assert_eq!(&[0, 1], &*right_encode(0));
+        assert_eq!(&[2, 1, 0], &*left_encode(256));
+        assert_eq!(&[1, 0, 2], &*right_encode(256));
+        assert_eq!(&[8, 255, 255, 255, 255, 255, 255, 255, 255], &*left_encode(u64::MAX));
+    }
+
+    #[test]
+    fn test_cshake() {
+        assert_eq!(hex::decode("c1c36925b6409a04f1b504fcbca9d82b4017277cb5ed2b2065fc1d3814d5aaf5").unwrap(),
+            cshake128(&range(0, 4), 256, b"", b"Email Signature").as_slice());
+        assert_eq!(hex::decode("07dc27b11e51fbac75bc7b3c1d983e8b4b85fb1defaf218912ac86430273091727f42b17ed1df63e8ec118f04b23633c1dfb1574c8fb55cb45da8e25afb092bb").unwrap(),
+            cshake256(&range(0, 200), 512, b"", b"Email Signature").as_slice());
+        // with empty N and S, cSHAKE is SHAKE
+        assert_eq!(shake128(b"abc", 256).as_slice(), cshake128(b"abc", 256, b"", b"").as_slice());
+    }
+
+    #[test]
+    fn test_kmac() {
+        let key = range(0x40, 0x60);
+        assert_eq!(hex::decode("e5780b0d3ea6f7d3a429c5706aa43a00fadbd7d49628839e3187243f456ee14e").unwrap(),
+            kmac128(&key, &range(0, 4), 256, b"").as_slice());
+        assert_eq!(hex::decode("d5be731c954ed7732846bb59dbe3a8e30f83e77a4bff4459f2f1c2b4ecebb8ce67ba01c62e8ab8578d2d499bd1bb276768781190020a306a97de281dcc30305d").unwrap(),
+            kmac_xof(ShakeVariant::SHAKE256, &key, &range(0, 200), 512, b"My Tagged Application").as_slice());
+    }
+
+    #[test]
+    fn test_tuple_hash() {
+        let items = [range(0, 3), range(0x10, 0x16), range(0x20, 0x29)];
+        assert_eq!(hex::decode("c5d8786c1afb9b82111ab34b65b2c0048fa64e6d48e263264ce1707d3ffc8ed1").unwrap(),
+            tuple_hash128(&[&items[0], &items[1]], 256, b"").as_slice());
+        assert_eq!(hex::decode("45000be63f9b6bfd89f54717670f69a9bc763591a4f05c50d68891a744bcc6e7d6d5b5e82c018da999ed35b0bb49c9678e526abd8e85c13ed254021db9e790ce").unwrap(),
+            tuple_hash256(&[&items[0], &items[1], &items[2]], 512, b"My Tuple App").as_slice());
+    }
+
+    #[test]
+    fn test_parallel_hash() {
+        let x: Vec<u8> = [range(0, 8), range(0x10, 0x18), range(0x20, 0x28)].concat();
+        assert_eq!(hex::decode("ba8dc1d1d979331d3f813603c67f72609ab5e44b94a0b8f9af46514454a2b4f5").unwrap(),
+            parallel_hash128(&x, 8, 256, b"").as_slice());
+        assert_eq!(hex::decode("cdf15289b54f6212b4bc270528b49526006dd9b54e2b6add1ef6900dda3963bb33a72491f236969ca8afaea29c682d47a393c065b38e29fae651a2091c833110").unwrap(),
+            parallel_hash256(&x, 8, 512, b"Parallel Data").as_slice());
+    }
+
+    #[test]
+    fn test_parallel_hash_split_updates() {
+        let x = range(0, 100);
+        let expected = parallel_hash128(&x, 16, 256, b"S");
+        let mut parallel_hash = ParallelHash::new(ShakeVariant::SHAKE128, 16, b"S");
+        for chunk in x.chunks(7) {
+            parallel_hash.update(chunk);
+        }
+        let mut output = [0u8; 32];
+        parallel_hash.finalize_into(&mut output);
+        assert_eq!(expected.as_slice(), output);
+    }
+}
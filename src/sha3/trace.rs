@@ -0,0 +1,300 @@
+//! Step-by-step traces of the sponge, for teaching and debugging: an observer sees the state
+//! after each absorbed block and after each step mapping of each round of KECCAK-p, and the
+//! states can be printed as lanes of integers or as a grid of bytes, with the bits changed by
+//! the last step marked:
+//!
+//! ```text
+//!   [0][0] = 0000000000000006
+//! ^ [0][0]   0000000000000004 (1 bit)
+//! ```
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write as _;
+
+use crate::sha3::constants::KECCAK_B;
+use crate::sha3::constants::KECCAK_NR;
+use crate::sha3::constants::get_el_from_b;
+use crate::sha3::secret::Zeroize;
+use crate::sha3::selftest::check_permutation;
+use crate::sha3::sha3::chi;
+use crate::sha3::sha3::iota;
+use crate::sha3::sha3::pad101;
+use crate::sha3::sha3::pi;
+use crate::sha3::sha3::rho;
+use crate::sha3::sha3::theta;
+use crate::sha3::types::ByteString;
+use crate::sha3::types::State;
+use crate::sha3::utils::bitstring_to_bytestr;
+use crate::sha3::utils::bitstring_to_state;
+use crate::sha3::utils::bytestr_to_bitstring;
+use crate::sha3::utils::concat_bitstrings;
+use crate::sha3::utils::state_to_bits;
+use crate::sha3::utils::state_to_bitstring;
+
+
+/// Bytes per line of the `Bytes` layout.
+const BYTES_PER_LINE: usize = 16;
+
+
+/// The step mappings of Rnd, in the order they are applied.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StepMapping {
+    Theta,
+    Rho,
+    Pi,
+    Chi,
+    Iota,
+}
+
+impl StepMapping {
+    pub const ALL: [StepMapping; 5] = [
+        StepMapping::Theta,
+        StepMapping::Rho,
+        StepMapping::Pi,
+        StepMapping::Chi,
+        StepMapping::Iota,
+    ];
+
+    /// Lowercase name, e.g. "theta".
+    pub fn name(&self) -> &'static str {
+        match self {
+            StepMapping::Theta => "theta",
+            StepMapping::Rho => "rho",
+            StepMapping::Pi => "pi",
+            StepMapping::Chi => "chi",
+            StepMapping::Iota => "iota",
+        }
+    }
+}
+
+/// The moment a traced state was observed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TracePoint {
+    /// Block `block` of the padded message was XORed into the state.
+    Absorb { block: usize },
+    /// `step` of round `round` (the round index ir of the specs) was applied during the
+    /// `permutation`-th call of KECCAK-p, counting those between squeezed blocks.
+    Step { permutation: usize, round: usize, step: StepMapping },
+}
+
+/// KECCAK-p[b, nr] as `keccak_p`, calling `observer` with the round index and the state
+/// after each step mapping.
+pub fn keccak_p_traced(b: usize, nr: usize, s: &mut [u8], mut observer: impl FnMut(usize, StepMapping, &State)) {
+    check_permutation();
+    let el = get_el_from_b(b);
+    assert_eq!(b, s.len());
+
+    let mut a = bitstring_to_state(s);
+    for ir in (12 + 2 * el - nr)..(12 + 2 * el) {
+        for step in StepMapping::ALL {
+            match step {
+                StepMapping::Theta => theta(&mut a),
+                StepMapping::Rho => rho(&mut a),
+                StepMapping::Pi => pi(&mut a),
+                StepMapping::Chi => chi(&mut a),
+                StepMapping::Iota => iota(&mut a, ir, el),
+            }
+            observer(ir, step, &a);
+        }
+    }
+    state_to_bits(&a, s);
+    a.zeroize();
+}
+
+/// KECCAK[c](M || suffix, d) as computed by the SHA3 functions, reporting every state to
+/// `observer`. `suffix` holds the domain separation bits: 01 for SHA3, 1111 for SHAKE and
+/// none for the original Keccak. `d` must be a multiple of 8.
+pub fn trace_sponge(keccak_c: usize, suffix: &[u8], message: &[u8], d: usize, mut observer: impl FnMut(TracePoint, &State)) -> ByteString {
+    assert!(KECCAK_B > keccak_c);
+    assert!(d.is_multiple_of(8));
+    let r = KECCAK_B - keccak_c;
+
+    let n = concat_bitstrings(&bytestr_to_bitstring(message), suffix);
+    let p = concat_bitstrings(&n, &pad101(r, n.len()));
+    let mut s = [0u8; KECCAK_B];
+    for (block, pi) in p.chunks(r).enumerate() {
+        for i in 0..r {
+            s[i] ^= pi[i];
+        }
+        observer(TracePoint::Absorb { block }, &bitstring_to_state(&s));
+        permute(&mut s, block, &mut observer);
+    }
+
+    let mut permutation = p.len() / r;
+    let mut z = ByteString::with_capacity(d / 8);
+    loop {
+        let len = (d / 8 - z.len()).min(r / 8);
+        z.extend_from_slice(&bitstring_to_bytestr(&s[..8 * len]));
+        if z.len() == d / 8 {
+            return z;
+        }
+        permute(&mut s, permutation, &mut observer);
+        permutation += 1;
+    }
+}
+
+fn permute(s: &mut [u8], permutation: usize, observer: &mut impl FnMut(TracePoint, &State)) {
+    keccak_p_traced(KECCAK_B, KECCAK_NR, s, |round, step, a| {
+        observer(TracePoint::Step { permutation, round, step }, a)
+    });
+}
+
+
+/// How `format_state` prints a state.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StateLayout {
+    /// The 25 lanes A\[x\]\[y\] as integers in hexadecimal, bit z of the lane being bit z of
+    /// the integer.
+    Lanes,
+    /// The bytes of the state string S in hexadecimal, 16 per line.
+    Bytes,
+}
+
+/// Lane A\[x, y, ·\] of a state with w <= 64; we display bits (a0 a1 a2 ...) as the integer ...a2a1a0.
+pub fn lane(a: &State, x: usize, y: usize) -> u64 {
+    assert!(a.len() <= 64);
+    (0..a.len()).fold(0, |lane, z| lane | (a[z][x][y] as u64 & 1) << z)
+}
+
+/// Number of bits that differ between two states of the same width.
+pub fn changed_bits(a: &State, previous: &State) -> usize {
+    assert_eq!(a.len(), previous.len());
+    a.iter().zip(previous.iter())
+        .map(|(slice, previous_slice)| {
+            slice.iter().flatten().zip(previous_slice.iter().flatten())
+                .filter(|(bit, previous_bit)| bit != previous_bit)
+                .count()
+        })
+        .sum()
+}
+
+/// Formats the state in `layout`, a line per lane or per 16 bytes. Given the `previous`
+/// state, each line with changed bits is followed by a line starting with "^" that shows
+/// the XOR of the two, i.e. the changed bits: the mask of a lane, or the byte differences
+/// with ".." for unchanged bytes.
+pub fn format_state(a: &State, layout: StateLayout, previous: Option<&State>) -> String {
+    let mut text = String::new();
+    match layout {
+        StateLayout::Lanes => {
+            let digits = a.len().div_ceil(4);
+            for x in 0..5 {
+                for y in 0..5 {
+                    let value = lane(a, x, y);
+                    writeln!(text, "  [{x}][{y}] = {value:0digits$X}").unwrap();
+                    let mask = previous.map_or(0, |previous| value ^ lane(previous, x, y));
+                    if mask != 0 {
+                        let count = mask.count_ones();
+                        let unit = if count == 1 { "bit" } else { "bits" };
+                        writeln!(text, "^ [{x}][{y}]   {mask:0digits$X} ({count} {unit})").unwrap();
+                    }
+                }
+            }
+        }
+        StateLayout::Bytes => {
+            let bytes = bitstring_to_bytestr(&state_to_bitstring(a));
+            let previous_bytes = previous.map(|previous| bitstring_to_bytestr(&state_to_bitstring(previous)));
+            for (i, line) in bytes.chunks(BYTES_PER_LINE).enumerate() {
+                let hex: Vec<String> = line.iter().map(|byte| format!("{byte:02x}")).collect();
+                writeln!(text, "  {}", hex.join(" ")).unwrap();
+                let Some(previous_bytes) = &previous_bytes else { continue };
+                let previous_line = &previous_bytes[i * BYTES_PER_LINE..][..line.len()];
+                if line != previous_line {
+                    let diff: Vec<String> = line.iter().zip(previous_line)
+                        .map(|(byte, previous_byte)| match byte ^ previous_byte {
+                            0 => String::from(".."),
+                            mask => format!("{mask:02x}"),
+                        })
+                        .collect();
+                    writeln!(text, "^ {}", diff.join(" ")).unwrap();
+                }
+            }
+        }
+    }
+    text
+}
+
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::sha3::sha3::keccak_p;
+    use crate::sha3::sha3::sha3_256;
+    use crate::sha3::sha3::shake128;
+    use crate::sha3::types::new_state;
+
+    #[test]
+    fn test_trace_sponge() {
+        let mut points = Vec::new();
+        let mut last = new_state(64);
+        let digest = trace_sponge(512, &[0, 1], b"abc", 256, |point, a| {
+            points.push(point);
+            last = a.clone();
+        });
+        assert_eq!(sha3_256(b"abc"), digest);
+        assert_eq!(1 + 24 * 5, points.len());
+        assert_eq!(TracePoint::Absorb { block: 0 }, points[0]);
+        assert_eq!(TracePoint::Step { permutation: 0, round: 0, step: StepMapping::Theta }, points[1]);
+        assert_eq!(TracePoint::Step { permutation: 0, round: 23, step: StepMapping::Iota }, points[120]);
+        // SHA3-256 of a short message is the first lanes of the state after the last round
+        assert_eq!(u64::from_le_bytes(digest[..8].try_into().unwrap()), lane(&last, 0, 0));
+    }
+
+    #[test]
+    fn test_trace_squeezing() {
+        // 2000 bits take two permutations between the squeezed blocks of SHAKE128 (r = 1344)
+        let mut permutations = 0;
+        let output = trace_sponge(256, &[1, 1, 1, 1], b"", 2000 + 8 * 168, |point, _| {
+            if let TracePoint::Step { permutation, .. } = point {
+                permutations = permutations.max(permutation + 1);
+            }
+        });
+        assert_eq!(shake128(b"", 2000 + 8 * 168), output);
+        assert_eq!(3, permutations);
+    }
+
+    #[test]
+    fn test_keccak_p_traced() {
+        let mut s = [0u8; KECCAK_B];
+        s[3] = 1;
+        let mut expected = s;
+        keccak_p(KECCAK_B, KECCAK_NR, &mut expected);
+
+        let mut steps = 0;
+        let mut last = new_state(64);
+        keccak_p_traced(KECCAK_B, KECCAK_NR, &mut s, |_, _, a| {
+            steps += 1;
+            last = a.clone();
+        });
+        assert_eq!(expected, s);
+        assert_eq!(24 * 5, steps);
+        assert_eq!(state_to_bitstring(&last).as_slice(), &expected[..]);
+    }
+
+    #[test]
+    fn test_format_state() {
+        let previous = new_state(64);
+        let mut a = new_state(64);
+        a[1][0][0] = 1;
+        a[2][0][0] = 1;
+        a[0][1][0] = 1;
+        assert_eq!(3, changed_bits(&a, &previous));
+
+        let lanes = format_state(&a, StateLayout::Lanes, Some(&previous));
+        let lines: Vec<&str> = lanes.lines().collect();
+        assert_eq!(25 + 2, lines.len());
+        assert_eq!("  [0][0] = 0000000000000006", lines[0]);
+        assert_eq!("^ [0][0]   0000000000000006 (2 bits)", lines[1]);
+        assert_eq!("  [0][1] = 0000000000000000", lines[2]);
+        assert_eq!("^ [1][0]   0000000000000001 (1 bit)", lines[7]);
+        assert!(!format_state(&a, StateLayout::Lanes, None).contains('^'));
+
+        let bytes = format_state(&a, StateLayout::Bytes, Some(&previous));
+        let lines: Vec<&str> = bytes.lines().collect();
+        assert_eq!(13 + 1, lines.len());
+        assert_eq!("  06 00 00 00 00 00 00 00 01 00 00 00 00 00 00 00", lines[0]);
+        assert_eq!("^ 06 .. .. .. .. .. .. .. 01 .. .. .. .. .. .. ..", lines[1]);
+        assert_eq!("  00 00 00 00 00 00 00 00", lines[13]);
+    }
+}
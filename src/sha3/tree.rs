@@ -0,0 +1,704 @@
+//! Fingerprints of directory trees: every file under a root is hashed with SHA3 on a pool
+//! of worker threads, and listed in a manifest whose text does not depend on the thread
+//! count, the order of the directory entries or the time of the walk:
+//!
+//! ```text
+//! sha3-tree 1 SHA3-256
+//! f 0644 11 <hex digest> docs/readme.txt
+//! l 0777 9 <hex digest> latest
+//! ```
+//!
+//! After the header comes a line per entry, sorted by path: the kind (`f` for a file, `l`
+//! for a recorded symlink, whose digest is that of its target path), the permission bits
+//! in octal, the size in bytes (of the target path for a symlink), the digest and the path
+//! relative to the root, '/'-separated and escaped like GNU checksum lines. Directories are
+//! not listed. The root digest is the SHA3 digest of this canonical text; written manifests
+//! end with it as a `# root` comment, which parsing ignores.
+
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::thread;
+
+use derive_more::{Display, Error};
+
+use crate::sha3::sha3::Sha3;
+use crate::sha3::sha3::sha3_into;
+use crate::sha3::sha3::SHA3_MAX_DIGEST_BYTES;
+use crate::sha3::types::ByteString;
+use crate::sha3::types::Sha3Variant;
+
+
+const MANIFEST_MAGIC: &str = "sha3-tree";
+const MANIFEST_VERSION: &str = "1";
+
+
+/// A shell-style pattern over '/'-separated relative paths: `*` matches within a path
+/// component, `**` across components, `?` one character other than '/', and `[a-z]` or
+/// `[!a-z]` one character of a class. A pattern without '/' is matched against the file
+/// name alone, as in `.gitignore`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Glob {
+    pattern: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Display, Error)]
+#[display("invalid glob '{_0}': unclosed character class")]
+pub struct GlobError(#[error(not(source))] pub String);
+
+impl Glob {
+    pub fn new(pattern: &str) -> Result<Glob, GlobError> {
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            if chars[i] == '[' {
+                i += class_end(&chars[i..]).ok_or_else(|| GlobError(pattern.to_string()))?;
+            }
+            i += 1;
+        }
+        Ok(Glob { pattern: pattern.to_string() })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    pub fn matches(&self, path: &str) -> bool {
+        let path = if self.pattern.contains('/') {
+            path
+        } else {
+            path.rsplit('/').next().unwrap_or(path)
+        };
+        let pattern: Vec<char> = self.pattern.chars().collect();
+        let path: Vec<char> = path.chars().collect();
+        glob_match(&pattern, &path)
+    }
+}
+
+fn glob_match(pattern: &[char], path: &[char]) -> bool {
+    match pattern.first() {
+        None => path.is_empty(),
+        Some('*') if pattern.get(1) == Some(&'*') => {
+            // "**/" also matches no directory at all
+            let rest = &pattern[2..];
+            if let Some(after_slash) = rest.strip_prefix(&['/'])
+                && glob_match(after_slash, path)
+            {
+                return true;
+            }
+            (0..=path.len()).any(|i| glob_match(rest, &path[i..]))
+        }
+        Some('*') => {
+            let segment_len = path.iter().position(|&c| c == '/').unwrap_or(path.len());
+            (0..=segment_len).any(|i| glob_match(&pattern[1..], &path[i..]))
+        }
+        Some('?') => path.first().is_some_and(|&c| c != '/') && glob_match(&pattern[1..], &path[1..]),
+        Some('[') => {
+            let Some(&c) = path.first() else {
+                return false;
+            };
+            let end = class_end(pattern).expect("checked by Glob::new");
+            let (negated, class) = match pattern[1] {
+                '!' => (true, &pattern[2..end]),
+                _ => (false, &pattern[1..end]),
+            };
+            debug_assert!(!class.is_empty());
+            let in_class = class_contains(class, c);
+            c != '/' && in_class != negated && glob_match(&pattern[end + 1..], &path[1..])
+        }
+        Some(&p) => path.first() == Some(&p) && glob_match(&pattern[1..], &path[1..]),
+    }
+}
+
+/// Index of the ']' closing the class that starts `pattern`; a ']' right after "[" or
+/// "[!" belongs to the class.
+fn class_end(pattern: &[char]) -> Option<usize> {
+    let first = if pattern.get(1) == Some(&'!') { 3 } else { 2 };
+    pattern.iter().skip(first).position(|&p| p == ']').map(|i| i + first)
+}
+
+fn class_contains(class: &[char], c: char) -> bool {
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == '-' {
+            if class[i] <= c && c <= class[i + 2] {
+                return true;
+            }
+            i += 3;
+        } else {
+            if class[i] == c {
+                return true;
+            }
+            i += 1;
+        }
+    }
+    false
+}
+
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    /// Leave symlinks out of the manifest.
+    #[default]
+    Skip,
+    /// Hash what the link points to, as if it were a file or directory in the tree.
+    Follow,
+    /// List the link itself, hashing its target path.
+    Record,
+}
+
+#[derive(Clone, Debug)]
+pub struct TreeOptions {
+    pub sha3_variant: Sha3Variant,
+    /// If not empty, only files matching one of these are listed.
+    pub include: Vec<Glob>,
+    /// Files and directories matching one of these are left out.
+    pub exclude: Vec<Glob>,
+    pub symlinks: SymlinkPolicy,
+    /// Number of worker threads, at least 1.
+    pub threads: usize,
+}
+
+impl Default for TreeOptions {
+    fn default() -> Self {
+        TreeOptions {
+            sha3_variant: Sha3Variant::SHA3_256,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            symlinks: SymlinkPolicy::Skip,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+        }
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EntryKind {
+    File,
+    Symlink,
+}
+
+impl EntryKind {
+    fn letter(&self) -> char {
+        match self {
+            EntryKind::File => 'f',
+            EntryKind::Symlink => 'l',
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TreeEntry {
+    /// Path relative to the root, with '/' separators.
+    pub path: String,
+    pub kind: EntryKind,
+    /// Permission bits, e.g. 0o644.
+    pub mode: u32,
+    pub size: u64,
+    pub digest: ByteString,
+}
+
+
+#[derive(Debug, Display, Error)]
+pub enum TreeError {
+    #[display("{}: {_1}", _0.display())]
+    Io(PathBuf, #[error(source)] io::Error),
+    #[display("{}: path is not valid UTF-8", _0.display())]
+    NonUtf8Path(#[error(not(source))] PathBuf),
+    #[display("{}: symlink cycle", _0.display())]
+    SymlinkCycle(#[error(not(source))] PathBuf),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Display, Error)]
+pub enum ManifestError {
+    #[display("missing or unsupported manifest header")]
+    Header,
+    #[display("line {_0}: improperly formatted entry")]
+    Entry(#[error(not(source))] usize),
+    #[display("line {_0}: entries are not sorted by path or are duplicated")]
+    Order(#[error(not(source))] usize),
+}
+
+
+/// The sorted entries of a tree and the algorithm that hashed them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Manifest {
+    pub sha3_variant: Sha3Variant,
+    pub entries: Vec<TreeEntry>,
+}
+
+/// Paths that differ between two manifests, each list sorted.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TreeDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// Paths in both whose kind, mode, size or digest changed.
+    pub changed: Vec<String>,
+}
+
+impl TreeDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl Manifest {
+    /// The canonical text the root digest is computed over.
+    pub fn canonical_text(&self) -> String {
+        let mut text = format!("{MANIFEST_MAGIC} {MANIFEST_VERSION} {}\n", variant_name(self.sha3_variant));
+        for entry in &self.entries {
+            let (is_escaped, path) = escape_path(&entry.path);
+            let prefix = if is_escaped { "\\" } else { "" };
+            let _ = writeln!(
+                text,
+                "{prefix}{} {:04o} {} {} {path}",
+                entry.kind.letter(), entry.mode, entry.size, hex::encode(&entry.digest[..]),
+            );
+        }
+        text
+    }
+
+    /// The canonical text followed by the root digest as a comment.
+    pub fn to_text(&self) -> String {
+        let mut text = self.canonical_text();
+        let _ = writeln!(text, "# root {}", hex::encode(&self.root_digest()[..]));
+        text
+    }
+
+    pub fn root_digest(&self) -> ByteString {
+        let mut digest = ByteString::from(vec![0u8; self.sha3_variant.output_len() / 8]);
+        sha3_into(self.sha3_variant, self.canonical_text().as_bytes(), &mut digest);
+        digest
+    }
+
+    /// Parses a manifest written by `to_text`; comments and empty lines are ignored.
+    pub fn parse(text: &str) -> Result<Manifest, ManifestError> {
+        let mut lines = text.lines().enumerate()
+            .map(|(i, line)| (i + 1, line))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+        let (_, header) = lines.next().ok_or(ManifestError::Header)?;
+        let sha3_variant = match header.split(' ').collect::<Vec<_>>()[..] {
+            [MANIFEST_MAGIC, MANIFEST_VERSION, name] => variant_from_name(name).ok_or(ManifestError::Header)?,
+            _ => return Err(ManifestError::Header),
+        };
+        let mut entries: Vec<TreeEntry> = Vec::new();
+        for (line_number, line) in lines {
+            let entry = parse_entry(line, sha3_variant).ok_or(ManifestError::Entry(line_number))?;
+            if entries.last().is_some_and(|last| last.path >= entry.path) {
+                return Err(ManifestError::Order(line_number));
+            }
+            entries.push(entry);
+        }
+        Ok(Manifest { sha3_variant, entries })
+    }
+
+    /// Compares this manifest with a `newer` one of the same tree.
+    pub fn diff(&self, newer: &Manifest) -> TreeDiff {
+        let mut diff = TreeDiff::default();
+        let (mut old, mut new) = (self.entries.iter().peekable(), newer.entries.iter().peekable());
+        loop {
+            match (old.peek(), new.peek()) {
+                (None, None) => return diff,
+                (Some(o), Some(n)) if o.path == n.path => {
+                    if o != n {
+                        diff.changed.push(o.path.clone());
+                    }
+                    old.next();
+                    new.next();
+                }
+                (Some(o), Some(n)) if o.path > n.path => {
+                    diff.added.push(n.path.clone());
+                    new.next();
+                }
+                (None, Some(n)) => {
+                    diff.added.push(n.path.clone());
+                    new.next();
+                }
+                (Some(o), _) => {
+                    diff.removed.push(o.path.clone());
+                    old.next();
+                }
+            }
+        }
+    }
+}
+
+fn variant_name(sha3_variant: Sha3Variant) -> &'static str {
+    match sha3_variant {
+        Sha3Variant::SHA3_224 => "SHA3-224",
+        Sha3Variant::SHA3_256 => "SHA3-256",
+        Sha3Variant::SHA3_384 => "SHA3-384",
+        Sha3Variant::SHA3_512 => "SHA3-512",
+    }
+}
+
+fn variant_from_name(name: &str) -> Option<Sha3Variant> {
+    [Sha3Variant::SHA3_224, Sha3Variant::SHA3_256, Sha3Variant::SHA3_384, Sha3Variant::SHA3_512]
+        .into_iter()
+        .find(|variant| variant_name(*variant) == name)
+}
+
+fn parse_entry(line: &str, sha3_variant: Sha3Variant) -> Option<TreeEntry> {
+    let (is_escaped, line) = match line.strip_prefix('\\') {
+        Some(line) => (true, line),
+        None => (false, line),
+    };
+    let mut fields = line.splitn(5, ' ');
+    let kind = match fields.next()? {
+        "f" => EntryKind::File,
+        "l" => EntryKind::Symlink,
+        _ => return None,
+    };
+    let mode = fields.next().filter(|mode| mode.len() == 4)?;
+    let mode = u32::from_str_radix(mode, 8).ok().filter(|mode| *mode <= 0o7777)?;
+    let size = fields.next()?;
+    if size.starts_with('+') || (size.len() > 1 && size.starts_with('0')) {
+        return None;
+    }
+    let size = size.parse().ok()?;
+    let digest = fields.next().filter(|digest| digest.bytes().all(|c| matches!(c, b'0'..=b'9' | b'a'..=b'f')))?;
+    let digest = ByteString::from(hex::decode(digest).ok()?);
+    if 8 * digest.len() != sha3_variant.output_len() {
+        return None;
+    }
+    let path = fields.next().filter(|path| !path.is_empty())?;
+    let path = if is_escaped { unescape_path(path)? } else { path.to_string() };
+    Some(TreeEntry { path, kind, mode, size, digest })
+}
+
+fn escape_path(path: &str) -> (bool, String) {
+    if !path.contains(['\\', '\n', '\r']) {
+        return (false, path.to_string());
+    }
+    (true, path.replace('\\', "\\\\").replace('\n', "\\n").replace('\r', "\\r"))
+}
+
+fn unescape_path(path: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(path.len());
+    let mut chars = path.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next()? {
+            '\\' => unescaped.push('\\'),
+            'n' => unescaped.push('\n'),
+            'r' => unescaped.push('\r'),
+            _ => return None,
+        }
+    }
+    Some(unescaped)
+}
+
+
+/// A file found by the walk, before hashing.
+struct PendingEntry {
+    path: String,
+    full_path: PathBuf,
+    kind: EntryKind,
+    mode: u32,
+}
+
+/// Walks `root` and hashes every selected file on `options.threads` workers.
+pub fn hash_tree(root: &Path, options: &TreeOptions) -> Result<Manifest, TreeError> {
+    let mut pending = Vec::new();
+    let mut visited = HashSet::new();
+    walk(root, "", options, &mut visited, &mut pending)?;
+    pending.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let results: Mutex<Vec<Option<Result<TreeEntry, TreeError>>>> = Mutex::new((0..pending.len()).map(|_| None).collect());
+    let next = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        for _ in 0..options.threads.clamp(1, pending.len().max(1)) {
+            scope.spawn(|| {
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(entry) = pending.get(i) else {
+                        break;
+                    };
+                    let result = hash_entry(entry, options.sha3_variant);
+                    results.lock().expect("no worker panics while holding the lock")[i] = Some(result);
+                }
+            });
+        }
+    });
+    let entries = results.into_inner().expect("workers have finished")
+        .into_iter()
+        .map(|result| result.expect("every entry was hashed"))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Manifest { sha3_variant: options.sha3_variant, entries })
+}
+
+/// Hashes `root` again with the algorithm of `manifest` and options otherwise taken from
+/// `options`, and compares the result with `manifest`.
+pub fn verify_tree(root: &Path, manifest: &Manifest, options: &TreeOptions) -> Result<TreeDiff, TreeError> {
+    let options = TreeOptions { sha3_variant: manifest.sha3_variant, ..options.clone() };
+    Ok(manifest.diff(&hash_tree(root, &options)?))
+}
+
+fn walk(
+    dir: &Path,
+    prefix: &str,
+    options: &TreeOptions,
+    visited: &mut HashSet<PathBuf>,
+    pending: &mut Vec<PendingEntry>,
+) -> Result<(), TreeError> {
+    // canonical paths of the directories being walked, to stop at symlink cycles
+    let canonical = fs::canonicalize(dir).map_err(io_error(dir))?;
+    if !visited.insert(canonical.clone()) {
+        return Err(TreeError::SymlinkCycle(dir.to_path_buf()));
+    }
+    for dir_entry in fs::read_dir(dir).map_err(io_error(dir))? {
+        let dir_entry = dir_entry.map_err(io_error(dir))?;
+        let full_path = dir_entry.path();
+        let name = dir_entry.file_name().into_string().map_err(|_| TreeError::NonUtf8Path(full_path.clone()))?;
+        let path = format!("{prefix}{name}");
+        if options.exclude.iter().any(|glob| glob.matches(&path)) {
+            continue;
+        }
+        let mut metadata = fs::symlink_metadata(&full_path).map_err(io_error(&full_path))?;
+        let mut kind = EntryKind::File;
+        if metadata.file_type().is_symlink() {
+            match options.symlinks {
+                SymlinkPolicy::Skip => continue,
+                SymlinkPolicy::Record => kind = EntryKind::Symlink,
+                SymlinkPolicy::Follow => metadata = fs::metadata(&full_path).map_err(io_error(&full_path))?,
+            }
+        }
+        if kind == EntryKind::File && metadata.is_dir() {
+            walk(&full_path, &format!("{path}/"), options, visited, pending)?;
+            continue;
+        }
+        if kind == EntryKind::File && !metadata.is_file() {
+            // sockets, FIFOs and devices have no stable content
+            continue;
+        }
+        if !options.include.is_empty() && !options.include.iter().any(|glob| glob.matches(&path)) {
+            continue;
+        }
+        pending.push(PendingEntry { path, full_path, kind, mode: permission_bits(&metadata) });
+    }
+    visited.remove(&canonical);
+    Ok(())
+}
+
+#[cfg(unix)]
+fn permission_bits(metadata: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o7777
+}
+
+#[cfg(not(unix))]
+fn permission_bits(metadata: &fs::Metadata) -> u32 {
+    if metadata.permissions().readonly() { 0o444 } else { 0o644 }
+}
+
+fn io_error(path: &Path) -> impl FnOnce(io::Error) -> TreeError + use<> {
+    let path = path.to_path_buf();
+    move |error| TreeError::Io(path, error)
+}
+
+fn hash_entry(entry: &PendingEntry, sha3_variant: Sha3Variant) -> Result<TreeEntry, TreeError> {
+    let digest_len = sha3_variant.output_len() / 8;
+    let mut digest = [0u8; SHA3_MAX_DIGEST_BYTES];
+    let size = match entry.kind {
+        EntryKind::File => {
+            let mut sha3 = Sha3::new(sha3_variant);
+            let size = io::copy(&mut File::open(&entry.full_path).map_err(io_error(&entry.full_path))?, &mut sha3)
+                .map_err(io_error(&entry.full_path))?;
+            sha3.digest_into(&mut digest[..digest_len]);
+            size
+        }
+        EntryKind::Symlink => {
+            let target = fs::read_link(&entry.full_path).map_err(io_error(&entry.full_path))?;
+            let target = target.to_str().ok_or_else(|| TreeError::NonUtf8Path(entry.full_path.clone()))?;
+            sha3_into(sha3_variant, target.as_bytes(), &mut digest[..digest_len]);
+            target.len() as u64
+        }
+    };
+    Ok(TreeEntry {
+        path: entry.path.clone(),
+        kind: entry.kind,
+        mode: entry.mode,
+        size,
+        digest: ByteString::from(digest[..digest_len].to_vec()),
+    })
+}
+
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    use crate::sha3::sha3::sha3_256;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("crypto-playground-tree-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn make_tree(name: &str) -> PathBuf {
+        let root = temp_dir(name);
+        fs::create_dir_all(root.join("docs/img")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+        fs::write(root.join("readme.md"), "hello").unwrap();
+        fs::write(root.join("docs/guide.txt"), "guide").unwrap();
+        fs::write(root.join("docs/img/logo.svg"), "<svg/>").unwrap();
+        fs::write(root.join("target/out.o"), [0u8; 3000]).unwrap();
+        root
+    }
+
+    fn paths(manifest: &Manifest) -> Vec<&str> {
+        manifest.entries.iter().map(|entry| entry.path.as_str()).collect()
+    }
+
+    #[test]
+    fn test_globs() {
+        let glob = |pattern| Glob::new(pattern).unwrap();
+        assert!(glob("*.txt").matches("docs/guide.txt"));
+        assert!(glob("*.txt").matches("guide.txt"));
+        assert!(!glob("docs/*.txt").matches("docs/a/guide.txt"));
+        assert!(glob("docs/**/*.svg").matches("docs/img/logo.svg"));
+        assert!(glob("docs/**/*.svg").matches("docs/logo.svg"));
+        assert!(glob("**/img/*").matches("docs/img/logo.svg"));
+        assert!(glob("target").matches("target"));
+        assert!(glob("file?.[ch]").matches("file1.c"));
+        assert!(!glob("file?.[ch]").matches("file1.o"));
+        assert!(glob("[!.]*").matches("visible"));
+        assert!(!glob("[!.]*").matches(".hidden"));
+        assert!(glob("v[0-9]").matches("v7"));
+        assert!(!glob("a?b").matches("a/b"));
+        assert!(glob("[]]").matches("]"));
+        assert!(glob("[!]]").matches("a"));
+        assert_eq!(Err(GlobError("[abc".to_string())), Glob::new("[abc"));
+        assert_eq!(Err(GlobError("a[]".to_string())), Glob::new("a[]"));
+    }
+
+    #[test]
+    fn test_hash_tree() {
+        let root = make_tree("hash");
+        let manifest = hash_tree(&root, &TreeOptions::default()).unwrap();
+        assert_eq!(vec!["docs/guide.txt", "docs/img/logo.svg", "readme.md", "target/out.o"], paths(&manifest));
+        let readme = &manifest.entries[2];
+        assert_eq!(EntryKind::File, readme.kind);
+        assert_eq!(5, readme.size);
+        assert_eq!(sha3_256(b"hello"), readme.digest);
+        assert_eq!(3000, manifest.entries[3].size);
+
+        // the manifest does not depend on the number of workers
+        for threads in [1, 3, 16] {
+            let options = TreeOptions { threads, ..TreeOptions::default() };
+            assert_eq!(manifest, hash_tree(&root, &options).unwrap());
+        }
+        assert_eq!(Ok(manifest.clone()), Manifest::parse(&manifest.to_text()));
+
+        let root_digest = hex::encode(&manifest.root_digest()[..]);
+        assert!(manifest.to_text().ends_with(&format!("# root {root_digest}\n")));
+        assert_eq!(sha3_256(manifest.canonical_text().as_bytes()), manifest.root_digest());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_include_exclude() {
+        let root = make_tree("globs");
+        let options = TreeOptions {
+            exclude: vec![Glob::new("target").unwrap()],
+            ..TreeOptions::default()
+        };
+        assert_eq!(vec!["docs/guide.txt", "docs/img/logo.svg", "readme.md"], paths(&hash_tree(&root, &options).unwrap()));
+
+        let options = TreeOptions {
+            include: vec![Glob::new("*.txt").unwrap(), Glob::new("*.md").unwrap()],
+            ..TreeOptions::default()
+        };
+        assert_eq!(vec!["docs/guide.txt", "readme.md"], paths(&hash_tree(&root, &options).unwrap()));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let root = make_tree("symlinks");
+        symlink("readme.md", root.join("latest")).unwrap();
+        symlink("..", root.join("docs/img/up")).unwrap();
+
+        let skipped = hash_tree(&root, &TreeOptions::default()).unwrap();
+        assert_eq!(4, skipped.entries.len());
+
+        let options = TreeOptions { symlinks: SymlinkPolicy::Record, ..TreeOptions::default() };
+        let recorded = hash_tree(&root, &options).unwrap();
+        assert_eq!(vec!["docs/guide.txt", "docs/img/logo.svg", "docs/img/up", "latest", "readme.md", "target/out.o"], paths(&recorded));
+        let latest = &recorded.entries[3];
+        assert_eq!((EntryKind::Symlink, 9), (latest.kind, latest.size));
+        assert_eq!(sha3_256(b"readme.md"), latest.digest);
+        assert_eq!(Ok(recorded.clone()), Manifest::parse(&recorded.to_text()));
+
+        // docs/img/up points back to docs
+        let options = TreeOptions { symlinks: SymlinkPolicy::Follow, ..TreeOptions::default() };
+        assert!(matches!(hash_tree(&root, &options), Err(TreeError::SymlinkCycle(_))));
+        fs::remove_file(root.join("docs/img/up")).unwrap();
+        let followed = hash_tree(&root, &options).unwrap();
+        assert_eq!(vec!["docs/guide.txt", "docs/img/logo.svg", "latest", "readme.md", "target/out.o"], paths(&followed));
+        assert_eq!(followed.entries[2].digest, followed.entries[3].digest);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_verify() {
+        let root = make_tree("verify");
+        let options = TreeOptions::default();
+        let before = hash_tree(&root, &options).unwrap();
+        assert!(verify_tree(&root, &before, &options).unwrap().is_empty());
+
+        fs::write(root.join("readme.md"), "hello!").unwrap();
+        fs::remove_file(root.join("docs/guide.txt")).unwrap();
+        fs::write(root.join("docs/new.txt"), "new").unwrap();
+        let diff = verify_tree(&root, &before, &options).unwrap();
+        assert_eq!(vec!["docs/new.txt".to_string()], diff.added);
+        assert_eq!(vec!["docs/guide.txt".to_string()], diff.removed);
+        assert_eq!(vec!["readme.md".to_string()], diff.changed);
+
+        // the manifest's algorithm wins over the options
+        let sha3_512 = TreeOptions { sha3_variant: Sha3Variant::SHA3_512, ..TreeOptions::default() };
+        let after = hash_tree(&root, &sha3_512).unwrap();
+        assert!(verify_tree(&root, &after, &options).unwrap().is_empty());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let digest = hex::encode([0xabu8; 32]);
+        assert_eq!(Err(ManifestError::Header), Manifest::parse(""));
+        assert_eq!(Err(ManifestError::Header), Manifest::parse("sha3-tree 2 SHA3-256\n"));
+        assert_eq!(Err(ManifestError::Header), Manifest::parse("sha3-tree 1 SHA-256\n"));
+        let malformed = [
+            format!("x 0644 1 {digest} a"),
+            format!("f 644 1 {digest} a"),
+            format!("f 0644 01 {digest} a"),
+            format!("f 0644 1 {} a", &digest[2..]),
+            format!("f 0644 1 {} a", digest.to_uppercase()),
+            format!("f 0644 1 {digest} "),
+            format!("\\f 0644 1 {digest} a\\t"),
+        ];
+        for line in malformed {
+            assert_eq!(Err(ManifestError::Entry(2)), Manifest::parse(&format!("sha3-tree 1 SHA3-256\n{line}\n")), "{line}");
+        }
+        let unsorted = format!("sha3-tree 1 SHA3-256\nf 0644 1 {digest} b\nf 0644 1 {digest} a\n");
+        assert_eq!(Err(ManifestError::Order(3)), Manifest::parse(&unsorted));
+
+        let escaped = format!("sha3-tree 1 SHA3-256\n# comment\n\\f 0644 1 {digest} a\\nb\n");
+        assert_eq!("a\nb", Manifest::parse(&escaped).unwrap().entries[0].path);
+    }
+}
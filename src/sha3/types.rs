@@ -1,5 +1,13 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+#[cfg(feature = "alloc")]
 use derive_more::{Deref, DerefMut, From};
 
+use crate::sha3::constants::KECCAK_MAX_W;
+use crate::sha3::secret::Zeroize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Sha3Variant {
     SHA3_224,
     SHA3_256,
@@ -7,10 +15,45 @@ pub enum Sha3Variant {
     SHA3_512,
 }
 
+impl Sha3Variant {
+    /// Capacity c of KECCAK[c], which is twice the digest length.
+    pub fn capacity(&self) -> usize {
+        2 * self.output_len()
+    }
+
+    /// Digest length d in bits.
+    pub fn output_len(&self) -> usize {
+        match self {
+            Sha3Variant::SHA3_224 => 224,
+            Sha3Variant::SHA3_256 => 256,
+            Sha3Variant::SHA3_384 => 384,
+            Sha3Variant::SHA3_512 => 512,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ShakeVariant {
+    SHAKE128,
+    SHAKE256,
+}
+
+impl ShakeVariant {
+    /// Capacity c of KECCAK[c], which is twice the security strength.
+    pub fn capacity(&self) -> usize {
+        match self {
+            ShakeVariant::SHAKE128 => 256,
+            ShakeVariant::SHAKE256 => 512,
+        }
+    }
+}
+
 /// each element x is (0 <= x <= 255)
-#[derive(Deref, DerefMut, From, Clone)]
+#[cfg(feature = "alloc")]
+#[derive(Deref, DerefMut, From, Clone, Default, Debug, PartialEq, Eq)]
 pub struct ByteString(Vec<u8>);
 
+#[cfg(feature = "alloc")]
 impl ByteString {
     pub fn new() -> Self {
         ByteString(Vec::new())
@@ -23,9 +66,11 @@ impl ByteString {
 
 /// it is expected that each element x is (0 <= x <= 1)
 //pub type BitString = Vec<u8>;
-#[derive(Deref, DerefMut, From, Clone)]
+#[cfg(feature = "alloc")]
+#[derive(Deref, DerefMut, From, Clone, Default)]
 pub struct BitString(Vec<u8>);
 
+#[cfg(feature = "alloc")]
 impl BitString {
     pub fn new() -> BitString {
         BitString(Vec::new())
@@ -43,11 +88,38 @@ pub type StateSlice = [[u8;5];5];
 /// Represents the 3-dimentional state, which is of size 5 x 5 x w, each value is a bit.
 /// 
 /// The notion "A\[x,y,z\]" should be accessed via A\[z\]\[x\]\[y\] in code.
-pub type State = Vec<StateSlice>;
+///
+/// The slices are kept in a fixed-size array, so that no allocation is needed;
+/// only the first w of them are in use and exposed via `Deref`.
+#[derive(Clone)]
+pub struct State {
+    w: usize,
+    slices: [StateSlice; KECCAK_MAX_W],
+}
+
+impl core::ops::Deref for State {
+    type Target = [StateSlice];
+
+    fn deref(&self) -> &[StateSlice] {
+        &self.slices[..self.w]
+    }
+}
+
+impl core::ops::DerefMut for State {
+    fn deref_mut(&mut self) -> &mut [StateSlice] {
+        &mut self.slices[..self.w]
+    }
+}
+
+impl Zeroize for State {
+    fn zeroize(&mut self) {
+        self.slices.zeroize();
+    }
+}
 
 
 /// represents "C\[x,z\]" and "D\[x,z\]", which should be accessed via CD\[z\]\[x\];
-pub type StatePlane = Vec<[u8; 5]>;
+pub type StatePlane = [[u8; 5]; KECCAK_MAX_W];
 
 
 pub fn new_slice() -> StateSlice {
@@ -55,17 +127,13 @@ pub fn new_slice() -> StateSlice {
 }
 
 pub fn new_state(w: usize) -> State {
-    let mut a = State::with_capacity(w);
-    for _z in 0..w {
-        a.push(new_slice());
+    assert!(w <= KECCAK_MAX_W);
+    State {
+        w,
+        slices: [new_slice(); KECCAK_MAX_W],
     }
-    a
 }
 
-pub fn new_plane(w: usize) -> StatePlane {
-    let mut plane = StatePlane::with_capacity(w);
-    for _z in 0..w {
-        plane.push([0u8; 5]);
-    }
-    plane
+pub fn new_plane() -> StatePlane {
+    [[0u8; 5]; KECCAK_MAX_W]
 }
@@ -1,56 +1,69 @@
+#[cfg(feature = "alloc")]
+use alloc::vec;
 
 use crate::sha3::types::State;
-use crate::sha3::types::StateSlice;
+use crate::sha3::types::new_state;
+#[cfg(feature = "alloc")]
 use crate::sha3::types::BitString;
+#[cfg(feature = "alloc")]
 use crate::sha3::types::ByteString;
+#[cfg(feature = "std")]
+use crate::sha3::trace::StateLayout;
+#[cfg(feature = "std")]
+use crate::sha3::trace::format_state;
 
 
 /// Converts an array of bytes to an array of bits, where each bit is of type u8.
 /// 
 /// [4] ==> \[00000100\] => \[0, 0, 1, 0, 0, 0, 0, 0\]
+#[cfg(feature = "alloc")]
 pub fn bytestr_to_bitstring(bytes: &[u8]) -> BitString {
-    let bytes_len = bytes.len();
-    let mut bits = BitString::new();
-    for i in 0..bytes_len {
-        for offset in 0..8 {
-            let bit =  (bytes[i] >> offset) & 1;
-            bits.push(bit);
-        }
-    }
+    let mut bits = BitString::from(vec![0u8; 8 * bytes.len()]);
+    bytes_to_bits(bytes, &mut bits);
     bits
 }
 
 /// Converts an array of bits (of u8 type) to an array of bytes.
 /// 
 /// \[0, 0, 1, 0, 0, 0, 0, 0\] => \[00000100\] => \[4\]
+#[cfg(feature = "alloc")]
 pub fn bitstring_to_bytestr(bits: &[u8]) -> ByteString {
-    assert!(bits.len() % 8 == 0);
-    let bytes_len = bits.len() / 8;
-    let mut res = ByteString::with_capacity(bytes_len);
-    for i in 0..bytes_len {
+    assert!(bits.len().is_multiple_of(8));
+    let mut res = ByteString::from(vec![0u8; bits.len() / 8]);
+    bits_to_bytes(bits, &mut res);
+    res
+}
+
+/// Same as `bytestr_to_bitstring`, but writes the 8 * bytes.len() bits into `bits`.
+pub fn bytes_to_bits(bytes: &[u8], bits: &mut [u8]) {
+    assert_eq!(8 * bytes.len(), bits.len());
+    for i in 0..bytes.len() {
+        for offset in 0..8 {
+            bits[8*i + offset] = (bytes[i] >> offset) & 1;
+        }
+    }
+}
+
+/// Same as `bitstring_to_bytestr`, but writes the bits.len() / 8 bytes into `bytes`.
+pub fn bits_to_bytes(bits: &[u8], bytes: &mut [u8]) {
+    assert_eq!(bits.len(), 8 * bytes.len());
+    for i in 0..bytes.len() {
         let mut byte: u8 = 0;
         for offset in 0..8 {
             let bit = bits[8*i + offset];
             byte |= bit << offset;
         }
-        res.push(byte);
+        bytes[i] = byte;
     }
-    res
 }
 
-// State conversion functions
-pub fn bitstring_to_state(bits: &BitString) -> State {
+// State conversion functions (Sec. 3.1.2 and 3.1.3); b = 25 w
+pub fn bitstring_to_state(bits: &[u8]) -> State {
     let b = bits.len();
-    assert!(b==1600); // this is hardcoded for SHA3 functions
-    let w = 64;
-    //let el = 6;
-
-    let mut a = State::with_capacity(w);
-    for _k in 0..w {
-        let state_slice: StateSlice = [[0; 5]; 5];
-        a.push(state_slice);
-    }
+    assert!(b.is_multiple_of(25));
+    let w = b / 25;
 
+    let mut a = new_state(w);
     for x in 0..5 {
         for y in 0..5 {
             for z in 0..w {
@@ -61,17 +74,10 @@ pub fn bitstring_to_state(bits: &BitString) -> State {
     a
 }
 
-pub fn state_to_bitstring(a: &State) -> BitString {
+/// Writes the 25 w bits of the state into `s`.
+pub fn state_to_bits(a: &State, s: &mut [u8]) {
     let w = a.len();
-    
-    // w and b are hardcoded for now as per SHA3 definitions
-    assert!(w==64); 
-    let b = 1600;
-
-    let mut s = BitString::with_capacity(b);
-    for _i in 0..b {
-        s.push(0);
-    }
+    assert_eq!(s.len(), 25 * w);
     for x in 0..5 {
         for y in 0..5 {
             for z in 0..w {
@@ -80,14 +86,23 @@ pub fn state_to_bitstring(a: &State) -> BitString {
             }
         }
     }
+}
+
+#[cfg(feature = "alloc")]
+#[allow(dead_code)]
+pub fn state_to_bitstring(a: &State) -> BitString {
+    let mut s = new_bitstring(25 * a.len());
+    state_to_bits(a, &mut s);
     s
 }
 
+#[allow(dead_code)]
 pub fn get_state_value(state_flat:&[u8], z:usize, x:usize, y:usize) -> u8 {
     let w = state_flat.len() / 25;
     state_flat[w*(5*y+x)+z]
 }
 
+#[allow(dead_code)]
 pub fn set_state_value(state_flat:&mut [u8], z:usize, x:usize, y:usize, val: u8){
     let w = state_flat.len() / 25;
     state_flat[w*(5*y+x)+z] = val;
@@ -95,6 +110,7 @@ pub fn set_state_value(state_flat:&mut [u8], z:usize, x:usize, y:usize, val: u8)
 
 
 
+#[cfg(feature = "alloc")]
 /// Returns a new truncated BitString by copying the first "s" bits.
 pub fn trunc(s: usize, x: &BitString) -> BitString {
     let mut x1 = BitString::with_capacity(s);
@@ -105,17 +121,7 @@ pub fn trunc(s: usize, x: &BitString) -> BitString {
 }
 
 
-/// Returns a new BitString by prepending 0 to the beginning of the old BitString.
-pub fn prepend_zero(x: &BitString) -> BitString {
-    let len = x.len();
-    let mut x1 = BitString::with_capacity(len+1);
-    x1.push(0);
-    for i in 0..len {
-        x1.push(x[i]);
-    }
-    x1
-}
-
+#[cfg(feature = "alloc")]
 /// Returns a new BitString of size w initialized to 0s.
 pub fn new_bitstring(w: usize) -> BitString {
     let mut bs = BitString::with_capacity(w);
@@ -125,6 +131,7 @@ pub fn new_bitstring(w: usize) -> BitString {
     bs
 }
 
+#[cfg(feature = "alloc")]
 /// Performs a xor operation on two BitStrings of equal length.
 pub fn xor_bitstrings(bs0: &BitString, bs1: &BitString) -> BitString {
     assert_eq!(bs0.len(), bs1.len());
@@ -137,6 +144,7 @@ pub fn xor_bitstrings(bs0: &BitString, bs1: &BitString) -> BitString {
     bs2
 }
 
+#[cfg(feature = "alloc")]
 // Concats Bitstings bs1 and bs2 to bs1 || bs2.
 //pub fn concat_bitstrings(bs0: &BitString, bs1: &BitString) -> BitString {
 pub fn concat_bitstrings(bs0: &[u8], bs1: &[u8]) -> BitString {
@@ -153,47 +161,22 @@ pub fn concat_bitstrings(bs0: &[u8], bs1: &[u8]) -> BitString {
 }
 
 
+#[cfg(feature = "std")]
+#[allow(dead_code)]
 pub fn debug_state_as_bytes(title: &str, a: &State) {
-    let w = a.len();
-    let len_bytes = w * 5 * 5 / 8;
-    let bit_str = state_to_bitstring(a);
-    let byte_str = bitstring_to_bytestr(&bit_str);
-    let hex =  hex::encode(&byte_str.as_slice());
-    //println!("{title} : \n{hex}");
     println!("{title} :");
-
-    for i in 0..len_bytes {
-        let substr = &hex[2*i..2*i+2];
-        print!("{substr} ");
-        if i>0 && i%16==15 {
-            println!();
-        }
-    }
-    println!();
+    print!("{}", format_state(a, StateLayout::Bytes, None));
 }
 
+#[cfg(feature = "std")]
+#[allow(dead_code)]
 pub fn debug_state_as_lanes_of_integers(title: &str, a: &State) {
-    let w = a.len();
-    assert!(w==64);
     println!("{title} (as lanes):");
-    for x in  0..5 {
-        for y in 0..5 {
-            let mut lane_bits = BitString::new();
-            for z in 0..w {
-                lane_bits.push(a[z][x][y]);
-            }
-            // We display bits (a0 a1 a2 ...) as integer  ...a2a1a0.
-            let mut lane_u64: u64 = 0;
-            for z in 0..w {
-                lane_u64 |= (lane_bits[z] as u64 & 1) << z;
-            }
-            println!("  [{x}][{y}] = {lane_u64:016X}");
-        }
-    }
+    print!("{}", format_state(a, StateLayout::Lanes, None));
 }
 
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use super::*;
 
@@ -207,6 +190,7 @@ mod tests {
         println!();
     }
 
+    #[allow(dead_code)]
     fn debug_state(title: &str, a: &State) {
         let w = a.len();
         println!("{title} of w={w} :");
@@ -272,14 +256,6 @@ mod tests {
         assert_eq!(expected, x1.as_slice());
     }
 
-    #[test]
-    fn test_prepend_zero(){
-        let x = BitString::from(vec![1, 1]);
-        let x1 = prepend_zero(&x);
-        let expected: [u8; 3] = [0, 1, 1];
-        assert_eq!(expected, x1.as_slice());
-    }
-
     #[test]
     fn test_xor_bitstrings(){
         let x0 = BitString::from(vec![0, 0, 1, 1]);
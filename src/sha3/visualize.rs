@@ -0,0 +1,474 @@
+//! Pictures of the state for explaining the step mappings: a `State` is drawn either as the
+//! grid of its 25 lanes or as the w slices of the 5 x 5 x w cube, in plain text, with ANSI
+//! colors or as SVG, with some bits marked.
+//!
+//! As in the figures of FIPS 202, x runs left to right as 3, 4, 0, 1, 2 and y top to bottom
+//! as 2, 1, 0, 4, 3, so that the lane x = y = 0 is in the center. The marks show either the
+//! bits a step mapping changed, or the bits it combines into a single output bit, as in
+//! Figures 3 (theta), 4 (rho), 5 (pi) and 6 (chi).
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::Write as _;
+
+use crate::sha3::constants::RHO_OFFSETS;
+use crate::sha3::trace::StepMapping;
+use crate::sha3::trace::keccak_p_traced;
+use crate::sha3::types::State;
+use crate::sha3::utils::bitstring_to_state;
+use crate::sha3::utils::bytes_to_bits;
+use crate::sha3::utils::new_bitstring;
+use crate::sha3::utils::state_to_bitstring;
+
+
+/// Values of x from left to right, as in the figures of FIPS 202.
+pub const X_ORDER: [usize; 5] = [3, 4, 0, 1, 2];
+/// Values of y from top to bottom, as in the figures of FIPS 202.
+pub const Y_ORDER: [usize; 5] = [2, 1, 0, 4, 3];
+
+/// Slices per row of the text rendering of the `Slices` view.
+const TEXT_SLICES_PER_ROW: usize = 12;
+/// Slices per row of the SVG rendering of the `Slices` view.
+const SVG_SLICES_PER_ROW: usize = 16;
+/// Bits per row of a lane in the SVG rendering of the `Lanes` view.
+const SVG_LANE_COLUMNS: usize = 8;
+/// Side of a bit in the SVG renderings, in pixels.
+const SVG_CELL: usize = 10;
+/// Space between lanes, slices and frames, and height of the labels, in pixels.
+const SVG_GAP: usize = 16;
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum View {
+    /// The 5 x 5 grid of lanes; in text, a line per lane, with z from left to right.
+    Lanes,
+    /// The slices of the cube side by side, z = 0 first.
+    Slices,
+}
+
+/// How a bit is highlighted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Mark {
+    Plain,
+    /// Changed by the last step mapping.
+    Changed,
+    /// An input of the step mapping for the target bit.
+    Source,
+    /// The position of the output bit whose inputs are marked.
+    Target,
+}
+
+/// A mark for each bit of a state of lane size w.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Marks {
+    w: usize,
+    /// In the order of the state string: index w (5y + x) + z.
+    marks: Vec<Mark>,
+}
+
+impl Marks {
+    pub fn none(w: usize) -> Marks {
+        Marks { w, marks: vec![Mark::Plain; 25 * w] }
+    }
+
+    /// Marks the bits that differ between `before` and `after`.
+    pub fn changes(before: &State, after: &State) -> Marks {
+        let w = after.len();
+        assert_eq!(before.len(), w);
+        let mut marks = Marks::none(w);
+        for x in 0..5 {
+            for y in 0..5 {
+                for z in 0..w {
+                    if before[z][x][y] != after[z][x][y] {
+                        marks.set(x, y, z, Mark::Changed);
+                    }
+                }
+            }
+        }
+        marks
+    }
+
+    /// Marks the bits of the input state that `step` combines into the bit A'\[x, y, z\] of its
+    /// output, and that position as the target.
+    pub fn step_sources(step: StepMapping, w: usize, (x, y, z): (usize, usize, usize)) -> Marks {
+        assert!(x < 5 && y < 5 && z < w);
+        let mut marks = Marks::none(w);
+        match step {
+            StepMapping::Theta => {
+                // the parities of the columns (x - 1, z) and (x + 1, z - 1) are added to the bit
+                for y1 in 0..5 {
+                    marks.set((x + 4) % 5, y1, z, Mark::Source);
+                    marks.set((x + 1) % 5, y1, (z + w - 1) % w, Mark::Source);
+                }
+                marks.set(x, y, z, Mark::Source);
+            }
+            StepMapping::Rho => marks.set(x, y, (z + w - RHO_OFFSETS[x][y] % w) % w, Mark::Source),
+            StepMapping::Pi => marks.set((x + 3 * y) % 5, x, z, Mark::Source),
+            StepMapping::Chi => {
+                for offset in 0..3 {
+                    marks.set((x + offset) % 5, y, z, Mark::Source);
+                }
+            }
+            // the round constant is added to the bit itself
+            StepMapping::Iota => marks.set(x, y, z, Mark::Source),
+        }
+        if marks.get(x, y, z) == Mark::Plain {
+            marks.set(x, y, z, Mark::Target);
+        }
+        marks
+    }
+
+    pub fn get(&self, x: usize, y: usize, z: usize) -> Mark {
+        self.marks[self.w * (5 * y + x) + z]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, z: usize, mark: Mark) {
+        self.marks[self.w * (5 * y + x) + z] = mark;
+    }
+
+    /// Number of bits with the mark.
+    pub fn count(&self, mark: Mark) -> usize {
+        self.marks.iter().filter(|m| **m == mark).count()
+    }
+}
+
+
+/// The state of lane size w whose string S starts with the bits of `bytes`, the other bits
+/// being zero; None if the bytes do not fit in 25 w bits.
+pub fn state_from_bytes(w: usize, bytes: &[u8]) -> Option<State> {
+    let mut s = new_bitstring(25 * w);
+    let len = 8 * bytes.len();
+    if len > s.len() {
+        return None;
+    }
+    bytes_to_bits(bytes, &mut s[..len]);
+    Some(bitstring_to_state(&s))
+}
+
+
+/// A state to draw, with a title and its marks.
+#[derive(Clone)]
+pub struct Frame {
+    pub title: String,
+    pub state: State,
+    pub marks: Marks,
+}
+
+/// The input state, then the state after each step mapping of KECCAK-p[25 w, nr] that `keep`
+/// selects by round index and step, with the bits that step changed marked.
+pub fn permutation_frames(a: &State, nr: usize, keep: impl Fn(usize, StepMapping) -> bool) -> Vec<Frame> {
+    let w = a.len();
+    let mut frames = vec![Frame { title: String::from("input"), state: a.clone(), marks: Marks::none(w) }];
+    let mut previous = a.clone();
+    let mut s = state_to_bitstring(a);
+    keccak_p_traced(25 * w, nr, &mut s, |round, step, after| {
+        if keep(round, step) {
+            frames.push(Frame {
+                title: format!("round {round}, {}", step.name()),
+                state: after.clone(),
+                marks: Marks::changes(&previous, after),
+            });
+        }
+        previous = after.clone();
+    });
+    frames
+}
+
+
+/// Character of a bit in plain text: `#` and `.` for 1 and 0, other pairs for marked bits.
+fn text_char(bit: u8, mark: Mark) -> char {
+    let (one, zero) = match mark {
+        Mark::Plain => ('#', '.'),
+        Mark::Changed => ('@', 'o'),
+        Mark::Source => ('+', '-'),
+        Mark::Target => ('X', 'x'),
+    };
+    if bit == 1 { one } else { zero }
+}
+
+/// ANSI background color of a marked bit.
+fn ansi_color(mark: Mark) -> Option<&'static str> {
+    match mark {
+        Mark::Plain => None,
+        Mark::Changed => Some("\x1b[41m"),
+        Mark::Source => Some("\x1b[44m"),
+        Mark::Target => Some("\x1b[43m"),
+    }
+}
+
+fn push_bit(text: &mut String, bit: u8, mark: Mark, ansi: bool) {
+    match ansi_color(mark).filter(|_| ansi) {
+        Some(color) => {
+            text.push_str(color);
+            text.push(text_char(bit, Mark::Plain));
+            text.push_str("\x1b[0m");
+        }
+        None if ansi => text.push(text_char(bit, Mark::Plain)),
+        None => text.push(text_char(bit, mark)),
+    }
+}
+
+/// Explains the characters, or the colors with `ansi`, of `render_text`.
+pub fn text_legend(ansi: bool) -> String {
+    if ansi {
+        let sample = |mark| format!("{}#.\x1b[0m", ansi_color(mark).unwrap());
+        format!("# 1, . 0; {} changed, {} source, {} target",
+            sample(Mark::Changed), sample(Mark::Source), sample(Mark::Target))
+    } else {
+        String::from("# 1, . 0; @ o changed to 1 or 0; + - source 1 or 0; X x target 1 or 0")
+    }
+}
+
+/// Draws the state as text, with the marked bits as other characters or, with `ansi`, on a
+/// colored background.
+pub fn render_text(a: &State, view: View, marks: &Marks, ansi: bool) -> String {
+    let w = a.len();
+    let mut text = String::new();
+    match view {
+        View::Lanes => {
+            for (row, y) in Y_ORDER.into_iter().enumerate() {
+                if row > 0 {
+                    text.push('\n');
+                }
+                for x in X_ORDER {
+                    write!(text, "x={x} y={y}  ").unwrap();
+                    for z in 0..w {
+                        push_bit(&mut text, a[z][x][y], marks.get(x, y, z), ansi);
+                    }
+                    text.push('\n');
+                }
+            }
+        }
+        View::Slices => {
+            let slices: Vec<usize> = (0..w).collect();
+            for (i, row) in slices.chunks(TEXT_SLICES_PER_ROW).enumerate() {
+                if i > 0 {
+                    text.push('\n');
+                }
+                let labels: Vec<String> = row.iter().map(|z| format!("{:<5}", format!("z={z}"))).collect();
+                writeln!(text, "{}", labels.join(" ").trim_end()).unwrap();
+                for y in Y_ORDER {
+                    for (j, &z) in row.iter().enumerate() {
+                        if j > 0 {
+                            text.push(' ');
+                        }
+                        for x in X_ORDER {
+                            push_bit(&mut text, a[z][x][y], marks.get(x, y, z), ansi);
+                        }
+                    }
+                    text.push('\n');
+                }
+            }
+        }
+    }
+    text
+}
+
+
+/// Fill color of a bit in SVG.
+fn svg_color(bit: u8, mark: Mark) -> &'static str {
+    match (mark, bit) {
+        (Mark::Plain, 1) => "#333333",
+        (Mark::Plain, _) => "#ffffff",
+        (Mark::Changed, 1) => "#c0392b",
+        (Mark::Changed, _) => "#f5b7b1",
+        (Mark::Source, 1) => "#1f618d",
+        (Mark::Source, _) => "#aed6f1",
+        (Mark::Target, 1) => "#d68910",
+        (Mark::Target, _) => "#fad7a0",
+    }
+}
+
+/// Width and height of a drawn state in pixels, without the title.
+fn svg_size(w: usize, view: View) -> (usize, usize) {
+    match view {
+        View::Lanes => {
+            let columns = w.min(SVG_LANE_COLUMNS);
+            let (lane_width, lane_height) = (columns * SVG_CELL, w / columns * SVG_CELL);
+            (SVG_GAP + 5 * (lane_width + SVG_GAP), SVG_GAP + 5 * (lane_height + SVG_GAP))
+        }
+        View::Slices => {
+            let columns = w.min(SVG_SLICES_PER_ROW);
+            let rows = w.div_ceil(SVG_SLICES_PER_ROW);
+            (columns * (5 * SVG_CELL + SVG_GAP), rows * (5 * SVG_CELL + 2 * SVG_GAP))
+        }
+    }
+}
+
+fn escape_svg(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn svg_text(svg: &mut String, x: usize, y: usize, text: &str) {
+    writeln!(svg, r#"<text x="{x}" y="{y}">{}</text>"#, escape_svg(text)).unwrap();
+}
+
+fn svg_bit(svg: &mut String, x: usize, y: usize, bit: u8, mark: Mark) {
+    writeln!(svg, r#"<rect x="{x}" y="{y}" width="{SVG_CELL}" height="{SVG_CELL}" fill="{}"/>"#, svg_color(bit, mark)).unwrap();
+}
+
+/// Draws the frames one below the other as an SVG image, each under its title.
+pub fn render_svg(frames: &[Frame], view: View) -> String {
+    let w = frames.first().map_or(1, |frame| frame.state.len());
+    let (width, height) = svg_size(w, view);
+    let frame_height = SVG_GAP + height + SVG_GAP;
+    let mut svg = String::new();
+    writeln!(svg, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{}" font-family="monospace" font-size="12">"#,
+        frames.len() * frame_height).unwrap();
+    writeln!(svg, r##"<rect width="100%" height="100%" fill="#ffffff"/>"##).unwrap();
+    for (i, frame) in frames.iter().enumerate() {
+        assert_eq!(w, frame.state.len());
+        writeln!(svg, r#"<g transform="translate(0,{})">"#, i * frame_height).unwrap();
+        svg_text(&mut svg, 0, 12, &frame.title);
+        writeln!(svg, r##"<g transform="translate(0,{SVG_GAP})" stroke="#cccccc" stroke-width="0.5">"##).unwrap();
+        match view {
+            View::Lanes => {
+                let columns = w.min(SVG_LANE_COLUMNS);
+                let (lane_width, lane_height) = (columns * SVG_CELL, w / columns * SVG_CELL);
+                for (column, x) in X_ORDER.into_iter().enumerate() {
+                    svg_text(&mut svg, SVG_GAP + column * (lane_width + SVG_GAP), 12, &format!("x={x}"));
+                }
+                for (row, y) in Y_ORDER.into_iter().enumerate() {
+                    let top = SVG_GAP + row * (lane_height + SVG_GAP);
+                    svg_text(&mut svg, 0, top + 12, &format!("{y}"));
+                    for (column, x) in X_ORDER.into_iter().enumerate() {
+                        let left = SVG_GAP + column * (lane_width + SVG_GAP);
+                        for z in 0..w {
+                            let (bit_x, bit_y) = (left + z % columns * SVG_CELL, top + z / columns * SVG_CELL);
+                            svg_bit(&mut svg, bit_x, bit_y, frame.state[z][x][y], frame.marks.get(x, y, z));
+                        }
+                    }
+                }
+            }
+            View::Slices => {
+                for z in 0..w {
+                    let left = z % SVG_SLICES_PER_ROW * (5 * SVG_CELL + SVG_GAP);
+                    let top = z / SVG_SLICES_PER_ROW * (5 * SVG_CELL + 2 * SVG_GAP);
+                    svg_text(&mut svg, left, top + 12, &format!("z={z}"));
+                    for (row, y) in Y_ORDER.into_iter().enumerate() {
+                        for (column, x) in X_ORDER.into_iter().enumerate() {
+                            let (bit_x, bit_y) = (left + column * SVG_CELL, top + SVG_GAP + row * SVG_CELL);
+                            svg_bit(&mut svg, bit_x, bit_y, frame.state[z][x][y], frame.marks.get(x, y, z));
+                        }
+                    }
+                }
+            }
+        }
+        writeln!(svg, "</g>").unwrap();
+        writeln!(svg, "</g>").unwrap();
+    }
+    writeln!(svg, "</svg>").unwrap();
+    svg
+}
+
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::sha3::types::new_state;
+
+    #[test]
+    fn test_step_sources() {
+        // theta: two columns of 5 bits and the bit itself
+        let marks = Marks::step_sources(StepMapping::Theta, 64, (0, 0, 0));
+        assert_eq!(11, marks.count(Mark::Source));
+        assert_eq!(0, marks.count(Mark::Target));
+        assert_eq!(Mark::Source, marks.get(4, 3, 0));
+        assert_eq!(Mark::Source, marks.get(1, 3, 63));
+
+        // rho: lane (1, 0) is rotated by 1
+        let marks = Marks::step_sources(StepMapping::Rho, 64, (1, 0, 5));
+        assert_eq!(Mark::Source, marks.get(1, 0, 4));
+        assert_eq!(Mark::Target, marks.get(1, 0, 5));
+
+        // pi: A'[x, y] = A[x + 3y, x]
+        let marks = Marks::step_sources(StepMapping::Pi, 8, (1, 2, 3));
+        assert_eq!(Mark::Source, marks.get(2, 1, 3));
+        assert_eq!(Mark::Target, marks.get(1, 2, 3));
+        assert_eq!(1, marks.count(Mark::Source));
+
+        // chi: the bit and the next two of its row
+        let marks = Marks::step_sources(StepMapping::Chi, 1, (4, 0, 0));
+        assert_eq!(3, marks.count(Mark::Source));
+        assert_eq!(Mark::Source, marks.get(1, 0, 0));
+        assert_eq!(Mark::Plain, marks.get(2, 0, 0));
+    }
+
+    #[test]
+    fn test_render_text() {
+        let mut a = new_state(2);
+        a[0][0][0] = 1;
+        a[1][3][2] = 1;
+        let mut marks = Marks::none(2);
+        marks.set(0, 0, 0, Mark::Changed);
+        marks.set(0, 0, 1, Mark::Source);
+
+        let lanes = render_text(&a, View::Lanes, &marks, false);
+        let lines: Vec<&str> = lanes.lines().collect();
+        assert_eq!(25 + 4, lines.len());
+        assert_eq!("x=3 y=2  .#", lines[0]);
+        assert_eq!("x=0 y=0  @-", lines[2 * 6 + 2]);
+
+        let slices = render_text(&a, View::Slices, &marks, false);
+        assert_eq!("\
+z=0   z=1
+..... #....
+..... .....
+..@.. ..-..
+..... .....
+..... .....
+", slices);
+
+        let ansi = render_text(&a, View::Slices, &marks, true);
+        assert!(ansi.contains("..\x1b[41m#\x1b[0m.."), "{ansi}");
+        assert!(ansi.contains("..\x1b[44m.\x1b[0m.."), "{ansi}");
+    }
+
+    #[test]
+    fn test_state_from_bytes() {
+        let a = state_from_bytes(8, &[0x01, 0x80]).unwrap();
+        assert_eq!(1, a[0][0][0]);
+        assert_eq!(1, a[7][1][0]);
+        assert_eq!(2, Marks::changes(&new_state(8), &a).count(Mark::Changed));
+        assert!(state_from_bytes(8, &[0; 25]).is_some());
+        assert!(state_from_bytes(8, &[0; 26]).is_none());
+    }
+
+    #[test]
+    fn test_permutation_frames() {
+        let mut a = new_state(8);
+        a[0][0][0] = 1;
+        let frames = permutation_frames(&a, 18, |round, _| round == 17);
+        assert_eq!(1 + 5, frames.len());
+        assert_eq!("input", frames[0].title);
+        assert_eq!("round 17, theta", frames[1].title);
+        assert_eq!("round 17, iota", frames[5].title);
+
+        // each frame marks the changes from the previous step, even an unselected one
+        let all = permutation_frames(&a, 1, |_, _| true);
+        assert_eq!(1 + 5, all.len());
+        assert_eq!(10, all[1].marks.count(Mark::Changed));
+        for pair in all.windows(2) {
+            assert_eq!(pair[1].marks, Marks::changes(&pair[0].state, &pair[1].state));
+        }
+    }
+
+    #[test]
+    fn test_render_svg() {
+        let a = new_state(8);
+        let frames = [
+            Frame { title: String::from("a < b"), state: a.clone(), marks: Marks::none(8) },
+            Frame { title: String::from("two"), state: a, marks: Marks::step_sources(StepMapping::Chi, 8, (0, 0, 0)) },
+        ];
+        for view in [View::Lanes, View::Slices] {
+            let svg = render_svg(&frames, view);
+            assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+            assert!(svg.ends_with("</svg>\n"));
+            assert_eq!(1 + 2 * 200, svg.matches("<rect ").count());
+            assert_eq!(3, svg.matches("fill=\"#aed6f1\"").count());
+            assert!(svg.contains(">a &lt; b</text>"));
+        }
+    }
+}
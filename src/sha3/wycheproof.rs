@@ -0,0 +1,285 @@
+//! Runner for negative tests in the format of Project Wycheproof, for the keyed modes.
+//!
+//! A file holds test groups with a `tagSize` (in bits), each with cases giving `key`, `msg`,
+//! `tag` and an expected `result`: "valid" tags must verify, "invalid" tags must be rejected,
+//! and "acceptable" ones may go either way. `flags` name the kind of edge case, e.g. a
+//! modified or truncated tag, and are described in the `notes` of the file.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+use derive_more::{Display, Error};
+
+use crate::sha3::hmac::HmacSha3;
+use crate::sha3::json::JsonError;
+use crate::sha3::json::JsonValue;
+use crate::sha3::json::parse_json;
+use crate::sha3::registry::Algorithm;
+use crate::sha3::sp800_185::Kmac;
+use crate::sha3::types::ByteString;
+
+
+/// Wycheproof names of the keyed modes that differ from their registry names.
+const ALIASES: [(&str, &str); 4] = [
+    ("HMACSHA3-224", "HMAC-SHA3-224"),
+    ("HMACSHA3-256", "HMAC-SHA3-256"),
+    ("HMACSHA3-384", "HMAC-SHA3-384"),
+    ("HMACSHA3-512", "HMAC-SHA3-512"),
+];
+
+/// Whether `algorithm` is one of the keyed modes tested here, HMAC-SHA3 and KMAC; each one
+/// has a file in `test_vectors/wycheproof`.
+pub fn is_keyed_mode(algorithm: &Algorithm) -> bool {
+    matches!(algorithm, Algorithm::HmacSha3(_) | Algorithm::Kmac(_))
+}
+
+/// Maps the `algorithm` names used by Wycheproof, e.g. "HMACSHA3-256" or "KMAC128", to the
+/// registry.
+pub fn algorithm_from_name(name: &str) -> Option<Algorithm> {
+    let name = ALIASES.iter()
+        .find(|(alias, _)| *alias == name)
+        .map_or(name, |(_, registry_name)| registry_name);
+    Algorithm::from_name(name).filter(is_keyed_mode)
+}
+
+/// Verifies `tag` as a tag of `tag_size` bits: HMAC tags are truncated to that size,
+/// while for KMAC it is the output length L.
+pub fn verify(algorithm: Algorithm, key: &[u8], msg: &[u8], customization: &[u8], tag: &[u8], tag_size: usize) -> bool {
+    if 8 * tag.len() != tag_size {
+        return false;
+    }
+    match algorithm {
+        Algorithm::HmacSha3(sha3_variant) => {
+            let mut hmac = HmacSha3::new(sha3_variant, key);
+            hmac.update(msg);
+            hmac.verify_truncated(tag)
+        }
+        Algorithm::Kmac(shake_variant) => {
+            let mut kmac = Kmac::new(shake_variant, key, customization);
+            kmac.update(msg);
+            kmac.verify(tag)
+        }
+        _ => unreachable!("{algorithm} is not a keyed mode"),
+    }
+}
+
+
+#[derive(Debug, Display, Error)]
+pub enum WycheproofError {
+    #[display("{}: {_1}", _0.display())]
+    Io(PathBuf, #[error(source)] io::Error),
+    #[display("{}: {_1}", _0.display())]
+    Json(PathBuf, #[error(source)] JsonError),
+    #[display("{}: {_1}", _0.display())]
+    InvalidFormat(PathBuf, #[error(not(source))] String),
+    #[display("{}: missing algorithm", _0.display())]
+    MissingAlgorithm(#[error(not(source))] PathBuf),
+    #[display("{}: unsupported algorithm '{_1}'", _0.display())]
+    UnsupportedAlgorithm(PathBuf, #[error(not(source))] String),
+}
+
+
+/// A case whose outcome did not match its expected result.
+pub struct WycheproofFailure {
+    pub tc_id: u64,
+    pub comment: String,
+    pub flags: Vec<String>,
+}
+
+impl fmt::Display for WycheproofFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "tcId {}", self.tc_id)?;
+        if !self.comment.is_empty() {
+            write!(f, " ({})", self.comment)?;
+        }
+        if !self.flags.is_empty() {
+            write!(f, " {:?}", self.flags)?;
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of running one Wycheproof file.
+pub struct WycheproofReport {
+    pub path: PathBuf,
+    pub algorithm: Algorithm,
+    pub passed: usize,
+    pub failed: usize,
+    /// Cases with an "acceptable" result, which pass whether or not the tag verifies.
+    pub acceptable: usize,
+    pub failures: Vec<WycheproofFailure>,
+}
+
+impl WycheproofReport {
+    pub fn is_success(&self) -> bool {
+        self.failed == 0
+    }
+}
+
+impl fmt::Display for WycheproofReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let file_name = self.path.file_name().unwrap_or_default().to_string_lossy();
+        write!(f, "{file_name}: passed {} (acceptable {}), failed {}", self.passed, self.acceptable, self.failed)?;
+        for failure in &self.failures {
+            write!(f, "\n  {failure}")?;
+        }
+        Ok(())
+    }
+}
+
+
+fn field<'a>(value: &'a JsonValue, name: &str) -> Result<&'a JsonValue, String> {
+    value.get(name).ok_or_else(|| format!("missing {name}"))
+}
+
+fn hex_field(value: &JsonValue, name: &str) -> Result<ByteString, String> {
+    field(value, name)?.as_str()
+        .and_then(|hex| hex::decode(hex).ok())
+        .map(ByteString::from)
+        .ok_or_else(|| format!("invalid hex string in {name}"))
+}
+
+fn number_field(value: &JsonValue, name: &str) -> Result<u64, String> {
+    field(value, name)?.as_u64().ok_or_else(|| format!("invalid number in {name}"))
+}
+
+/// Runs the cases of a parsed Wycheproof file.
+pub fn run_wycheproof(path: &Path, file: &JsonValue) -> Result<WycheproofReport, WycheproofError> {
+    let invalid = |message: String| WycheproofError::InvalidFormat(path.to_path_buf(), message);
+    let algorithm_name = file.get("algorithm")
+        .and_then(JsonValue::as_str)
+        .ok_or_else(|| WycheproofError::MissingAlgorithm(path.to_path_buf()))?;
+    let algorithm = algorithm_from_name(algorithm_name)
+        .ok_or_else(|| WycheproofError::UnsupportedAlgorithm(path.to_path_buf(), algorithm_name.to_string()))?;
+
+    let mut report = WycheproofReport {
+        path: path.to_path_buf(),
+        algorithm,
+        passed: 0,
+        failed: 0,
+        acceptable: 0,
+        failures: Vec::new(),
+    };
+    let groups = field(file, "testGroups").map_err(invalid)?
+        .as_array().ok_or_else(|| invalid("invalid testGroups".to_string()))?;
+    for group in groups {
+        let tag_size = number_field(group, "tagSize").map_err(invalid)? as usize;
+        let cases = field(group, "tests").map_err(invalid)?
+            .as_array().ok_or_else(|| invalid("invalid tests".to_string()))?;
+        for case in cases {
+            let tc_id = number_field(case, "tcId").map_err(invalid)?;
+            let in_case = |message: String| invalid(format!("tcId {tc_id}: {message}"));
+            let key = hex_field(case, "key").map_err(in_case)?;
+            let msg = hex_field(case, "msg").map_err(in_case)?;
+            let tag = hex_field(case, "tag").map_err(in_case)?;
+            let customization = match case.get("customization") {
+                Some(_) => hex_field(case, "customization").map_err(in_case)?,
+                None => ByteString::new(),
+            };
+
+            let is_verified = verify(algorithm, &key, &msg, &customization, &tag, tag_size);
+            let is_expected = match case.get("result").and_then(JsonValue::as_str) {
+                Some("valid") => is_verified,
+                Some("invalid") => !is_verified,
+                Some("acceptable") => {
+                    report.acceptable += 1;
+                    true
+                }
+                result => return Err(in_case(format!("invalid result {result:?}"))),
+            };
+            if is_expected {
+                report.passed += 1;
+            } else {
+                report.failed += 1;
+                report.failures.push(WycheproofFailure {
+                    tc_id,
+                    comment: case.get("comment").and_then(JsonValue::as_str).unwrap_or("").to_string(),
+                    flags: case.get("flags").and_then(JsonValue::as_array).unwrap_or(&[])
+                        .iter()
+                        .filter_map(|flag| flag.as_str().map(str::to_string))
+                        .collect(),
+                });
+            }
+        }
+    }
+    Ok(report)
+}
+
+pub fn run_wycheproof_file(path: &Path) -> Result<WycheproofReport, WycheproofError> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| WycheproofError::Io(path.to_path_buf(), e))?;
+    let file = parse_json(&content)
+        .map_err(|e| WycheproofError::Json(path.to_path_buf(), e))?;
+    run_wycheproof(path, &file)
+}
+
+/// Runs all .json files directly under `dir`, in sorted order.
+pub fn run_wycheproof_dir(dir: &Path) -> Result<Vec<WycheproofReport>, WycheproofError> {
+    let entries = fs::read_dir(dir)
+        .map_err(|e| WycheproofError::Io(dir.to_path_buf(), e))?;
+    let mut paths = Vec::new();
+    for entry in entries {
+        let path = entry
+            .map_err(|e| WycheproofError::Io(dir.to_path_buf(), e))?
+            .path();
+        if path.extension().is_some_and(|ext| ext == "json") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    paths.iter().map(|path| run_wycheproof_file(path)).collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::sha3::types::Sha3Variant;
+    use crate::sha3::types::ShakeVariant;
+
+    use super::*;
+
+    fn run_dir() -> Vec<WycheproofReport> {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("test_vectors/wycheproof");
+        run_wycheproof_dir(&dir).unwrap()
+    }
+
+    #[test]
+    fn test_all_wycheproof_files() {
+        let reports = run_dir();
+        for report in &reports {
+            assert!(report.is_success(), "{report}");
+            assert!(report.passed > 0, "{report}");
+        }
+    }
+
+    #[test]
+    fn test_every_keyed_mode_has_a_file() {
+        let reports = run_dir();
+        for algorithm in Algorithm::ALL.into_iter().filter(is_keyed_mode) {
+            assert!(reports.iter().any(|report| report.algorithm == algorithm), "no Wycheproof file for {algorithm}");
+        }
+    }
+
+    #[test]
+    fn test_algorithm_names() {
+        assert_eq!(Some(Algorithm::HmacSha3(Sha3Variant::SHA3_384)), algorithm_from_name("HMACSHA3-384"));
+        assert_eq!(Some(Algorithm::Kmac(ShakeVariant::SHAKE256)), algorithm_from_name("KMAC256"));
+        // in the registry, but not keyed
+        assert_eq!(None, algorithm_from_name("SHA3-256"));
+        assert_eq!(None, algorithm_from_name("KMACXOF128"));
+    }
+
+    #[test]
+    fn test_unexpected_results_are_reported() {
+        let file = parse_json(r#"{"algorithm": "HMACSHA3-256", "testGroups": [{"tagSize": 128, "tests": [
+            {"tcId": 1, "comment": "truncated tag", "key": "6b6579", "msg": "", "tag": "00112233445566778899aabbccddeeff", "result": "valid", "flags": ["ModifiedTag"]},
+            {"tcId": 2, "comment": "", "key": "6b6579", "msg": "", "tag": "", "result": "invalid", "flags": []},
+            {"tcId": 3, "comment": "", "key": "", "msg": "", "tag": "", "result": "acceptable", "flags": []}]}]}"#).unwrap();
+        let report = run_wycheproof(Path::new("hmac.json"), &file).unwrap();
+        assert_eq!((2, 1, 1), (report.passed, report.failed, report.acceptable));
+        assert_eq!("hmac.json: passed 2 (acceptable 1), failed 1\n  tcId 1 (truncated tag) [\"ModifiedTag\"]", report.to_string());
+    }
+}
@@ -0,0 +1,68 @@
+//! The state of the self-tests is global, so these tests run in their own binary, apart
+//! from the unit tests, and in a single test function.
+
+use std::panic;
+use std::sync::mpsc;
+use std::thread;
+
+use crypto_playground::sha3::selftest::SelfTestError;
+use crypto_playground::sha3::selftest::SelfTestStatus;
+use crypto_playground::sha3::selftest::hold_self_test;
+use crypto_playground::sha3::selftest::inject_fault;
+use crypto_playground::sha3::selftest::kat_names;
+use crypto_playground::sha3::selftest::self_test;
+use crypto_playground::sha3::selftest::self_test_waits;
+use crypto_playground::sha3::selftest::status;
+use crypto_playground::sha3::sha3::sha3_256;
+
+#[test]
+fn test_self_test_states() {
+    assert_eq!(SelfTestStatus::Untested, status());
+    // the power-on self-test runs on first use
+    assert_eq!(32, sha3_256(b"abc").len());
+    assert_eq!(SelfTestStatus::Operational, status());
+
+    check_injected_faults();
+    check_permutation_waits_for_self_test();
+}
+
+fn check_injected_faults() {
+    assert!(!inject_fault(Some("SHA3-257")));
+    for name in kat_names() {
+        assert!(inject_fault(Some(name)));
+        assert_eq!(Err(SelfTestError { name }), self_test());
+        assert_eq!(SelfTestStatus::Error, status());
+        assert!(panic::catch_unwind(|| sha3_256(b"abc")).is_err(), "{name}");
+
+        assert!(inject_fault(None));
+        assert_eq!(Ok(()), self_test());
+        assert_eq!(SelfTestStatus::Operational, status());
+        assert_eq!(32, sha3_256(b"abc").len());
+    }
+}
+
+fn check_permutation_waits_for_self_test() {
+    hold_self_test(true);
+    let self_test_thread = thread::spawn(self_test);
+    while status() != SelfTestStatus::Running {
+        thread::yield_now();
+    }
+
+    let waits = self_test_waits();
+    let (sender, receiver) = mpsc::channel();
+    let hasher = thread::spawn(move || sender.send(sha3_256(b"abc")).unwrap());
+    while self_test_waits() == waits {
+        assert!(!hasher.is_finished(), "the hasher did not wait for the self-test");
+        thread::yield_now();
+    }
+    // the hasher has reached the permutation, and cannot get past it while the self-test runs
+    assert_eq!(Err(mpsc::TryRecvError::Empty), receiver.try_recv());
+    assert_eq!(SelfTestStatus::Running, status());
+
+    hold_self_test(false);
+    assert_eq!(Ok(()), self_test_thread.join().unwrap());
+    let digest = receiver.recv().unwrap();
+    assert_eq!("3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532", hex::encode(digest.as_slice()));
+    hasher.join().unwrap();
+    assert_eq!(SelfTestStatus::Operational, status());
+}